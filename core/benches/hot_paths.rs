@@ -0,0 +1,132 @@
+// The paths that run on every block or every grab: the input callback writing into the rolling
+// buffer, the copies and peaks the UI, the recovery flush and the plugin take out of it, and
+// writing a grab to disk. Run one group with e.g. `cargo bench -p rolling-sampler-core -- export`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rolling_sampler_core::backend::Signal;
+use rolling_sampler_core::export::Take;
+use rolling_sampler_core::{dsp, CircularBuffer};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const BUFFER_SECONDS: usize = 10;
+const TAKE_SECONDS: usize = 5;
+
+// (sample rate, channels), from a laptop mic up to a multichannel interface
+const FORMATS: [(u32, usize); 4] = [(44_100, 1), (48_000, 2), (96_000, 2), (48_000, 8)];
+
+// Callback sizes from a low latency setup up to a default one on a busy machine
+const BLOCK_FRAMES: [usize; 3] = [64, 512, 4096];
+
+fn format_name(sample_rate: u32, channels: usize) -> String {
+    format!("{} Hz {} ch", sample_rate, channels)
+}
+
+fn sine(frames: usize, sample_rate: u32, channels: usize) -> Vec<f32> {
+    let signal = Signal::Sine {
+        hz: 440.0,
+        amplitude: 0.5,
+    };
+    (0..frames as u64)
+        .flat_map(|frame| std::iter::repeat_n(signal.sample(frame, sample_rate), channels))
+        .collect()
+}
+
+// A buffer that has already wrapped around, as it is for all but the first few seconds
+fn full_buffer(sample_rate: u32, channels: usize) -> CircularBuffer {
+    let frames = BUFFER_SECONDS * sample_rate as usize;
+    let mut buffer = CircularBuffer::new(frames * channels);
+    buffer.channels = channels;
+    buffer.sample_rate = sample_rate;
+    buffer.add_samples(&sine(frames + frames / 3, sample_rate, channels));
+    buffer
+}
+
+fn add_samples(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_samples");
+    for (sample_rate, channels) in FORMATS {
+        let mut buffer = full_buffer(sample_rate, channels);
+        for block_frames in BLOCK_FRAMES {
+            let block = sine(block_frames, sample_rate, channels);
+            group.throughput(Throughput::Elements(block.len() as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format_name(sample_rate, channels), block_frames),
+                &block,
+                |b, block| b.iter(|| buffer.add_samples(black_box(block))),
+            );
+        }
+    }
+    group.finish();
+}
+
+// The newest second, as the plugin and the scrub preview copy it, and the whole window, as a
+// recovery flush does
+fn snapshot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+    for (sample_rate, channels) in FORMATS {
+        let buffer = full_buffer(sample_rate, channels);
+        let second = sample_rate as u64 * channels as u64;
+        let end = buffer.total_written;
+        for (name, start) in [
+            ("last second", end - second),
+            ("whole window", buffer.window_start()),
+        ] {
+            group.throughput(Throughput::Elements(end - start));
+            group.bench_function(
+                BenchmarkId::new(format_name(sample_rate, channels), name),
+                |b| b.iter(|| buffer.copy_range(black_box(start), black_box(end))),
+            );
+        }
+    }
+    group.finish();
+}
+
+// Per block for the meters, and over a whole grab for the export analysis
+fn peak(c: &mut Criterion) {
+    let mut group = c.benchmark_group("peak");
+    for frames in [512, BUFFER_SECONDS * 48_000] {
+        let samples = sine(frames, 48_000, 2);
+        group.throughput(Throughput::Elements(samples.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(frames),
+            &samples,
+            |b, samples| b.iter(|| dsp::peak(black_box(samples))),
+        );
+    }
+    group.finish();
+}
+
+// A whole save as the writer thread does it: the WAV, its INFO chunk, the hash and the sidecar.
+// The files go to a temp folder that is removed afterwards.
+fn export(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("rolling-sampler-bench-{}", std::process::id()));
+    let mut group = c.benchmark_group("export");
+    group.sample_size(10);
+    for (sample_rate, channels) in FORMATS {
+        let take = Take {
+            samples: Arc::new(sine(
+                TAKE_SECONDS * sample_rate as usize,
+                sample_rate,
+                channels,
+            )),
+            channels: channels as u16,
+            sample_rate,
+            path: dir.join("bench.wav"),
+            name: "bench".to_owned(),
+            ..Take::default()
+        };
+        group.throughput(Throughput::Bytes(
+            (take.samples.len() * std::mem::size_of::<f32>()) as u64,
+        ));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format_name(sample_rate, channels)),
+            &take,
+            |b, take| b.iter(|| take.save().expect("bench take saved")),
+        );
+    }
+    group.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, add_samples, snapshot, peak, export);
+criterion_main!(benches);
@@ -0,0 +1,392 @@
+// Offline analysis helpers run over grabbed (interleaved) audio
+
+use crate::dsp::{self, Biquad, OnsetDetector};
+use crate::pitch::NOTE_NAMES;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+// Integrated loudness (ITU-R BS.1770 / EBU R128) fed a chunk at a time: K-weighted mean square
+// in 100 ms steps, combined into 400 ms blocks that overlap by 75%, then gated at -70 LUFS and
+// again 10 LU under the ungated result. Every channel counts equally, as for mono and stereo.
+pub struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    step_frames: usize,
+    step_energy: f64,   // Summed over the channels for the step so far
+    step_filled: usize, // Frames in the step so far
+    steps: Vec<f64>,    // Mean square of each finished 100 ms step
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        LoudnessMeter {
+            filters: (0..channels.max(1))
+                .map(|_| Biquad::k_weighting(sample_rate as f32))
+                .collect(),
+            step_frames: (sample_rate as usize / 10).max(1),
+            step_energy: 0.0,
+            step_filled: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        let channels = self.filters.len();
+        for frame in samples.chunks_exact(channels) {
+            for (sample, [shelf, high_pass]) in frame.iter().zip(self.filters.iter_mut()) {
+                let weighted = high_pass.process(shelf.process(*sample)) as f64;
+                self.step_energy += weighted * weighted;
+            }
+            self.step_filled += 1;
+            if self.step_filled == self.step_frames {
+                self.steps.push(self.step_energy / self.step_frames as f64);
+                self.step_energy = 0.0;
+                self.step_filled = 0;
+            }
+        }
+    }
+
+    // LUFS, or None if there is less than one block or it's all below the absolute gate
+    pub fn integrated(&self) -> Option<f32> {
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(4)
+            .map(|w| w.iter().sum::<f64>() / 4.0)
+            .collect();
+        let loudness = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let gated_mean = |threshold: f64| {
+            let above: Vec<f64> = blocks
+                .iter()
+                .copied()
+                .filter(|&energy| energy > 0.0 && loudness(energy) > threshold)
+                .collect();
+            (!above.is_empty()).then(|| above.iter().sum::<f64>() / above.len() as f64)
+        };
+        let ungated = gated_mean(-70.0)?;
+        let integrated = gated_mean(loudness(ungated) - 10.0)?;
+        Some(loudness(integrated) as f32)
+    }
+}
+
+pub fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f32> {
+    let mut meter = LoudnessMeter::new(channels, sample_rate);
+    meter.add_samples(samples);
+    meter.integrated()
+}
+
+// How a stereo take holds up when summed to mono
+pub struct MonoCheck {
+    pub correlation: f32,  // -1 (out of phase) to 1 (identical channels)
+    pub mono_loss_db: f32, // How much quieter the mono sum is than the average channel
+    pub better_channel: usize,
+}
+
+impl MonoCheck {
+    // Losing this much level in the mono sum means something is badly out of phase
+    const MAX_MONO_LOSS_DB: f32 = 6.0;
+
+    pub fn has_cancellation(&self) -> bool {
+        self.mono_loss_db >= Self::MAX_MONO_LOSS_DB
+    }
+}
+
+// Only meaningful for stereo, returns None for anything else or for silence
+pub fn mono_compatibility(samples: &[f32], channels: usize) -> Option<MonoCheck> {
+    if channels != 2 {
+        return None;
+    }
+
+    let (mut left_energy, mut right_energy, mut cross, mut mono_energy) = (0.0, 0.0, 0.0, 0.0);
+    for frame in samples.chunks_exact(2) {
+        let (left, right) = (frame[0] as f64, frame[1] as f64);
+        left_energy += left * left;
+        right_energy += right * right;
+        cross += left * right;
+        let mono = (left + right) * 0.5;
+        mono_energy += mono * mono;
+    }
+    if left_energy == 0.0 || right_energy == 0.0 {
+        return None;
+    }
+
+    let correlation = cross / (left_energy * right_energy).sqrt();
+    let channel_energy = (left_energy + right_energy) * 0.5;
+    // Identical channels give the same energy in mono, so any loss is due to cancellation
+    let mono_loss_db = 10.0 * (channel_energy / mono_energy.max(f64::MIN_POSITIVE)).log10();
+
+    Some(MonoCheck {
+        correlation: correlation as f32,
+        mono_loss_db: mono_loss_db as f32,
+        better_channel: if left_energy >= right_energy { 0 } else { 1 },
+    })
+}
+
+// A distinct sound found in a longer grab, in frames
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+    pub start: usize,
+    pub end: usize,
+    pub peak_db: f32,
+}
+
+// Find distinct events in a grab by energy-based segmentation: anything sitting clearly above
+// the noise floor (estimated from the quietest part of the grab) counts as an event.
+// `sensitivity_db` is how far above the floor a sound has to rise.
+pub fn detect_events(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    sensitivity_db: f32,
+) -> Vec<Event> {
+    let channels = channels.max(1);
+    let hop = (sample_rate as usize / 100).max(1); // 10 ms analysis frames
+    let min_gap = 30; // Hops of quiet (300 ms) needed to split two events
+    let min_length = 5; // Hops (50 ms), anything shorter is a tick rather than an event
+    let pre_roll = 5; // Hops kept before the event crosses the threshold
+    let post_roll = 10; // ...and after it drops back below
+    let hysteresis_db = 3.0;
+
+    let frames = samples.len() / channels;
+    let levels: Vec<(f32, f32)> = (0..frames.div_ceil(hop))
+        .map(|hop_index| {
+            let start = hop_index * hop * channels;
+            let end = ((hop_index + 1) * hop * channels).min(samples.len());
+            let block = &samples[start..end];
+            let energy = block.iter().map(|s| s * s).sum::<f32>() / block.len().max(1) as f32;
+            let peak = block.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            (
+                10.0 * energy.max(1e-12).log10(),
+                20.0 * peak.max(1e-6).log10(),
+            )
+        })
+        .collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    // The 10th percentile level is a robust guess at the background noise
+    let mut sorted: Vec<f32> = levels.iter().map(|(rms, _)| *rms).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let floor = sorted[sorted.len() / 10].max(-80.0); // Digital silence would make everything loud
+    let enter = floor + sensitivity_db;
+    let exit = enter - hysteresis_db;
+
+    // Collect runs of loud hops, then merge the ones separated by short gaps
+    let mut runs: Vec<(usize, usize, f32)> = Vec::new();
+    let mut current: Option<(usize, f32)> = None;
+    for (index, &(rms, peak)) in levels.iter().enumerate() {
+        match current {
+            None if rms >= enter => current = Some((index, peak)),
+            Some((start, run_peak)) if rms >= exit => current = Some((start, run_peak.max(peak))),
+            Some((start, run_peak)) => {
+                runs.push((start, index, run_peak));
+                current = None;
+            }
+            None => {}
+        }
+    }
+    if let Some((start, run_peak)) = current {
+        runs.push((start, levels.len(), run_peak));
+    }
+
+    let mut merged: Vec<(usize, usize, f32)> = Vec::new();
+    for run in runs {
+        match merged.last_mut() {
+            Some(last) if run.0 - last.1 < min_gap => {
+                last.1 = run.1;
+                last.2 = last.2.max(run.2);
+            }
+            _ => merged.push(run),
+        }
+    }
+
+    merged
+        .into_iter()
+        .filter(|(start, end, _)| end - start >= min_length)
+        .map(|(start, end, peak_db)| Event {
+            start: start.saturating_sub(pre_roll) * hop,
+            end: ((end + post_roll) * hop).min(frames),
+            peak_db,
+        })
+        .collect()
+}
+
+// Cut a grab into one-shots at each detected hit, in frames. Each slice starts a touch before its
+// hit and runs up to the next one, minus any tail that has already died away under `floor`.
+pub fn slice_hits(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    sensitivity_db: f32,
+    floor: f32,
+) -> Vec<(usize, usize)> {
+    let channels = channels.max(1);
+    let pre_roll = sample_rate as usize / 200; // 5 ms, so the attack isn't cut
+    let tail = sample_rate as usize / 50; // 20 ms left after the last sound above the floor
+    let mut detector = OnsetDetector::new(sample_rate, sensitivity_db, floor);
+    let onsets: Vec<usize> = samples
+        .chunks_exact(channels)
+        .enumerate()
+        .filter(|(_, frame)| detector.process(dsp::peak(frame)))
+        .map(|(index, _)| index.saturating_sub(pre_roll))
+        .collect();
+
+    let frames = samples.len() / channels;
+    onsets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &start)| {
+            let next = onsets.get(index + 1).copied().unwrap_or(frames);
+            let last_sound = (start..next).rev().find(|&frame| {
+                dsp::peak(&samples[frame * channels..(frame + 1) * channels]) >= floor
+            })?;
+            Some((start, (last_sound + tail).min(next)))
+        })
+        .collect()
+}
+
+// Tempo of a grab in BPM, from the autocorrelation of an onset strength envelope (rises in log
+// energy every ~10 ms). Tempos near 120 are preferred, which settles most half/double time
+// ambiguity the way a listener would. None when there isn't enough audio or no clear pulse.
+pub fn detect_tempo(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f32> {
+    let channels = channels.max(1);
+    let hop = 512;
+    let hop_seconds = hop as f32 / sample_rate.max(1) as f32;
+    let energies: Vec<f32> = samples
+        .chunks(hop * channels)
+        .map(|block| (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32 + 1e-10).ln())
+        .collect();
+    if (energies.len() as f32) * hop_seconds < 4.0 {
+        return None; // A couple of bars at least
+    }
+    let novelty: Vec<f32> = energies
+        .windows(2)
+        .map(|w| (w[1] - w[0]).max(0.0))
+        .collect();
+    let mean = novelty.iter().sum::<f32>() / novelty.len() as f32;
+    let novelty: Vec<f32> = novelty.iter().map(|n| n - mean).collect();
+
+    let autocorrelation = |lag: usize| {
+        novelty
+            .iter()
+            .zip(&novelty[lag..])
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+            / (novelty.len() - lag) as f32
+    };
+    let lag_for = |bpm: f32| 60.0 / (bpm * hop_seconds);
+    let (min_lag, max_lag) = (lag_for(200.0) as usize, lag_for(60.0).ceil() as usize);
+    if max_lag + 1 >= novelty.len() / 2 {
+        return None;
+    }
+    let scores: Vec<f32> = (0..=max_lag + 1).map(autocorrelation).collect();
+    let (best, score) = (min_lag.max(1)..=max_lag)
+        .map(|lag| {
+            let bpm = 60.0 / (lag as f32 * hop_seconds);
+            let prior = (-0.5 * (bpm / 120.0).log2().powi(2)).exp();
+            (lag, scores[lag] * prior)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if score <= 0.1 * scores[0] {
+        return None; // Nothing repeats strongly enough to call a beat
+    }
+
+    let (before, at, after) = (scores[best - 1], scores[best], scores[best + 1]);
+    let curve = before + after - 2.0 * at;
+    let shift = if curve.abs() > f32::EPSILON {
+        (before - after) / (2.0 * curve)
+    } else {
+        0.0
+    };
+    Some(60.0 / ((best as f32 + shift.clamp(-0.5, 0.5)) * hop_seconds))
+}
+
+// Krumhansl-Kessler key profiles, how strongly each degree of the scale is felt to belong to
+// the key, from the tonic up
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+const KEY_FFT_SIZE: usize = 8192; // Fine enough to tell semitones apart down to ~100 Hz
+
+// Likely key of a melodic grab, e.g. ("A minor", 0.82): the energy in each pitch class summed
+// over the take (a chroma vector) is correlated with the major and minor profiles in all twelve
+// keys and the best match wins. The score is that correlation. None for silence, or when no key
+// fits well, as for drums and noise.
+pub fn detect_key(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    reference_hz: f32,
+) -> Option<(String, f32)> {
+    let channels = channels.max(1);
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    if mono.len() < KEY_FFT_SIZE {
+        return None;
+    }
+
+    // The pitch class of each bin in the range melodies and chords live in
+    let bin_hz = sample_rate as f32 / KEY_FFT_SIZE as f32;
+    let classes: Vec<Option<usize>> = (0..KEY_FFT_SIZE / 2)
+        .map(|bin| {
+            let hz = bin as f32 * bin_hz;
+            (80.0..=5000.0).contains(&hz).then(|| {
+                let midi = 69.0 + 12.0 * (hz / reference_hz).log2();
+                (midi.round() as i32).rem_euclid(12) as usize
+            })
+        })
+        .collect();
+    let window: Vec<f32> = (0..KEY_FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / KEY_FFT_SIZE as f32).cos())
+        .collect();
+    let fft = FftPlanner::new().plan_fft_forward(KEY_FFT_SIZE);
+    let mut chroma = [0.0f32; 12];
+    let mut spectrum = vec![Complex::new(0.0f32, 0.0); KEY_FFT_SIZE];
+    for start in (0..=mono.len() - KEY_FFT_SIZE).step_by(KEY_FFT_SIZE / 2) {
+        for (value, (&sample, &w)) in spectrum.iter_mut().zip(mono[start..].iter().zip(&window)) {
+            *value = Complex::new(sample * w, 0.0);
+        }
+        fft.process(&mut spectrum);
+        for (value, class) in spectrum.iter().zip(&classes) {
+            if let Some(class) = class {
+                chroma[*class] += value.norm();
+            }
+        }
+    }
+    if chroma.iter().all(|&energy| energy <= f32::EPSILON) {
+        return None;
+    }
+
+    let (name, score) = (0..12)
+        .flat_map(|tonic| {
+            [("major", &MAJOR_PROFILE), ("minor", &MINOR_PROFILE)].map(|(mode, profile)| {
+                let rotated: Vec<f32> = (0..12).map(|i| chroma[(tonic + i) % 12]).collect();
+                (
+                    format!("{} {}", NOTE_NAMES[tonic], mode),
+                    correlation(&rotated, profile),
+                )
+            })
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    (score >= 0.5).then_some((name, score))
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        0.0
+    } else {
+        covariance / (variance_a * variance_b).sqrt()
+    }
+}
@@ -0,0 +1,379 @@
+// Where audio comes from and goes to. The app talks to cpal; tests use the mock, which makes up
+// its input and only runs when asked, so buffering, grabbing and export can be checked without
+// any audio hardware.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, StreamConfig, SupportedBufferSize};
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use crate::RecorderError;
+
+pub trait AudioBackend {
+    type Stream; // Audio keeps flowing until this is dropped
+
+    fn input_devices(&self) -> Result<Vec<String>, RecorderError>;
+    fn output_devices(&self) -> Result<Vec<String>, RecorderError>;
+
+    // What the named input runs at when left to itself
+    fn input_config(&self, device: &str) -> Result<StreamConfig, RecorderError>;
+
+    // Likewise for the named output, asking for blocks of `block_frames` where the device lets
+    // us choose
+    fn output_config(
+        &self,
+        device: &str,
+        block_frames: Option<u32>,
+    ) -> Result<StreamConfig, RecorderError>;
+
+    // Interleaved f32 blocks from the named input, as they arrive, with when each block's first
+    // frame was captured counted from the first block's. Errors while it runs go to `on_error`.
+    fn build_capture(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        on_data: impl FnMut(&[f32], Duration) + Send + 'static,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self::Stream, RecorderError>;
+
+    // Asks for interleaved f32 blocks to send to the named output
+    fn build_playback(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        fill: impl FnMut(&mut [f32]) + Send + 'static,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self::Stream, RecorderError>;
+}
+
+// The system's default host
+pub struct CpalBackend {
+    host: cpal::Host,
+}
+
+impl CpalBackend {
+    pub fn new() -> Self {
+        CpalBackend {
+            host: cpal::default_host(),
+        }
+    }
+
+    pub fn host_name(&self) -> &'static str {
+        self.host.id().name()
+    }
+
+    fn names(
+        devices: Result<impl Iterator<Item = cpal::Device>, cpal::DevicesError>,
+    ) -> Result<Vec<String>, RecorderError> {
+        let devices = devices.map_err(|e| RecorderError::Device(e.to_string()))?;
+        Ok(devices.filter_map(|device| device.name().ok()).collect())
+    }
+
+    fn find(
+        devices: Result<impl Iterator<Item = cpal::Device>, cpal::DevicesError>,
+        name: &str,
+    ) -> Result<cpal::Device, RecorderError> {
+        devices
+            .map_err(|e| RecorderError::Device(e.to_string()))?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| RecorderError::Device(format!("{} is not connected", name)))
+    }
+
+    // Only f32 streams are opened, so the callbacks never convert
+    fn check_format(
+        config: &cpal::SupportedStreamConfig,
+        direction: &str,
+    ) -> Result<(), RecorderError> {
+        if config.sample_format() == cpal::SampleFormat::F32 {
+            return Ok(());
+        }
+        Err(RecorderError::Device(format!(
+            "unsupported {} format {}",
+            direction,
+            config.sample_format()
+        )))
+    }
+}
+
+impl Default for CpalBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioBackend for CpalBackend {
+    type Stream = cpal::Stream;
+
+    fn input_devices(&self) -> Result<Vec<String>, RecorderError> {
+        Self::names(self.host.input_devices())
+    }
+
+    fn output_devices(&self) -> Result<Vec<String>, RecorderError> {
+        Self::names(self.host.output_devices())
+    }
+
+    fn input_config(&self, device: &str) -> Result<StreamConfig, RecorderError> {
+        let device = Self::find(self.host.input_devices(), device)?;
+        let config = device
+            .default_input_config()
+            .map_err(|e| RecorderError::Device(format!("no input configuration ({})", e)))?;
+        Self::check_format(&config, "input")?;
+        Ok(config.into())
+    }
+
+    fn output_config(
+        &self,
+        device: &str,
+        block_frames: Option<u32>,
+    ) -> Result<StreamConfig, RecorderError> {
+        let device = Self::find(self.host.output_devices(), device)?;
+        let config = device
+            .default_output_config()
+            .map_err(|e| RecorderError::Device(format!("no output configuration ({})", e)))?;
+        Self::check_format(&config, "output")?;
+        let buffer_size = match (block_frames, config.buffer_size()) {
+            (Some(frames), SupportedBufferSize::Range { min, max }) => {
+                BufferSize::Fixed(frames.clamp(*min, *max))
+            }
+            _ => BufferSize::Default,
+        };
+        Ok(StreamConfig {
+            buffer_size,
+            ..config.into()
+        })
+    }
+
+    fn build_capture(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        mut on_data: impl FnMut(&[f32], Duration) + Send + 'static,
+        mut on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<cpal::Stream, RecorderError> {
+        let device = Self::find(self.host.input_devices(), device)?;
+        let mut first = None;
+        let stream = device
+            .build_input_stream(
+                config,
+                move |data: &[f32], info: &cpal::InputCallbackInfo| {
+                    let capture = info.timestamp().capture;
+                    let first = *first.get_or_insert(capture);
+                    on_data(data, capture.duration_since(&first).unwrap_or_default());
+                },
+                move |e| on_error(e.to_string()),
+                None,
+            )
+            .map_err(|e| RecorderError::Device(format!("Failed to open the input: {}", e)))?;
+        stream
+            .play()
+            .map_err(|e| RecorderError::Stream(e.to_string()))?;
+        Ok(stream)
+    }
+
+    fn build_playback(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        mut fill: impl FnMut(&mut [f32]) + Send + 'static,
+        mut on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<cpal::Stream, RecorderError> {
+        let device = Self::find(self.host.output_devices(), device)?;
+        let stream = device
+            .build_output_stream(
+                config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| fill(data),
+                move |e| on_error(e.to_string()),
+                None,
+            )
+            .map_err(|e| RecorderError::Device(format!("Failed to open the output: {}", e)))?;
+        stream
+            .play()
+            .map_err(|e| RecorderError::Stream(e.to_string()))?;
+        Ok(stream)
+    }
+}
+
+// What the mock input plays, the same on every channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+    Silence,
+    Sine { hz: f32, amplitude: f32 },
+    // A single full sample every so many frames, starting with the first
+    Clicks { every_frames: u64, amplitude: f32 },
+}
+
+impl Signal {
+    pub fn sample(&self, frame: u64, sample_rate: u32) -> f32 {
+        match *self {
+            Signal::Silence => 0.0,
+            Signal::Sine { hz, amplitude } => {
+                // Phase from the frame number alone, so a long run doesn't drift
+                let cycles = (frame as f64 * hz as f64 / sample_rate as f64).fract();
+                amplitude * (TAU * cycles as f32).sin()
+            }
+            Signal::Clicks {
+                every_frames,
+                amplitude,
+            } => {
+                if frame.is_multiple_of(every_frames.max(1)) {
+                    amplitude
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+// Devices that exist by name only. Nothing runs by itself: the test pumps a capture stream or
+// pulls from a playback one, so every run sees exactly the same audio.
+pub struct MockBackend {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub signal: Signal,
+    pub config: StreamConfig, // What every mock input reports, 48 kHz stereo unless changed
+}
+
+impl MockBackend {
+    pub fn new(signal: Signal) -> Self {
+        MockBackend {
+            inputs: vec!["Mock Input".to_owned()],
+            outputs: vec!["Mock Output".to_owned()],
+            signal,
+            config: StreamConfig {
+                channels: 2,
+                sample_rate: cpal::SampleRate(48000),
+                buffer_size: cpal::BufferSize::Default,
+            },
+        }
+    }
+
+    fn check(devices: &[String], device: &str) -> Result<(), RecorderError> {
+        if devices.iter().any(|name| name == device) {
+            Ok(())
+        } else {
+            Err(RecorderError::Device(format!(
+                "{} is not connected",
+                device
+            )))
+        }
+    }
+}
+
+type CaptureCallback = Box<dyn FnMut(&[f32], Duration) + Send>;
+type PlaybackCallback = Box<dyn FnMut(&mut [f32]) + Send>;
+
+enum Callback {
+    Capture(CaptureCallback),
+    Playback(PlaybackCallback),
+}
+
+pub struct MockStream {
+    callback: Callback,
+    signal: Signal,
+    channels: usize,
+    sample_rate: u32,
+    frame: u64, // Frames delivered so far
+}
+
+impl MockStream {
+    // Feed the next `frames` frames of the signal to a capture callback
+    pub fn pump(&mut self, frames: usize) {
+        let Callback::Capture(on_data) = &mut self.callback else {
+            return;
+        };
+        let block: Vec<f32> = (self.frame..self.frame + frames as u64)
+            .flat_map(|frame| {
+                let sample = self.signal.sample(frame, self.sample_rate);
+                std::iter::repeat_n(sample, self.channels)
+            })
+            .collect();
+        let captured = Duration::from_secs_f64(self.frame as f64 / self.sample_rate as f64);
+        self.frame += frames as u64;
+        on_data(&block, captured);
+    }
+
+    // Lose the next `frames` frames, as a dropout in a real input would
+    pub fn skip(&mut self, frames: usize) {
+        self.frame += frames as u64;
+    }
+
+    // Ask a playback callback for the next `frames` frames
+    pub fn pull(&mut self, frames: usize) -> Vec<f32> {
+        let Callback::Playback(fill) = &mut self.callback else {
+            return Vec::new();
+        };
+        let mut block = vec![0.0; frames * self.channels];
+        fill(&mut block);
+        self.frame += frames as u64;
+        block
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frame
+    }
+}
+
+impl AudioBackend for MockBackend {
+    type Stream = MockStream;
+
+    fn input_devices(&self) -> Result<Vec<String>, RecorderError> {
+        Ok(self.inputs.clone())
+    }
+
+    fn output_devices(&self) -> Result<Vec<String>, RecorderError> {
+        Ok(self.outputs.clone())
+    }
+
+    fn input_config(&self, device: &str) -> Result<StreamConfig, RecorderError> {
+        Self::check(&self.inputs, device)?;
+        Ok(self.config.clone())
+    }
+
+    fn output_config(
+        &self,
+        device: &str,
+        block_frames: Option<u32>,
+    ) -> Result<StreamConfig, RecorderError> {
+        Self::check(&self.outputs, device)?;
+        Ok(StreamConfig {
+            buffer_size: block_frames.map_or(BufferSize::Default, BufferSize::Fixed),
+            ..self.config.clone()
+        })
+    }
+
+    // The mock never fails once it's open, so `on_error` is never called
+    fn build_capture(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        on_data: impl FnMut(&[f32], Duration) + Send + 'static,
+        _on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<MockStream, RecorderError> {
+        Self::check(&self.inputs, device)?;
+        Ok(MockStream {
+            callback: Callback::Capture(Box::new(on_data)),
+            signal: self.signal,
+            channels: config.channels.max(1) as usize,
+            sample_rate: config.sample_rate.0,
+            frame: 0,
+        })
+    }
+
+    fn build_playback(
+        &self,
+        device: &str,
+        config: &StreamConfig,
+        fill: impl FnMut(&mut [f32]) + Send + 'static,
+        _on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<MockStream, RecorderError> {
+        Self::check(&self.outputs, device)?;
+        Ok(MockStream {
+            callback: Callback::Playback(Box::new(fill)),
+            signal: Signal::Silence,
+            channels: config.channels.max(1) as usize,
+            sample_rate: config.sample_rate.0,
+            frame: 0,
+        })
+    }
+}
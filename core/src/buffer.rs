@@ -0,0 +1,202 @@
+// The rolling history that grabs are cut from: a ring of interleaved samples that switches to
+// a growing static buffer when a grab starts, so nothing is lost while it's being saved
+
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct CircularBuffer {
+    pub circular_buffer: Vec<f32>,
+    pub static_buffer: Vec<f32>,
+    pub max_size: usize,
+    pub write_pos: usize,
+    pub current_size: usize,
+    pub is_static_mode: bool,
+    pub arm_threshold: Option<f32>, // When set, nothing is buffered until a sample reaches this level
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub onset_time: Option<DateTime<Utc>>, // When the armed threshold was crossed
+    pub vox: Option<Vox>,                  // Voice activated recording, when enabled
+    pub vox_finished: bool, // Set once a VOX recording has gone quiet for the hang time
+    pub id: u64,            // Tells buffers apart when one is swapped in for another
+    pub total_written: u64, // Samples added since the buffer was created
+    pub static_start: u64,  // total_written when the grab started
+}
+
+static NEXT_BUFFER_ID: AtomicU64 = AtomicU64::new(0);
+
+// Voice activation: go static when the signal rises above the threshold and report back once it
+// has stayed below it for the hang time
+pub struct Vox {
+    threshold: f32,
+    hang_frames: usize,
+    pre_roll_frames: usize,
+    quiet_frames: usize,
+}
+
+impl CircularBuffer {
+    pub fn new(max_size: usize) -> Self {
+        CircularBuffer {
+            circular_buffer: Vec::with_capacity(max_size),
+            static_buffer: Vec::new(), // Start with an empty static buffer
+            max_size,
+            write_pos: 0,
+            current_size: 0,
+            is_static_mode: false,
+            arm_threshold: None,
+            channels: 1,
+            sample_rate: 0,
+            onset_time: None,
+            vox: None,
+            vox_finished: false,
+            id: NEXT_BUFFER_ID.fetch_add(1, Ordering::Relaxed),
+            total_written: 0,
+            static_start: 0,
+        }
+    }
+
+    pub fn arm(&mut self, threshold: f32, channels: usize, sample_rate: u32) {
+        self.arm_threshold = Some(threshold);
+        self.channels = channels.max(1);
+        self.sample_rate = sample_rate;
+        self.onset_time = None;
+    }
+
+    pub fn enable_vox(
+        &mut self,
+        threshold: f32,
+        hang_frames: usize,
+        pre_roll_frames: usize,
+        channels: usize,
+    ) {
+        self.channels = channels.max(1);
+        self.vox = Some(Vox {
+            threshold,
+            hang_frames,
+            pre_roll_frames,
+            quiet_frames: 0,
+        });
+    }
+
+    // Track the VOX state for a block of incoming samples
+    fn update_vox(&mut self, samples: &[f32]) {
+        let channels = self.channels;
+        let Some(vox) = &mut self.vox else {
+            return;
+        };
+        let threshold = vox.threshold;
+
+        if !self.is_static_mode {
+            if samples.iter().any(|s| s.abs() >= threshold) {
+                println!("VOX triggered");
+                let pre_roll = vox.pre_roll_frames * channels;
+                vox.quiet_frames = 0;
+                self.onset_time = Some(Utc::now());
+                self.start_static_mode();
+                // Only keep a short pre-roll instead of the whole history
+                let excess = self.static_buffer.len().saturating_sub(pre_roll);
+                self.static_buffer.drain(..excess);
+                self.current_size = self.static_buffer.len();
+            }
+        } else if !self.vox_finished {
+            for frame in samples.chunks(channels) {
+                if frame.iter().all(|s| s.abs() < threshold) {
+                    vox.quiet_frames += 1;
+                } else {
+                    vox.quiet_frames = 0;
+                }
+            }
+            self.vox_finished = vox.quiet_frames >= vox.hang_frames;
+        }
+    }
+
+    pub fn disarm(&mut self) {
+        self.arm_threshold = None;
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.arm_threshold.is_some()
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        let samples = match self.arm_threshold {
+            Some(threshold) => match samples.iter().position(|s| s.abs() >= threshold) {
+                Some(pos) => {
+                    // Start from the beginning of the frame containing the hit so the
+                    // channels stay interleaved correctly
+                    let start = pos - pos % self.channels;
+                    let frames_since_onset = (samples.len() - start) / self.channels;
+                    let micros =
+                        frames_since_onset as i64 * 1_000_000 / self.sample_rate.max(1) as i64;
+                    self.onset_time = Some(Utc::now() - Duration::microseconds(micros));
+                    self.start_static_mode();
+                    &samples[start..]
+                }
+                None => return, // Still waiting for signal, drop everything
+            },
+            None => samples,
+        };
+        self.update_vox(samples);
+        self.total_written += samples.len() as u64;
+
+        if self.is_static_mode {
+            // In static mode, add samples to the static buffer
+            self.static_buffer.extend_from_slice(samples);
+            self.current_size += samples.len();
+        } else {
+            // In circular mode, overwrite old data if necessary
+            for &sample in samples {
+                if self.current_size < self.max_size {
+                    self.circular_buffer.push(sample);
+                    self.current_size += 1;
+                } else {
+                    self.circular_buffer[self.write_pos] = sample;
+                }
+                self.write_pos = (self.write_pos + 1) % self.max_size;
+            }
+        }
+    }
+
+    pub fn start_static_mode(&mut self) {
+        if self.is_static_mode {
+            return;
+        }
+        self.is_static_mode = true;
+        self.static_start = self.total_written;
+        self.arm_threshold = None; // A manual grab overrides waiting for signal
+
+        println!("Transitioning to static mode");
+
+        // Move the ring into the static buffer in time order. Rotating in place rather than
+        // copying means a long buffer is never held twice.
+        let mut history = std::mem::take(&mut self.circular_buffer);
+        if self.current_size >= self.max_size {
+            history.rotate_left(self.write_pos);
+        }
+        history.append(&mut self.static_buffer);
+        self.static_buffer = history;
+    }
+
+    // Absolute index (see `total_written`) of the oldest sample still held
+    pub fn window_start(&self) -> u64 {
+        self.total_written - self.current_size as u64
+    }
+
+    // Samples between two absolute indices, limited to what is still in the window
+    pub fn copy_range(&self, start: u64, end: u64) -> Vec<f32> {
+        let start = start.max(self.window_start());
+        let end = end.min(self.total_written);
+        (start..end).map(|index| self.sample_at(index)).collect()
+    }
+
+    // Sample by absolute index, which must be within the window
+    pub fn sample_at(&self, index: u64) -> f32 {
+        let offset = (index - self.window_start()) as usize;
+        if self.is_static_mode {
+            self.static_buffer[offset]
+        } else if self.current_size < self.max_size {
+            self.circular_buffer[offset]
+        } else {
+            self.circular_buffer[(self.write_pos + offset) % self.max_size]
+        }
+    }
+}
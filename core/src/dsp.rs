@@ -0,0 +1,776 @@
+// Signal processing building blocks shared by the capture and export paths
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Second order IIR filter (RBJ cookbook), transposed direct form II
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn from_coefficients(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn high_pass(sample_rate: f32, cutoff_hz: f32, q: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Biquad::from_coefficients(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn low_pass(sample_rate: f32, cutoff_hz: f32, q: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Biquad::from_coefficients(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    // Shelves with the cookbook's steepest slope (S = 1) that doesn't overshoot
+    pub fn low_shelf(sample_rate: f32, corner_hz: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * corner_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let beta = a.sqrt() * sin * std::f32::consts::SQRT_2; // 2 * sqrt(A) * alpha
+        Biquad::from_coefficients(
+            a * ((a + 1.0) - (a - 1.0) * cos + beta),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - beta),
+            (a + 1.0) + (a - 1.0) * cos + beta,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - beta,
+        )
+    }
+
+    pub fn high_shelf(sample_rate: f32, corner_hz: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * corner_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let beta = a.sqrt() * sin * std::f32::consts::SQRT_2; // 2 * sqrt(A) * alpha
+        Biquad::from_coefficients(
+            a * ((a + 1.0) + (a - 1.0) * cos + beta),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - beta),
+            (a + 1.0) - (a - 1.0) * cos + beta,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - beta,
+        )
+    }
+
+    pub fn peaking(sample_rate: f32, centre_hz: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * centre_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Biquad::from_coefficients(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    // The two stages of the ITU-R BS.1770 K-weighting curve: a shelf of about +4 dB above
+    // 1.5 kHz for the head, then a high-pass around 38 Hz
+    pub fn k_weighting(sample_rate: f32) -> [Biquad; 2] {
+        let k = (PI * 1681.974 / sample_rate).tan();
+        let q = 0.707_175_2;
+        let vh = db_to_gain(3.999_844);
+        let vb = vh.powf(0.499_666_8);
+        let shelf = Biquad::from_coefficients(
+            vh + vb * k / q + k * k,
+            2.0 * (k * k - vh),
+            vh - vb * k / q + k * k,
+            1.0 + k / q + k * k,
+            2.0 * (k * k - 1.0),
+            1.0 - k / q + k * k,
+        );
+        let k = (PI * 38.13547 / sample_rate).tan();
+        let q = 0.500_327;
+        let high_pass = Biquad::from_coefficients(
+            1.0,
+            -2.0,
+            1.0,
+            1.0 + k / q + k * k,
+            2.0 * (k * k - 1.0),
+            1.0 - k / q + k * k,
+        );
+        [shelf, high_pass]
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+// Q for a maximally flat (Butterworth) second order response
+pub const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+// One filter per channel over interleaved audio, keeping its state between blocks
+pub struct InterleavedFilter {
+    filters: Vec<Biquad>,
+}
+
+impl InterleavedFilter {
+    pub fn new(channels: usize, make_filter: impl Fn() -> Biquad) -> Self {
+        InterleavedFilter {
+            filters: (0..channels).map(|_| make_filter()).collect(),
+        }
+    }
+
+    // New settings for every channel without allocating, e.g. from an audio callback. The
+    // filter state carries over so the change doesn't click.
+    pub fn retune(&mut self, make_filter: impl Fn() -> Biquad) {
+        for filter in &mut self.filters {
+            let (z1, z2) = (filter.z1, filter.z2);
+            *filter = Biquad {
+                z1,
+                z2,
+                ..make_filter()
+            };
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.filters.len();
+        for frame in samples.chunks_exact_mut(channels) {
+            for (sample, filter) in frame.iter_mut().zip(self.filters.iter_mut()) {
+                *sample = filter.process(*sample);
+            }
+        }
+    }
+}
+
+// Measures the DC offset on each channel of interleaved audio and, when asked, takes it out with
+// a one-pole high-pass just a few Hz up, too low to touch anything audible
+pub struct DcBlocker {
+    previous_in: Vec<f32>,
+    previous_out: Vec<f32>,
+    offsets: Vec<f32>, // Long running average of each channel
+    pole: f32,
+    average: f32,
+}
+
+impl DcBlocker {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let rate = sample_rate.max(1) as f32;
+        DcBlocker {
+            previous_in: vec![0.0; channels],
+            previous_out: vec![0.0; channels],
+            offsets: vec![0.0; channels],
+            pole: 1.0 - 2.0 * PI * 5.0 / rate,
+            average: 1.0 - (-1.0 / rate).exp(), // About a second
+        }
+    }
+
+    pub fn measure(&mut self, samples: &[f32]) {
+        let channels = self.offsets.len();
+        for frame in samples.chunks_exact(channels) {
+            for (offset, &sample) in self.offsets.iter_mut().zip(frame) {
+                *offset += (sample - *offset) * self.average;
+            }
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.offsets.len();
+        for frame in samples.chunks_exact_mut(channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let out =
+                    *sample - self.previous_in[channel] + self.pole * self.previous_out[channel];
+                self.previous_in[channel] = *sample;
+                self.previous_out[channel] = out;
+                *sample = out;
+            }
+        }
+    }
+
+    // The largest offset on any channel, keeping its sign
+    pub fn offset(&self) -> f32 {
+        self.offsets.iter().fold(
+            0.0f32,
+            |worst, &o| if o.abs() > worst.abs() { o } else { worst },
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GateSettings {
+    pub threshold_db: f32,
+    pub attack_ms: f32,
+    pub hold_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for GateSettings {
+    fn default() -> Self {
+        GateSettings {
+            threshold_db: -50.0,
+            attack_ms: 1.0,
+            hold_ms: 150.0,
+            release_ms: 200.0,
+        }
+    }
+}
+
+// For handing to a running gate, see `live::LiveSettings`
+impl From<GateSettings> for [f32; 4] {
+    fn from(settings: GateSettings) -> Self {
+        [
+            settings.threshold_db,
+            settings.attack_ms,
+            settings.hold_ms,
+            settings.release_ms,
+        ]
+    }
+}
+
+impl From<[f32; 4]> for GateSettings {
+    fn from([threshold_db, attack_ms, hold_ms, release_ms]: [f32; 4]) -> Self {
+        GateSettings {
+            threshold_db,
+            attack_ms,
+            hold_ms,
+            release_ms,
+        }
+    }
+}
+
+// Band gains of the monitoring EQ, in dB
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EqSettings {
+    pub low_db: f32,
+    pub mid_db: f32,
+    pub high_db: f32,
+}
+
+impl From<EqSettings> for [f32; 3] {
+    fn from(settings: EqSettings) -> Self {
+        [settings.low_db, settings.mid_db, settings.high_db]
+    }
+}
+
+impl From<[f32; 3]> for EqSettings {
+    fn from([low_db, mid_db, high_db]: [f32; 3]) -> Self {
+        EqSettings {
+            low_db,
+            mid_db,
+            high_db,
+        }
+    }
+}
+
+// Fixed band centres, like the EQ on a small mixer channel
+const EQ_LOW_HZ: f32 = 120.0;
+const EQ_MID_HZ: f32 = 1000.0;
+const EQ_MID_Q: f32 = 0.7;
+const EQ_HIGH_HZ: f32 = 6000.0;
+
+// Low shelf, mid bell and high shelf over interleaved audio
+pub struct ThreeBandEq {
+    sample_rate: f32,
+    low: InterleavedFilter,
+    mid: InterleavedFilter,
+    high: InterleavedFilter,
+}
+
+impl ThreeBandEq {
+    pub fn new(channels: usize, sample_rate: u32, settings: EqSettings) -> Self {
+        let flat = || Biquad::peaking(sample_rate as f32, EQ_MID_HZ, EQ_MID_Q, 0.0);
+        let mut eq = ThreeBandEq {
+            sample_rate: sample_rate as f32,
+            low: InterleavedFilter::new(channels.max(1), flat),
+            mid: InterleavedFilter::new(channels.max(1), flat),
+            high: InterleavedFilter::new(channels.max(1), flat),
+        };
+        eq.configure(settings);
+        eq
+    }
+
+    // New gains without allocating or resetting the filters, so it can change while running
+    pub fn configure(&mut self, settings: EqSettings) {
+        let rate = self.sample_rate;
+        self.low
+            .retune(|| Biquad::low_shelf(rate, EQ_LOW_HZ, settings.low_db));
+        self.mid
+            .retune(|| Biquad::peaking(rate, EQ_MID_HZ, EQ_MID_Q, settings.mid_db));
+        self.high
+            .retune(|| Biquad::high_shelf(rate, EQ_HIGH_HZ, settings.high_db));
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        self.low.process(samples);
+        self.mid.process(samples);
+        self.high.process(samples);
+    }
+}
+
+// Silences the room noise between phrases: opens when the loudest channel crosses the
+// threshold, stays open for the hold time after it drops back, then fades out over the release
+pub struct NoiseGate {
+    channels: usize,
+    sample_rate: f32,
+    threshold: f32,
+    attack: f32,
+    release: f32,
+    hold_frames: usize,
+    held: usize, // Frames of hold left
+    gain: f32,
+}
+
+impl NoiseGate {
+    pub fn new(channels: usize, sample_rate: u32, settings: GateSettings) -> Self {
+        let mut gate = NoiseGate {
+            channels: channels.max(1),
+            sample_rate: sample_rate.max(1) as f32,
+            threshold: 0.0,
+            attack: 1.0,
+            release: 1.0,
+            hold_frames: 0,
+            held: 0,
+            gain: 0.0,
+        };
+        gate.configure(settings);
+        gate
+    }
+
+    // New settings without losing where the gate is, so it can change while running
+    pub fn configure(&mut self, settings: GateSettings) {
+        let coeff = |ms: f32| 1.0 - (-1000.0 / (ms.max(0.01) * self.sample_rate)).exp();
+        self.threshold = db_to_gain(settings.threshold_db);
+        self.attack = coeff(settings.attack_ms);
+        self.release = coeff(settings.release_ms);
+        self.hold_frames = (settings.hold_ms.max(0.0) / 1000.0 * self.sample_rate) as usize;
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            if peak(frame) >= self.threshold {
+                self.held = self.hold_frames.max(1);
+            }
+            let open = self.held > 0;
+            self.held = self.held.saturating_sub(1);
+            if open {
+                self.gain += (1.0 - self.gain) * self.attack;
+            } else {
+                self.gain -= self.gain * self.release;
+            }
+            frame.iter_mut().for_each(|s| *s *= self.gain);
+        }
+    }
+}
+
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// The frame nearest `frame`, at most `max_distance` away, where the sum of the channels crosses
+// zero, so a cut there doesn't click. `frame` itself if there isn't one.
+pub fn nearest_zero_crossing(
+    samples: &[f32],
+    channels: usize,
+    frame: usize,
+    max_distance: usize,
+) -> usize {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let sum = |i: usize| -> f32 { samples[i * channels..(i + 1) * channels].iter().sum() };
+    let crossing =
+        |i: usize| i == 0 || i >= frames || sum(i) == 0.0 || (sum(i - 1) < 0.0) != (sum(i) < 0.0);
+    (0..=max_distance)
+        .flat_map(|d| [frame.checked_add(d), frame.checked_sub(d)])
+        .flatten()
+        .filter(|&i| i <= frames)
+        .find(|&i| crossing(i))
+        .unwrap_or(frame)
+}
+
+// Equal-power crossfade of the end of a loop into `lead_in`, the audio just before the loop
+// starts, so that wrapping from the last frame back to the first carries on as the recording
+// did. The fade is as long as `lead_in`.
+pub fn loop_crossfade(looped: &mut [f32], lead_in: &[f32], channels: usize) {
+    let channels = channels.max(1);
+    let fade_frames = (lead_in.len() / channels).min(looped.len() / channels);
+    let tail = looped.len() - fade_frames * channels;
+    for (i, (tail_frame, lead_frame)) in looped[tail..]
+        .chunks_mut(channels)
+        .zip(lead_in.chunks(channels))
+        .enumerate()
+    {
+        let angle = (i as f32 + 0.5) / fade_frames as f32 * PI / 2.0;
+        let (fade_out, fade_in) = (angle.cos(), angle.sin());
+        for (sample, &lead) in tail_frame.iter_mut().zip(lead_frame) {
+            *sample = *sample * fade_out + lead * fade_in;
+        }
+    }
+}
+
+// Repair clicks and crackle (e.g. from vinyl) by finding samples that jump far away from the
+// local median and interpolating across them. Strength goes from 0 (only the most obvious
+// clicks) to 1 (aggressive, may start to soften real transients).
+pub fn declick(samples: &mut [f32], channels: usize, sample_rate: u32, strength: f32) {
+    let strength = strength.clamp(0.0, 1.0);
+    let threshold = 20.0 - 14.0 * strength; // Multiple of the local residual level
+    let max_click_len = (sample_rate as usize / 500).max(4); // Longer than ~2 ms is not a click
+    const SCALE_WINDOW: usize = 1024;
+    const MARGIN: usize = 2;
+
+    for channel in 0..channels {
+        let signal: Vec<f32> = samples
+            .iter()
+            .skip(channel)
+            .step_by(channels)
+            .copied()
+            .collect();
+        let len = signal.len();
+        if len < 5 {
+            continue;
+        }
+
+        // Residual from a 5 point median, which follows the music but not the spikes
+        let residual: Vec<f32> = (0..len)
+            .map(|n| {
+                let mut window = [0.0f32; 5];
+                for (i, w) in window.iter_mut().enumerate() {
+                    *w = signal[(n + i).saturating_sub(2).min(len - 1)];
+                }
+                window.sort_by(|a, b| a.total_cmp(b));
+                (signal[n] - window[2]).abs()
+            })
+            .collect();
+
+        // Prefix sums for a cheap moving average of the residual
+        let mut prefix = Vec::with_capacity(len + 1);
+        prefix.push(0.0f64);
+        for &value in &residual {
+            prefix.push(prefix[prefix.len() - 1] + value as f64);
+        }
+
+        let mut is_click = vec![false; len];
+        for (n, &value) in residual.iter().enumerate() {
+            let start = n.saturating_sub(SCALE_WINDOW / 2);
+            let end = (n + SCALE_WINDOW / 2).min(len);
+            let local = ((prefix[end] - prefix[start]) / (end - start) as f64) as f32;
+            if value > threshold * local.max(1e-4) {
+                let from = n.saturating_sub(MARGIN);
+                let to = (n + MARGIN).min(len - 1);
+                is_click[from..=to].iter_mut().for_each(|c| *c = true);
+            }
+        }
+
+        let mut repaired = signal.clone();
+        let mut n = 0;
+        while n < len {
+            if !is_click[n] {
+                n += 1;
+                continue;
+            }
+            let start = n;
+            while n < len && is_click[n] {
+                n += 1;
+            }
+            if n - start > max_click_len {
+                continue;
+            }
+            // Interpolate between the good samples either side of the click
+            let before = if start > 0 {
+                signal[start - 1]
+            } else {
+                signal[n.min(len - 1)]
+            };
+            let after = if n < len { signal[n] } else { before };
+            let span = (n - start + 1) as f32;
+            for (i, sample) in repaired[start..n].iter_mut().enumerate() {
+                let t = (i + 1) as f32 / span;
+                *sample = before + (after - before) * t;
+            }
+        }
+
+        for (frame, value) in samples.chunks_exact_mut(channels).zip(repaired) {
+            frame[channel] = value;
+        }
+    }
+}
+
+// De-clicking a block at a time: each block is repaired along with enough of the audio either
+// side of it that the result matches running `declick` over the whole take
+pub struct Declicker {
+    channels: usize,
+    sample_rate: u32,
+    strength: f32,
+    pending: Vec<f32>, // Context already emitted, then audio still to be emitted
+    start: usize,      // Where the audio still to be emitted begins in `pending`
+}
+
+impl Declicker {
+    // Covers the local level window plus the longest click, with some to spare
+    const CONTEXT_FRAMES: usize = 2048;
+
+    pub fn new(channels: usize, sample_rate: u32, strength: f32) -> Self {
+        Declicker {
+            channels: channels.max(1),
+            sample_rate,
+            strength,
+            pending: Vec::new(),
+            start: 0,
+        }
+    }
+
+    // Returns the repaired audio that is ready, which lags the input by the context length
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend_from_slice(samples);
+        let context = Self::CONTEXT_FRAMES * self.channels;
+        let ready = self.pending.len().saturating_sub(context);
+        if ready <= self.start {
+            return Vec::new();
+        }
+        let repaired = self.repair(ready);
+        let keep_from = ready.saturating_sub(context);
+        self.pending.drain(..keep_from);
+        self.start = ready - keep_from;
+        repaired
+    }
+
+    // Everything still held back, once there is no more input
+    pub fn finish(&mut self) -> Vec<f32> {
+        let repaired = self.repair(self.pending.len());
+        self.pending.clear();
+        self.start = 0;
+        repaired
+    }
+
+    fn repair(&self, end: usize) -> Vec<f32> {
+        let mut window = self.pending.clone();
+        declick(&mut window, self.channels, self.sample_rate, self.strength);
+        window[self.start..end].to_vec()
+    }
+}
+
+// Tape-style speed change (pitch follows speed) by linear interpolation, a block at a time.
+// A ratio of 2 plays twice as fast, 0.5 half as fast.
+pub struct Varispeed {
+    channels: usize,
+    ratio: f64,
+    position: f64,  // Read position in frames, relative to the start of `tail`
+    tail: Vec<f32>, // Last frame of the previous block, needed to interpolate across blocks
+    anti_alias: Vec<InterleavedFilter>,
+}
+
+impl Varispeed {
+    pub fn new(channels: usize, sample_rate: u32, ratio: f32) -> Self {
+        let channels = channels.max(1);
+        // Speeding up folds everything above the new Nyquist back down, so filter it out first
+        let anti_alias = if ratio > 1.0 {
+            let cutoff = 0.45 * sample_rate as f32 / ratio;
+            (0..2)
+                .map(|_| {
+                    InterleavedFilter::new(channels, || {
+                        Biquad::low_pass(sample_rate as f32, cutoff, BUTTERWORTH_Q)
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Varispeed {
+            channels,
+            ratio: ratio.max(0.01) as f64,
+            position: 0.0,
+            tail: Vec::new(),
+            anti_alias,
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        let mut frames = std::mem::take(&mut self.tail);
+        let start = frames.len();
+        frames.extend_from_slice(samples);
+        for filter in &mut self.anti_alias {
+            filter.process(&mut frames[start..]);
+        }
+
+        let count = frames.len() / channels;
+        let mut output = Vec::new();
+        while self.position + 1.0 < count as f64 {
+            let index = self.position as usize;
+            let t = (self.position - index as f64) as f32;
+            let (a, b) = (index * channels, (index + 1) * channels);
+            for channel in 0..channels {
+                let (from, to) = (frames[a + channel], frames[b + channel]);
+                output.push(from + (to - from) * t);
+            }
+            self.position += self.ratio;
+        }
+        if count > 0 {
+            self.position -= (count - 1) as f64;
+            self.tail = frames[(count - 1) * channels..].to_vec();
+        }
+        output
+    }
+}
+
+// Spots percussive hits as the level jumping well above its recent average. `sensitivity_db` is
+// how sudden the jump has to be, `floor` the quietest level that counts as a hit at all.
+pub struct OnsetDetector {
+    fast: f32, // Peak follower with a short release
+    slow: f32, // Slow moving average of the level
+    fast_release: f32,
+    slow_coeff: f32,
+    ratio: f32,
+    floor: f32,
+    refractory_frames: usize, // Ignore the ringing and bounces right after a hit
+    since_onset: usize,
+}
+
+impl OnsetDetector {
+    pub fn new(sample_rate: u32, sensitivity_db: f32, floor: f32) -> Self {
+        let rate = sample_rate.max(1) as f32;
+        let coeff = |seconds: f32| (-1.0 / (seconds * rate)).exp();
+        let refractory_frames = (rate * 0.1) as usize;
+        OnsetDetector {
+            fast: 0.0,
+            slow: 0.0,
+            fast_release: coeff(0.01),
+            slow_coeff: coeff(0.2),
+            ratio: db_to_gain(sensitivity_db),
+            floor,
+            refractory_frames,
+            since_onset: refractory_frames,
+        }
+    }
+
+    // Feed the level of one frame (e.g. the loudest channel), returns true on a hit
+    pub fn process(&mut self, level: f32) -> bool {
+        self.fast = if level > self.fast {
+            level
+        } else {
+            self.fast * self.fast_release
+        };
+        let onset = self.since_onset >= self.refractory_frames
+            && self.fast >= self.floor
+            && self.fast > self.slow.max(1e-6) * self.ratio;
+        self.slow = level + (self.slow - level) * self.slow_coeff;
+        self.since_onset = if onset { 0 } else { self.since_onset + 1 };
+        onset
+    }
+}
+
+// Brickwall peak limiter with a short lookahead, so the gain is already down by the time a
+// transient arrives. The audio is delayed by the lookahead; anything the gain still misses is
+// clipped at the ceiling as a last resort.
+pub struct PeakLimiter {
+    channels: usize,
+    sample_rate: f32,
+    ceiling: f32,
+    delay: Vec<f32>,    // The last `lookahead` frames, interleaved
+    required: Vec<f32>, // Gain each of those frames needs to stay under the ceiling
+    index: usize,
+    gain: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl PeakLimiter {
+    pub fn new(channels: usize, sample_rate: u32, ceiling_db: f32, lookahead_seconds: f32) -> Self {
+        let channels = channels.max(1);
+        let rate = sample_rate.max(1) as f32;
+        let lookahead = ((lookahead_seconds * rate) as usize).max(1);
+        PeakLimiter {
+            channels,
+            sample_rate: rate,
+            ceiling: db_to_gain(ceiling_db),
+            delay: vec![0.0; lookahead * channels],
+            required: vec![1.0; lookahead],
+            index: 0,
+            gain: 1.0,
+            attack: 1.0 - (-5.0 / lookahead as f32).exp(), // Most of the way down within the lookahead
+            release: 1.0 - (-1.0 / (0.1 * rate)).exp(),
+        }
+    }
+
+    pub fn set_ceiling(&mut self, ceiling_db: f32) {
+        self.ceiling = db_to_gain(ceiling_db);
+    }
+
+    // Time constant of the gain coming back up, 100 ms unless set
+    pub fn set_release(&mut self, release_ms: f32) {
+        self.release = 1.0 - (-1000.0 / (release_ms.max(1.0) * self.sample_rate)).exp();
+    }
+
+    pub fn lookahead_frames(&self) -> usize {
+        self.required.len()
+    }
+
+    // Limits interleaved audio in place, returning the lowest gain applied
+    pub fn process(&mut self, samples: &mut [f32]) -> f32 {
+        let channels = self.channels;
+        let mut lowest = 1.0f32;
+        for frame in samples.chunks_exact_mut(channels) {
+            let peak = peak(frame);
+            self.required[self.index] = if peak > self.ceiling {
+                self.ceiling / peak
+            } else {
+                1.0
+            };
+            let target = self.required.iter().fold(1.0f32, |low, &g| low.min(g));
+            let coeff = if target < self.gain {
+                self.attack
+            } else {
+                self.release
+            };
+            self.gain += (target - self.gain) * coeff;
+            lowest = lowest.min(self.gain);
+
+            let delayed = &mut self.delay[self.index * channels..(self.index + 1) * channels];
+            for (sample, slot) in frame.iter_mut().zip(delayed.iter_mut()) {
+                let out = *slot * self.gain;
+                *slot = *sample;
+                *sample = out.clamp(-self.ceiling, self.ceiling);
+            }
+            self.index = (self.index + 1) % self.required.len();
+        }
+        lowest
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+// Failures the user needs to see, sent from the UI thread, the audio callbacks or a writer thread
+#[derive(Debug)]
+pub enum RecorderError {
+    Device(String), // An audio device couldn't be opened or configured
+    Stream(String), // A running stream reported a problem
+    Save(String),   // A take or a copy of it couldn't be written
+    Other(String),
+}
+
+impl RecorderError {
+    // The same kind of error, saying what was being done when it happened
+    pub fn context(self, doing: &str) -> Self {
+        match self {
+            RecorderError::Device(message) => {
+                RecorderError::Device(format!("{}: {}", doing, message))
+            }
+            RecorderError::Stream(message) => {
+                RecorderError::Stream(format!("{}: {}", doing, message))
+            }
+            RecorderError::Save(message) => RecorderError::Save(format!("{}: {}", doing, message)),
+            RecorderError::Other(message) => {
+                RecorderError::Other(format!("{}: {}", doing, message))
+            }
+        }
+    }
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecorderError::Device(message) => write!(f, "Audio device: {}", message),
+            RecorderError::Stream(message) => write!(f, "Audio stream: {}", message),
+            RecorderError::Save(message) => write!(f, "Saving: {}", message),
+            RecorderError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
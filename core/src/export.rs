@@ -0,0 +1,894 @@
+use crate::analysis::{self, LoudnessMeter};
+use crate::dsp::{self, Biquad, Declicker, GateSettings, InterleavedFilter, NoiseGate, Varispeed};
+use crate::error::RecorderError;
+use crate::hotfolder::{self, HotFolder};
+use crate::metadata::{self, Sidecar, TakeAnalysis};
+use crate::pitch;
+use crate::stretch::Stretcher;
+use chrono::{DateTime, Utc};
+use hound::{SampleFormat as HoundSampleFormat, WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+// A processing step applied to a take on the writer thread before it is written
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage")]
+pub enum ExportStage {
+    DeClick { strength: f32 },
+    HighPass { cutoff_hz: f32 },
+    Normalize { peak_db: f32 },
+    Gate(GateSettings),
+    Loudness { lufs: f32 },                       // Integrated loudness target
+    Stretch { source_bpm: f32, target_bpm: f32 }, // Source 0 uses the detected tempo
+    Channels { mode: ChannelMode },
+}
+
+// Ways of rearranging the first two channels, e.g. for decoding a mid/side recording
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ChannelMode {
+    SumToMono, // Every channel averaged into a mono file
+    Swap,
+    MidSideEncode, // Left/right in, mid/side out
+    MidSideDecode, // Mid/side in, left/right out
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 4] = [
+        ChannelMode::SumToMono,
+        ChannelMode::Swap,
+        ChannelMode::MidSideEncode,
+        ChannelMode::MidSideDecode,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelMode::SumToMono => "Sum to mono",
+            ChannelMode::Swap => "Swap left/right",
+            ChannelMode::MidSideEncode => "L/R to mid/side",
+            ChannelMode::MidSideDecode => "Mid/side to L/R",
+        }
+    }
+
+    // Rearrange an interleaved chunk, which becomes mono for `SumToMono`. Anything other than a
+    // sum needs at least two channels and leaves the rest alone.
+    fn process(&self, mut chunk: Vec<f32>, channels: usize) -> Vec<f32> {
+        match self {
+            ChannelMode::SumToMono => {
+                return chunk
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                    .collect();
+            }
+            _ if channels < 2 => {}
+            ChannelMode::Swap => chunk
+                .chunks_mut(channels)
+                .for_each(|frame| frame.swap(0, 1)),
+            ChannelMode::MidSideEncode => {
+                for frame in chunk.chunks_mut(channels) {
+                    let (left, right) = (frame[0], frame[1]);
+                    frame[0] = 0.5 * (left + right);
+                    frame[1] = 0.5 * (left - right);
+                }
+            }
+            ChannelMode::MidSideDecode => {
+                for frame in chunk.chunks_mut(channels) {
+                    let (mid, side) = (frame[0], frame[1]);
+                    frame[0] = mid + side;
+                    frame[1] = mid - side;
+                }
+            }
+        }
+        chunk
+    }
+}
+
+impl ExportStage {
+    // One of each stage with sensible settings, for adding to a chain
+    pub const DEFAULTS: [ExportStage; 7] = [
+        ExportStage::DeClick { strength: 0.5 },
+        ExportStage::HighPass { cutoff_hz: 30.0 },
+        ExportStage::Gate(GateSettings {
+            threshold_db: -50.0,
+            attack_ms: 1.0,
+            hold_ms: 150.0,
+            release_ms: 200.0,
+        }),
+        ExportStage::Normalize { peak_db: -1.0 },
+        ExportStage::Loudness { lufs: -14.0 },
+        ExportStage::Stretch {
+            source_bpm: 0.0,
+            target_bpm: 120.0,
+        },
+        ExportStage::Channels {
+            mode: ChannelMode::SumToMono,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportStage::DeClick { .. } => "De-click",
+            ExportStage::HighPass { .. } => "High-pass",
+            ExportStage::Normalize { .. } => "Normalize",
+            ExportStage::Gate(_) => "Noise gate",
+            ExportStage::Loudness { .. } => "Loudness",
+            ExportStage::Stretch { .. } => "Time-stretch",
+            ExportStage::Channels { .. } => "Channels",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ExportStage::DeClick { strength } => format!("De-click {:.0}%", strength * 100.0),
+            ExportStage::HighPass { cutoff_hz } => format!("High-pass {:.0} Hz", cutoff_hz),
+            ExportStage::Normalize { peak_db } => format!("Normalize to {:.1} dBFS", peak_db),
+            ExportStage::Gate(gate) => format!("Noise gate at {:.0} dBFS", gate.threshold_db),
+            ExportStage::Loudness { lufs } => format!("Loudness to {:.1} LUFS", lufs),
+            ExportStage::Stretch {
+                source_bpm,
+                target_bpm,
+            } if *source_bpm > 0.0 => {
+                format!("Time-stretch {:.1} to {:.1} BPM", source_bpm, target_bpm)
+            }
+            ExportStage::Stretch { target_bpm, .. } => {
+                format!("Time-stretch to {:.1} BPM", target_bpm)
+            }
+            ExportStage::Channels { mode } => mode.label().to_owned(),
+        }
+    }
+}
+
+// An extra render of a grab, saved next to it with a suffix (e.g. `_rev`), as a starting kit for
+// sound design
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "variant")]
+pub enum ExportVariant {
+    Reverse,
+    Speed { ratio: f32 }, // Repitched, like changing the speed of a tape
+}
+
+impl ExportVariant {
+    pub const DEFAULTS: [ExportVariant; 3] = [
+        ExportVariant::Reverse,
+        ExportVariant::Speed { ratio: 0.5 },
+        ExportVariant::Speed { ratio: 2.0 },
+    ];
+
+    pub fn name(&self) -> String {
+        match *self {
+            ExportVariant::Reverse => "Reversed".to_owned(),
+            ExportVariant::Speed { ratio: 0.5 } => "Half speed".to_owned(),
+            ExportVariant::Speed { ratio: 2.0 } => "Double speed".to_owned(),
+            ExportVariant::Speed { ratio } => format!("{}x speed", ratio),
+        }
+    }
+
+    // File name suffix, e.g. "rev", "x05" or "x2"
+    pub fn suffix(&self) -> String {
+        match *self {
+            ExportVariant::Reverse => "rev".to_owned(),
+            ExportVariant::Speed { ratio } => format!("x{}", ratio.to_string().replace('.', "")),
+        }
+    }
+}
+
+// Takes are processed and written this many frames at a time, so exporting never needs a
+// second copy of the whole grab
+const CHUNK_FRAMES: usize = 16384;
+
+// Streaming form of an export stage, built fresh for every pass over a take
+enum Processor {
+    Speed(Varispeed),
+    DeClick(Declicker),
+    Filter(InterleavedFilter),
+    Gate(NoiseGate),
+    Gain(f32),
+    Stretch(Stretcher),
+    Channels(ChannelMode, usize),
+}
+
+impl Processor {
+    fn process(&mut self, mut chunk: Vec<f32>) -> Vec<f32> {
+        match self {
+            Processor::Speed(varispeed) => varispeed.process(&chunk),
+            Processor::DeClick(declicker) => declicker.process(&chunk),
+            Processor::Filter(filter) => {
+                filter.process(&mut chunk);
+                chunk
+            }
+            Processor::Gate(gate) => {
+                gate.process(&mut chunk);
+                chunk
+            }
+            Processor::Gain(gain) => {
+                chunk.iter_mut().for_each(|s| *s *= *gain);
+                chunk
+            }
+            Processor::Stretch(stretcher) => stretcher.process(&chunk),
+            Processor::Channels(mode, channels) => mode.process(chunk, *channels),
+        }
+    }
+
+    // Audio held back for lookahead, once the input has run out
+    fn finish(&mut self) -> Vec<f32> {
+        match self {
+            Processor::DeClick(declicker) => declicker.finish(),
+            Processor::Stretch(stretcher) => stretcher.finish(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Fixes chosen in the mono compatibility prompt, applied as the take is read
+#[derive(Clone, Copy, Debug)]
+pub enum ChannelFix {
+    FlipPolarity(usize),
+    KeepChannel(usize),
+}
+
+// Everything needed to write a grab to disk, detached from the live buffer so capture can carry
+// on while the file is being written
+#[derive(Clone, Default)]
+pub struct Take {
+    pub samples: Arc<Vec<f32>>, // Interleaved, shared with the last grab view rather than copied
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub path: PathBuf,
+    pub name: String,
+    pub preset: String,
+    pub metadata: Vec<(String, String)>,
+    pub onset_time: Option<DateTime<Utc>>,
+    pub stages: Vec<ExportStage>, // Export chain from the preset, applied in order
+    pub channel_fix: Option<ChannelFix>,
+    pub variants: Vec<ExportVariant>, // Extra renders saved alongside this take
+    pub variant: Option<ExportVariant>, // Set on the takes rendering one of those
+    pub hot_folder: Option<HotFolder>, // Also dropped into this DAW folder once saved
+    pub time_reference: Option<u64>,  // Timecode of the first frame as samples since midnight
+    pub post_save_command: Option<String>, // Run on each file once it's saved, see `run_post_save`
+    pub channel_names: Vec<String>,   // One per input channel, for the iXML track list and stems
+    pub armed_channels: Option<Vec<usize>>, // Input channels written, in order, or all of them
+    pub stems: bool,                  // Also save each written channel as a mono file, see `stem`
+}
+
+impl Take {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    // A new take holding just part of this one, saved next to it with a suffix on the name
+    pub fn excerpt(&self, start_frame: usize, end_frame: usize, suffix: &str) -> Take {
+        let channels = self.channels.max(1) as usize;
+        let end = (end_frame * channels).min(self.samples.len());
+        let start = (start_frame * channels).min(end);
+        let name = format!("{}_{}", self.name, suffix);
+        Take {
+            samples: Arc::new(self.samples[start..end].to_vec()),
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            path: self.path.with_file_name(format!("{}.wav", name)),
+            name,
+            preset: self.preset.clone(),
+            metadata: self.metadata.clone(),
+            onset_time: self.onset_time,
+            stages: self.stages.clone(),
+            channel_fix: self.channel_fix,
+            variants: self.variants.clone(),
+            variant: self.variant,
+            hot_folder: self.hot_folder.clone(),
+            time_reference: self.later_reference(start_frame),
+            post_save_command: self.post_save_command.clone(),
+            channel_names: self.channel_names.clone(),
+            armed_channels: self.armed_channels.clone(),
+            stems: self.stems,
+        }
+    }
+
+    // The time reference `frames` into the take
+    fn later_reference(&self, frames: usize) -> Option<u64> {
+        self.time_reference
+            .map(|reference| reference + frames as u64)
+    }
+
+    // One hit cut out of the take into a subfolder named after it, with short fades so it
+    // starts and stops without a click
+    pub fn slice(&self, start_frame: usize, end_frame: usize, number: usize) -> Take {
+        let mut slice = self.excerpt(start_frame, end_frame, &format!("slice{:02}", number));
+        slice.stems = false;
+        let folder = format!("{}_slices", self.name);
+        slice.path = self
+            .path
+            .with_file_name(folder)
+            .join(format!("{}.wav", slice.name));
+
+        let channels = self.channels.max(1) as usize;
+        let mut samples = slice.samples.to_vec();
+        let frames = samples.len() / channels;
+        let fade_in = (self.sample_rate as usize / 500).min(frames / 2); // 2 ms
+        let fade_out = (self.sample_rate as usize / 100).min(frames / 2); // 10 ms
+        for (index, frame) in samples.chunks_exact_mut(channels).enumerate() {
+            let gain = if index < fade_in {
+                index as f32 / fade_in as f32
+            } else if index >= frames - fade_out {
+                (frames - index) as f32 / fade_out as f32
+            } else {
+                1.0
+            };
+            frame.iter_mut().for_each(|s| *s *= gain);
+        }
+        slice.samples = Arc::new(samples);
+        slice
+    }
+
+    // Save under another name in the same folder
+    pub fn rename(&mut self, name: &str) {
+        self.path = self.path.with_file_name(format!("{}.wav", name));
+        self.name = name.to_owned();
+    }
+
+    // Just the frames between start and end, keeping the name and path
+    pub fn trimmed(&self, start_frame: usize, end_frame: usize) -> Take {
+        let channels = self.channels.max(1) as usize;
+        let end = (end_frame * channels).min(self.samples.len());
+        let start = (start_frame * channels).min(end);
+        let samples = if start == 0 && end == self.samples.len() {
+            Arc::clone(&self.samples)
+        } else {
+            Arc::new(self.samples[start..end].to_vec())
+        };
+        Take {
+            samples,
+            time_reference: self.later_reference(start / channels),
+            ..self.clone()
+        }
+    }
+
+    // The part from `start_frame` to `end_frame` made to loop, with its end crossfaded into the
+    // audio before `start_frame` over up to `fade_frames`
+    pub fn looped(&self, start_frame: usize, end_frame: usize, fade_frames: usize) -> Take {
+        let channels = self.channels.max(1) as usize;
+        let take = self.trimmed(start_frame, end_frame);
+        let fade_frames = fade_frames.min(start_frame).min(take.frames() / 2);
+        if fade_frames == 0 {
+            return take;
+        }
+        let mut samples = take.samples.to_vec();
+        let lead_in = &self.samples[(start_frame - fade_frames) * channels..start_frame * channels];
+        dsp::loop_crossfade(&mut samples, lead_in, channels);
+        Take {
+            samples: Arc::new(samples),
+            ..take
+        }
+    }
+
+    // `input`, which lines up with this take from `from_frame` on, in place of the audio between
+    // `start_frame` and `end_frame`. Each edge is crossfaded over up to `fade_frames` inside the
+    // punch so the seams don't click.
+    pub fn punched(
+        &self,
+        input: &[f32],
+        from_frame: usize,
+        start_frame: usize,
+        end_frame: usize,
+        fade_frames: usize,
+    ) -> Take {
+        let channels = self.channels.max(1) as usize;
+        let start = start_frame.max(from_frame).min(self.frames());
+        let end = end_frame
+            .min(from_frame + input.len() / channels)
+            .min(self.frames());
+        let mut samples = self.samples.to_vec();
+        if end > start {
+            let fade_frames = fade_frames.min((end - start) / 2).max(1);
+            for frame in start..end {
+                let edge = (frame - start).min(end - 1 - frame);
+                let mix = ((edge + 1) as f32 / fade_frames as f32).min(1.0);
+                let new = &input[(frame - from_frame) * channels..][..channels];
+                for (old, new) in samples[frame * channels..][..channels].iter_mut().zip(new) {
+                    *old += (new - *old) * mix;
+                }
+            }
+        }
+        Take {
+            samples: Arc::new(samples),
+            ..self.clone()
+        }
+    }
+
+    // The same audio rendered as a variant, sharing the samples rather than copying them
+    pub fn with_variant(&self, variant: ExportVariant) -> Take {
+        let name = format!("{}_{}", self.name, variant.suffix());
+        Take {
+            path: self.path.with_file_name(format!("{}.wav", name)),
+            name,
+            variants: Vec::new(),
+            variant: Some(variant),
+            stems: false,
+            ..self.clone()
+        }
+    }
+
+    // One input channel on its own, into a subfolder of mono files named after the channels.
+    // It shares the samples, picking the channel out as it's written.
+    pub fn stem(&self, channel: usize) -> Take {
+        let name = format!("{}_{}", self.name, file_safe(&self.channel_name(channel)));
+        let folder = format!("{}_stems", self.name);
+        Take {
+            path: self
+                .path
+                .with_file_name(folder)
+                .join(format!("{}.wav", name)),
+            name,
+            channel_fix: Some(ChannelFix::KeepChannel(channel)),
+            channel_names: vec![self.channel_name(channel)],
+            armed_channels: None,
+            variants: Vec::new(),
+            stems: false,
+            ..self.clone()
+        }
+    }
+
+    // The input channels written to the file, before any export stages
+    fn written_channels(&self) -> Vec<usize> {
+        match (self.channel_fix, &self.armed_channels) {
+            (Some(ChannelFix::KeepChannel(channel)), _) => vec![channel],
+            (_, Some(armed)) => armed.clone(),
+            (_, None) => (0..self.channels as usize).collect(),
+        }
+    }
+
+    fn channel_name(&self, channel: usize) -> String {
+        self.channel_names
+            .get(channel)
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| format!("Ch{}", channel + 1), str::to_owned)
+    }
+
+    // Names of the channels in the written file, when any were named and the export chain
+    // keeps them apart
+    pub fn output_channel_names(&self) -> Vec<String> {
+        let channels = self.written_channels();
+        let named = channels.iter().any(|&channel| {
+            self.channel_names
+                .get(channel)
+                .is_some_and(|name| !name.trim().is_empty())
+        });
+        if !named || self.output_channels() as usize != channels.len() {
+            return Vec::new();
+        }
+        channels
+            .into_iter()
+            .map(|channel| self.channel_name(channel))
+            .collect()
+    }
+
+    pub fn flip_polarity(&mut self, channel: usize) {
+        self.channel_fix = Some(ChannelFix::FlipPolarity(channel));
+    }
+
+    // Keep just one channel, e.g. when a stereo pair cancels badly in mono
+    pub fn keep_channel(&mut self, channel: usize) {
+        self.channel_fix = Some(ChannelFix::KeepChannel(channel));
+    }
+
+    // Channels after any channel fix, going into the export stages
+    fn source_channels(&self) -> u16 {
+        match (self.channel_fix, &self.armed_channels) {
+            (Some(ChannelFix::KeepChannel(_)), _) => 1,
+            (_, Some(armed)) => armed.len() as u16,
+            _ => self.channels,
+        }
+    }
+
+    // Channels in the written file
+    pub fn output_channels(&self) -> u16 {
+        let summed = self.stages.contains(&ExportStage::Channels {
+            mode: ChannelMode::SumToMono,
+        });
+        if summed {
+            1
+        } else {
+            self.source_channels()
+        }
+    }
+
+    // Length of the written file, which changes for speed variants and time-stretches. A stretch
+    // from a tempo that has yet to be detected counts as unchanged.
+    pub fn output_duration_seconds(&self) -> f64 {
+        let duration = match self.variant {
+            Some(ExportVariant::Speed { ratio }) => self.duration_seconds() / ratio as f64,
+            _ => self.duration_seconds(),
+        };
+        self.stages
+            .iter()
+            .fold(duration, |duration, stage| match *stage {
+                ExportStage::Stretch {
+                    source_bpm,
+                    target_bpm,
+                } => match self.tempo(source_bpm, false) {
+                    Some(source) => duration * (source / target_bpm) as f64,
+                    None => duration,
+                },
+                _ => duration,
+            })
+    }
+
+    // The tempo going into a time-stretch: the stage's own, else the one tagged at grab time,
+    // else (if `detect`) detected now. A speed variant changes it along with the pitch.
+    fn tempo(&self, source_bpm: f32, detect: bool) -> Option<f32> {
+        let tagged = || {
+            self.metadata
+                .iter()
+                .find(|(key, _)| key == "bpm")
+                .and_then(|(_, value)| value.parse().ok())
+        };
+        let detected = || {
+            detect
+                .then(|| {
+                    analysis::detect_tempo(&self.samples, self.channels as usize, self.sample_rate)
+                })
+                .flatten()
+        };
+        let bpm = Some(source_bpm)
+            .filter(|&bpm| bpm > 0.0)
+            .or_else(tagged)
+            .or_else(detected)?;
+        Some(match self.variant {
+            Some(ExportVariant::Speed { ratio }) => bpm * ratio,
+            _ => bpm,
+        })
+    }
+
+    // Walk the take a chunk at a time (backwards for a reversed variant) with any channel fix
+    // applied and just the armed channels kept
+    fn source_chunks(&self) -> impl Iterator<Item = Vec<f32>> + '_ {
+        let channels = self.channels.max(1) as usize;
+        let reverse = self.variant == Some(ExportVariant::Reverse);
+        let chunks: Box<dyn Iterator<Item = &[f32]>> = if reverse {
+            Box::new(self.samples.rchunks(CHUNK_FRAMES * channels))
+        } else {
+            Box::new(self.samples.chunks(CHUNK_FRAMES * channels))
+        };
+        chunks
+            .map(move |chunk| {
+                if reverse {
+                    chunk.chunks(channels).rev().flatten().copied().collect()
+                } else {
+                    chunk.to_vec()
+                }
+            })
+            .map(move |chunk| match self.channel_fix {
+                None => chunk,
+                Some(ChannelFix::FlipPolarity(channel)) => {
+                    let mut chunk = chunk;
+                    for sample in chunk.iter_mut().skip(channel).step_by(channels) {
+                        *sample = -*sample;
+                    }
+                    chunk
+                }
+                Some(ChannelFix::KeepChannel(channel)) => chunk
+                    .iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .copied()
+                    .collect(),
+            })
+            .map(
+                move |chunk| match (&self.armed_channels, self.channel_fix) {
+                    (Some(armed), None | Some(ChannelFix::FlipPolarity(_))) => chunk
+                        .chunks(channels)
+                        .flat_map(|frame| armed.iter().map(|&channel| frame[channel]))
+                        .collect(),
+                    _ => chunk,
+                },
+            )
+    }
+
+    // Stream the take through the processors, handing the output to `sink` as it comes out
+    fn run(
+        &self,
+        processors: &mut [Processor],
+        mut sink: impl FnMut(&[f32]) -> Result<(), hound::Error>,
+    ) -> Result<(), hound::Error> {
+        for chunk in self.source_chunks() {
+            let output = processors
+                .iter_mut()
+                .fold(chunk, |chunk, processor| processor.process(chunk));
+            sink(&output)?;
+        }
+        // Flush each processor's lookahead through the ones after it
+        for index in 0..processors.len() {
+            let held_back = processors[index].finish();
+            let output = processors[index + 1..]
+                .iter_mut()
+                .fold(held_back, |chunk, processor| processor.process(chunk));
+            sink(&output)?;
+        }
+        Ok(())
+    }
+
+    fn processors(&self, stages: &[ExportStage]) -> Vec<Processor> {
+        let mut channels = self.source_channels().max(1) as usize; // Until a stage sums to mono
+        let sample_rate = self.sample_rate;
+        let mut processors = Vec::new();
+        if let Some(ExportVariant::Speed { ratio }) = self.variant {
+            processors.push(Processor::Speed(Varispeed::new(
+                channels,
+                sample_rate,
+                ratio,
+            )));
+        }
+        for (index, stage) in stages.iter().enumerate() {
+            processors.push(match *stage {
+                ExportStage::DeClick { strength } => {
+                    Processor::DeClick(Declicker::new(channels, sample_rate, strength))
+                }
+                ExportStage::HighPass { cutoff_hz } => {
+                    Processor::Filter(InterleavedFilter::new(channels, || {
+                        Biquad::high_pass(sample_rate as f32, cutoff_hz, dsp::BUTTERWORTH_Q)
+                    }))
+                }
+                ExportStage::Gate(settings) => {
+                    Processor::Gate(NoiseGate::new(channels, sample_rate, settings))
+                }
+                ExportStage::Normalize { peak_db } => {
+                    // Needs the peak after the earlier stages, found with an extra pass
+                    let mut peak = 0.0f32;
+                    let _ = self.run(&mut self.processors(&stages[..index]), |chunk| {
+                        peak = peak.max(dsp::peak(chunk));
+                        Ok(())
+                    });
+                    let gain = if peak > 0.0 {
+                        dsp::db_to_gain(peak_db) / peak
+                    } else {
+                        1.0
+                    };
+                    Processor::Gain(gain)
+                }
+                ExportStage::Loudness { lufs } => {
+                    // Measured after the earlier stages too, in the same way
+                    let mut meter = LoudnessMeter::new(channels, sample_rate);
+                    let _ = self.run(&mut self.processors(&stages[..index]), |chunk| {
+                        meter.add_samples(chunk);
+                        Ok(())
+                    });
+                    match meter.integrated() {
+                        Some(measured) => Processor::Gain(dsp::db_to_gain(lufs - measured)),
+                        None => Processor::Gain(1.0), // Too short or silent to measure
+                    }
+                }
+                ExportStage::Stretch {
+                    source_bpm,
+                    target_bpm,
+                } => Processor::Stretch(Stretcher::new(
+                    channels,
+                    self.tempo(source_bpm, true)
+                        .map_or(1.0, |source| source / target_bpm.max(1.0)),
+                )),
+                ExportStage::Channels { mode } => {
+                    let processor = Processor::Channels(mode, channels);
+                    if mode == ChannelMode::SumToMono {
+                        channels = 1;
+                    }
+                    processor
+                }
+            });
+        }
+        processors
+    }
+
+    // The note tagged at grab time, or else found now. Left out of speed variants, which are
+    // repitched.
+    fn note(&self) -> Option<String> {
+        if self.variant.is_some() {
+            return None;
+        }
+        let tagged = self.metadata.iter().find(|(key, _)| key == "note");
+        match tagged {
+            Some((_, note)) => Some(note.clone()),
+            None => pitch::take_pitch(
+                &self.samples,
+                self.channels as usize,
+                self.sample_rate,
+                440.0,
+            )
+            .map(|(note, _)| note),
+        }
+    }
+
+    // Write the WAV, tag it and record it in the sidecar and session log
+    pub fn save(&self) -> Result<Sidecar, Box<dyn Error + Send + Sync>> {
+        println!(
+            "Recorded shape: ({}, {})",
+            self.frames(),
+            self.output_channels()
+        );
+        println!("Sample rate: {}", self.sample_rate);
+
+        let spec = WavSpec {
+            channels: self.output_channels(),
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            sample_format: HoundSampleFormat::Float,
+        };
+        if let Some(folder) = self.path.parent() {
+            fs::create_dir_all(folder)?; // Slices go in a subfolder that may not exist yet
+        }
+        let mut writer = WavWriter::create(&self.path, spec)?;
+        let mut processors = self.processors(&self.stages);
+        let channels = self.output_channels().max(1) as usize;
+        let mut written = 0;
+        let (mut peak, mut energy) = (0.0f32, 0.0f64);
+        let mut loudness = LoudnessMeter::new(channels, self.sample_rate);
+        self.run(&mut processors, |chunk| {
+            written += chunk.len();
+            peak = peak.max(dsp::peak(chunk));
+            energy += chunk.iter().map(|&s| (s as f64).powi(2)).sum::<f64>();
+            loudness.add_samples(chunk);
+            chunk
+                .iter()
+                .try_for_each(|&sample| writer.write_sample(sample))
+        })?;
+        writer.finalize()?;
+        let duration_seconds = (written / channels) as f64 / self.sample_rate as f64;
+        let analysis = TakeAnalysis {
+            peak_db: 20.0 * peak.max(1e-6).log10(),
+            rms_db: 10.0 * (energy / written.max(1) as f64).max(1e-12).log10() as f32,
+            lufs: loudness.integrated(),
+            duration_seconds,
+            note: self.note(),
+        };
+
+        // Tag the file with the preset's metadata, and its timecode for the edit
+        metadata::write_info_chunk(&self.path, &self.name, &self.metadata)?;
+        if let Some(time_reference) = self.time_reference {
+            metadata::write_bext_chunk(&self.path, &self.name, time_reference)?;
+        }
+        let channel_names = self.output_channel_names();
+        if !channel_names.is_empty() {
+            metadata::write_ixml_chunk(&self.path, &self.name, &channel_names)?;
+        }
+
+        // Hash the file only once nothing else is going to be written to it
+        let sha256 = metadata::sha256_file(&self.path)?;
+
+        let sidecar = Sidecar {
+            file: self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            created: Utc::now().to_rfc3339(),
+            sample_rate: self.sample_rate,
+            channels: self.output_channels(),
+            duration_seconds,
+            preset: self.preset.clone(),
+            metadata: self.metadata.iter().cloned().collect(),
+            onset_time: self.onset_time.map(|time| time.to_rfc3339()),
+            sha256,
+            processing: self
+                .variant
+                .map(|variant| variant.name())
+                .into_iter()
+                .chain(self.stages.iter().map(ExportStage::describe))
+                .collect(),
+            analysis: Some(analysis),
+            channel_names,
+            ..Sidecar::default()
+        };
+        metadata::write_sidecar(&self.path, &sidecar)?;
+        if let Some(save_dir) = self.path.parent() {
+            metadata::append_session_log(save_dir, &sidecar)?;
+        }
+        Ok(sidecar)
+    }
+
+    // Save on a writer thread so the UI and the audio callback are never held up by disk I/O
+    // The thread hands back the main file's path and analysis, for the take history.
+    pub fn save_in_background(
+        self,
+        errors: Sender<RecorderError>,
+    ) -> JoinHandle<Option<(PathBuf, TakeAnalysis)>> {
+        thread::spawn(move || {
+            let variants = self
+                .variants
+                .iter()
+                .map(|&variant| self.with_variant(variant));
+            // Stems of a take that has more than one channel to split
+            let stems = if self.stems && self.source_channels() > 1 {
+                self.written_channels()
+            } else {
+                Vec::new()
+            };
+            let stems = stems.into_iter().map(|channel| self.stem(channel));
+            let mut analysis = None;
+            for take in std::iter::once(self.clone()).chain(variants).chain(stems) {
+                match take.save() {
+                    Ok(sidecar) => {
+                        println!("Recording saved to {}", take.path.display());
+                        if take.path == self.path {
+                            analysis = sidecar.analysis.map(|a| (take.path.clone(), a));
+                        }
+                    }
+                    Err(e) => {
+                        let message = format!("Failed to save {}: {}", take.path.display(), e);
+                        eprintln!("{}", message);
+                        let _ = errors.send(RecorderError::Save(message));
+                        continue;
+                    }
+                }
+                if let Some(command) = &take.post_save_command {
+                    if let Err(message) = run_post_save(command, &take.path) {
+                        eprintln!("{}", message);
+                        let _ = errors.send(RecorderError::Other(message));
+                    }
+                }
+                if let Some(hot_folder) = &take.hot_folder {
+                    match hot_folder.deliver(&take.path) {
+                        Ok(path) => println!("Dropped into hot folder as {}", path.display()),
+                        Err(e) => {
+                            let message = format!("Failed to copy to the hot folder: {}", e);
+                            eprintln!("{}", message);
+                            let _ = errors.send(RecorderError::Save(message));
+                        }
+                    }
+                }
+            }
+            analysis
+        })
+    }
+}
+
+// Runs the user's command on a saved file, e.g. `rclone copy {file} remote:samples`. `{file}`
+// becomes the file's path, quoted for the shell, and is added at the end when the command
+// doesn't mention it. The path is also in $ROLLING_SAMPLER_FILE, as for the hot folder command.
+pub fn run_post_save(command: &str, file: &Path) -> Result<(), String> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Ok(());
+    }
+    let quoted = shell_quote(&file.to_string_lossy());
+    let command = if command.contains("{file}") {
+        command.replace("{file}", &quoted)
+    } else {
+        format!("{} {}", command, quoted)
+    };
+    // Runs on the writer thread, so waiting for it holds nothing else up
+    match hotfolder::shell(&command)
+        .env("ROLLING_SAMPLER_FILE", file)
+        .status()
+    {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("Post-save command exited with {}", status)),
+        Err(e) => Err(format!("Failed to run the post-save command: {}", e)),
+    }
+}
+
+// Channel names go into stem file names, so anything a file system might object to goes
+fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn shell_quote(text: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", text)
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
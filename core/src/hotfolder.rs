@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A folder a DAW watches (e.g. a Bitwig or Ableton browser location) that every saved grab is
+// also dropped into
+#[derive(Clone, Debug)]
+pub struct HotFolder {
+    pub dir: PathBuf,
+    pub take_names: bool, // Name files "Take 001.wav", "Take 002.wav"... instead of by time
+    pub notify_command: String, // Run after each drop, with the file in $ROLLING_SAMPLER_FILE
+}
+
+impl HotFolder {
+    // Copy a saved WAV in. It is written under a hidden temporary name and renamed into place,
+    // so the DAW never picks up a half-written file.
+    pub fn deliver(&self, wav: &Path) -> io::Result<PathBuf> {
+        let file_name = if self.take_names {
+            format!("Take {:03}.wav", self.next_take_number()?)
+        } else {
+            wav.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "grab.wav".to_owned())
+        };
+        let target = self.dir.join(&file_name);
+        let partial = self.dir.join(format!(".{}.part", file_name));
+        fs::copy(wav, &partial)?;
+        fs::rename(&partial, &target)?;
+        self.notify(&target);
+        Ok(target)
+    }
+
+    fn next_take_number(&self) -> io::Result<u32> {
+        let mut highest = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            let number = name
+                .strip_prefix("Take ")
+                .and_then(|rest| rest.strip_suffix(".wav"))
+                .and_then(|number| number.parse::<u32>().ok());
+            if let Some(number) = number {
+                highest = highest.max(number);
+            }
+        }
+        Ok(highest + 1)
+    }
+
+    fn notify(&self, file: &Path) {
+        let command = self.notify_command.trim();
+        if command.is_empty() {
+            return;
+        }
+        // Runs on the writer thread, so waiting for it holds nothing else up
+        match shell(command).env("ROLLING_SAMPLER_FILE", file).status() {
+            Ok(status) if !status.success() => {
+                eprintln!("Hot folder command exited with {}", status)
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to run hot folder command: {}", e),
+        }
+    }
+}
+
+// A command line of the user's, run by the platform's shell
+pub(crate) fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
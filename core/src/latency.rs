@@ -0,0 +1,50 @@
+// Round-trip latency: a click is played out of the output and recorded back on the input
+// (through a loopback cable, or a mic in front of a speaker), then found in the recording by
+// cross-correlation, which copes with it coming back quiet, filtered or upside down.
+
+use std::f32::consts::PI;
+
+const CLICK_SECONDS: f32 = 0.005;
+const CLICK_LOW_HZ: f32 = 1000.0;
+const CLICK_HIGH_HZ: f32 = 8000.0;
+const MIN_CORRELATION: f32 = 0.5; // Normalised, below this it's taken to be noise
+
+// A 5 ms chirp under a Hann window: short enough to place precisely, and broad enough to get
+// through a speaker and a mic
+pub fn click(sample_rate: u32) -> Vec<f32> {
+    let length = (CLICK_SECONDS * sample_rate as f32) as usize;
+    let sweep = (CLICK_HIGH_HZ - CLICK_LOW_HZ) / CLICK_SECONDS;
+    (0..length)
+        .map(|index| {
+            let t = index as f32 / sample_rate as f32;
+            let phase = 2.0 * PI * (CLICK_LOW_HZ * t + 0.5 * sweep * t * t);
+            let window = 0.5 - 0.5 * (2.0 * PI * index as f32 / length as f32).cos();
+            0.5 * window * phase.sin()
+        })
+        .collect()
+}
+
+// Where `click` starts in `recorded` (mono), if it's clearly in there
+pub fn find_click(recorded: &[f32], click: &[f32]) -> Option<usize> {
+    if click.is_empty() || recorded.len() < click.len() {
+        return None;
+    }
+    let click_norm = click.iter().map(|s| s * s).sum::<f32>().sqrt();
+    // Energy of the recording under the click at each lag, kept as a running sum
+    let mut window_energy: f32 = recorded[..click.len()].iter().map(|s| s * s).sum();
+    // The strongest match, which then has to look like the click and not just loud noise
+    let mut best = (0, 0.0f32, 0.0f32);
+    for lag in 0..=recorded.len() - click.len() {
+        if lag > 0 {
+            let (gone, new) = (recorded[lag - 1], recorded[lag + click.len() - 1]);
+            window_energy = (window_energy - gone * gone + new * new).max(0.0);
+        }
+        let correlation: f32 = click.iter().zip(&recorded[lag..]).map(|(c, r)| c * r).sum();
+        if correlation.abs() > best.1 {
+            best = (lag, correlation.abs(), window_energy);
+        }
+    }
+    let (lag, correlation, energy) = best;
+    let normalised = correlation / (click_norm * energy.sqrt()).max(1e-9);
+    (normalised >= MIN_CORRELATION).then_some(lag)
+}
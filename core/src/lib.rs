@@ -0,0 +1,77 @@
+//! The capture engine behind Rolling Sampler, for embedding retroactive capture in other apps.
+//!
+//! [`recorder::Recorder`] is the whole engine behind one handle: it opens the input and the
+//! monitoring output, runs the capture chain, keeps the rolling buffer and cuts grabs from it,
+//! over any [`backend::AudioBackend`]. The rest of this crate is the pieces it's made of, which
+//! can be used on their own as well.
+//!
+//! Audio from an input callback goes into a [`CircularBuffer`], which keeps the last few
+//! seconds or minutes. Calling [`CircularBuffer::start_static_mode`] freezes that history and
+//! keeps appending to it, so a grab includes what happened before it was asked for. The grabbed
+//! samples become an [`export::Take`], which runs its export stages (see [`dsp`], [`stretch`])
+//! and writes a WAV with tags and a JSON sidecar, on a writer thread if you like.
+//!
+//! ```no_run
+//! use rolling_sampler_core::export::Take;
+//! use rolling_sampler_core::CircularBuffer;
+//! use std::path::PathBuf;
+//! use std::sync::Arc;
+//!
+//! let (channels, sample_rate) = (2, 48_000);
+//! let mut buffer = CircularBuffer::new(30 * sample_rate as usize * channels);
+//! // From the input callback:
+//! buffer.add_samples(&[0.0; 512]);
+//!
+//! buffer.start_static_mode();
+//! let take = Take {
+//!     name: "Take".to_owned(),
+//!     path: PathBuf::from("Take.wav"),
+//!     samples: Arc::new(std::mem::take(&mut buffer.static_buffer)),
+//!     channels: channels as u16,
+//!     sample_rate,
+//!     ..Take::default()
+//! };
+//! take.save().expect("saving the take");
+//! ```
+//!
+//! [`state`] is the recorder's lifecycle, [`schedule`] fires timed grabs, [`stats`] and [`meter`]
+//! gather stream health and levels from inside the callbacks, and [`playback`], [`overdub`] and
+//! [`roundtrip`] play through the output while the input keeps rolling. [`monitor`] and
+//! [`routing`] carry input to an output stream for live monitoring, with a
+//! [`metronome`] to mix in, and [`latency`] measures the round trip back from it. [`analysis`]
+//! and [`pitch`] look at grabbed audio (loudness, hits, tempo, key, note). [`live`] hands
+//! settings changed in a UI to a running audio callback without a lock, and [`tap`] hands its
+//! audio to a writer thread the same way. [`patch`] maps saved takes across a keyboard as an SFZ
+//! or DecentSampler instrument. [`backend`] lists devices and opens every stream behind a trait,
+//! for cpal and a mock that feeds made-up input on demand so the engine can be tested without
+//! audio hardware.
+
+pub mod analysis;
+pub mod backend;
+pub mod buffer;
+pub mod dsp;
+pub mod error;
+pub mod export;
+pub mod hotfolder;
+pub mod latency;
+pub mod live;
+pub mod metadata;
+pub mod meter;
+pub mod metronome;
+pub mod monitor;
+pub mod overdub;
+pub mod patch;
+pub mod pitch;
+pub mod playback;
+pub mod recorder;
+pub mod roundtrip;
+pub mod routing;
+pub mod schedule;
+pub mod state;
+pub mod stats;
+pub mod stretch;
+pub mod tap;
+pub mod timecode;
+
+pub use buffer::CircularBuffer;
+pub use error::RecorderError;
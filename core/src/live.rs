@@ -0,0 +1,59 @@
+// Settings changed from the UI while an audio callback is using them, handed over without a lock
+// so dragging a slider can never hold up the audio. The values are f32s kept as bits, with a
+// generation counter so the callback only reconfigures (new filter coefficients and the like)
+// when something has actually changed. There should only be one thread setting them.
+
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+pub struct LiveSettings<const N: usize> {
+    enabled: AtomicBool,
+    values: [AtomicU32; N],
+    generation: AtomicU64, // Odd while a change is half written
+}
+
+impl<const N: usize> LiveSettings<N> {
+    pub fn new(enabled: bool, values: [f32; N]) -> Self {
+        LiveSettings {
+            enabled: AtomicBool::new(enabled),
+            values: values.map(|value| AtomicU32::new(value.to_bits())),
+            generation: AtomicU64::new(2), // So the first look always finds a change
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, values: [f32; N]) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (slot, value) in self.values.iter().zip(values) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn get(&self) -> [f32; N] {
+        std::array::from_fn(|index| f32::from_bits(self.values[index].load(Ordering::Relaxed)))
+    }
+
+    // The values when they've changed since the generation in `seen`, which is then moved on.
+    // A change that's still being written is picked up on a later call.
+    pub fn changed(&self, seen: &mut u64) -> Option<[f32; N]> {
+        let generation = self.generation.load(Ordering::Acquire);
+        if generation == *seen || generation % 2 == 1 {
+            return None;
+        }
+        let values = self.get();
+        fence(Ordering::Acquire);
+        if self.generation.load(Ordering::Relaxed) != generation {
+            return None;
+        }
+        *seen = generation;
+        Some(values)
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Everything we know about a saved grab, written to a JSON file next to the WAV
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sidecar {
+    pub file: String,
+    pub created: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_seconds: f64,
+    pub preset: String,
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onset_time: Option<String>, // Set when the take was started by the signal threshold
+    pub sha256: String, // Hash of the finished WAV file so archives can be verified later
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub processing: Vec<String>, // Export stages that were applied, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<TakeAnalysis>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub channel_names: Vec<String>, // In file order, for multi-track takes with named inputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>, // 1 to 5 stars, given after saving
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+// Levels and pitch of the file as written, for telling takes apart without listening to them
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TakeAnalysis {
+    pub peak_db: f32, // dBFS
+    pub rms_db: f32,  // dBFS, over every channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lufs: Option<f32>, // Integrated loudness, None when too short or quiet to measure
+    pub duration_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl TakeAnalysis {
+    // One line for the take history, e.g. "Peak -1.2 dBFS, RMS -17.8 dBFS, -14.3 LUFS, 4.0 s, A2"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            format!("Peak {:.1} dBFS", self.peak_db),
+            format!("RMS {:.1} dBFS", self.rms_db),
+        ];
+        parts.extend(self.lufs.map(|lufs| format!("{:.1} LUFS", lufs)));
+        parts.push(format!("{:.1} s", self.duration_seconds));
+        parts.extend(self.note.clone());
+        parts.join(", ")
+    }
+}
+
+pub fn sidecar_path(wav_path: &Path) -> PathBuf {
+    wav_path.with_extension("json")
+}
+
+pub fn write_sidecar(wav_path: &Path, sidecar: &Sidecar) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(sidecar)?;
+    fs::write(sidecar_path(wav_path), json)
+}
+
+pub fn read_sidecar(wav_path: &Path) -> io::Result<Sidecar> {
+    let json = fs::read_to_string(sidecar_path(wav_path))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+// Hex encoded SHA-256 of a file, read in chunks so long takes don't need to fit in memory twice
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&chunk[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+// Append one line per saved take to a log in the save folder, tab separated:
+// created, file name, sha256
+pub fn append_session_log(save_dir: &Path, sidecar: &Sidecar) -> io::Result<()> {
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(save_dir.join("rolling-sampler-log.tsv"))?;
+    writeln!(
+        log,
+        "{}\t{}\t{}",
+        sidecar.created, sidecar.file, sidecar.sha256
+    )
+}
+
+// Append a LIST/INFO chunk to a finalised WAV file. hound has no API for extra chunks, so the
+// chunk is written after the data chunk.
+// Standard INFO ids only allow a fixed set of keys, so free-form fields go into the comment.
+pub fn write_info_chunk(
+    wav_path: &Path,
+    name: &str,
+    fields: &[(String, String)],
+) -> io::Result<()> {
+    let comment = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut entries: Vec<(&[u8; 4], &str)> = vec![(b"INAM", name), (b"ISFT", "Rolling Sampler")];
+    if !comment.is_empty() {
+        entries.push((b"ICMT", &comment));
+    }
+
+    let mut info = Vec::new();
+    info.extend_from_slice(b"INFO");
+    for (id, text) in entries {
+        // INFO strings are null-terminated and each sub-chunk is padded to an even length
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        info.extend_from_slice(&bytes);
+        if bytes.len() % 2 == 1 {
+            info.push(0);
+        }
+    }
+
+    append_chunk(wav_path, b"LIST", &info)
+}
+
+// Append a BWF bext chunk, for the time reference editors use to place the file on their
+// timeline: samples since midnight at the first sample. The rest is left empty or plain, since
+// the INFO chunk and sidecar already carry the details.
+pub fn write_bext_chunk(wav_path: &Path, name: &str, time_reference: u64) -> io::Result<()> {
+    let mut bext = vec![0u8; 602];
+    let mut put = |offset: usize, length: usize, bytes: &[u8]| {
+        let length = bytes.len().min(length);
+        bext[offset..offset + length].copy_from_slice(&bytes[..length]);
+    };
+    put(0, 256, name.as_bytes()); // Description
+    put(256, 32, b"Rolling Sampler"); // Originator
+    let now = chrono::Local::now();
+    put(320, 10, now.format("%Y-%m-%d").to_string().as_bytes()); // Origination date and time
+    put(330, 8, now.format("%H:%M:%S").to_string().as_bytes());
+    put(338, 8, &time_reference.to_le_bytes()); // Low then high 32 bits
+    put(346, 2, &1u16.to_le_bytes()); // Version
+    append_chunk(wav_path, b"bext", &bext)
+}
+
+// Append an iXML chunk with a track list naming each channel, which is where field recorders
+// put them and where DAWs look when splitting a polyphonic file into tracks
+pub fn write_ixml_chunk(wav_path: &Path, name: &str, channel_names: &[String]) -> io::Result<()> {
+    let tracks: String = channel_names
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| {
+            format!(
+                "<TRACK><CHANNEL_INDEX>{0}</CHANNEL_INDEX><INTERLEAVE_INDEX>{0}</INTERLEAVE_INDEX>\
+                 <NAME>{1}</NAME></TRACK>",
+                index + 1,
+                xml_escape(channel)
+            )
+        })
+        .collect();
+    let ixml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<BWFXML><IXML_VERSION>1.61</IXML_VERSION>\
+         <PROJECT>Rolling Sampler</PROJECT><TAPE>{}</TAPE><TRACK_LIST>\
+         <TRACK_COUNT>{}</TRACK_COUNT>{}</TRACK_LIST></BWFXML>",
+        xml_escape(name),
+        channel_names.len(),
+        tracks
+    );
+    append_chunk(wav_path, b"iXML", ixml.as_bytes())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Append a chunk to a finalised WAV file and patch up the RIFF size in the header to cover it
+fn append_chunk(wav_path: &Path, id: &[u8; 4], payload: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(wav_path)?;
+    let mut end = file.seek(SeekFrom::End(0))?;
+    if end % 2 == 1 {
+        // Chunks must start on an even offset
+        file.write_all(&[0])?;
+        end += 1;
+    }
+    file.write_all(id)?;
+    file.write_all(&(payload.len() as u32).to_le_bytes())?;
+    file.write_all(payload)?;
+
+    // The RIFF size covers everything after the first 8 bytes, i.e. the old file (minus its
+    // 8 byte header) plus the new chunk (its 8 byte header plus the payload)
+    let riff_size = end + payload.len() as u64;
+    let mut header = [0u8; 4];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    if &header != b"RIFF" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a RIFF file",
+        ));
+    }
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(riff_size as u32).to_le_bytes())?;
+    Ok(())
+}
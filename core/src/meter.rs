@@ -0,0 +1,66 @@
+// Levels gathered inside the audio callbacks for meters drawn elsewhere, e.g. once per UI frame
+
+use chrono::{DateTime, Local};
+use std::sync::Mutex;
+
+// Samples this close to full scale count as clipped, since converters rarely hit exactly 1.0
+pub const CLIP_LEVEL: f32 = 0.999;
+
+// What the capture callback has seen on each channel since the UI last looked
+#[derive(Clone, Copy, Default)]
+pub struct Accumulated {
+    pub peak: f32,
+    pub sum_squares: f64,
+    pub sum_abs: f64,
+    pub count: u64,
+    pub clipped: u64,
+    pub first_clip: Option<DateTime<Local>>,
+}
+
+// Filled in by the capture (or monitoring) callback, drained once per UI frame
+pub struct MeterTap {
+    channels: Mutex<Vec<Accumulated>>,
+}
+
+impl MeterTap {
+    pub fn new(channels: usize) -> Self {
+        MeterTap {
+            channels: Mutex::new(vec![Accumulated::default(); channels.max(1)]),
+        }
+    }
+
+    pub fn add_samples(&self, data: &[f32]) {
+        accumulate(&mut self.channels.lock().unwrap(), data);
+    }
+
+    // For callbacks that mustn't wait on the UI: the block goes unmetered if it's reading
+    pub fn try_add_samples(&self, data: &[f32]) {
+        if let Ok(mut channels) = self.channels.try_lock() {
+            accumulate(&mut channels, data);
+        }
+    }
+
+    // What came in since the last call, per channel
+    pub fn take(&self) -> Vec<Accumulated> {
+        let mut channels = self.channels.lock().unwrap();
+        let taken = channels.clone();
+        channels.fill(Accumulated::default());
+        taken
+    }
+}
+
+fn accumulate(channels: &mut [Accumulated], data: &[f32]) {
+    let count = channels.len();
+    for frame in data.chunks(count) {
+        for (level, &sample) in channels.iter_mut().zip(frame) {
+            level.peak = level.peak.max(sample.abs());
+            level.sum_squares += (sample as f64) * (sample as f64);
+            level.sum_abs += sample.abs() as f64;
+            level.count += 1;
+            if sample.abs() >= CLIP_LEVEL {
+                level.clipped += 1;
+                level.first_clip.get_or_insert_with(Local::now);
+            }
+        }
+    }
+}
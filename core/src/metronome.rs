@@ -0,0 +1,158 @@
+// A click to play in time to, mixed into the monitoring output and never into what's captured.
+// It can count in a few bars first, then either keep clicking or go quiet so only the count-in
+// is heard. The first beat of each bar is accented with a higher, louder click.
+
+use crate::dsp;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+const CLICK_SECONDS: f32 = 0.03;
+const CLICK_DECAY_SECONDS: f32 = 0.006; // Time constant of the click's fade
+const ACCENT_HZ: f32 = 2000.0;
+const BEAT_HZ: f32 = 1000.0;
+const BEAT_LEVEL: f32 = 0.6; // Of the accent
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetronomeSettings {
+    pub bpm: f32,
+    pub beats_per_bar: u32,
+    pub count_in_bars: u32,
+    pub after_count_in: bool, // Keep clicking once the count-in is over
+    pub level_db: f32,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        MetronomeSettings {
+            bpm: 120.0,
+            beats_per_bar: 4,
+            count_in_bars: 1,
+            after_count_in: true,
+            level_db: -12.0,
+        }
+    }
+}
+
+// For handing to a running click, see `live::LiveSettings`. The counts are small enough to be
+// exact as floats.
+impl From<MetronomeSettings> for [f32; 5] {
+    fn from(settings: MetronomeSettings) -> Self {
+        [
+            settings.bpm,
+            settings.beats_per_bar as f32,
+            settings.count_in_bars as f32,
+            if settings.after_count_in { 1.0 } else { 0.0 },
+            settings.level_db,
+        ]
+    }
+}
+
+impl From<[f32; 5]> for MetronomeSettings {
+    fn from([bpm, beats_per_bar, count_in_bars, after_count_in, level_db]: [f32; 5]) -> Self {
+        MetronomeSettings {
+            bpm,
+            beats_per_bar: beats_per_bar as u32,
+            count_in_bars: count_in_bars as u32,
+            after_count_in: after_count_in != 0.0,
+            level_db,
+        }
+    }
+}
+
+impl MetronomeSettings {
+    pub fn count_in_beats(&self) -> u64 {
+        self.count_in_bars as u64 * self.beats_per_bar.max(1) as u64
+    }
+
+    // e.g. "Count-in 3/4" or "Bar 5, beat 2", for the beat numbered from 0 at the start
+    pub fn describe_beat(&self, beat: u64) -> String {
+        let beats_per_bar = self.beats_per_bar.max(1) as u64;
+        let count_in = self.count_in_beats();
+        if beat < count_in {
+            format!("Count-in {}/{}", beat % beats_per_bar + 1, beats_per_bar)
+        } else {
+            let beat = beat - count_in;
+            format!(
+                "Bar {}, beat {}",
+                beat / beats_per_bar + 1,
+                beat % beats_per_bar + 1
+            )
+        }
+    }
+}
+
+pub struct Metronome {
+    sample_rate: f32,
+    settings: MetronomeSettings,
+    position: f64, // In beats since the start, the first click being at 0
+    accent: Vec<f32>,
+    beat: Vec<f32>,
+}
+
+impl Metronome {
+    pub fn new(sample_rate: u32, settings: MetronomeSettings) -> Self {
+        Metronome {
+            sample_rate: sample_rate as f32,
+            settings,
+            position: 0.0,
+            accent: click(sample_rate, ACCENT_HZ, 1.0),
+            beat: click(sample_rate, BEAT_HZ, BEAT_LEVEL),
+        }
+    }
+
+    // Takes effect from the next sample, carrying on from the same beat, so the tempo can be
+    // changed while playing without a restart or a new count-in
+    pub fn configure(&mut self, settings: MetronomeSettings) {
+        self.settings = settings;
+    }
+
+    // Back to the first beat of the count-in
+    pub fn restart(&mut self) {
+        self.position = 0.0;
+    }
+
+    // The beat sounding now, numbered from 0 with the count-in included
+    pub fn beat(&self) -> u64 {
+        self.position as u64
+    }
+
+    pub fn is_counting_in(&self) -> bool {
+        self.beat() < self.settings.count_in_beats()
+    }
+
+    // Adds the click to every channel of interleaved audio
+    pub fn mix(&mut self, data: &mut [f32], channels: usize) {
+        let beats_per_frame = self.settings.bpm.max(1.0) as f64 / 60.0 / self.sample_rate as f64;
+        let frames_per_beat = 1.0 / beats_per_frame;
+        let gain = dsp::db_to_gain(self.settings.level_db);
+        let beats_per_bar = self.settings.beats_per_bar.max(1) as u64;
+        for frame in data.chunks_mut(channels.max(1)) {
+            let beat = self.position as u64;
+            let audible = self.settings.after_count_in || beat < self.settings.count_in_beats();
+            let within = (self.position.fract() * frames_per_beat) as usize;
+            let voice = if beat.is_multiple_of(beats_per_bar) {
+                &self.accent
+            } else {
+                &self.beat
+            };
+            if audible {
+                if let Some(sample) = voice.get(within) {
+                    frame.iter_mut().for_each(|out| *out += sample * gain);
+                }
+            }
+            self.position += beats_per_frame;
+        }
+    }
+}
+
+// A short sine burst that dies away fast, easy to hear over an instrument without masking it
+fn click(sample_rate: u32, hz: f32, level: f32) -> Vec<f32> {
+    let length = (CLICK_SECONDS * sample_rate as f32) as usize;
+    (0..length)
+        .map(|index| {
+            let t = index as f32 / sample_rate as f32;
+            level * (-t / CLICK_DECAY_SECONDS).exp() * (2.0 * PI * hz * t).sin()
+        })
+        .collect()
+}
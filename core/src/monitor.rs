@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Most the drift correction may speed up or slow down playback, 0.5% is inaudible
+const MAX_CORRECTION: f64 = 0.005;
+// Automatic latency steps up this much after a dropout and eases down after a clean stretch
+const AUTO_RAISE: f64 = 1.5;
+const AUTO_LOWER: f64 = 0.9;
+const AUTO_CLEAN_SECONDS: f64 = 2.0;
+const SINC_HALF: u64 = 16; // Taps either side of the read position for the HQ sinc
+const SINC_PHASES: usize = 256; // Fractional positions the sinc weights are worked out for
+
+// Single producer, single consumer ring of interleaved samples from the input callback to the
+// monitoring output callback. Samples are kept as bits in atomics so neither side ever locks,
+// and all of it is allocated up front so neither side allocates.
+pub struct MonitorFifo {
+    slots: Box<[AtomicU32]>,
+    channels: usize,
+    written: AtomicU64, // Samples pushed since it was created, only moved by the input side
+    read: AtomicU64,    // Samples consumed, only moved by the output side
+    // Measurements for the latency display, all in frames
+    input_block: AtomicUsize,     // Frames in the last input callback
+    output_block: AtomicUsize,    // Frames in the last output callback
+    target: AtomicUsize,          // Depth the reader is holding, in input frames
+    depth: AtomicUsize,           // Smoothed depth the reader sees, in input frames
+    resampler_delay: AtomicUsize, // Input frames the resampler holds back
+}
+
+// Where the time goes between a sound at the input and hearing it, in milliseconds
+pub struct MonitorLatency {
+    pub input_ms: f32,
+    pub fifo_ms: f32,
+    pub target_ms: f32,
+    pub output_ms: f32,
+    pub resampler_ms: f32,
+    pub limiter_ms: f32,
+}
+
+impl MonitorLatency {
+    pub fn total_ms(&self) -> f32 {
+        self.input_ms + self.fifo_ms + self.output_ms + self.resampler_ms + self.limiter_ms
+    }
+}
+
+impl MonitorFifo {
+    pub fn new(frames: usize, channels: usize) -> Self {
+        let channels = channels.max(1);
+        MonitorFifo {
+            slots: (0..frames.max(1) * channels)
+                .map(|_| AtomicU32::new(0))
+                .collect(),
+            channels,
+            written: AtomicU64::new(0),
+            read: AtomicU64::new(0),
+            input_block: AtomicUsize::new(0),
+            output_block: AtomicUsize::new(0),
+            target: AtomicUsize::new(0),
+            depth: AtomicUsize::new(0),
+            resampler_delay: AtomicUsize::new(0),
+        }
+    }
+
+    // Input side. A block that doesn't fit is dropped rather than overwriting unread audio.
+    pub fn push(&self, data: &[f32]) -> bool {
+        self.input_block
+            .store(data.len() / self.channels, Ordering::Relaxed);
+        let written = self.written.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        let free = self.slots.len() as u64 - (written - read);
+        if data.len() as u64 > free {
+            return false;
+        }
+        for (i, &sample) in data.iter().enumerate() {
+            self.slot(written + i as u64)
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.written
+            .store(written + data.len() as u64, Ordering::Release);
+        true
+    }
+
+    // Input and output driver buffers aren't visible to cpal, so a callback's worth of each
+    // stands in for them
+    pub fn latency(&self, input_rate: u32, output_rate: u32) -> MonitorLatency {
+        let input_ms = |frames: &AtomicUsize| {
+            frames.load(Ordering::Relaxed) as f32 * 1000.0 / input_rate as f32
+        };
+        MonitorLatency {
+            input_ms: input_ms(&self.input_block),
+            fifo_ms: input_ms(&self.depth),
+            target_ms: input_ms(&self.target),
+            output_ms: self.output_block.load(Ordering::Relaxed) as f32 * 1000.0
+                / output_rate.max(1) as f32,
+            resampler_ms: input_ms(&self.resampler_delay),
+            limiter_ms: 0.0, // The limiter comes after the FIFO, so it's added by the caller
+        }
+    }
+
+    fn slot(&self, index: u64) -> &AtomicU32 {
+        &self.slots[(index % self.slots.len() as u64) as usize]
+    }
+
+    fn sample(&self, frame: u64, channel: usize) -> f32 {
+        f32::from_bits(
+            self.slot(frame * self.channels as u64 + channel as u64)
+                .load(Ordering::Relaxed),
+        )
+    }
+}
+
+// How the monitoring output converts the input's rate, separate from anything done on export.
+// Each looks further ahead than the last, which adds to the latency.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MonitorResampler {
+    Linear,   // Two points, the least delay and work, a little dull at the top
+    Balanced, // Four point cubic Hermite
+    HqSinc,   // Windowed sinc, clean but a few milliseconds of delay
+}
+
+impl MonitorResampler {
+    pub const ALL: [MonitorResampler; 3] = [
+        MonitorResampler::Linear,
+        MonitorResampler::Balanced,
+        MonitorResampler::HqSinc,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MonitorResampler::Linear => "Low-latency linear",
+            MonitorResampler::Balanced => "Balanced",
+            MonitorResampler::HqSinc => "HQ sinc",
+        }
+    }
+
+    // Frames before and after the read position each output sample uses
+    fn behind(&self) -> u64 {
+        match self {
+            MonitorResampler::Linear => 0,
+            MonitorResampler::Balanced => 1,
+            MonitorResampler::HqSinc => SINC_HALF - 1,
+        }
+    }
+
+    fn ahead(&self) -> u64 {
+        match self {
+            MonitorResampler::Linear => 1,
+            MonitorResampler::Balanced => 2,
+            MonitorResampler::HqSinc => SINC_HALF,
+        }
+    }
+}
+
+// Output side of the FIFO, one per monitoring stream. It converts to the output rate with the
+// chosen resampler, holds the FIFO at a target depth by nudging that rate to follow clock drift
+// between the devices, and mixes the inputs into the outputs by the given routes.
+pub struct MonitorReader {
+    fifo: Arc<MonitorFifo>,
+    routes: Vec<Vec<usize>>, // Input channels averaged into each output channel
+    resampler: MonitorResampler,
+    sinc: Vec<f32>,    // SINC_PHASES + 1 rows of 2 * SINC_HALF taps, built up front
+    step: f64,         // Input frames per output frame at the nominal rates
+    target: f64,       // Depth to hold, in input frames
+    max_target: f64,   // Most the automatic latency may raise the target to
+    auto: bool,        // Whether the target follows how much jitter the devices show
+    clean_frames: f64, // Output frames played since the last dropout
+    output_rate: f64,
+    frame: u64,    // Next input frame to play
+    depth: f64,    // Smoothed depth at the start of each block
+    position: f64, // Fraction of a frame past the read position
+    primed: bool,  // Whether the FIFO has filled up to the target since a dropout
+}
+
+impl MonitorReader {
+    pub fn new(
+        fifo: Arc<MonitorFifo>,
+        routes: Vec<Vec<usize>>,
+        resampler: MonitorResampler,
+        input_rate: u32,
+        output_rate: u32,
+        target_frames: usize,
+        auto: bool,
+    ) -> Self {
+        // Anything left from an earlier stream is stale
+        let frame = fifo.written.load(Ordering::Acquire) / fifo.channels as u64;
+        fifo.read.store(
+            frame.saturating_sub(resampler.behind()) * fifo.channels as u64,
+            Ordering::Release,
+        );
+        let target = target_frames.max(1) as f64;
+        fifo.target.store(target_frames, Ordering::Relaxed);
+        fifo.resampler_delay
+            .store(resampler.ahead() as usize, Ordering::Relaxed);
+        let step = input_rate as f64 / output_rate.max(1) as f64;
+        MonitorReader {
+            fifo,
+            routes,
+            resampler,
+            sinc: match resampler {
+                MonitorResampler::HqSinc => sinc_table(step),
+                _ => Vec::new(),
+            },
+            step,
+            target,
+            max_target: input_rate as f64 * 0.2,
+            auto,
+            clean_frames: 0.0,
+            output_rate: output_rate as f64,
+            frame,
+            depth: target,
+            position: 0.0,
+            primed: false,
+        }
+    }
+
+    // Fills an interleaved output block, returning false if the input ran dry part way
+    pub fn fill(&mut self, data: &mut [f32], output_channels: usize) -> bool {
+        let frames = (data.len() / output_channels.max(1)) as f64;
+        if self.auto {
+            self.adapt_target(frames);
+        }
+        let fifo = &self.fifo;
+        fifo.output_block.store(frames as usize, Ordering::Relaxed);
+        let channels = fifo.channels as u64;
+        let last = fifo.written.load(Ordering::Acquire) / channels; // One past the newest frame
+        let available = last.saturating_sub(self.frame) as f64;
+
+        if !self.primed {
+            if available < self.target {
+                data.fill(0.0);
+                return true; // Still filling, not a dropout
+            }
+            self.primed = true;
+            self.depth = self.target;
+        }
+        // Far behind, e.g. after the output stalled: drop the backlog instead of playing it late
+        if available > 2.0 * self.target + frames * self.step {
+            self.frame += (available - self.target) as u64;
+            self.position = 0.0;
+            self.depth = self.target;
+        }
+
+        self.depth += (available - self.depth) * 0.05;
+        fifo.depth.store(self.depth as usize, Ordering::Relaxed);
+        let error = (self.depth - self.target) / self.target;
+        let step = self.step * (1.0 + (error * 0.01).clamp(-MAX_CORRECTION, MAX_CORRECTION));
+
+        let ahead = self.resampler.ahead();
+        let mut complete = true;
+        for frame in data.chunks_mut(output_channels) {
+            let offset = self.position as u64;
+            let a = self.frame + offset;
+            if a + ahead >= last {
+                frame.fill(0.0);
+                complete = false;
+                continue;
+            }
+            let fraction = (self.position - offset as f64) as f32;
+            for (sample, sources) in frame.iter_mut().zip(&self.routes) {
+                let sum: f32 = sources
+                    .iter()
+                    .map(|&channel| self.interpolate(a, fraction, channel))
+                    .sum();
+                *sample = sum / sources.len().max(1) as f32;
+            }
+            for sample in frame.iter_mut().skip(self.routes.len()) {
+                *sample = 0.0;
+            }
+            self.position += step;
+        }
+
+        let consumed = (self.position as u64).min(last.saturating_sub(self.frame));
+        self.position -= consumed as f64;
+        self.frame += consumed;
+        // Frames the resampler still looks back at stay unread so they aren't overwritten
+        let fifo = &self.fifo;
+        fifo.read.store(
+            self.frame.saturating_sub(self.resampler.behind()) * channels,
+            Ordering::Release,
+        );
+        if !complete {
+            // Wait for the FIFO to fill back up rather than stuttering
+            self.primed = false;
+            self.position = 0.0;
+            if self.auto {
+                self.target = (self.target * AUTO_RAISE).min(self.max_target);
+                self.clean_frames = 0.0;
+                fifo.target.store(self.target as usize, Ordering::Relaxed);
+            }
+        }
+        complete
+    }
+
+    // One input channel at `fraction` of the way from frame `a` to the next
+    fn interpolate(&self, a: u64, fraction: f32, channel: usize) -> f32 {
+        let x = |offset: i64| self.fifo.sample(a.wrapping_add_signed(offset), channel);
+        match self.resampler {
+            MonitorResampler::Linear => {
+                let (x0, x1) = (x(0), x(1));
+                x0 + (x1 - x0) * fraction
+            }
+            MonitorResampler::Balanced => {
+                let (xm1, x0, x1, x2) = (x(-1), x(0), x(1), x(2));
+                let c1 = 0.5 * (x1 - xm1);
+                let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+                let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+                ((c3 * fraction + c2) * fraction + c1) * fraction + x0
+            }
+            MonitorResampler::HqSinc => {
+                let taps = 2 * SINC_HALF as usize;
+                let phase = (fraction * SINC_PHASES as f32).round() as usize;
+                let row = &self.sinc[phase * taps..(phase + 1) * taps];
+                row.iter()
+                    .enumerate()
+                    .map(|(k, &weight)| weight * x(k as i64 - (SINC_HALF as i64 - 1)))
+                    .sum()
+            }
+        }
+    }
+
+    // Eases the target down while playback stays clean, but never below what one block of input
+    // or output needs, since audio arrives and leaves a block at a time
+    fn adapt_target(&mut self, frames: f64) {
+        self.clean_frames += frames;
+        if self.clean_frames < AUTO_CLEAN_SECONDS * self.output_rate {
+            return;
+        }
+        self.clean_frames = 0.0;
+        let input_block = self.fifo.input_block.load(Ordering::Relaxed) as f64;
+        let floor = input_block.max(frames * self.step).max(1.0);
+        self.target = (self.target * AUTO_LOWER).max(floor);
+        self.fifo
+            .target
+            .store(self.target as usize, Ordering::Relaxed);
+    }
+}
+
+// Blackman windowed sinc weights for each fractional phase, taps running from SINC_HALF - 1
+// frames back to SINC_HALF ahead. The cutoff drops below the output's Nyquist when the rate goes
+// down, so nothing folds back.
+fn sinc_table(step: f64) -> Vec<f32> {
+    let cutoff = (1.0 / step).min(1.0);
+    let half = SINC_HALF as f64;
+    let mut table = Vec::with_capacity((SINC_PHASES + 1) * 2 * SINC_HALF as usize);
+    for phase in 0..=SINC_PHASES {
+        let fraction = phase as f64 / SINC_PHASES as f64;
+        let row: Vec<f64> = (0..2 * SINC_HALF)
+            .map(|k| {
+                let x = k as f64 - (half - 1.0) - fraction;
+                let sinc = if x.abs() < 1e-9 {
+                    1.0
+                } else {
+                    (std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * cutoff * x)
+                };
+                let w = std::f64::consts::PI * (x + half) / half; // 0 to 2π across the taps
+                sinc * (0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos())
+            })
+            .collect();
+        let sum: f64 = row.iter().sum();
+        table.extend(row.iter().map(|weight| (weight / sum) as f32));
+    }
+    table
+}
@@ -0,0 +1,89 @@
+use crate::backend::AudioBackend;
+use crate::export::Take;
+use crate::playback::CuePlayback;
+use crate::{CircularBuffer, RecorderError};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+// One pass of layering: a take (the bed) plays once out of the output while the input keeps
+// rolling, and the input from when the bed was heard until it ended is collected as the new
+// layer. The measured round trip is taken off where the layer starts, so it lines up with what
+// the player heard rather than with when the bed was sent. The input is copied out as it comes
+// in, so a bed longer than the rolling buffer still works.
+
+pub struct Overdub<S = cpal::Stream> {
+    playback: CuePlayback<S>,
+    frames: u64, // Of the bed
+    channels: u64,
+    latency_frames: u64,
+    buffer_id: u64,
+    next: u64, // Absolute index (see `CircularBuffer::total_written`) of the next sample to copy
+    layer: Vec<f32>,
+}
+
+impl<S> Overdub<S> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn start<B: AudioBackend<Stream = S>>(
+        backend: &B,
+        device: &str,
+        bed: &Take,
+        buffer_id: u64, // Of the buffer the layer is collected from
+        input_written: Arc<AtomicU64>,
+        input_channels: usize,
+        latency_ms: f32,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, RecorderError> {
+        let playback = CuePlayback::start(
+            backend,
+            device,
+            Arc::clone(&bed.samples),
+            bed.channels as usize,
+            bed.sample_rate,
+            input_written,
+            input_channels,
+            on_error,
+        )?;
+        Ok(Overdub {
+            playback,
+            frames: bed.frames() as u64,
+            channels: input_channels.max(1) as u64,
+            latency_frames: (latency_ms.max(0.0) / 1000.0 * bed.sample_rate as f32) as u64,
+            buffer_id,
+            next: u64::MAX, // Set once the bed's start has gone out
+            layer: Vec::new(),
+        })
+    }
+
+    // How far through the bed playback is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.playback.position() as f32 / self.frames.max(1) as f32
+    }
+
+    // Copies what the input has added since the last call, and hands back the layer once it's
+    // all in. An error means the pass can't be completed.
+    pub fn poll(&mut self, buffer: &CircularBuffer) -> Result<Option<Vec<f32>>, String> {
+        if buffer.id != self.buffer_id {
+            return Err("the input buffer was reset during the overdub".to_owned());
+        }
+        let Some(cued) = self.playback.cued() else {
+            return Ok(None);
+        };
+        let start = (cued + self.latency_frames) * self.channels;
+        let end = start + self.frames * self.channels;
+        if self.next == u64::MAX {
+            self.next = start;
+        }
+        if self.next < buffer.window_start() {
+            return Err("the input buffer overran, try a longer buffer".to_owned());
+        }
+        let to = end.min(buffer.total_written);
+        if to > self.next {
+            self.layer.extend(buffer.copy_range(self.next, to));
+            self.next = to;
+        }
+        if self.next < end {
+            return Ok(None);
+        }
+        Ok(Some(std::mem::take(&mut self.layer)))
+    }
+}
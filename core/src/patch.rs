@@ -0,0 +1,171 @@
+// Sampler patches mapping saved takes across the keyboard, so sliced hits or an auto-sampled
+// synth can be played straight away: SFZ for most soft samplers, and DecentSampler's
+// `.dspreset`. Sample paths are relative to the patch, which goes in the samples' folder.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    Sfz,
+    DecentSampler,
+}
+
+impl PatchFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Sfz => "sfz",
+            PatchFormat::DecentSampler => "dspreset",
+        }
+    }
+}
+
+// A sample and the keys and velocities that play it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Zone {
+    pub file: String, // Relative to the patch
+    pub root: u8,     // The key it plays unpitched on
+    pub low_key: u8,
+    pub high_key: u8,
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+}
+
+pub const KIT_FIRST_KEY: u8 = 36; // C1, the kick of a General MIDI kit
+
+// One sample per key from `first_key` up, as a drum kit is laid out. Each plays at its own
+// pitch whatever the velocity.
+pub fn chromatic_zones(files: &[String], first_key: u8) -> Vec<Zone> {
+    files
+        .iter()
+        .zip(first_key..=127)
+        .map(|(file, key)| Zone {
+            file: file.clone(),
+            root: key,
+            low_key: key,
+            high_key: key,
+            low_velocity: 1,
+            high_velocity: 127,
+        })
+        .collect()
+}
+
+// Samples of notes (note, velocity, file) spread over the whole keyboard: each note covers the
+// keys up to halfway to the sampled notes either side, and each velocity the ones above the
+// next softer layer of the same note, the loudest up to 127
+pub fn multisample_zones(samples: &[(u8, u8, String)]) -> Vec<Zone> {
+    let mut notes: Vec<u8> = samples.iter().map(|&(note, _, _)| note).collect();
+    notes.sort_unstable();
+    notes.dedup();
+    let mut zones = Vec::new();
+    for (index, &note) in notes.iter().enumerate() {
+        let halfway = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        let low_key = match index {
+            0 => 0,
+            _ => halfway(notes[index - 1], note) + 1,
+        };
+        let high_key = notes
+            .get(index + 1)
+            .map_or(127, |&next| halfway(note, next));
+        let mut layers: Vec<(u8, &String)> = samples
+            .iter()
+            .filter(|&&(n, _, _)| n == note)
+            .map(|(_, velocity, file)| (*velocity, file))
+            .collect();
+        layers.sort_by_key(|&(velocity, _)| velocity);
+        layers.dedup_by_key(|&mut (velocity, _)| velocity);
+        for (layer, &(velocity, file)) in layers.iter().enumerate() {
+            zones.push(Zone {
+                file: file.clone(),
+                root: note,
+                low_key,
+                high_key,
+                low_velocity: match layer {
+                    0 => 1,
+                    _ => layers[layer - 1].0 + 1,
+                },
+                high_velocity: if layer + 1 == layers.len() {
+                    127
+                } else {
+                    velocity
+                },
+            });
+        }
+    }
+    zones
+}
+
+// `one_shot` plays each sample through to its end whatever the key does, for drum hits. They
+// also follow the velocity, which a multisample's layers take care of instead.
+pub fn sfz(name: &str, zones: &[Zone], one_shot: bool) -> String {
+    let mut sfz = format!("// {}, written by Rolling Sampler\n\n<group>\n", name);
+    sfz.push_str(if one_shot {
+        "loop_mode=one_shot\n"
+    } else {
+        "amp_veltrack=0\n"
+    });
+    for zone in zones {
+        sfz.push_str(&format!(
+            // The sample last, since its path runs to the end of the line and may have spaces
+            "<region> pitch_keycenter={} lokey={} hikey={} lovel={} hivel={} sample={}\n",
+            zone.root,
+            zone.low_key,
+            zone.high_key,
+            zone.low_velocity,
+            zone.high_velocity,
+            zone.file.replace('\\', "/")
+        ));
+    }
+    sfz
+}
+
+pub fn decent_sampler(zones: &[Zone], one_shot: bool) -> String {
+    let mut preset = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<DecentSampler minVersion=\"1.0.0\">\n  <groups>\n",
+    );
+    // DecentSampler has no one-shot mode, so hits get a release long enough to ring out
+    preset.push_str(if one_shot {
+        "    <group ampVelTrack=\"1\" release=\"10\">\n"
+    } else {
+        "    <group ampVelTrack=\"0\">\n"
+    });
+    for zone in zones {
+        preset.push_str(&format!(
+            "      <sample path=\"{}\" rootNote=\"{}\" loNote=\"{}\" hiNote=\"{}\" loVel=\"{}\" hiVel=\"{}\"/>\n",
+            xml_attribute(&zone.file.replace('\\', "/")),
+            zone.root,
+            zone.low_key,
+            zone.high_key,
+            zone.low_velocity,
+            zone.high_velocity
+        ));
+    }
+    preset.push_str("    </group>\n  </groups>\n</DecentSampler>\n");
+    preset
+}
+
+// Writes `<name>.sfz` or `<name>.dspreset` into `dir`, where the samples are
+pub fn write_patch(
+    dir: &Path,
+    name: &str,
+    format: PatchFormat,
+    zones: &[Zone],
+    one_shot: bool,
+) -> io::Result<PathBuf> {
+    let path = dir.join(format!("{}.{}", name, format.extension()));
+    let text = match format {
+        PatchFormat::Sfz => sfz(name, zones, one_shot),
+        PatchFormat::DecentSampler => decent_sampler(zones, one_shot),
+    };
+    fs::create_dir_all(dir)?;
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+fn xml_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
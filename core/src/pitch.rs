@@ -0,0 +1,104 @@
+// YIN pitch detection and note naming, for the tuner and for tagging takes
+
+pub const WINDOW: usize = 1024; // Frames compared at each lag
+pub const LOWEST_HZ: f32 = 50.0;
+const HIGHEST_HZ: f32 = 1500.0;
+const THRESHOLD: f32 = 0.15; // YIN's dip threshold, lower is stricter
+const GATE_DB: f32 = -50.0; // Quieter than this reads as no note
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+// Most windows looked at across a take, enough to outvote a few octave errors
+const TAKE_WINDOWS: usize = 16;
+
+// The note a whole take sits on, e.g. ("A#2", 116.5), as the median of the pitches found in
+// evenly spaced windows. None for silence, noise or anything without a steady pitch.
+pub fn take_pitch(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    reference_hz: f32,
+) -> Option<(String, f32)> {
+    let channels = channels.max(1);
+    let length = WINDOW + (sample_rate as f32 / LOWEST_HZ) as usize;
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    if mono.len() < length {
+        return None;
+    }
+    let hop = ((mono.len() - length) / TAKE_WINDOWS).max(length / 2);
+    let mut pitches: Vec<f32> = (0..=mono.len() - length)
+        .step_by(hop)
+        .filter_map(|start| detect_pitch(&mono[start..start + length], sample_rate))
+        .collect();
+    if pitches.is_empty() {
+        return None;
+    }
+    pitches.sort_by(|a, b| a.total_cmp(b));
+    let hz = pitches[pitches.len() / 2];
+    let reading = Reading::new(hz, reference_hz);
+    Some((format!("{}{}", reading.name, reading.octave), hz))
+}
+
+// A frequency as the nearest note and how far off it is
+pub struct Reading {
+    pub hz: f32,
+    pub name: &'static str,
+    pub octave: i32,
+    pub cents: f32,
+}
+
+impl Reading {
+    pub fn new(hz: f32, reference_hz: f32) -> Self {
+        let midi = 69.0 + 12.0 * (hz / reference_hz).log2();
+        let nearest = midi.round();
+        Reading {
+            hz,
+            name: NOTE_NAMES[(nearest as i32).rem_euclid(12) as usize],
+            octave: (nearest as i32).div_euclid(12) - 1,
+            cents: (midi - nearest) * 100.0,
+        }
+    }
+}
+
+// YIN (de Cheveigné and Kawahara, 2002): the lag where the signal best matches itself, from
+// the cumulative mean normalised difference function with parabolic interpolation
+pub fn detect_pitch(frames: &[f32], sample_rate: u32) -> Option<f32> {
+    let mean_square = frames.iter().map(|s| s * s).sum::<f32>() / frames.len() as f32;
+    if 10.0 * mean_square.max(1e-12).log10() < GATE_DB {
+        return None;
+    }
+    let min_lag = (sample_rate as f32 / HIGHEST_HZ) as usize;
+    let max_lag = frames.len() - WINDOW;
+
+    let mut normalized = vec![1.0f32; max_lag + 1];
+    let mut running_sum = 0.0;
+    for lag in 1..=max_lag {
+        let difference: f32 = (0..WINDOW)
+            .map(|i| {
+                let delta = frames[i] - frames[i + lag];
+                delta * delta
+            })
+            .sum();
+        running_sum += difference;
+        normalized[lag] = difference * lag as f32 / running_sum.max(f32::EPSILON);
+    }
+
+    // The first dip under the threshold, followed down to its lowest point
+    let mut lag = (min_lag.max(2)..max_lag).find(|&lag| normalized[lag] < THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    let (before, at, after) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+    let curve = before + after - 2.0 * at;
+    let shift = if curve.abs() > f32::EPSILON {
+        (before - after) / (2.0 * curve)
+    } else {
+        0.0
+    };
+    Some(sample_rate as f32 / (lag as f32 + shift))
+}
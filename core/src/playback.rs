@@ -0,0 +1,292 @@
+use crate::backend::AudioBackend;
+use crate::RecorderError;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Interleaved audio, e.g. read back from disk
+pub struct Audio {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+// Plays part of a take through an output device, e.g. to review a grab before saving it.
+// Playback stops when this is dropped.
+pub struct Playback<S = cpal::Stream> {
+    _stream: S,
+    position: Arc<AtomicUsize>, // Current frame in the take
+    seek: Arc<AtomicUsize>,     // Frame to jump to, or usize::MAX for none
+    finished: Arc<AtomicBool>,
+    end_frame: usize,
+}
+
+impl<S> Playback<S> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn start<B: AudioBackend<Stream = S>>(
+        backend: &B,
+        device: &str,
+        samples: Arc<Vec<f32>>,
+        channels: usize,
+        sample_rate: u32,
+        start_frame: usize,
+        end_frame: usize,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, RecorderError> {
+        let config = backend.output_config(device, None)?;
+        let output_channels = config.channels as usize;
+        let channels = channels.max(1);
+        let end_frame = end_frame.min(samples.len() / channels);
+        // Read through the take at its own rate whatever rate the device runs at
+        let step = sample_rate as f64 / config.sample_rate.0 as f64;
+
+        let position = Arc::new(AtomicUsize::new(start_frame));
+        let seek = Arc::new(AtomicUsize::new(usize::MAX));
+        let finished = Arc::new(AtomicBool::new(false));
+        let stream_position = Arc::clone(&position);
+        let stream_seek = Arc::clone(&seek);
+        let stream_finished = Arc::clone(&finished);
+        let mut read_position = start_frame as f64;
+
+        let source = Source {
+            samples,
+            channels,
+            gain: 1.0,
+        };
+        let stream = backend.build_playback(
+            device,
+            &config,
+            move |data| {
+                let target = stream_seek.swap(usize::MAX, Ordering::Relaxed);
+                if target != usize::MAX {
+                    read_position = target as f64;
+                }
+                for frame in data.chunks_mut(output_channels) {
+                    if read_position as usize + 1 >= end_frame {
+                        frame.fill(0.0);
+                        stream_finished.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    source.write_frame(read_position, frame);
+                    read_position += step;
+                }
+                stream_position.store(read_position as usize, Ordering::Relaxed);
+            },
+            on_error,
+        )?;
+
+        Ok(Playback {
+            _stream: stream,
+            position,
+            seek,
+            finished,
+            end_frame,
+        })
+    }
+
+    pub fn position(&self) -> usize {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    pub fn end_frame(&self) -> usize {
+        self.end_frame
+    }
+
+    // Carry on playing from another frame, picked up at the start of the next output block
+    pub fn seek(&self, frame: usize) {
+        let frame = frame.min(self.end_frame.saturating_sub(1));
+        self.position.store(frame, Ordering::Relaxed);
+        self.seek.store(frame, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+// Plays a take once, from the start, noting how far the input had got (see
+// `CircularBuffer::total_written`, in frames) as its first frame went out, so what is played
+// along to it can be lined up with it. That is only known to the nearest input block.
+pub struct CuePlayback<S = cpal::Stream> {
+    _stream: S,
+    position: Arc<AtomicUsize>,
+    cued: Arc<AtomicU64>, // Input frame at the take's first frame, u64::MAX until it's played
+}
+
+impl<S> CuePlayback<S> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn start<B: AudioBackend<Stream = S>>(
+        backend: &B,
+        device: &str,
+        samples: Arc<Vec<f32>>,
+        channels: usize,
+        sample_rate: u32,              // Of the take, which is the input's
+        input_written: Arc<AtomicU64>, // `CircularBuffer::total_written`, kept by the input callback
+        input_channels: usize,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, RecorderError> {
+        let config = backend.output_config(device, None)?;
+        let output_channels = config.channels as usize;
+        let output_rate = config.sample_rate.0;
+        let step = sample_rate as f64 / output_rate as f64;
+        let input_channels = input_channels.max(1) as u64;
+        let source = Source {
+            samples,
+            channels: channels.max(1),
+            gain: 1.0,
+        };
+
+        let position = Arc::new(AtomicUsize::new(0));
+        let cued = Arc::new(AtomicU64::new(u64::MAX));
+        let stream_position = Arc::clone(&position);
+        let stream_cued = Arc::clone(&cued);
+        let mut read_position = 0.0f64;
+
+        let stream = backend.build_playback(
+            device,
+            &config,
+            move |data| {
+                if read_position == 0.0 {
+                    let written = input_written.load(Ordering::Relaxed) / input_channels;
+                    stream_cued.store(written, Ordering::Relaxed);
+                }
+                for frame in data.chunks_mut(output_channels) {
+                    source.write_frame(read_position, frame); // Silence past the end
+                    read_position += step;
+                }
+                stream_position.store(read_position as usize, Ordering::Relaxed);
+            },
+            on_error,
+        )?;
+
+        Ok(CuePlayback {
+            _stream: stream,
+            position,
+            cued,
+        })
+    }
+
+    pub fn position(&self) -> usize {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    pub fn cued(&self) -> Option<u64> {
+        Some(self.cued.load(Ordering::Relaxed)).filter(|&frame| frame != u64::MAX)
+    }
+}
+
+// Interleaved audio read at fractional frame positions
+struct Source {
+    samples: Arc<Vec<f32>>,
+    channels: usize,
+    gain: f32,
+}
+
+impl Source {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    // Linear interpolation between the frames either side of `position`, silence past the end
+    fn write_frame(&self, position: f64, frame: &mut [f32]) {
+        let index = position as usize;
+        if index + 1 >= self.frames() {
+            frame.fill(0.0);
+            return;
+        }
+        let t = (position - index as f64) as f32;
+        for (channel, out) in frame.iter_mut().enumerate() {
+            // Mono takes go to every output, extra outputs get silence
+            let source = if self.channels == 1 { 0 } else { channel };
+            *out = if source < self.channels {
+                let a = self.samples[index * self.channels + source];
+                let b = self.samples[(index + 1) * self.channels + source];
+                (a + (b - a) * t) * self.gain
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+// Two takes playing in lockstep, looping, with only one of them audible at a time so they can
+// be flipped between without losing the place. Both are level matched to the quieter one.
+pub struct AbPlayback<S = cpal::Stream> {
+    _stream: S,
+    playing_b: Arc<AtomicBool>,
+    pub b_offset_db: f32, // How much louder B was than A before matching
+}
+
+impl<S> AbPlayback<S> {
+    pub fn start<B: AudioBackend<Stream = S>>(
+        backend: &B,
+        device: &str,
+        a: Audio,
+        b: Audio,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, RecorderError> {
+        let config = backend.output_config(device, None)?;
+        let output_channels = config.channels as usize;
+        let device_rate = config.sample_rate.0 as f64;
+
+        let (rms_a, rms_b) = (rms(&a.samples), rms(&b.samples));
+        let target = rms_a.min(rms_b);
+        let gain = |rms: f32| if rms > 0.0 { target / rms } else { 1.0 };
+        let b_offset_db = 20.0 * (rms_b.max(1e-9) / rms_a.max(1e-9)).log10();
+
+        let make_source = |audio: Audio, rms_value: f32| Source {
+            samples: Arc::new(audio.samples),
+            channels: audio.channels.max(1),
+            gain: gain(rms_value),
+        };
+        let (rate_a, rate_b) = (a.sample_rate as f64, b.sample_rate as f64);
+        let a = make_source(a, rms_a);
+        let b = make_source(b, rms_b);
+        // Loop around once the longer one has finished
+        let length = (a.frames() as f64 / rate_a).max(b.frames() as f64 / rate_b);
+
+        let playing_b = Arc::new(AtomicBool::new(false));
+        let stream_playing_b = Arc::clone(&playing_b);
+        let mut time = 0.0;
+        let stream = backend.build_playback(
+            device,
+            &config,
+            move |data| {
+                let b_audible = stream_playing_b.load(Ordering::Relaxed);
+                for frame in data.chunks_mut(output_channels) {
+                    if b_audible {
+                        b.write_frame(time * rate_b, frame);
+                    } else {
+                        a.write_frame(time * rate_a, frame);
+                    }
+                    time += 1.0 / device_rate;
+                    if time >= length {
+                        time = 0.0;
+                    }
+                }
+            },
+            on_error,
+        )?;
+
+        Ok(AbPlayback {
+            _stream: stream,
+            playing_b,
+            b_offset_db,
+        })
+    }
+
+    pub fn switch(&self) {
+        self.playing_b.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn is_playing_b(&self) -> bool {
+        self.playing_b.load(Ordering::Relaxed)
+    }
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
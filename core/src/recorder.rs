@@ -0,0 +1,797 @@
+// The recorder itself: the input stream filling the rolling buffer through the capture chain,
+// the monitoring output, and grabs cut from the buffer and handed over to be saved. It knows
+// nothing about windows or settings files; an app sets it up, calls in when the user does
+// something, and polls it once per frame (or loop) for what the audio has done since.
+
+use crate::backend::AudioBackend;
+use crate::dsp::{
+    self, Biquad, DcBlocker, EqSettings, GateSettings, InterleavedFilter, NoiseGate, PeakLimiter,
+    ThreeBandEq, BUTTERWORTH_Q,
+};
+use crate::export::Take;
+use crate::live::LiveSettings;
+use crate::metadata::TakeAnalysis;
+use crate::meter::MeterTap;
+use crate::metronome::{Metronome, MetronomeSettings};
+use crate::monitor::{MonitorFifo, MonitorLatency, MonitorReader, MonitorResampler};
+use crate::overdub::Overdub;
+use crate::playback::{AbPlayback, Audio, Playback};
+use crate::roundtrip::RoundTripTest;
+use crate::routing::MonitorRouting;
+use crate::schedule::Scheduler;
+use crate::state::RecorderState;
+use crate::stats::{StreamStats, XrunDetector};
+use crate::tap::InputTap;
+use crate::{CircularBuffer, RecorderError};
+use chrono::{DateTime, Local};
+use cpal::{BufferSize, StreamConfig};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+// Stands in for the input's configuration when there is no usable input device
+pub const FALLBACK_CONFIG: StreamConfig = StreamConfig {
+    channels: 2,
+    sample_rate: cpal::SampleRate(48000),
+    buffer_size: BufferSize::Default,
+};
+const MONITOR_BLOCK_FRAMES: u32 = 256; // Output block size asked for when monitoring
+const TAP_SECONDS: f32 = 4.0; // Headroom for a writer fed from the input to fall behind
+const LIMITER_LOOKAHEAD_SECONDS: f32 = 0.0015;
+
+// What starts a grab without anyone asking for one
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Arming {
+    pub threshold_db: Option<f32>, // Keep the buffer empty until the input crosses this
+    pub vox: Option<VoxSettings>,  // Grab while there is signal, one take per utterance
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxSettings {
+    pub threshold_db: f32,
+    pub hang_seconds: f32, // How long the signal has to stay quiet before the take ends
+    pub pre_roll_seconds: f32,
+}
+
+// How the input reaches the monitoring output
+#[derive(Clone, Debug)]
+pub struct MonitorSettings {
+    pub routing: MonitorRouting,
+    pub custom_routes: Vec<usize>, // See `MonitorRouting::routes`
+    pub latency_ms: f32,           // Audio held between the input and the output
+    pub auto_latency: bool,        // Lower the held audio until just before dropouts
+    pub resampler: MonitorResampler,
+    pub limiter_ceiling_db: Option<f32>, // Peak limiter at the end of the path, when set
+}
+
+// A point in the rolling history, tracked by absolute sample index so it stays put as the ring
+// wraps around
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub number: usize,
+    pub index: u64,      // See `CircularBuffer::total_written`
+    pub generation: u64, // Of the history the index counts in, see `Recorder::restore_markers`
+    pub time: DateTime<Local>,
+}
+
+struct MarkerExport {
+    name: String,
+    start: u64,
+    end: u64,
+}
+
+pub struct Recorder<B: AudioBackend> {
+    pub backend: B, // Every stream is opened through it, by device name
+    pub state: RecorderState,
+    pub buffer: Arc<Mutex<CircularBuffer>>,
+    pub input_written: Arc<AtomicU64>, // The buffer's `total_written`, for callbacks that can't lock it
+    pub config: StreamConfig,          // The input's
+    pub buffer_seconds: f32,           // History to keep, from the next `start_input` on
+    pub arming: Arming,                // See `set_arming`
+    pub input_devices: Vec<String>,
+    pub input_index: usize, // Of the selected input device
+    pub output_devices: Vec<String>,
+    pub output_index: usize,
+    pub output_config: Option<StreamConfig>, // The monitoring stream's, while it runs
+    pub high_pass_hz: Arc<AtomicU32>,        // Capture high-pass cutoff, 0 for off, set live
+    pub dc_block: Arc<AtomicBool>,           // Whether the capture path removes DC offset
+    pub dc_offset: Arc<AtomicU32>,           // Measured input DC offset as bits, before any removal
+    pub capture_gate: Arc<LiveSettings<4>>,  // Noise gate before buffering, see `GateSettings`
+    pub capture_limiter: Arc<LiveSettings<2>>, // Threshold and release of the limiter before buffering
+    pub monitor_gain: Arc<AtomicU32>,          // Linear gain on the monitoring output as bits
+    pub monitor_eq: Arc<LiveSettings<3>>,      // EQ band gains on the monitoring output
+    pub metronome: Arc<LiveSettings<5>>, // Click in the monitoring output, see `MetronomeSettings`
+    pub metronome_beat: Arc<AtomicU64>,  // Beat the click is on, counted from when it started
+    pub input_peak: Arc<AtomicU32>,      // Peak of the latest input callback as bits
+    pub meter_tap: Arc<MeterTap>,        // Per-channel levels from the input callback
+    pub monitor_meter_tap: Arc<MeterTap>, // and of what's sent to the monitoring output
+    pub stream_stats: Arc<StreamStats>,  // Xruns and errors from the audio callbacks
+    // Where the input callback also sends its audio, replaced with each input stream
+    pub safety_tap: Arc<InputTap>,
+    pub broadcast_tap: Arc<InputTap>,
+    pub markers: Vec<Marker>, // Dropped while playing, dropped again once they roll out
+    pub scheduler: Scheduler, // Fires timed grabs from a background thread
+    input_stream: Option<B::Stream>,
+    output_stream: Option<B::Stream>,
+    monitor_fifo: Arc<MonitorFifo>, // Input to the monitoring output, replaced with each input stream
+    monitoring: Arc<AtomicBool>,
+    monitor_limiter_frames: usize, // Lookahead of the monitoring limiter, 0 when it's off
+    buffer_size: usize,            // Samples the rolling window holds
+    generation: u64, // Moved on whenever the history restarts and indices count from 0 again
+    marker_exports: Vec<MarkerExport>, // Waiting for the audio after a marker to come in
+    saves: Vec<JoinHandle<Option<(PathBuf, TakeAnalysis)>>>, // Writer threads that may still be running
+}
+
+impl<B: AudioBackend> Recorder<B> {
+    // Nothing is opened until `start_input`; list the devices first
+    pub fn new(backend: B, buffer_seconds: f32) -> Self {
+        let config = FALLBACK_CONFIG;
+        let buffer_size =
+            (buffer_seconds * config.sample_rate.0 as f32 * config.channels as f32) as usize;
+        let channels = config.channels as usize;
+        Recorder {
+            backend,
+            state: RecorderState::Idle,
+            buffer: Arc::new(Mutex::new(CircularBuffer::new(buffer_size))),
+            input_written: Arc::new(AtomicU64::new(0)),
+            config,
+            buffer_seconds,
+            arming: Arming::default(),
+            input_devices: Vec::new(),
+            input_index: 0,
+            output_devices: Vec::new(),
+            output_index: 0,
+            output_config: None,
+            high_pass_hz: Arc::new(AtomicU32::new(0)),
+            dc_block: Arc::new(AtomicBool::new(false)),
+            dc_offset: Arc::new(AtomicU32::new(0)),
+            capture_gate: Arc::new(LiveSettings::new(false, GateSettings::default().into())),
+            capture_limiter: Arc::new(LiveSettings::new(false, [0.0, 100.0])),
+            monitor_gain: Arc::new(AtomicU32::new(1f32.to_bits())),
+            monitor_eq: Arc::new(LiveSettings::new(false, EqSettings::default().into())),
+            metronome: Arc::new(LiveSettings::new(
+                false,
+                MetronomeSettings::default().into(),
+            )),
+            metronome_beat: Arc::new(AtomicU64::new(0)),
+            input_peak: Arc::new(AtomicU32::new(0)),
+            meter_tap: Arc::new(MeterTap::new(channels)),
+            monitor_meter_tap: Arc::new(MeterTap::new(2)),
+            stream_stats: Arc::new(StreamStats::default()),
+            safety_tap: Arc::new(InputTap::new(0)),
+            broadcast_tap: Arc::new(InputTap::new(0)),
+            markers: Vec::new(),
+            scheduler: Scheduler::spawn(Vec::new()),
+            input_stream: None,
+            output_stream: None,
+            monitor_fifo: Arc::new(MonitorFifo::new(0, channels)),
+            monitoring: Arc::new(AtomicBool::new(false)),
+            monitor_limiter_frames: 0,
+            buffer_size,
+            marker_exports: Vec::new(),
+            generation: 0,
+            saves: Vec::new(),
+        }
+    }
+
+    // List the devices, selecting the named ones if they're there and the first otherwise. On
+    // an error the old lists are kept.
+    pub fn list_devices(
+        &mut self,
+        input: Option<&str>,
+        output: Option<&str>,
+    ) -> Result<(), RecorderError> {
+        let inputs = self.backend.input_devices()?;
+        let outputs = self.backend.output_devices()?;
+        let position = |devices: &[String], name: Option<&str>| {
+            name.and_then(|name| devices.iter().position(|device| device == name))
+                .unwrap_or(0)
+        };
+        self.input_index = position(&inputs, input);
+        self.output_index = position(&outputs, output);
+        self.input_devices = inputs;
+        self.output_devices = outputs;
+        Ok(())
+    }
+
+    // The selected devices' names, or an error when there aren't any
+    pub fn input_device(&self) -> Result<&str, RecorderError> {
+        self.input_devices
+            .get(self.input_index)
+            .map(String::as_str)
+            .ok_or_else(|| RecorderError::Device("no input device found".to_owned()))
+    }
+
+    pub fn output_device(&self) -> Result<&str, RecorderError> {
+        self.output_devices
+            .get(self.output_index)
+            .map(String::as_str)
+            .ok_or_else(|| RecorderError::Device("no output device found".to_owned()))
+    }
+
+    // (Re)open the selected input with an empty buffer of `buffer_seconds`. Monitoring reads
+    // from the old input until it's started again.
+    pub fn start_input(&mut self) -> Result<(), RecorderError> {
+        let result = self.open_input();
+        match &result {
+            Ok(()) => self.state.enter(RecorderState::Rolling),
+            Err(e) => self.state.enter(RecorderState::Error(e.to_string())),
+        };
+        result
+    }
+
+    fn open_input(&mut self) -> Result<(), RecorderError> {
+        self.input_stream = None;
+        let input_device = self.input_device()?.to_owned();
+        self.config = self.backend.input_config(&input_device)?;
+        // The same length of history whatever rate and channel count the device has
+        self.buffer_size = (self.buffer_seconds
+            * self.config.sample_rate.0 as f32
+            * self.config.channels as f32) as usize;
+
+        self.reset_buffer();
+        self.prepare_buffer(&mut self.buffer.lock().unwrap());
+        let sample_buffer = Arc::clone(&self.buffer);
+        let input_written = Arc::clone(&self.input_written);
+        let input_peak = Arc::clone(&self.input_peak);
+        self.meter_tap = Arc::new(MeterTap::new(self.config.channels as usize));
+        let meter_tap = Arc::clone(&self.meter_tap);
+
+        // A second of headroom between the input and the monitoring output
+        self.monitor_fifo = Arc::new(MonitorFifo::new(
+            self.config.sample_rate.0 as usize,
+            self.config.channels as usize,
+        ));
+        let monitor_fifo = Arc::clone(&self.monitor_fifo);
+        let is_monitoring = Arc::clone(&self.monitoring);
+        let num_channels = self.config.channels as usize;
+        let stream_stats = Arc::clone(&self.stream_stats);
+        let sample_rate = self.config.sample_rate.0;
+        let mut xrun_detector = XrunDetector::default();
+        let tap_samples =
+            (TAP_SECONDS * self.config.sample_rate.0 as f32 * self.config.channels as f32) as usize;
+        self.safety_tap = Arc::new(InputTap::new(tap_samples));
+        let safety_tap = Arc::clone(&self.safety_tap);
+        self.broadcast_tap = Arc::new(InputTap::new(tap_samples));
+        let broadcast_tap = Arc::clone(&self.broadcast_tap);
+        let high_pass_hz = Arc::clone(&self.high_pass_hz);
+        let make_high_pass =
+            move |hz: u32| Biquad::high_pass(sample_rate as f32, hz as f32, BUTTERWORTH_Q);
+        let mut high_pass_at = high_pass_hz.load(Ordering::Relaxed);
+        let mut high_pass = InterleavedFilter::new(num_channels, || make_high_pass(high_pass_at));
+        let mut filtered = Vec::new();
+        let dc_block = Arc::clone(&self.dc_block);
+        let dc_offset = Arc::clone(&self.dc_offset);
+        let mut dc_blocker = DcBlocker::new(num_channels, sample_rate);
+        let capture_gate = Arc::clone(&self.capture_gate);
+        let mut gate_at = 0;
+        let mut gate = NoiseGate::new(num_channels, sample_rate, GateSettings::default());
+        let capture_limiter = Arc::clone(&self.capture_limiter);
+        let mut limiter_at = 0;
+        let mut limiter =
+            PeakLimiter::new(num_channels, sample_rate, 0.0, LIMITER_LOOKAHEAD_SECONDS);
+
+        let stream = self.backend.build_capture(
+            &input_device,
+            &self.config,
+            move |data, captured| {
+                let started = Instant::now();
+                let xrun = xrun_detector.check(captured, data.len() / num_channels, sample_rate);
+                stream_stats.record_input_callback(xrun);
+
+                // DC and high-pass filtering before anything else sees the audio, so the offset
+                // and rumble never get in
+                dc_blocker.measure(data);
+                dc_offset.store(dc_blocker.offset().to_bits(), Ordering::Relaxed);
+                let block_dc = dc_block.load(Ordering::Relaxed);
+                let cutoff = high_pass_hz.load(Ordering::Relaxed);
+                let gate_on = capture_gate.enabled();
+                if let Some(settings) = capture_gate.changed(&mut gate_at) {
+                    gate.configure(settings.into());
+                }
+                let limiter_on = capture_limiter.enabled();
+                if let Some([threshold_db, release_ms]) = capture_limiter.changed(&mut limiter_at) {
+                    limiter.set_ceiling(threshold_db);
+                    limiter.set_release(release_ms);
+                }
+                let data = if cutoff == 0 && !block_dc && !gate_on && !limiter_on {
+                    data
+                } else {
+                    filtered.clear();
+                    filtered.extend_from_slice(data);
+                    if block_dc {
+                        dc_blocker.process(&mut filtered);
+                    }
+                    if cutoff != 0 {
+                        if cutoff != high_pass_at {
+                            high_pass_at = cutoff;
+                            high_pass.retune(|| make_high_pass(cutoff));
+                        }
+                        high_pass.process(&mut filtered);
+                    }
+                    if gate_on {
+                        gate.process(&mut filtered);
+                    }
+                    // Last, so nothing after it can push the level back over
+                    if limiter_on {
+                        stream_stats
+                            .capture_limiter
+                            .record(limiter.process(&mut filtered));
+                    }
+                    &filtered[..]
+                };
+
+                // Meter tap
+                let peak = data.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+                input_peak.store(peak.to_bits(), Ordering::Relaxed);
+                meter_tap.add_samples(data);
+
+                // Write to sample_buffer
+                let mut buffer = sample_buffer.lock().unwrap();
+                buffer.add_samples(data);
+                input_written.store(buffer.total_written, Ordering::Relaxed);
+                drop(buffer);
+
+                safety_tap.push(data);
+                broadcast_tap.push(data);
+
+                if is_monitoring.load(Ordering::Relaxed) {
+                    monitor_fifo.push(data);
+                }
+                stream_stats.record_input_busy(started.elapsed());
+            },
+            self.stream_stats.error_handler("input"),
+        )?;
+        self.input_stream = Some(stream);
+        Ok(())
+    }
+
+    pub fn has_input(&self) -> bool {
+        self.input_stream.is_some()
+    }
+
+    // The open streams, for backends that are driven by hand like the mock
+    pub fn input_stream(&mut self) -> Option<&mut B::Stream> {
+        self.input_stream.as_mut()
+    }
+
+    pub fn output_stream(&mut self) -> Option<&mut B::Stream> {
+        self.output_stream.as_mut()
+    }
+
+    // Only one reader may use the FIFO at a time, so a running monitoring stream is replaced
+    pub fn start_monitoring(&mut self, settings: &MonitorSettings) -> Result<(), RecorderError> {
+        self.output_stream = None;
+        let output_device = self.output_device()?.to_owned();
+        // Small blocks keep the latency down, where the device lets us choose
+        let config = self
+            .backend
+            .output_config(&output_device, Some(MONITOR_BLOCK_FRAMES))?;
+
+        let num_output_channels = config.channels as usize;
+        let routes = settings.routing.routes(
+            &settings.custom_routes,
+            self.config.channels as usize,
+            num_output_channels,
+        );
+        let target_frames =
+            (settings.latency_ms / 1000.0 * self.config.sample_rate.0 as f32) as usize;
+        let mut reader = MonitorReader::new(
+            Arc::clone(&self.monitor_fifo),
+            routes,
+            settings.resampler,
+            self.config.sample_rate.0,
+            config.sample_rate.0,
+            target_frames,
+            settings.auto_latency,
+        );
+        let mut limiter = settings.limiter_ceiling_db.map(|ceiling_db| {
+            PeakLimiter::new(
+                num_output_channels,
+                config.sample_rate.0,
+                ceiling_db,
+                LIMITER_LOOKAHEAD_SECONDS,
+            )
+        });
+        self.monitor_limiter_frames = limiter.as_ref().map_or(0, |l| l.lookahead_frames());
+        let monitor_gain = Arc::clone(&self.monitor_gain);
+        let monitor_eq = Arc::clone(&self.monitor_eq);
+        let mut eq_at = 0;
+        let metronome_settings = Arc::clone(&self.metronome);
+        let metronome_beat = Arc::clone(&self.metronome_beat);
+        // Made here so the callback never allocates
+        let mut metronome = Metronome::new(config.sample_rate.0, self.metronome.get().into());
+        let mut metronome_at = 0;
+        let mut metronome_was_on = false;
+        let mut eq = ThreeBandEq::new(
+            num_output_channels,
+            config.sample_rate.0,
+            EqSettings::default(),
+        );
+        let stream_stats = Arc::clone(&self.stream_stats);
+        self.monitor_meter_tap = Arc::new(MeterTap::new(num_output_channels));
+        let meter_tap = Arc::clone(&self.monitor_meter_tap);
+        let output_stream = self
+            .backend
+            .build_playback(
+                &output_device,
+                &config,
+                move |data| {
+                    if !reader.fill(data, num_output_channels) {
+                        stream_stats.record_monitor_underrun(); // Part of this block is silence
+                    }
+                    if let Some(settings) = monitor_eq.changed(&mut eq_at) {
+                        eq.configure(settings.into()); // New coefficients only when they've changed
+                    }
+                    if monitor_eq.enabled() {
+                        eq.process(data);
+                    }
+                    let gain = f32::from_bits(monitor_gain.load(Ordering::Relaxed));
+                    if gain != 1.0 {
+                        data.iter_mut().for_each(|sample| *sample *= gain);
+                    }
+                    // After the gain, so turning the instrument down doesn't lose the click
+                    if let Some(settings) = metronome_settings.changed(&mut metronome_at) {
+                        metronome.configure(settings.into());
+                    }
+                    let metronome_on = metronome_settings.enabled();
+                    if metronome_on {
+                        if !metronome_was_on {
+                            metronome.restart(); // Each start counts in afresh
+                        }
+                        metronome.mix(data, num_output_channels);
+                        metronome_beat.store(metronome.beat(), Ordering::Relaxed);
+                    }
+                    metronome_was_on = metronome_on;
+                    if let Some(limiter) = &mut limiter {
+                        stream_stats.monitor_limiter.record(limiter.process(data));
+                    }
+                    // Last, so it shows exactly what reaches the headphones
+                    meter_tap.try_add_samples(data);
+                },
+                self.stream_stats.error_handler("output"),
+            )
+            .map_err(|e| e.context("can't monitor"))?;
+
+        self.output_stream = Some(output_stream);
+        self.output_config = Some(config);
+        self.monitoring.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn stop_monitoring(&mut self) {
+        self.output_stream = None;
+        self.output_config = None;
+        self.monitoring.store(false, Ordering::SeqCst);
+        self.metronome.set_enabled(false); // It has nowhere to play
+    }
+
+    pub fn is_monitoring(&self) -> bool {
+        self.output_stream.is_some()
+    }
+
+    // End-to-end delay of the monitoring path, while monitoring
+    pub fn monitor_latency(&self) -> Option<MonitorLatency> {
+        let output = self.output_config.as_ref()?;
+        Some(MonitorLatency {
+            limiter_ms: self.monitor_limiter_frames as f32 * 1000.0 / output.sample_rate.0 as f32,
+            ..self
+                .monitor_fifo
+                .latency(self.config.sample_rate.0, output.sample_rate.0)
+        })
+    }
+
+    // The click only plays while monitoring. Each start counts in afresh; changing the settings
+    // of a running click carries on from the same beat.
+    pub fn set_metronome(&self, running: bool, settings: MetronomeSettings) {
+        if running && !self.metronome.enabled() {
+            self.metronome_beat.store(0, Ordering::Relaxed);
+        }
+        self.metronome.set(settings.into());
+        self.metronome.set_enabled(running);
+    }
+
+    // Waiting for signal and VOX take effect straight away, unless a grab is under way, which
+    // carries on as it started
+    pub fn set_arming(&mut self, arming: Arming) {
+        let was = std::mem::replace(&mut self.arming, arming);
+        if self.state.is_grabbing() {
+            return;
+        }
+        if was.threshold_db.is_none() && arming.threshold_db.is_some() {
+            // Start from an empty buffer so the take begins at the hit
+            self.reset_buffer();
+            self.prepare_buffer(&mut self.buffer.lock().unwrap());
+            return;
+        }
+        let mut buffer = self.buffer.lock().unwrap();
+        match arming.threshold_db {
+            Some(threshold_db) if buffer.is_armed() => buffer.arm(
+                dsp::db_to_gain(threshold_db),
+                self.config.channels as usize,
+                self.config.sample_rate.0,
+            ),
+            Some(_) => {}
+            None => buffer.disarm(),
+        }
+        if arming.vox != was.vox {
+            buffer.vox = None;
+            self.apply_vox(&mut buffer);
+        }
+    }
+
+    // Apply the wait-for-signal and VOX modes to a fresh buffer
+    fn prepare_buffer(&self, buffer: &mut CircularBuffer) {
+        let channels = self.config.channels as usize;
+        let sample_rate = self.config.sample_rate.0;
+        // For anything reading the buffer without the stream config, like a crash recovery copy
+        buffer.channels = channels;
+        buffer.sample_rate = sample_rate;
+        if let Some(threshold_db) = self.arming.threshold_db {
+            buffer.arm(dsp::db_to_gain(threshold_db), channels, sample_rate);
+        }
+        self.apply_vox(buffer);
+    }
+
+    fn apply_vox(&self, buffer: &mut CircularBuffer) {
+        let Some(vox) = self.arming.vox else {
+            return;
+        };
+        let sample_rate = self.config.sample_rate.0;
+        let seconds_to_frames = |seconds: f32| (seconds * sample_rate as f32) as usize;
+        buffer.enable_vox(
+            dsp::db_to_gain(vox.threshold_db),
+            seconds_to_frames(vox.hang_seconds),
+            seconds_to_frames(vox.pre_roll_seconds),
+            self.config.channels as usize,
+        );
+    }
+
+    // Empty the buffer, keeping its size. The contents are replaced in place so a running input
+    // stream keeps writing to it.
+    pub fn reset_buffer(&mut self) {
+        *self.buffer.lock().unwrap() = CircularBuffer::new(self.buffer_size);
+        self.input_written.store(0, Ordering::Relaxed);
+        // The history they pointed into is gone
+        self.generation += 1;
+        self.markers.clear();
+        self.marker_exports.clear();
+    }
+
+    // Freeze the history and keep everything from here on, if there is an input to grab from
+    pub fn start_grab(&mut self) -> bool {
+        if !self.state.enter(RecorderState::Grabbing) {
+            return false;
+        }
+        self.buffer.lock().unwrap().start_static_mode();
+        true
+    }
+
+    // Stop the grab in progress and hand over what it caught (in `static_buffer`). A fresh
+    // buffer is swapped in while holding the lock, so the input stream keeps running and no
+    // audio is lost while the grab is written out.
+    pub fn grab(&mut self) -> Option<CircularBuffer> {
+        if !self.state.enter(RecorderState::Saving) {
+            return None;
+        }
+        let grabbed = {
+            let mut buffer = self.buffer.lock().unwrap();
+            let mut next = CircularBuffer::new(buffer.max_size);
+            next.total_written = buffer.total_written;
+            if self.arming.threshold_db.is_none() {
+                // Carry on rolling from the end of the grab so the history stays continuous
+                let samples = &buffer.static_buffer;
+                let carried = &samples[samples.len().saturating_sub(next.max_size)..];
+                // Keep absolute indices running on so markers still point at the same audio
+                next.total_written -= carried.len() as u64;
+                next.add_samples(carried);
+            }
+            self.prepare_buffer(&mut next);
+            std::mem::replace(&mut *buffer, next)
+        };
+        self.state.enter(RecorderState::Rolling);
+        Some(grabbed)
+    }
+
+    // Throw away the grab in progress and start rolling again from empty
+    pub fn discard(&mut self) {
+        self.reset_buffer();
+        self.prepare_buffer(&mut self.buffer.lock().unwrap());
+        self.state.enter(RecorderState::Rolling);
+    }
+
+    // An armed or VOX buffer goes static by itself once the input is loud enough, which makes
+    // that a grab
+    pub fn poll_auto_grab(&mut self) {
+        if self.state == RecorderState::Rolling && self.buffer.lock().unwrap().is_static_mode {
+            self.state.enter(RecorderState::Grabbing);
+        }
+    }
+
+    // Whether a VOX grab has gone quiet for the hang time and wants stopping
+    pub fn vox_finished(&self) -> bool {
+        self.buffer.lock().unwrap().vox_finished
+    }
+
+    // Number of frames captured so far in the current grab
+    pub fn buffered_frames(&self) -> usize {
+        self.buffer.lock().unwrap().current_size / self.config.channels.max(1) as usize
+    }
+
+    // Length of the rolling window in seconds
+    pub fn window_seconds(&self) -> f32 {
+        self.buffer_size as f32 / (self.config.sample_rate.0 as f32 * self.config.channels as f32)
+    }
+
+    pub fn drop_marker(&mut self, number: usize) {
+        let index = self.buffer.lock().unwrap().total_written;
+        println!("Marker {} dropped", number);
+        self.markers.push(Marker {
+            number,
+            index,
+            generation: self.generation,
+            time: Local::now(),
+        });
+    }
+
+    // Put back markers taken away earlier, e.g. with a session that was switched away from.
+    // Those from a history that has since restarted would point at the wrong audio, and those
+    // that have rolled out point at none, so both are dropped.
+    pub fn restore_markers(&mut self, markers: Vec<Marker>) {
+        let window_start = self.buffer.lock().unwrap().window_start();
+        for marker in markers {
+            if marker.generation == self.generation && marker.index >= window_start {
+                self.markers.push(marker);
+            } else {
+                println!("Marker {} is no longer in the buffer", marker.number);
+            }
+        }
+    }
+
+    pub fn marker(&self, number: usize) -> Option<&Marker> {
+        self.markers.iter().find(|marker| marker.number == number)
+    }
+
+    // Queue an export, handed back by `poll_markers` as soon as all of its audio has come in
+    pub fn export_between(&mut self, name: String, start: u64, end: u64) {
+        let frame = self.config.channels.max(1) as u64;
+        self.marker_exports.push(MarkerExport {
+            name,
+            start: start / frame * frame,
+            end: end / frame * frame,
+        });
+    }
+
+    pub fn cancel_exports(&mut self) {
+        self.marker_exports.clear();
+    }
+
+    pub fn pending_exports(&self) -> usize {
+        self.marker_exports.len()
+    }
+
+    // Forgets the markers that have rolled out of the buffer, and returns the exports whose
+    // audio is all in: their name, the absolute index they start at and their samples
+    pub fn poll_markers(&mut self) -> Vec<(String, u64, Vec<f32>)> {
+        let mut ready = Vec::new();
+        let buffer = self.buffer.lock().unwrap();
+        let window_start = buffer.window_start();
+        self.markers.retain(|marker| {
+            let kept = marker.index >= window_start;
+            if !kept {
+                println!("Marker {} rolled out of the buffer", marker.number);
+            }
+            kept
+        });
+        self.marker_exports.retain(|export| {
+            if buffer.total_written < export.end {
+                return true;
+            }
+            let start = export.start.max(window_start);
+            ready.push((
+                export.name.clone(),
+                start,
+                buffer.copy_range(start, export.end),
+            ));
+            false
+        });
+        ready
+    }
+
+    // Write a take on a thread of its own, reporting failures to `errors`
+    pub fn save(&mut self, take: Take, errors: Sender<RecorderError>) {
+        self.saves.push(take.save_in_background(errors));
+    }
+
+    pub fn is_saving(&self) -> bool {
+        !self.saves.is_empty()
+    }
+
+    // The analysis from writer threads that have finished
+    pub fn collect_saves(&mut self) -> Vec<(PathBuf, TakeAnalysis)> {
+        let (finished, running) = std::mem::take(&mut self.saves)
+            .into_iter()
+            .partition(|save| save.is_finished());
+        self.saves = running;
+        finished
+            .into_iter()
+            .filter_map(|save| save.join().ok().flatten())
+            .collect()
+    }
+
+    // Block until every take handed to a writer thread is on disk
+    pub fn wait_for_saves(&mut self) -> Vec<(PathBuf, TakeAnalysis)> {
+        std::mem::take(&mut self.saves)
+            .into_iter()
+            .filter_map(|save| save.join().ok().flatten())
+            .collect()
+    }
+
+    // Plays interleaved audio through the selected output, from one frame to another
+    pub fn play(
+        &self,
+        samples: Arc<Vec<f32>>,
+        channels: usize,
+        sample_rate: u32,
+        start_frame: usize,
+        end_frame: usize,
+    ) -> Result<Playback<B::Stream>, RecorderError> {
+        Playback::start(
+            &self.backend,
+            self.output_device()?,
+            samples,
+            channels,
+            sample_rate,
+            start_frame,
+            end_frame,
+            self.stream_stats.error_handler("playback"),
+        )
+    }
+
+    pub fn play_ab(&self, a: Audio, b: Audio) -> Result<AbPlayback<B::Stream>, RecorderError> {
+        AbPlayback::start(
+            &self.backend,
+            self.output_device()?,
+            a,
+            b,
+            self.stream_stats.error_handler("A/B playback"),
+        )
+    }
+
+    // Plays `bed` once while collecting the input over it, see `Overdub`. The bed has to have
+    // the input's channels and rate.
+    pub fn overdub(
+        &self,
+        bed: &Take,
+        latency_ms: f32,
+        label: &'static str,
+    ) -> Result<Overdub<B::Stream>, RecorderError> {
+        let buffer_id = self.buffer.lock().unwrap().id;
+        Overdub::start(
+            &self.backend,
+            self.output_device()?,
+            bed,
+            buffer_id,
+            Arc::clone(&self.input_written),
+            self.config.channels as usize,
+            latency_ms,
+            self.stream_stats.error_handler(label),
+        )
+    }
+
+    // Plays clicks to be heard again on the input, see `RoundTripTest`. Stop monitoring first,
+    // since with the output looped back to the input it would feed back.
+    pub fn start_round_trip(&self) -> Result<RoundTripTest<B::Stream>, RecorderError> {
+        RoundTripTest::start(
+            &self.backend,
+            self.output_device()?,
+            Arc::clone(&self.input_written),
+            self.config.channels as usize,
+            self.config.sample_rate.0,
+            self.stream_stats.error_handler("latency test"),
+        )
+    }
+}
@@ -0,0 +1,119 @@
+use crate::backend::AudioBackend;
+use crate::{latency, CircularBuffer, RecorderError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Measures the round trip from the output back to the input. A few clicks are played out of
+// the output while the input keeps rolling, noting how far the input had got as each went out;
+// each click found again in the buffer afterwards is one measurement. The input's position is
+// only known to the nearest input block, which the median over the clicks doesn't take away.
+// The output callback only touches atomics, so it doesn't add jitter to what it's measuring.
+
+const CLICKS: usize = 5;
+const SPACING_SECONDS: f32 = 0.4; // Between clicks, and before the first while the stream settles
+const LISTEN_SECONDS: f32 = 0.5; // Longest round trip looked for
+const MIN_HEARD: usize = 3;
+
+pub struct RoundTripTest<S = cpal::Stream> {
+    _stream: S,
+    sent: Arc<[AtomicU64; CLICKS]>, // Input frame at each click, u64::MAX until it has gone out
+    started: Instant,
+    input_channels: usize,
+    input_rate: u32,
+}
+
+impl<S> RoundTripTest<S> {
+    pub fn start<B: AudioBackend<Stream = S>>(
+        backend: &B,
+        device: &str,
+        input_written: Arc<AtomicU64>, // `CircularBuffer::total_written`, kept by the input callback
+        input_channels: usize,
+        input_rate: u32,
+        on_error: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, RecorderError> {
+        let config = backend.output_config(device, None)?;
+        let output_channels = config.channels as usize;
+        let output_rate = config.sample_rate.0;
+        let input_channels = input_channels.max(1);
+        let click = latency::click(output_rate);
+        let spacing = (SPACING_SECONDS * output_rate as f32) as u64;
+        let sent = Arc::new(std::array::from_fn(|_| AtomicU64::new(u64::MAX)));
+        let stream_sent = Arc::clone(&sent);
+        let mut played = 0u64; // Output frames so far
+
+        let stream = backend.build_playback(
+            device,
+            &config,
+            move |data| {
+                for (index, frame) in data.chunks_mut(output_channels).enumerate() {
+                    let position = played + index as u64;
+                    let (number, within) = (position / spacing, (position % spacing) as usize);
+                    let sample = if (1..=CLICKS as u64).contains(&number) && within < click.len() {
+                        click[within]
+                    } else {
+                        0.0
+                    };
+                    frame.fill(sample);
+                    if within == 0 && (1..=CLICKS as u64).contains(&number) {
+                        // Where the input is now, plus how far into this block the click starts
+                        let written = input_written.load(Ordering::Relaxed) / input_channels as u64;
+                        let offset = index as u64 * input_rate as u64 / output_rate as u64;
+                        stream_sent[number as usize - 1].store(written + offset, Ordering::Relaxed);
+                    }
+                }
+                played += (data.len() / output_channels) as u64;
+            },
+            on_error,
+        )?;
+        println!("Measuring the round-trip latency");
+        Ok(RoundTripTest {
+            _stream: stream,
+            sent,
+            started: Instant::now(),
+            input_channels,
+            input_rate,
+        })
+    }
+
+    // Every click has been played and has had time to come back
+    pub fn is_done(&self) -> bool {
+        let seconds = (CLICKS + 1) as f32 * SPACING_SECONDS + LISTEN_SECONDS;
+        self.started.elapsed() > Duration::from_secs_f32(seconds)
+    }
+
+    // The median round trip in milliseconds and how many clicks were heard, or None when too
+    // few came back to trust it
+    pub fn result(&self, buffer: &CircularBuffer) -> Option<(f32, usize)> {
+        let channels = self.input_channels;
+        let click = latency::click(self.input_rate);
+        let listen = (LISTEN_SECONDS * self.input_rate as f32) as u64 + click.len() as u64;
+        let mut delays: Vec<usize> = self
+            .sent
+            .iter()
+            .map(|sent| sent.load(Ordering::Relaxed))
+            .filter(|&start| start != u64::MAX)
+            .filter_map(|start| {
+                let (from, to) = (start * channels as u64, (start + listen) * channels as u64);
+                if from < buffer.window_start() || to > buffer.total_written {
+                    return None;
+                }
+                let mono: Vec<f32> = buffer
+                    .copy_range(from, to)
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                    .collect();
+                latency::find_click(&mono, &click)
+            })
+            .collect();
+        if delays.len() < MIN_HEARD {
+            return None;
+        }
+        delays.sort_unstable();
+        let median = delays[delays.len() / 2];
+        Some((
+            median as f32 * 1000.0 / self.input_rate as f32,
+            delays.len(),
+        ))
+    }
+}
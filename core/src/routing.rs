@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+// How the input channels reach the output channels when monitoring
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MonitorRouting {
+    Direct,        // Input n to output n, a mono input to every output
+    MonoToAll,     // Every input averaged into every output, e.g. a mic on input 1 in both ears
+    SwapLeftRight, // Inputs 1 and 2 to outputs 2 and 1
+    Custom,        // One chosen input (or none) per output
+}
+
+impl MonitorRouting {
+    pub const ALL: [MonitorRouting; 4] = [
+        MonitorRouting::Direct,
+        MonitorRouting::MonoToAll,
+        MonitorRouting::SwapLeftRight,
+        MonitorRouting::Custom,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MonitorRouting::Direct => "Direct",
+            MonitorRouting::MonoToAll => "Mono to all outputs",
+            MonitorRouting::SwapLeftRight => "Swap left/right",
+            MonitorRouting::Custom => "Custom",
+        }
+    }
+
+    // The input channels averaged into each output channel. `custom` holds an input per output
+    // counting from 1, with 0 for silence.
+    pub fn routes(&self, custom: &[usize], inputs: usize, outputs: usize) -> Vec<Vec<usize>> {
+        let inputs = inputs.max(1);
+        (0..outputs)
+            .map(|output| match self {
+                MonitorRouting::Direct if inputs == 1 => vec![0],
+                MonitorRouting::Direct => direct(output, inputs),
+                MonitorRouting::MonoToAll => (0..inputs).collect(),
+                MonitorRouting::SwapLeftRight if inputs >= 2 && output < 2 => vec![1 - output],
+                MonitorRouting::SwapLeftRight => direct(output, inputs),
+                MonitorRouting::Custom => match custom.get(output) {
+                    Some(&input) if input >= 1 && input <= inputs => vec![input - 1],
+                    Some(_) => Vec::new(),
+                    None => direct(output, inputs),
+                },
+            })
+            .collect()
+    }
+}
+
+fn direct(output: usize, inputs: usize) -> Vec<usize> {
+    if output < inputs {
+        vec![output]
+    } else {
+        Vec::new()
+    }
+}
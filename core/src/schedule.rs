@@ -0,0 +1,157 @@
+use chrono::{DateTime, Duration, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScheduleKind {
+    At,    // Once a day at a clock time
+    Every, // On a repeating interval
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    pub enabled: bool,
+    pub kind: ScheduleKind,
+    pub time: String,          // "HH:MM" in local time, used by ScheduleKind::At
+    pub interval_minutes: u32, // Used by ScheduleKind::Every
+    pub capture_seconds: f32,  // Saved from the end of the history when it fires, 0 for all of it
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            enabled: true,
+            kind: ScheduleKind::Every,
+            time: "12:00".to_owned(),
+            interval_minutes: 60,
+            capture_seconds: 0.0,
+        }
+    }
+}
+
+impl Schedule {
+    // A schedule can only save what the rolling buffer holds
+    pub fn check(&self, buffer_seconds: f32) -> Result<(), String> {
+        if self.capture_seconds > buffer_seconds {
+            return Err(format!(
+                "Wants the last {:.0} s but the buffer only holds {:.0} s, lengthen the buffer or shorten the schedule",
+                self.capture_seconds, buffer_seconds
+            ));
+        }
+        Ok(())
+    }
+
+    // Work out when this schedule should fire next, or None if it is disabled or invalid
+    fn next_fire(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        if !self.enabled {
+            return None;
+        }
+        match self.kind {
+            ScheduleKind::At => {
+                let time = NaiveTime::parse_from_str(self.time.trim(), "%H:%M").ok()?;
+                let mut next = now
+                    .date_naive()
+                    .and_time(time)
+                    .and_local_timezone(Local)
+                    .single()?;
+                if next <= now {
+                    next += Duration::days(1);
+                }
+                Some(next)
+            }
+            ScheduleKind::Every if self.interval_minutes > 0 => {
+                Some(now + Duration::minutes(self.interval_minutes as i64))
+            }
+            ScheduleKind::Every => None,
+        }
+    }
+}
+
+// A schedule together with when it is next due
+struct Entry {
+    schedule: Schedule,
+    next_fire: Option<DateTime<Local>>,
+}
+
+type Waker = Box<dyn Fn() + Send>;
+
+// Runs a background thread that checks the schedules once a second and reports the ones that
+// are due. The recorder polls for them from the UI loop, the waker makes sure that loop runs.
+pub struct Scheduler {
+    entries: Arc<Mutex<Vec<Entry>>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    fired: Receiver<usize>,
+}
+
+impl Scheduler {
+    pub fn spawn(schedules: Vec<Schedule>) -> Self {
+        let (sender, fired) = channel();
+        let scheduler = Scheduler {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            waker: Arc::new(Mutex::new(None)),
+            fired,
+        };
+        scheduler.set_schedules(schedules);
+
+        let entries = Arc::clone(&scheduler.entries);
+        let waker = Arc::clone(&scheduler.waker);
+        thread::spawn(move || run(entries, waker, sender));
+        scheduler
+    }
+
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    // Replace the schedules, restarting every interval from now
+    pub fn set_schedules(&self, schedules: Vec<Schedule>) {
+        let now = Local::now();
+        *self.entries.lock().unwrap() = schedules
+            .into_iter()
+            .map(|schedule| Entry {
+                next_fire: schedule.next_fire(now),
+                schedule,
+            })
+            .collect();
+    }
+
+    pub fn next_fire(&self, index: usize) -> Option<DateTime<Local>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(index)
+            .and_then(|entry| entry.next_fire)
+    }
+
+    // Indices of the schedules that have fired since the last call
+    pub fn poll(&self) -> Vec<usize> {
+        self.fired.try_iter().collect()
+    }
+}
+
+fn run(entries: Arc<Mutex<Vec<Entry>>>, waker: Arc<Mutex<Option<Waker>>>, sender: Sender<usize>) {
+    loop {
+        thread::sleep(std::time::Duration::from_secs(1));
+
+        let now = Local::now();
+        let mut any_fired = false;
+        for (index, entry) in entries.lock().unwrap().iter_mut().enumerate() {
+            if entry.next_fire.is_some_and(|next| next <= now) {
+                entry.next_fire = entry.schedule.next_fire(now);
+                if sender.send(index).is_err() {
+                    return; // The scheduler was dropped
+                }
+                any_fired = true;
+            }
+        }
+
+        if any_fired {
+            if let Some(wake) = waker.lock().unwrap().as_ref() {
+                wake();
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// What the recorder is doing, as one value rather than flags spread over the recorder. Every
+// change goes through `enter`, which turns down the ones that make no sense (a grab with no input
+// open, saving a grab that never started), so new ways of starting and ending grabs all see the
+// same rules. Monitoring isn't part of it, since it runs the same in every state.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecorderState {
+    Idle,          // No input opened yet
+    Rolling,       // Keeping the last few seconds, ready to grab
+    Grabbing,      // Keeping everything from the history onwards until the grab is stopped
+    Saving,        // Stopped, with the take being handed over to be written
+    Error(String), // The input couldn't be opened; picking a device or a rescan tries again
+}
+
+impl RecorderState {
+    // (Re)opening the input can end anything, and anything can fail; the rest follows a grab
+    // from start to finish
+    pub fn can_become(&self, next: &RecorderState) -> bool {
+        use RecorderState::*;
+        matches!(
+            (self, next),
+            (_, Rolling) | (_, Error(_)) | (Rolling, Grabbing) | (Grabbing, Saving)
+        )
+    }
+
+    // Move to `next` if it follows from here, reporting the ones that don't
+    pub fn enter(&mut self, next: RecorderState) -> bool {
+        if !self.can_become(&next) {
+            eprintln!(
+                "Ignoring a change from {} to {}",
+                self.label(),
+                next.label()
+            );
+            return false;
+        }
+        *self = next;
+        true
+    }
+
+    pub fn is_grabbing(&self) -> bool {
+        *self == RecorderState::Grabbing
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecorderState::Idle => "Idle",
+            RecorderState::Rolling => "Rolling",
+            RecorderState::Grabbing => "Grabbing",
+            RecorderState::Saving => "Saving",
+            RecorderState::Error(_) => "Error",
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// Health of the audio streams, counted from inside the callbacks without blocking them, for the
+// diagnostics report and the status bar
+
+use crate::RecorderError;
+use chrono::Local;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Only the most recent errors are interesting for a bug report
+const MAX_ERRORS: usize = 20;
+
+// Health counters for the audio streams, updated from the callbacks
+#[derive(Default)]
+pub struct StreamStats {
+    input_callbacks: AtomicU64,
+    input_xruns: AtomicU64,
+    input_busy_nanos: AtomicU64, // Time spent inside the input callback
+    monitor_underruns: AtomicU64,
+    pub monitor_limiter: GainReduction,
+    pub capture_limiter: GainReduction,
+    errors: Mutex<VecDeque<String>>,
+    alerts: Mutex<Option<Sender<RecorderError>>>, // Where errors are shown to the user
+}
+
+// Most gain reduction a limiter applied since the UI last looked, in dB as bits
+#[derive(Default)]
+pub struct GainReduction(AtomicU32);
+
+impl GainReduction {
+    pub fn record(&self, gain: f32) {
+        // Positive floats order the same as their bits, so the largest reduction wins
+        let reduction_db = -20.0 * gain.max(1e-6).log10();
+        self.0
+            .fetch_max(reduction_db.max(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    // Jumps to each new reduction and eases off, so short bursts stay visible
+    pub fn update_display(&self, shown_db: &mut f32) {
+        let reduction_db = f32::from_bits(self.0.swap(0, Ordering::Relaxed));
+        *shown_db = reduction_db.max(*shown_db * 0.9);
+    }
+}
+
+impl StreamStats {
+    pub fn record_input_callback(&self, xrun: bool) {
+        self.input_callbacks.fetch_add(1, Ordering::Relaxed);
+        if xrun {
+            self.input_xruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_input_busy(&self, busy: Duration) {
+        self.input_busy_nanos
+            .fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_monitor_underrun(&self) {
+        self.monitor_underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn input_callbacks(&self) -> u64 {
+        self.input_callbacks.load(Ordering::Relaxed)
+    }
+
+    // Total time spent inside the input callback
+    pub fn input_busy(&self) -> Duration {
+        Duration::from_nanos(self.input_busy_nanos.load(Ordering::Relaxed))
+    }
+
+    pub fn input_xruns(&self) -> u64 {
+        self.input_xruns.load(Ordering::Relaxed)
+    }
+
+    pub fn monitor_underruns(&self) -> u64 {
+        self.monitor_underruns.load(Ordering::Relaxed)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.lock().unwrap().len()
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.errors.lock().unwrap().back().cloned()
+    }
+
+    // Oldest first, with when they happened
+    pub fn recent_errors(&self) -> Vec<String> {
+        self.errors.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn report_to(&self, alerts: Sender<RecorderError>) {
+        *self.alerts.lock().unwrap() = Some(alerts);
+    }
+
+    pub fn record_error(&self, stream: &str, err: &str) {
+        eprintln!("An error occurred on the {} stream: {}", stream, err);
+        if let Some(alerts) = self.alerts.lock().unwrap().as_ref() {
+            let _ = alerts.send(RecorderError::Stream(format!("{}: {}", stream, err)));
+        }
+        let mut errors = self.errors.lock().unwrap();
+        if errors.len() == MAX_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(format!(
+            "{} [{}] {}",
+            Local::now().format("%H:%M:%S"),
+            stream,
+            err
+        ));
+    }
+
+    // Error callback for a stream that keeps the error for the diagnostics report
+    pub fn error_handler(
+        self: &Arc<Self>,
+        stream: &'static str,
+    ) -> impl FnMut(String) + Send + 'static {
+        let stats = Arc::clone(self);
+        move |err| stats.record_error(stream, &err)
+    }
+}
+
+// Spots dropped input by comparing each callback's capture time with where the previous block
+// should have ended
+#[derive(Default)]
+pub struct XrunDetector {
+    expected_next: Option<Duration>,
+}
+
+impl XrunDetector {
+    // `capture` is when the block's first frame came in, as the backend counts it
+    pub fn check(&mut self, capture: Duration, frames: usize, sample_rate: u32) -> bool {
+        let block = Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64);
+        // Allow half a block of jitter before calling it a dropout
+        let xrun = self
+            .expected_next
+            .and_then(|expected| capture.checked_sub(expected))
+            .is_some_and(|late| late > block / 2);
+        self.expected_next = Some(capture + block);
+        xrun
+    }
+}
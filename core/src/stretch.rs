@@ -0,0 +1,201 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+const FFT_SIZE: usize = 2048;
+const SYNTHESIS_HOP: usize = FFT_SIZE / 4;
+
+// Change the length of interleaved audio by `ratio` (2 is twice as long) without changing its
+// pitch, with a phase vocoder: frames are read `SYNTHESIS_HOP / ratio` apart and written
+// `SYNTHESIS_HOP` apart, with each bin's phase advanced at its measured frequency so the partials
+// stay continuous across the new spacing. Each channel is stretched on its own.
+pub fn time_stretch(samples: &[f32], channels: usize, ratio: f32) -> Vec<f32> {
+    let mut stretcher = Stretcher::new(channels, ratio);
+    let mut output = stretcher.process(samples);
+    output.extend(stretcher.finish());
+    output
+}
+
+// `time_stretch` a chunk at a time, for exports. Only one window of input and one of overlap-add
+// output are kept per channel, and output is handed back as soon as no later frame can add to it.
+pub struct Stretcher {
+    channels: usize,
+    ratio: f32,
+    analysis_hop: f32,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    states: Vec<ChannelState>,
+    weight: Vec<f32>,    // Sum of the squared windows under each held output sample
+    frame: usize,        // Next analysis frame
+    input_start: usize,  // Input frame the held input starts at, where the next frame reads
+    input_frames: usize, // Seen so far
+    output_start: usize, // Output frame the held output starts at, everything before is out
+}
+
+struct ChannelState {
+    input: Vec<f32>,
+    output: Vec<f32>,
+    previous_phase: Vec<f32>,
+    output_phase: Vec<f32>,
+}
+
+impl Stretcher {
+    pub fn new(channels: usize, ratio: f32) -> Self {
+        let channels = channels.max(1);
+        let mut planner = FftPlanner::new();
+        let bins = FFT_SIZE / 2 + 1;
+        Stretcher {
+            channels,
+            ratio,
+            analysis_hop: SYNTHESIS_HOP as f32 / ratio,
+            forward: planner.plan_fft_forward(FFT_SIZE),
+            inverse: planner.plan_fft_inverse(FFT_SIZE),
+            window: (0..FFT_SIZE)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos())
+                .collect(),
+            spectrum: vec![Complex::new(0.0, 0.0); FFT_SIZE],
+            states: (0..channels)
+                .map(|_| ChannelState {
+                    input: Vec::new(),
+                    output: Vec::new(),
+                    previous_phase: vec![0.0; bins],
+                    output_phase: vec![0.0; bins],
+                })
+                .collect(),
+            weight: Vec::new(),
+            frame: 0,
+            input_start: 0,
+            input_frames: 0,
+            output_start: 0,
+        }
+    }
+
+    fn unchanged(&self) -> bool {
+        (self.ratio - 1.0).abs() < 1e-4
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        if self.unchanged() {
+            return samples.to_vec();
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            // Frames a short ratio's hop jumps over are never read
+            if self.input_frames >= self.input_start {
+                for (state, &sample) in self.states.iter_mut().zip(frame) {
+                    state.input.push(sample);
+                }
+            }
+            self.input_frames += 1;
+        }
+        // As much output as the input so far makes, which the final length can't be short of
+        let known = (self.input_frames as f32 * self.ratio) as usize;
+        while self.read() + FFT_SIZE <= self.input_frames && self.write() < known {
+            self.run_frame();
+        }
+        self.emit(self.write().min(known))
+    }
+
+    // The rest, once there is no more input
+    pub fn finish(&mut self) -> Vec<f32> {
+        if self.unchanged() {
+            return Vec::new();
+        }
+        let output_frames = (self.input_frames as f32 * self.ratio).round() as usize;
+        while self.read() < self.input_frames && self.write() < output_frames {
+            self.run_frame();
+        }
+        self.emit(output_frames)
+    }
+
+    fn read(&self) -> usize {
+        (self.frame as f32 * self.analysis_hop) as usize
+    }
+
+    fn write(&self) -> usize {
+        self.frame * SYNTHESIS_HOP
+    }
+
+    fn run_frame(&mut self) {
+        let bins = FFT_SIZE / 2 + 1;
+        let bin_frequency = |bin: usize| 2.0 * PI * bin as f32 / FFT_SIZE as f32; // Radians per sample
+        let analysis_hop = self.analysis_hop;
+        let offset = self.write() - self.output_start;
+        if self.weight.len() < offset + FFT_SIZE {
+            self.weight.resize(offset + FFT_SIZE, 0.0);
+        }
+        for state in &mut self.states {
+            for (i, value) in self.spectrum.iter_mut().enumerate() {
+                let sample = state.input.get(i).copied().unwrap_or(0.0);
+                *value = Complex::new(sample * self.window[i], 0.0);
+            }
+            self.forward.process(&mut self.spectrum);
+
+            for bin in 0..bins {
+                let (magnitude, phase) = self.spectrum[bin].to_polar();
+                if self.frame == 0 {
+                    state.output_phase[bin] = phase;
+                } else {
+                    // How far the phase moved beyond what the bin's centre frequency accounts for
+                    let expected = bin_frequency(bin) * analysis_hop;
+                    let deviation = wrap_phase(phase - state.previous_phase[bin] - expected);
+                    let frequency = bin_frequency(bin) + deviation / analysis_hop;
+                    state.output_phase[bin] =
+                        wrap_phase(state.output_phase[bin] + frequency * SYNTHESIS_HOP as f32);
+                }
+                state.previous_phase[bin] = phase;
+                self.spectrum[bin] = Complex::from_polar(magnitude, state.output_phase[bin]);
+                if bin > 0 && bin < FFT_SIZE / 2 {
+                    self.spectrum[FFT_SIZE - bin] = self.spectrum[bin].conj();
+                }
+            }
+            self.inverse.process(&mut self.spectrum);
+
+            if state.output.len() < offset + FFT_SIZE {
+                state.output.resize(offset + FFT_SIZE, 0.0);
+            }
+            for (i, value) in self.spectrum.iter().enumerate() {
+                state.output[offset + i] += value.re / FFT_SIZE as f32 * self.window[i];
+            }
+        }
+        for (i, &window) in self.window.iter().enumerate() {
+            self.weight[offset + i] += window * window;
+        }
+
+        self.frame += 1;
+        let passed = self.read() - self.input_start;
+        for state in &mut self.states {
+            state.input.drain(..passed.min(state.input.len()));
+        }
+        self.input_start += passed;
+    }
+
+    // Interleaved output up to `end`, which no frame still to come writes before
+    fn emit(&mut self, end: usize) -> Vec<f32> {
+        let frames = end.saturating_sub(self.output_start);
+        let mut output = Vec::with_capacity(frames * self.channels);
+        for i in 0..frames {
+            let weight = self.weight.get(i).copied().unwrap_or(0.0);
+            for state in &self.states {
+                let sample = state.output.get(i).copied().unwrap_or(0.0);
+                output.push(if weight > 1e-3 {
+                    sample / weight
+                } else {
+                    sample
+                });
+            }
+        }
+        for state in &mut self.states {
+            state.output.drain(..frames.min(state.output.len()));
+        }
+        self.weight.drain(..frames.min(self.weight.len()));
+        self.output_start += frames;
+        output
+    }
+}
+
+fn wrap_phase(phase: f32) -> f32 {
+    (phase + PI).rem_euclid(2.0 * PI) - PI
+}
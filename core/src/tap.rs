@@ -0,0 +1,81 @@
+// Single producer, single consumer ring that hands the input callback's audio to a thread that
+// writes it somewhere else, like the safety recording or the live stream. Like `MonitorFifo` it
+// keeps samples as bits in atomics and is allocated up front, so the callback never locks or
+// allocates to feed it. The callback only pushes while it's enabled.
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+pub struct InputTap {
+    slots: Box<[AtomicU32]>,
+    written: AtomicU64, // Samples pushed since it was created, only moved by the callback
+    read: AtomicU64,    // Samples taken, only moved by the consumer
+    dropped: AtomicU64, // Samples that didn't fit because the consumer fell behind
+    enabled: AtomicBool,
+}
+
+impl InputTap {
+    pub fn new(samples: usize) -> Self {
+        InputTap {
+            slots: (0..samples.max(1)).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicU64::new(0),
+            read: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    // Callback side. Whole blocks go in or none of it does, so the consumer always sees whole
+    // frames; one that doesn't fit is dropped rather than overwriting what isn't read yet.
+    pub fn push(&self, data: &[f32]) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+        let written = self.written.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        let free = self.slots.len() as u64 - (written - read);
+        if data.len() as u64 > free {
+            self.dropped.fetch_add(data.len() as u64, Ordering::Relaxed);
+            return false;
+        }
+        for (i, &sample) in data.iter().enumerate() {
+            self.slot(written + i as u64)
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.written
+            .store(written + data.len() as u64, Ordering::Release);
+        true
+    }
+
+    // Consumer side. Appends everything pushed since the last call and returns how much that was.
+    pub fn pop(&self, output: &mut Vec<f32>) -> usize {
+        let read = self.read.load(Ordering::Relaxed);
+        let written = self.written.load(Ordering::Acquire);
+        output.extend(
+            (read..written).map(|index| f32::from_bits(self.slot(index).load(Ordering::Relaxed))),
+        );
+        self.read.store(written, Ordering::Release);
+        (written - read) as usize
+    }
+
+    // Consumer side, for a new consumer to start from now rather than with what an earlier one
+    // left behind
+    pub fn skip(&self) {
+        self.read
+            .store(self.written.load(Ordering::Acquire), Ordering::Release);
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn slot(&self, index: u64) -> &AtomicU32 {
+        &self.slots[(index % self.slots.len() as u64) as usize]
+    }
+}
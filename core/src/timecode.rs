@@ -0,0 +1,286 @@
+// SMPTE timecode from the two places a film set sends it: MIDI Timecode from a MIDI input, and
+// LTC, the timecode audio signal, from a channel of the audio input. Takes are stamped with it
+// (see `export::Take::time_reference`) so they line up with the camera in an edit.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameRate {
+    Fps24,
+    Fps25,
+    Fps2997Drop,
+    Fps30,
+}
+
+impl FrameRate {
+    // Frames counted per second in the timecode's numbers
+    pub fn nominal(&self) -> u64 {
+        match self {
+            FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps2997Drop | FrameRate::Fps30 => 30,
+        }
+    }
+
+    // Frames per second of real time
+    pub fn fps(&self) -> f64 {
+        match self {
+            FrameRate::Fps2997Drop => 30_000.0 / 1001.0,
+            _ => self.nominal() as f64,
+        }
+    }
+
+    fn is_drop_frame(&self) -> bool {
+        *self == FrameRate::Fps2997Drop
+    }
+
+    // MTC's two rate bits
+    fn from_mtc(bits: u8) -> FrameRate {
+        match bits & 3 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps2997Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub rate: FrameRate,
+}
+
+const DAY_SECONDS: f64 = 24.0 * 3600.0;
+
+impl Timecode {
+    // Frames since midnight. Drop-frame timecode skips frame numbers 0 and 1 at the start of
+    // every minute except each tenth, so they don't count.
+    pub fn frame_number(&self) -> u64 {
+        let nominal = self.rate.nominal();
+        let minutes = 60 * self.hours as u64 + self.minutes as u64;
+        let frames = (60 * minutes + self.seconds as u64) * nominal + self.frames as u64;
+        if self.rate.is_drop_frame() {
+            frames - 2 * (minutes - minutes / 10)
+        } else {
+            frames
+        }
+    }
+
+    pub fn from_frame_number(frame: u64, rate: FrameRate) -> Timecode {
+        let nominal = rate.nominal();
+        let mut frame = frame % (DAY_SECONDS * rate.fps()).round() as u64;
+        if rate.is_drop_frame() {
+            // 17982 frames in ten minutes, 1798 in each dropped minute after the first
+            let (tens, rest) = (frame / 17_982, frame % 17_982);
+            frame += 18 * tens + if rest > 1 { 2 * ((rest - 2) / 1798) } else { 0 };
+        }
+        Timecode {
+            hours: (frame / (3600 * nominal) % 24) as u8,
+            minutes: (frame / (60 * nominal) % 60) as u8,
+            seconds: (frame / nominal % 60) as u8,
+            frames: (frame % nominal) as u8,
+            rate,
+        }
+    }
+
+    // Real seconds since midnight
+    pub fn seconds(&self) -> f64 {
+        self.frame_number() as f64 / self.rate.fps()
+    }
+
+    // The frame running `seconds` after midnight, wrapping at the end of the day
+    pub fn from_seconds(seconds: f64, rate: FrameRate) -> Timecode {
+        let seconds = seconds.rem_euclid(DAY_SECONDS);
+        Timecode::from_frame_number((seconds * rate.fps()).floor() as u64, rate)
+    }
+
+    pub fn plus_frames(&self, frames: u64) -> Timecode {
+        Timecode::from_frame_number(self.frame_number() + frames, self.rate)
+    }
+
+    // For file names, which can't have colons
+    pub fn file_safe(&self) -> String {
+        format!(
+            "{:02}-{:02}-{:02}-{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+
+    fn is_valid(&self) -> bool {
+        self.hours < 24
+            && self.minutes < 60
+            && self.seconds < 60
+            && (self.frames as u64) < self.rate.nominal()
+    }
+}
+
+// 01:02:03:04, with a semicolon before the frames for drop-frame as is the custom
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = if self.rate.is_drop_frame() { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+// MIDI Timecode: eight quarter-frame messages spread over two frames while running, each
+// carrying a nibble of the time, and a full-frame SysEx message after a locate
+#[derive(Default)]
+pub struct MtcDecoder {
+    pieces: [u8; 8],
+    seen: u8, // One bit per piece received since the last complete time
+}
+
+impl MtcDecoder {
+    pub fn message(&mut self, message: &[u8]) -> Option<Timecode> {
+        match *message {
+            [0xf1, data] => {
+                let piece = (data >> 4) as usize & 7;
+                self.pieces[piece] = data & 0x0f;
+                self.seen |= 1 << piece;
+                if piece != 7 || self.seen != 0xff {
+                    return None;
+                }
+                self.seen = 0;
+                let p = self.pieces;
+                let timecode = Timecode {
+                    hours: p[6] | (p[7] & 1) << 4,
+                    minutes: p[4] | (p[5] & 3) << 4,
+                    seconds: p[2] | (p[3] & 3) << 4,
+                    frames: p[0] | (p[1] & 1) << 4,
+                    rate: FrameRate::from_mtc(p[7] >> 1),
+                };
+                // The time was that of the first piece, two frames ago
+                timecode.is_valid().then(|| timecode.plus_frames(2))
+            }
+            [0xf0, 0x7f, _, 0x01, 0x01, hours, minutes, seconds, frames, 0xf7] => {
+                let timecode = Timecode {
+                    hours: hours & 0x1f,
+                    minutes,
+                    seconds,
+                    frames,
+                    rate: FrameRate::from_mtc(hours >> 5),
+                };
+                timecode.is_valid().then_some(timecode)
+            }
+            _ => None,
+        }
+    }
+}
+
+// LTC frames are 80 bits, ending in this sync word (bits 64 to 79, first sent lowest)
+const LTC_SYNC: u128 = 0xbffc;
+const LTC_HYSTERESIS: f32 = 0.01; // Level a transition has to cross, to ignore noise around zero
+
+// Decodes LTC from one channel of audio. Each bit starts with a transition and ones have
+// another in the middle, so bits are told apart by the time between transitions, tracking the
+// bit length as the signal's speed drifts.
+pub struct LtcDecoder {
+    sample_rate: u32,
+    high: bool,
+    since_edge: f64,        // Samples since the last transition
+    bit_length: f64,        // Samples per bit, as measured
+    half_bit: bool,         // Half a one has been seen, waiting for the other half
+    word: u128,             // The last 80 bits, the newest at bit 79
+    position: u64,          // Samples fed so far
+    last_sync: Option<u64>, // Position the previous frame ended at
+}
+
+impl LtcDecoder {
+    pub fn new(sample_rate: u32) -> Self {
+        LtcDecoder {
+            sample_rate,
+            high: false,
+            since_edge: 0.0,
+            bit_length: sample_rate as f64 / (25.0 * 80.0), // Until the signal says otherwise
+            half_bit: false,
+            word: 0,
+            position: 0,
+            last_sync: None,
+        }
+    }
+
+    // Feed the next samples of the timecode channel. Returns the last frame completed in them,
+    // with the position (in samples fed since the decoder was made) that frame started at.
+    pub fn feed(&mut self, samples: impl IntoIterator<Item = f32>) -> Option<(Timecode, u64)> {
+        let mut decoded = None;
+        for sample in samples {
+            self.position += 1;
+            self.since_edge += 1.0;
+            let high = if self.high {
+                sample > -LTC_HYSTERESIS
+            } else {
+                sample > LTC_HYSTERESIS
+            };
+            if high == self.high {
+                continue;
+            }
+            self.high = high;
+            let interval = std::mem::take(&mut self.since_edge);
+            if interval > 1.5 * self.bit_length || interval < 0.25 * self.bit_length {
+                // Nothing like a bit of the speed we had, so start again at this speed
+                self.bit_length = interval.max(2.0);
+                self.half_bit = false;
+                continue;
+            }
+            let bit = if interval > 0.75 * self.bit_length {
+                self.bit_length = 0.9 * self.bit_length + 0.1 * interval;
+                self.half_bit = false;
+                0
+            } else if self.half_bit {
+                self.bit_length = 0.9 * self.bit_length + 0.1 * (2.0 * interval);
+                self.half_bit = false;
+                1
+            } else {
+                self.half_bit = true;
+                continue;
+            };
+            self.word = (self.word >> 1) | (bit as u128) << 79;
+            if (self.word >> 64) & 0xffff != LTC_SYNC {
+                continue;
+            }
+            // Bit lengths are only whole samples apart, so the frame rate comes from the time
+            // between sync words, once there have been two in a row
+            let bits_length = (80.0 * self.bit_length).round() as u64;
+            let length = self
+                .last_sync
+                .map(|last| self.position - last)
+                .filter(|&length| length.abs_diff(bits_length) < bits_length / 10)
+                .unwrap_or(bits_length);
+            self.last_sync = Some(self.position);
+            if let Some(timecode) = self.frame(length) {
+                decoded = Some((timecode, self.position.saturating_sub(length)));
+            }
+        }
+        decoded
+    }
+
+    fn frame(&self, length: u64) -> Option<Timecode> {
+        let bits = |from: u32, count: u32| ((self.word >> from) & ((1 << count) - 1)) as u8;
+        let fps = self.sample_rate as f64 / length as f64;
+        let rate = if bits(10, 1) == 1 {
+            FrameRate::Fps2997Drop
+        } else if fps < 24.5 {
+            FrameRate::Fps24
+        } else if fps < 27.5 {
+            FrameRate::Fps25
+        } else {
+            FrameRate::Fps30
+        };
+        let timecode = Timecode {
+            hours: bits(48, 4) + 10 * bits(56, 2),
+            minutes: bits(32, 4) + 10 * bits(40, 3),
+            seconds: bits(16, 4) + 10 * bits(24, 3),
+            frames: bits(0, 4) + 10 * bits(8, 2),
+            rate,
+        };
+        timecode.is_valid().then_some(timecode)
+    }
+}
@@ -0,0 +1,140 @@
+use rolling_sampler_core::analysis::{self, LoudnessMeter};
+use rolling_sampler_core::export::{ExportStage, Take};
+use rolling_sampler_core::stretch;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const RATE: u32 = 48_000;
+
+fn sine(hz: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+    (0..(seconds * RATE as f32) as usize)
+        .map(|i| amplitude * (2.0 * PI * hz * i as f32 / RATE as f32).sin())
+        .collect()
+}
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rolling-sampler-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// The BS.1770 reference: a full scale sine on one channel reads -3 LUFS
+#[test]
+fn sine_at_minus_20_dbfs_reads_minus_23_lufs() {
+    let samples = sine(997.0, 0.1, 5.0);
+    let lufs = analysis::integrated_loudness(&samples, 1, RATE).unwrap();
+    assert!((lufs + 23.0).abs() < 0.2, "{} LUFS", lufs);
+
+    // The same a block at a time, as the export chain feeds it
+    let mut meter = LoudnessMeter::new(1, RATE);
+    for chunk in samples.chunks(1000) {
+        meter.add_samples(chunk);
+    }
+    assert_eq!(meter.integrated(), Some(lufs));
+}
+
+#[test]
+fn silence_has_no_loudness() {
+    assert_eq!(analysis::integrated_loudness(&[0.0; 96_000], 2, RATE), None);
+}
+
+#[test]
+fn loudness_stage_normalizes_to_the_target() {
+    let dir = temp_dir("loudness");
+    let path = dir.join("Take.wav");
+    let take = Take {
+        name: "Take".to_owned(),
+        path: path.clone(),
+        samples: Arc::new(sine(997.0, 0.1, 5.0)),
+        channels: 1,
+        sample_rate: RATE,
+        stages: vec![ExportStage::Loudness { lufs: -14.0 }],
+        ..Take::default()
+    };
+    take.save().unwrap();
+
+    let saved: Vec<f32> = hound::WavReader::open(&path)
+        .unwrap()
+        .samples::<f32>()
+        .map(Result::unwrap)
+        .collect();
+    let lufs = analysis::integrated_loudness(&saved, 1, RATE).unwrap();
+    assert!((lufs + 14.0).abs() < 0.2, "{} LUFS", lufs);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn click_track_at_120_bpm_detects_120() {
+    let mut samples = vec![0.0f32; 10 * RATE as usize * 2];
+    let beat = RATE as usize / 2;
+    for start in (0..samples.len() / 2).step_by(beat) {
+        // A 5 ms blip of 2 kHz on both channels
+        for i in 0..RATE as usize / 200 {
+            let Some(frame) = samples.get_mut((start + i) * 2..(start + i) * 2 + 2) else {
+                break;
+            };
+            let value = 0.8 * (2.0 * PI * 2000.0 * i as f32 / RATE as f32).sin();
+            frame.fill(value);
+        }
+    }
+    let bpm = analysis::detect_tempo(&samples, 2, RATE).unwrap();
+    assert!((bpm - 120.0).abs() < 1.0, "{} BPM", bpm);
+}
+
+#[test]
+fn too_little_audio_has_no_tempo() {
+    assert_eq!(
+        analysis::detect_tempo(&sine(440.0, 0.5, 1.0), 1, RATE),
+        None
+    );
+}
+
+#[test]
+fn stretching_by_2_doubles_the_length() {
+    let mono = sine(440.0, 0.5, 1.0);
+    let stereo: Vec<f32> = mono.iter().flat_map(|&s| [s, s]).collect();
+    let stretched = stretch::time_stretch(&stereo, 2, 2.0);
+    assert_eq!(stretched.len(), stereo.len() * 2);
+    assert_eq!(
+        stretch::time_stretch(&stereo, 2, 0.5).len(),
+        stereo.len() / 2
+    );
+
+    // Same pitch, just longer: as many zero crossings per second as before
+    let crossings = |samples: &[f32]| {
+        samples
+            .iter()
+            .step_by(2)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter(|w| w[0].signum() != w[1].signum())
+            .count() as f32
+    };
+    let ratio = crossings(&stretched) / crossings(&stereo);
+    assert!((ratio - 2.0).abs() < 0.1, "{}", ratio);
+}
+
+fn chord(notes_hz: [f32; 3]) -> Vec<f32> {
+    let tones = notes_hz.map(|hz| sine(hz, 0.2, 2.0));
+    (0..tones[0].len())
+        .map(|i| tones.iter().map(|tone| tone[i]).sum())
+        .collect()
+}
+
+#[test]
+fn triads_are_in_their_keys() {
+    let (key, score) =
+        analysis::detect_key(&chord([261.63, 329.63, 392.0]), 1, RATE, 440.0).unwrap();
+    assert_eq!(key, "C major");
+    assert!(score >= 0.5);
+    // The relative minor shares two of the notes
+    let (key, _) = analysis::detect_key(&chord([220.0, 261.63, 329.63]), 1, RATE, 440.0).unwrap();
+    assert_eq!(key, "A minor");
+}
+
+#[test]
+fn silence_has_no_key() {
+    assert_eq!(analysis::detect_key(&[0.0; 48_000], 1, RATE, 440.0), None);
+}
@@ -0,0 +1,33 @@
+// Finding the latency click in made-up recordings
+
+use rolling_sampler_core::latency::{click, find_click};
+
+const RATE: u32 = 48_000;
+
+// Quiet, repeatable noise
+fn noise(length: usize, level: f32) -> Vec<f32> {
+    let mut state = 12345u32;
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            level * ((state >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0)
+        })
+        .collect()
+}
+
+#[test]
+fn finds_a_quiet_inverted_click_in_noise() {
+    let click = click(RATE);
+    let mut recorded = noise(RATE as usize / 2, 0.005);
+    for (sample, c) in recorded[1234..].iter_mut().zip(&click) {
+        *sample -= 0.2 * c;
+    }
+    assert_eq!(find_click(&recorded, &click), Some(1234));
+}
+
+#[test]
+fn noise_alone_is_no_click() {
+    let click = click(RATE);
+    assert_eq!(find_click(&noise(RATE as usize / 2, 0.1), &click), None);
+    assert_eq!(find_click(&vec![0.0; 1000], &click), None);
+}
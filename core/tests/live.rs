@@ -0,0 +1,25 @@
+use rolling_sampler_core::dsp::GateSettings;
+use rolling_sampler_core::live::LiveSettings;
+
+#[test]
+fn changes_are_seen_once() {
+    let gate = GateSettings::default();
+    let live = LiveSettings::new(false, gate.into());
+    let mut seen = 0;
+    assert_eq!(live.changed(&mut seen).map(GateSettings::from), Some(gate));
+    assert_eq!(live.changed(&mut seen), None);
+
+    let quieter = GateSettings {
+        threshold_db: -60.0,
+        ..gate
+    };
+    live.set(quieter.into());
+    live.set_enabled(true);
+    assert!(live.enabled());
+    assert_eq!(
+        live.changed(&mut seen).map(GateSettings::from),
+        Some(quieter)
+    );
+    assert_eq!(live.changed(&mut seen), None);
+    assert_eq!(GateSettings::from(live.get()), quieter);
+}
@@ -0,0 +1,93 @@
+use rolling_sampler_core::metronome::{Metronome, MetronomeSettings};
+
+const RATE: u32 = 48_000;
+
+// Frames where a click starts: loud after at least a beat's worth of quiet
+fn onsets(samples: &[f32]) -> Vec<usize> {
+    let mut onsets = Vec::new();
+    let mut quiet = usize::MAX;
+    for (index, sample) in samples.iter().enumerate() {
+        if sample.abs() > 1e-3 {
+            if quiet > 1000 {
+                onsets.push(index);
+            }
+            quiet = 0;
+        } else {
+            quiet = quiet.saturating_add(1);
+        }
+    }
+    onsets
+}
+
+#[test]
+fn clicks_on_every_beat_with_the_bar_accented() {
+    let settings = MetronomeSettings {
+        bpm: 120.0,
+        beats_per_bar: 3,
+        count_in_bars: 0,
+        level_db: 0.0,
+        ..MetronomeSettings::default()
+    };
+    let mut metronome = Metronome::new(RATE, settings);
+    // Two seconds of stereo, in blocks like an output callback's
+    let mut out = vec![0.0f32; 2 * RATE as usize * 2];
+    for block in out.chunks_mut(512) {
+        metronome.mix(block, 2);
+    }
+    let left: Vec<f32> = out.chunks(2).map(|frame| frame[0]).collect();
+    let starts = onsets(&left);
+    // Half a second apart at 120 BPM, give or take the first sample of the sine being 0
+    assert_eq!(starts.len(), 4);
+    for (beat, &start) in starts.iter().enumerate() {
+        assert!(
+            start.abs_diff(beat * RATE as usize / 2) <= 1,
+            "{:?}",
+            starts
+        );
+    }
+    let peak = |start: usize| {
+        left[start..start + 1000]
+            .iter()
+            .fold(0.0f32, |m, s| m.max(s.abs()))
+    };
+    assert!(peak(starts[0]) > peak(starts[1]));
+    assert!(peak(starts[3]) > peak(starts[2]));
+    assert_eq!(metronome.beat(), 4);
+}
+
+#[test]
+fn count_in_alone_goes_quiet_after() {
+    let settings = MetronomeSettings {
+        bpm: 240.0,
+        beats_per_bar: 4,
+        count_in_bars: 1,
+        after_count_in: false,
+        ..MetronomeSettings::default()
+    };
+    let mut metronome = Metronome::new(RATE, settings);
+    assert!(metronome.is_counting_in());
+    let mut out = vec![0.0f32; 2 * RATE as usize];
+    metronome.mix(&mut out, 1);
+    assert_eq!(onsets(&out).len(), 4);
+    assert!(!metronome.is_counting_in());
+    assert_eq!(settings.describe_beat(2), "Count-in 3/4");
+    assert_eq!(settings.describe_beat(9), "Bar 2, beat 2");
+
+    // Started again, as the output callback does after it's switched back on
+    metronome.restart();
+    assert!(metronome.is_counting_in());
+    assert_eq!(metronome.beat(), 0);
+}
+
+#[test]
+fn settings_survive_the_trip_through_atomics() {
+    let settings = MetronomeSettings {
+        bpm: 96.5,
+        beats_per_bar: 7,
+        count_in_bars: 2,
+        after_count_in: false,
+        level_db: -6.0,
+    };
+    let values: [f32; 5] = settings.into();
+    assert_eq!(MetronomeSettings::from(values), settings);
+}
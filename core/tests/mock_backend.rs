@@ -0,0 +1,305 @@
+// The engine end to end on the mock backend: input into the rolling buffer, a grab, and the
+// exported file
+
+use cpal::{BufferSize, SampleRate, StreamConfig};
+use rolling_sampler_core::backend::{AudioBackend, MockBackend, MockStream, Signal};
+use rolling_sampler_core::export::{ExportStage, Take};
+use rolling_sampler_core::{CircularBuffer, RecorderError};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const RATE: u32 = 48_000;
+const CHANNELS: u16 = 2;
+const BLOCK: usize = 512;
+
+fn config() -> StreamConfig {
+    StreamConfig {
+        channels: CHANNELS,
+        sample_rate: SampleRate(RATE),
+        buffer_size: BufferSize::Default,
+    }
+}
+
+// A capture stream writing into a buffer holding `seconds` of audio, like the app's input
+fn capture(signal: Signal, seconds: usize) -> (MockStream, Arc<Mutex<CircularBuffer>>) {
+    let buffer = Arc::new(Mutex::new(CircularBuffer::new(
+        seconds * RATE as usize * CHANNELS as usize,
+    )));
+    let callback_buffer = Arc::clone(&buffer);
+    let stream = MockBackend::new(signal)
+        .build_capture(
+            "Mock Input",
+            &config(),
+            move |data, _| callback_buffer.lock().unwrap().add_samples(data),
+            |_| {},
+        )
+        .unwrap();
+    (stream, buffer)
+}
+
+fn pump_seconds(stream: &mut MockStream, seconds: f64) {
+    let frames = (seconds * RATE as f64) as usize;
+    for _ in 0..frames / BLOCK {
+        stream.pump(BLOCK);
+    }
+    stream.pump(frames % BLOCK);
+}
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rolling-sampler-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn lists_and_opens_mock_devices() {
+    let backend = MockBackend::new(Signal::Silence);
+    assert_eq!(backend.input_devices().unwrap(), ["Mock Input"]);
+    assert_eq!(backend.output_devices().unwrap(), ["Mock Output"]);
+    assert!(backend
+        .build_capture("Mock Input", &config(), |_, _| {}, |_| {})
+        .is_ok());
+}
+
+#[test]
+fn missing_device_is_an_error() {
+    let mut backend = MockBackend::new(Signal::Silence);
+    backend.inputs.clear();
+    let result = backend.build_capture("Mock Input", &config(), |_, _| {}, |_| {});
+    assert!(matches!(result, Err(RecorderError::Device(_))));
+}
+
+#[test]
+fn same_signal_every_run() {
+    let signal = Signal::Sine {
+        hz: 440.0,
+        amplitude: 0.5,
+    };
+    let (mut first, first_buffer) = capture(signal, 1);
+    let (mut second, second_buffer) = capture(signal, 1);
+    pump_seconds(&mut first, 0.25);
+    pump_seconds(&mut second, 0.25);
+    assert_eq!(
+        first_buffer.lock().unwrap().circular_buffer,
+        second_buffer.lock().unwrap().circular_buffer
+    );
+}
+
+#[test]
+fn buffer_keeps_only_the_latest_window() {
+    let (mut stream, buffer) = capture(
+        Signal::Clicks {
+            every_frames: RATE as u64 / 10,
+            amplitude: 1.0,
+        },
+        1,
+    );
+    pump_seconds(&mut stream, 3.5);
+
+    let buffer = buffer.lock().unwrap();
+    assert_eq!(stream.frames(), 3 * RATE as u64 + RATE as u64 / 2);
+    assert_eq!(buffer.total_written, stream.frames() * CHANNELS as u64);
+    assert_eq!(buffer.current_size, buffer.max_size);
+    // The oldest sample held is from 2.5 seconds in, which is on a click
+    let oldest = buffer.window_start();
+    assert_eq!(
+        oldest,
+        (2 * RATE as u64 + RATE as u64 / 2) * CHANNELS as u64
+    );
+    assert_eq!(buffer.sample_at(oldest), 1.0);
+    assert_eq!(buffer.sample_at(oldest + CHANNELS as u64), 0.0);
+}
+
+#[test]
+fn grab_includes_history_and_what_follows() {
+    let (mut stream, buffer) = capture(
+        Signal::Sine {
+            hz: 100.0,
+            amplitude: 0.5,
+        },
+        2,
+    );
+    pump_seconds(&mut stream, 5.0);
+    buffer.lock().unwrap().start_static_mode();
+    pump_seconds(&mut stream, 1.0);
+
+    let buffer = buffer.lock().unwrap();
+    assert!(buffer.is_static_mode);
+    assert_eq!(
+        buffer.static_buffer.len(),
+        3 * RATE as usize * CHANNELS as usize
+    );
+    // In time order across the point the ring was frozen
+    let expected = Signal::Sine {
+        hz: 100.0,
+        amplitude: 0.5,
+    };
+    for (i, frame) in buffer.static_buffer.chunks(CHANNELS as usize).enumerate() {
+        let sample = expected.sample(3 * RATE as u64 + i as u64, RATE);
+        assert!((frame[0] - sample).abs() < 1e-6);
+        assert_eq!(frame[0], frame[1]);
+    }
+}
+
+#[test]
+fn grab_exports_a_tagged_wav() {
+    let (mut stream, buffer) = capture(
+        Signal::Sine {
+            hz: 440.0,
+            amplitude: 0.5,
+        },
+        2,
+    );
+    pump_seconds(&mut stream, 2.0);
+    let samples = {
+        let mut buffer = buffer.lock().unwrap();
+        buffer.start_static_mode();
+        std::mem::take(&mut buffer.static_buffer)
+    };
+
+    let dir = temp_dir("export");
+    let path = dir.join("Take.wav");
+    let take = Take {
+        name: "Take".to_owned(),
+        path: path.clone(),
+        samples: Arc::new(samples),
+        channels: CHANNELS,
+        sample_rate: RATE,
+        stages: vec![ExportStage::Normalize { peak_db: -1.0 }],
+        ..Take::default()
+    };
+    let sidecar = take.save().unwrap();
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().channels, CHANNELS);
+    assert_eq!(reader.spec().sample_rate, RATE);
+    assert_eq!(reader.duration(), 2 * RATE);
+    let peak = reader
+        .samples::<f32>()
+        .map(|sample| sample.unwrap().abs())
+        .fold(0.0, f32::max);
+    assert!((20.0 * peak.log10() + 1.0).abs() < 0.05);
+
+    assert_eq!(sidecar.file, "Take.wav");
+    assert!((sidecar.duration_seconds - 2.0).abs() < 1e-9);
+    let analysis = sidecar.analysis.unwrap();
+    assert_eq!(analysis.note.as_deref(), Some("A4"));
+    assert!(dir.join("Take.json").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// The hook gets the saved file's path quoted, so names with spaces and quotes survive the shell
+#[cfg(unix)]
+#[test]
+fn post_save_command_gets_the_file() {
+    let dir = temp_dir("post-save");
+    let path = dir.join("Take 'one'.wav");
+    let copy = dir.join("copy.wav");
+    let take = Take {
+        name: "Take 'one'".to_owned(),
+        path: path.clone(),
+        samples: Arc::new(vec![0.0; 2 * RATE as usize]),
+        channels: CHANNELS,
+        sample_rate: RATE,
+        post_save_command: Some(format!("cp {{file}} '{}'", copy.display())),
+        ..Take::default()
+    };
+    let (errors, reported) = std::sync::mpsc::channel();
+    take.save_in_background(errors).join().unwrap();
+    assert!(reported.try_recv().is_err());
+    assert_eq!(std::fs::read(&copy).unwrap(), std::fs::read(&path).unwrap());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// Three inputs with the middle one disarmed: a two channel file naming its tracks, and a mono
+// stem of each
+#[test]
+fn poly_take_keeps_armed_channels_and_saves_stems() {
+    let dir = temp_dir("poly");
+    let path = dir.join("Room.wav");
+    let frames = RATE as usize;
+    let samples = (0..frames).flat_map(|_| [0.1, 0.2, 0.3]).collect();
+    let take = Take {
+        name: "Room".to_owned(),
+        path: path.clone(),
+        samples: Arc::new(samples),
+        channels: 3,
+        sample_rate: RATE,
+        channel_names: vec!["Kick".to_owned(), "Snare".to_owned(), "Bass DI".to_owned()],
+        armed_channels: Some(vec![0, 2]),
+        stems: true,
+        ..Take::default()
+    };
+    let (errors, reported) = std::sync::mpsc::channel();
+    take.save_in_background(errors).join().unwrap();
+    assert!(reported.try_recv().is_err());
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().channels, 2);
+    let first: Vec<f32> = reader
+        .samples::<f32>()
+        .take(2)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(first, [0.1, 0.3]);
+    let file = std::fs::read(&path).unwrap();
+    let ixml = String::from_utf8_lossy(&file);
+    assert!(ixml.contains("<NAME>Kick</NAME>") && ixml.contains("<NAME>Bass DI</NAME>"));
+    let sidecar = std::fs::read_to_string(dir.join("Room.json")).unwrap();
+    assert!(sidecar.contains("Bass DI"));
+
+    for (stem, value) in [("Room_Kick", 0.1), ("Room_Bass_DI", 0.3)] {
+        let stem = dir.join("Room_stems").join(format!("{}.wav", stem));
+        let mut reader = hound::WavReader::open(&stem).unwrap();
+        assert_eq!(reader.spec().channels, 1);
+        assert_eq!(reader.duration(), RATE);
+        assert_eq!(reader.samples::<f32>().next().unwrap().unwrap(), value);
+    }
+    assert!(!dir.join("Room_stems").join("Room_Snare.wav").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn playback_pulls_from_the_fill_callback() {
+    let backend = MockBackend::new(Signal::Silence);
+    let mut next = 0.0;
+    let mut stream = backend
+        .build_playback(
+            "Mock Output",
+            &config(),
+            move |data| {
+                for sample in data.iter_mut() {
+                    *sample = next;
+                    next += 1.0;
+                }
+            },
+            |_| {},
+        )
+        .unwrap();
+    let first = stream.pull(2);
+    let second = stream.pull(2);
+    assert_eq!(first, [0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(second, [4.0, 5.0, 6.0, 7.0]);
+    assert_eq!(stream.frames(), 4);
+}
+
+// Input lined up from frame 100 replaces frames 200 to 400 only, fading in and out at the edges
+#[test]
+fn punch_replaces_only_between_the_points() {
+    let take = Take {
+        samples: Arc::new(vec![0.0; 1000 * CHANNELS as usize]),
+        channels: CHANNELS,
+        sample_rate: RATE,
+        ..Take::default()
+    };
+    let input = vec![1.0; 500 * CHANNELS as usize];
+    let punched = take.punched(&input, 100, 200, 400, 10);
+    let frame = |index: usize| punched.samples[index * CHANNELS as usize];
+    assert_eq!(frame(199), 0.0);
+    assert!(frame(200) > 0.0 && frame(200) < 1.0);
+    assert_eq!(frame(300), 1.0);
+    assert!(frame(399) > 0.0 && frame(399) < 1.0);
+    assert_eq!(frame(400), 0.0);
+    assert_eq!(punched.frames(), take.frames());
+}
@@ -0,0 +1,64 @@
+// Key and velocity mapping of sampler patches, and the SFZ written from it
+
+use rolling_sampler_core::patch::{self, Zone};
+
+fn zone(file: &str, root: u8, keys: (u8, u8), velocities: (u8, u8)) -> Zone {
+    Zone {
+        file: file.to_owned(),
+        root,
+        low_key: keys.0,
+        high_key: keys.1,
+        low_velocity: velocities.0,
+        high_velocity: velocities.1,
+    }
+}
+
+#[test]
+fn slices_take_a_key_each() {
+    let files = vec!["kick.wav".to_owned(), "snare.wav".to_owned()];
+    assert_eq!(
+        patch::chromatic_zones(&files, 36),
+        [
+            zone("kick.wav", 36, (36, 36), (1, 127)),
+            zone("snare.wav", 37, (37, 37), (1, 127)),
+        ]
+    );
+}
+
+#[test]
+fn multisamples_split_keys_halfway_and_stack_velocity_layers() {
+    let samples = vec![
+        (60, 127, "C4_v127.wav".to_owned()),
+        (48, 127, "C3_v127.wav".to_owned()),
+        (48, 64, "C3_v064.wav".to_owned()),
+        (60, 64, "C4_v064.wav".to_owned()),
+    ];
+    assert_eq!(
+        patch::multisample_zones(&samples),
+        [
+            zone("C3_v064.wav", 48, (0, 54), (1, 64)),
+            zone("C3_v127.wav", 48, (0, 54), (65, 127)),
+            zone("C4_v064.wav", 60, (55, 127), (1, 64)),
+            zone("C4_v127.wav", 60, (55, 127), (65, 127)),
+        ]
+    );
+    // The top of the keyboard doesn't overflow
+    let high = vec![
+        (126, 100, "a.wav".to_owned()),
+        (127, 100, "b.wav".to_owned()),
+    ];
+    let zones = patch::multisample_zones(&high);
+    assert_eq!((zones[0].high_key, zones[1].low_key), (126, 127));
+}
+
+#[test]
+fn sfz_lists_a_region_per_zone() {
+    let zones = [zone("Synth C3.wav", 48, (0, 54), (1, 127))];
+    let sfz = patch::sfz("Synth", &zones, false);
+    assert!(sfz.contains(
+        "<region> pitch_keycenter=48 lokey=0 hikey=54 lovel=1 hivel=127 sample=Synth C3.wav\n"
+    ));
+    assert!(patch::sfz("Kit", &zones, true).contains("loop_mode=one_shot"));
+    let preset = patch::decent_sampler(&zones, false);
+    assert!(preset.contains("path=\"Synth C3.wav\" rootNote=\"48\" loNote=\"0\" hiNote=\"54\""));
+}
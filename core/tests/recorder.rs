@@ -0,0 +1,190 @@
+// The core's Recorder on the mock backend: the same input stream, capture chain, grabs and
+// monitoring the app runs, pumped by hand
+
+use rolling_sampler_core::backend::{MockBackend, Signal};
+use rolling_sampler_core::monitor::MonitorResampler;
+use rolling_sampler_core::recorder::{Arming, MonitorSettings, Recorder};
+use rolling_sampler_core::routing::MonitorRouting;
+use rolling_sampler_core::state::RecorderState;
+
+const RATE: u64 = 48_000;
+const CHANNELS: u64 = 2;
+const BLOCK: usize = 512;
+
+fn recorder(signal: Signal, buffer_seconds: f32) -> Recorder<MockBackend> {
+    let mut recorder = Recorder::new(MockBackend::new(signal), buffer_seconds);
+    recorder.list_devices(None, None).unwrap();
+    recorder.start_input().unwrap();
+    recorder
+}
+
+fn pump_seconds(recorder: &mut Recorder<MockBackend>, seconds: f64) {
+    let stream = recorder.input_stream().unwrap();
+    let frames = (seconds * RATE as f64) as usize;
+    for _ in 0..frames / BLOCK {
+        stream.pump(BLOCK);
+    }
+    stream.pump(frames % BLOCK);
+}
+
+#[test]
+fn grab_keeps_history_and_rolls_on() {
+    let mut recorder = recorder(
+        Signal::Sine {
+            hz: 100.0,
+            amplitude: 0.5,
+        },
+        1.0,
+    );
+    assert_eq!(recorder.state, RecorderState::Rolling);
+    pump_seconds(&mut recorder, 2.0);
+    assert!(recorder.start_grab());
+    pump_seconds(&mut recorder, 0.5);
+    assert_eq!(recorder.buffered_frames(), 3 * RATE as usize / 2);
+
+    let grabbed = recorder.grab().unwrap();
+    assert_eq!(recorder.state, RecorderState::Rolling);
+    assert_eq!(grabbed.static_buffer.len() as u64, 3 * RATE * CHANNELS / 2);
+    // The next buffer carries on from the end of the grab, at the same absolute indices
+    let buffer = recorder.buffer.lock().unwrap();
+    assert_eq!(buffer.total_written, grabbed.total_written);
+    assert_eq!(buffer.current_size as u64, RATE * CHANNELS);
+    let last = buffer.total_written - 1;
+    assert_eq!(
+        buffer.sample_at(last),
+        *grabbed.static_buffer.last().unwrap()
+    );
+}
+
+#[test]
+fn missing_input_is_an_error_state() {
+    let mut backend = MockBackend::new(Signal::Silence);
+    backend.inputs.clear();
+    let mut recorder = Recorder::new(backend, 1.0);
+    recorder.list_devices(None, None).unwrap();
+    assert!(recorder.start_input().is_err());
+    assert!(matches!(recorder.state, RecorderState::Error(_)));
+    assert!(!recorder.has_input());
+    assert!(!recorder.start_grab());
+}
+
+#[test]
+fn armed_buffer_waits_for_the_hit() {
+    let mut recorder = recorder(
+        Signal::Clicks {
+            every_frames: RATE,
+            amplitude: 1.0,
+        },
+        1.0,
+    );
+    pump_seconds(&mut recorder, 0.25);
+    recorder.set_arming(Arming {
+        threshold_db: Some(-6.0),
+        vox: None,
+    });
+    pump_seconds(&mut recorder, 0.5);
+    recorder.poll_auto_grab();
+    assert_eq!(recorder.state, RecorderState::Rolling);
+    assert_eq!(recorder.buffer.lock().unwrap().total_written, 0);
+
+    // The click at one second starts the grab, with the take beginning on it
+    pump_seconds(&mut recorder, 0.5);
+    recorder.poll_auto_grab();
+    assert_eq!(recorder.state, RecorderState::Grabbing);
+    let grabbed = recorder.grab().unwrap();
+    assert_eq!(grabbed.static_buffer[0], 1.0);
+    assert_eq!(grabbed.static_buffer.len() as u64, RATE / 4 * CHANNELS);
+}
+
+#[test]
+fn marker_export_waits_for_its_audio() {
+    let mut recorder = recorder(Signal::Silence, 2.0);
+    pump_seconds(&mut recorder, 1.0);
+    recorder.drop_marker(1);
+    let index = recorder.marker(1).unwrap().index;
+    recorder.export_between("Around".to_owned(), index - RATE, index + RATE);
+    assert!(recorder.poll_markers().is_empty());
+    assert_eq!(recorder.pending_exports(), 1);
+
+    pump_seconds(&mut recorder, 1.0);
+    let ready = recorder.poll_markers();
+    assert_eq!(ready.len(), 1);
+    let (name, start, samples) = &ready[0];
+    assert_eq!(name, "Around");
+    assert_eq!(*start, index - RATE);
+    assert_eq!(samples.len() as u64, 2 * RATE);
+
+    // Once it rolls out of the buffer the marker goes
+    pump_seconds(&mut recorder, 2.0);
+    recorder.poll_markers();
+    assert!(recorder.marker(1).is_none());
+}
+
+#[test]
+fn markers_from_a_restarted_history_are_dropped() {
+    // Parked with one session's one second buffer, brought back under another's two seconds
+    let mut recorder = recorder(Signal::Silence, 1.0);
+    pump_seconds(&mut recorder, 0.5);
+    recorder.drop_marker(1);
+    let parked = std::mem::take(&mut recorder.markers);
+    recorder.buffer_seconds = 2.0;
+    recorder.start_input().unwrap();
+    // Far enough in that the old index would be inside the new window
+    pump_seconds(&mut recorder, 1.0);
+    recorder.restore_markers(parked);
+    assert!(recorder.marker(1).is_none());
+
+    // Switching with the same buffer length keeps the history running, and the markers with it
+    recorder.drop_marker(2);
+    let parked = std::mem::take(&mut recorder.markers);
+    pump_seconds(&mut recorder, 0.5);
+    recorder.restore_markers(parked);
+    assert!(recorder.marker(2).is_some());
+
+    // Unless they rolled out while they were away
+    let parked = std::mem::take(&mut recorder.markers);
+    pump_seconds(&mut recorder, 2.0);
+    recorder.restore_markers(parked);
+    assert!(recorder.marker(2).is_none());
+}
+
+#[test]
+fn monitoring_plays_the_input() {
+    let mut recorder = recorder(
+        Signal::Sine {
+            hz: 440.0,
+            amplitude: 0.5,
+        },
+        1.0,
+    );
+    let settings = MonitorSettings {
+        routing: MonitorRouting::Direct,
+        custom_routes: Vec::new(),
+        latency_ms: 5.0,
+        auto_latency: false,
+        resampler: MonitorResampler::Linear,
+        limiter_ceiling_db: None,
+    };
+    recorder.start_monitoring(&settings).unwrap();
+    assert!(recorder.is_monitoring());
+    pump_seconds(&mut recorder, 0.1);
+    let out = recorder.output_stream().unwrap().pull(BLOCK);
+    let peak = out.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!((peak - 0.5).abs() < 0.05, "peak {}", peak);
+    assert!(recorder.monitor_latency().is_some());
+
+    recorder.stop_monitoring();
+    assert!(!recorder.is_monitoring());
+    assert!(recorder.output_stream().is_none());
+}
+
+#[test]
+fn dropped_input_counts_as_an_xrun() {
+    let mut recorder = recorder(Signal::Silence, 1.0);
+    pump_seconds(&mut recorder, 0.1);
+    let stream = recorder.input_stream().unwrap();
+    stream.skip(BLOCK);
+    stream.pump(BLOCK);
+    stream.pump(BLOCK);
+    assert_eq!(recorder.stream_stats.input_xruns(), 1);
+}
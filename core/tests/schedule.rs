@@ -0,0 +1,13 @@
+use rolling_sampler_core::schedule::Schedule;
+
+#[test]
+fn capture_longer_than_the_buffer_is_rejected() {
+    let mut schedule = Schedule::default();
+    assert!(schedule.check(5.0).is_ok()); // The whole buffer
+    schedule.capture_seconds = 5.0;
+    assert!(schedule.check(5.0).is_ok());
+    // "The last 10 minutes every hour" needs a buffer that long
+    schedule.capture_seconds = 600.0;
+    let error = schedule.check(60.0).unwrap_err();
+    assert!(error.contains("600 s"), "{}", error);
+}
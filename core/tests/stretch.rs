@@ -0,0 +1,19 @@
+use rolling_sampler_core::stretch::{time_stretch, Stretcher};
+
+// Exports feed the stretch a chunk at a time, which has to come out the same as all at once
+#[test]
+fn chunks_stretch_like_the_whole_take() {
+    let samples: Vec<f32> = (0..20_000)
+        .map(|i| (i as f32 * 0.01).sin() * (i as f32 * 0.0007).cos())
+        .collect();
+    for ratio in [0.5, 1.5, 2.0] {
+        let whole = time_stretch(&samples, 2, ratio);
+        let mut stretcher = Stretcher::new(2, ratio);
+        let mut chunked = Vec::new();
+        for chunk in samples.chunks(1234) {
+            chunked.extend(stretcher.process(chunk));
+        }
+        chunked.extend(stretcher.finish());
+        assert_eq!(chunked, whole);
+    }
+}
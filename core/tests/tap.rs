@@ -0,0 +1,43 @@
+use rolling_sampler_core::tap::InputTap;
+
+#[test]
+fn only_takes_blocks_while_enabled() {
+    let tap = InputTap::new(8);
+    assert!(!tap.push(&[1.0, 2.0]));
+    tap.set_enabled(true);
+    assert!(tap.push(&[3.0, 4.0]));
+
+    let mut taken = Vec::new();
+    assert_eq!(tap.pop(&mut taken), 2);
+    assert_eq!(taken, [3.0, 4.0]);
+    assert_eq!(tap.pop(&mut taken), 0);
+}
+
+#[test]
+fn drops_a_block_that_does_not_fit() {
+    let tap = InputTap::new(4);
+    tap.set_enabled(true);
+    assert!(tap.push(&[1.0, 2.0, 3.0]));
+    assert!(!tap.push(&[4.0, 5.0]));
+    assert_eq!(tap.dropped(), 2);
+
+    // Wraps around once the consumer has caught up
+    let mut taken = Vec::new();
+    tap.pop(&mut taken);
+    assert!(tap.push(&[6.0, 7.0, 8.0]));
+    taken.clear();
+    tap.pop(&mut taken);
+    assert_eq!(taken, [6.0, 7.0, 8.0]);
+}
+
+#[test]
+fn a_new_consumer_skips_what_was_left() {
+    let tap = InputTap::new(4);
+    tap.set_enabled(true);
+    tap.push(&[1.0, 2.0]);
+    tap.skip();
+    tap.push(&[3.0]);
+    let mut taken = Vec::new();
+    tap.pop(&mut taken);
+    assert_eq!(taken, [3.0]);
+}
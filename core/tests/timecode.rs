@@ -0,0 +1,146 @@
+// Timecode arithmetic, and the MTC and LTC decoders fed made-up signals
+
+use rolling_sampler_core::timecode::{FrameRate, LtcDecoder, MtcDecoder, Timecode};
+
+fn timecode(hours: u8, minutes: u8, seconds: u8, frames: u8, rate: FrameRate) -> Timecode {
+    Timecode {
+        hours,
+        minutes,
+        seconds,
+        frames,
+        rate,
+    }
+}
+
+// The 80 bits of an LTC frame, bit 0 first
+fn ltc_bits(time: &Timecode) -> Vec<u8> {
+    let mut bits = vec![0u8; 80];
+    let mut put = |from: usize, count: usize, value: u8| {
+        for bit in 0..count {
+            bits[from + bit] = (value >> bit) & 1;
+        }
+    };
+    put(0, 4, time.frames % 10);
+    put(8, 2, time.frames / 10);
+    put(10, 1, (time.rate == FrameRate::Fps2997Drop) as u8);
+    put(16, 4, time.seconds % 10);
+    put(24, 3, time.seconds / 10);
+    put(32, 4, time.minutes % 10);
+    put(40, 3, time.minutes / 10);
+    put(48, 4, time.hours % 10);
+    put(56, 2, time.hours / 10);
+    for (bit, &value) in [0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1]
+        .iter()
+        .enumerate()
+    {
+        bits[64 + bit] = value;
+    }
+    bits
+}
+
+// Biphase mark: a transition at the start of every bit, and another in the middle of ones
+fn ltc_audio(start: Timecode, frames: u64, sample_rate: u32) -> Vec<f32> {
+    let bit_length = sample_rate as f64 / (start.rate.fps() * 80.0);
+    let mut level = 0.5f32;
+    let mut samples = Vec::new();
+    let mut position = 0.0;
+    for frame in 0..frames {
+        for bit in ltc_bits(&start.plus_frames(frame)) {
+            let halves = if bit == 1 { [0.5, 0.5] } else { [1.0, 0.0] };
+            for half in halves.into_iter().filter(|&half| half > 0.0) {
+                level = -level;
+                position += half * bit_length;
+                while (samples.len() as f64) < position {
+                    samples.push(level);
+                }
+            }
+        }
+    }
+    samples
+}
+
+#[test]
+fn drop_frame_skips_the_first_two_frames_of_most_minutes() {
+    let rate = FrameRate::Fps2997Drop;
+    let before = timecode(0, 0, 59, 29, rate);
+    assert_eq!(before.plus_frames(1), timecode(0, 1, 0, 2, rate));
+    // but not every tenth minute
+    let before = timecode(0, 9, 59, 29, rate);
+    assert_eq!(before.plus_frames(1), timecode(0, 10, 0, 0, rate));
+    // An hour of drop-frame timecode is an hour of real time, near enough
+    assert!((timecode(1, 0, 0, 0, rate).seconds() - 3600.0).abs() < 0.01);
+}
+
+#[test]
+fn frame_numbers_round_trip() {
+    for rate in [
+        FrameRate::Fps24,
+        FrameRate::Fps25,
+        FrameRate::Fps2997Drop,
+        FrameRate::Fps30,
+    ] {
+        for frame in [0, 1, 1799, 1800, 17_982, 107_892, 2_000_000] {
+            let time = Timecode::from_frame_number(frame, rate);
+            assert_eq!(time.frame_number(), frame, "{:?} frame {}", rate, frame);
+        }
+    }
+}
+
+#[test]
+fn display_marks_drop_frame() {
+    assert_eq!(
+        timecode(1, 2, 3, 4, FrameRate::Fps25).to_string(),
+        "01:02:03:04"
+    );
+    assert_eq!(
+        timecode(1, 2, 3, 4, FrameRate::Fps2997Drop).to_string(),
+        "01:02:03;04"
+    );
+    assert_eq!(
+        timecode(1, 2, 3, 4, FrameRate::Fps25).file_safe(),
+        "01-02-03-04"
+    );
+}
+
+#[test]
+fn mtc_full_frame() {
+    let mut decoder = MtcDecoder::default();
+    // 25 fps (rate bits 01) at 10:20:30:12
+    let message = [0xf0, 0x7f, 0x7f, 0x01, 0x01, 0x20 | 10, 20, 30, 12, 0xf7];
+    assert_eq!(
+        decoder.message(&message),
+        Some(timecode(10, 20, 30, 12, FrameRate::Fps25))
+    );
+}
+
+#[test]
+fn mtc_quarter_frames_complete_two_frames_on() {
+    let mut decoder = MtcDecoder::default();
+    // 30 fps (rate bits 11) at 01:02:03:04, low nibble then high bits of each field
+    let pieces = [4, 0, 3, 0, 2, 0, 1, 0b110];
+    let mut decoded = None;
+    for (piece, value) in pieces.into_iter().enumerate() {
+        decoded = decoder.message(&[0xf1, (piece as u8) << 4 | value]);
+    }
+    assert_eq!(decoded, Some(timecode(1, 2, 3, 6, FrameRate::Fps30)));
+}
+
+#[test]
+fn ltc_decodes_frames_and_where_they_start() {
+    for (rate, sample_rate) in [(FrameRate::Fps25, 48_000), (FrameRate::Fps24, 44_100)] {
+        let start = timecode(12, 34, 56, 7, rate);
+        let audio = ltc_audio(start, 10, sample_rate);
+        let mut decoder = LtcDecoder::new(sample_rate);
+        let (time, position) = decoder.feed(audio).expect("a frame decoded");
+        // The ninth of the ten, since a frame's last bit only ends at the next frame's first
+        // transition
+        assert_eq!(time, start.plus_frames(8));
+        let expected = 8.0 * sample_rate as f64 / rate.fps();
+        assert!(
+            (position as f64 - expected).abs() < 4.0,
+            "frame at {} rather than {}",
+            position,
+            expected
+        );
+    }
+}
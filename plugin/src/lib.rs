@@ -0,0 +1,234 @@
+// Rolling Sampler as a CLAP/VST3 effect. It passes the track through untouched while keeping the
+// last minute of it in the same rolling buffer the app uses; turning on "Grab" (or any MIDI
+// note) saves the last "Buffer" seconds as a WAV with sidecar through the core's export code.
+// Plugin formats have no way to add a clip to the host's project, so grabs go to a folder for
+// the DAW's browser instead, `Music/Rolling Sampler` by default.
+
+use nih_plug::prelude::*;
+use rolling_sampler_core::export::Take;
+use rolling_sampler_core::CircularBuffer;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const MAX_BUFFER_SECONDS: f32 = 60.0;
+
+struct RollingSampler {
+    params: Arc<SamplerParams>,
+    buffer: CircularBuffer,
+    scratch: Vec<f32>, // One block interleaved, allocated up front for the largest block
+    channels: usize,
+    sample_rate: u32,
+    grab_was_on: bool, // Grabs happen when the parameter turns on, not while it stays on
+}
+
+#[derive(Params)]
+struct SamplerParams {
+    #[id = "seconds"]
+    pub buffer_seconds: FloatParam,
+    #[id = "grab"]
+    pub grab: BoolParam,
+}
+
+impl Default for SamplerParams {
+    fn default() -> Self {
+        SamplerParams {
+            buffer_seconds: FloatParam::new(
+                "Buffer",
+                10.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: MAX_BUFFER_SECONDS,
+                },
+            )
+            .with_step_size(0.5)
+            .with_unit(" s"),
+            grab: BoolParam::new("Grab", false),
+        }
+    }
+}
+
+impl Default for RollingSampler {
+    fn default() -> Self {
+        RollingSampler {
+            params: Arc::new(SamplerParams::default()),
+            buffer: CircularBuffer::new(1), // Sized in initialize once the rate is known
+            scratch: Vec::new(),
+            channels: 2,
+            sample_rate: 48000,
+            grab_was_on: false,
+        }
+    }
+}
+
+// Audio handed to the background thread to be written
+pub struct Grab {
+    samples: Vec<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+fn grab_folder() -> PathBuf {
+    dirs::audio_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("Rolling Sampler")
+}
+
+// Same naming as the app's grabs, numbered when two land in the same second
+fn take_name(folder: &Path) -> String {
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut name = timestamp.clone();
+    let mut count = 1;
+    while folder.join(format!("{}.wav", name)).exists() {
+        count += 1;
+        name = format!("{}_{}", timestamp, count);
+    }
+    name
+}
+
+fn save(grab: Grab) {
+    let folder = grab_folder();
+    let name = take_name(&folder);
+    let take = Take {
+        path: folder.join(format!("{}.wav", name)),
+        name,
+        samples: Arc::new(grab.samples),
+        channels: grab.channels,
+        sample_rate: grab.sample_rate,
+        preset: "Plugin".to_owned(),
+        ..Take::default()
+    };
+    match take.save() {
+        Ok(_) => nih_log!("Grab saved to {}", take.path.display()),
+        Err(e) => nih_error!("Failed to save {}: {}", take.path.display(), e),
+    }
+}
+
+impl RollingSampler {
+    // Copies the newest part of the buffer. The copy is the one allocation on the audio thread,
+    // and only happens when a grab is asked for.
+    fn grab(&self, context: &mut impl ProcessContext<Self>) {
+        let wanted = (self.params.buffer_seconds.value() * self.sample_rate as f32) as u64
+            * self.channels as u64;
+        let end = self.buffer.total_written;
+        let samples = self.buffer.copy_range(end.saturating_sub(wanted), end);
+        if samples.is_empty() {
+            return;
+        }
+        context.execute_background(Grab {
+            samples,
+            channels: self.channels as u16,
+            sample_rate: self.sample_rate,
+        });
+    }
+}
+
+impl Plugin for RollingSampler {
+    const NAME: &'static str = "Rolling Sampler";
+    const VENDOR: &'static str = "Rolling Sampler";
+    const URL: &'static str = "https://github.com/jbremz/rolling-sampler";
+    const EMAIL: &'static str = "";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic; // Any note grabs, for a pad or a key
+    const SAMPLE_ACCURATE_AUTOMATION: bool = false;
+
+    type SysExMessage = ();
+    type BackgroundTask = Grab;
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        Box::new(save)
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        _context: &mut impl InitContext<Self>,
+    ) -> bool {
+        self.channels = audio_io_layout
+            .main_input_channels
+            .map_or(2, |channels| channels.get() as usize);
+        self.sample_rate = buffer_config.sample_rate as u32;
+        // Always the longest window, so turning up "Buffer" never needs a reallocation
+        let size = (MAX_BUFFER_SECONDS * buffer_config.sample_rate) as usize * self.channels;
+        self.buffer = CircularBuffer::new(size);
+        self.buffer.channels = self.channels;
+        self.buffer.sample_rate = self.sample_rate;
+        self.scratch = Vec::with_capacity(buffer_config.max_buffer_size as usize * self.channels);
+        true
+    }
+
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        _aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        // The output is the input as it is, only a copy goes into the buffer
+        self.scratch.clear();
+        for frame in buffer.iter_samples() {
+            for sample in frame {
+                self.scratch.push(*sample);
+            }
+        }
+        self.buffer.add_samples(&self.scratch);
+
+        let mut grab = false;
+        while let Some(event) = context.next_event() {
+            if let NoteEvent::NoteOn { .. } = event {
+                grab = true;
+            }
+        }
+        let grab_on = self.params.grab.value();
+        if grab_on && !self.grab_was_on {
+            grab = true;
+        }
+        self.grab_was_on = grab_on;
+        if grab {
+            self.grab(context);
+        }
+        ProcessStatus::Normal
+    }
+}
+
+impl ClapPlugin for RollingSampler {
+    const CLAP_ID: &'static str = "com.jbremz.rolling-sampler";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("Keeps the last minute of a track and saves it on demand");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Utility,
+        ClapFeature::Stereo,
+        ClapFeature::Mono,
+    ];
+}
+
+impl Vst3Plugin for RollingSampler {
+    const VST3_CLASS_ID: [u8; 16] = *b"RollingSamplerFx";
+    const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] =
+        &[Vst3SubCategory::Fx, Vst3SubCategory::Tools];
+}
+
+nih_export_clap!(RollingSampler);
+nih_export_vst3!(RollingSampler);
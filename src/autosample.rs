@@ -0,0 +1,231 @@
+#[cfg(feature = "midi")]
+use midir::{MidiOutput, MidiOutputConnection};
+use rolling_sampler_core::dsp;
+use rolling_sampler_core::pitch::NOTE_NAMES;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+// Samples a hardware synth: each note of a range is played at each velocity on a MIDI output
+// while the input keeps rolling, and the audio from its note on to the end of its release is
+// handed back to be trimmed and saved like any other take. One note sounds at a time, so the
+// next starts once the last one's release has been captured.
+
+#[cfg(feature = "midi")]
+type Connection = MidiOutputConnection;
+#[cfg(not(feature = "midi"))]
+type Connection = ();
+
+const ALL_NOTES_OFF: u8 = 123;
+
+pub struct AutoSampleSettings {
+    pub port: String,
+    pub channel: u8, // 1 to 16
+    pub low_note: u8,
+    pub high_note: u8,
+    pub step: u8, // Semitones between sampled notes
+    pub velocities: Vec<u8>,
+    pub hold_seconds: f32,    // Note on to note off
+    pub release_seconds: f32, // Captured after the note off, for the tail
+}
+
+// A note that has finished, with where its audio lies in the rolling buffer (see
+// `CircularBuffer::total_written`)
+pub struct Capture {
+    pub note: u8,
+    pub velocity: u8,
+    pub start: u64,
+    pub end: u64,
+}
+
+struct Playing {
+    note: u8,
+    velocity: u8,
+    start: u64,
+    started: Instant,
+    released: bool, // The note off has been sent
+}
+
+pub struct AutoSampler {
+    #[cfg_attr(not(feature = "midi"), allow(dead_code))] // Only sent on with the feature
+    connection: Connection,
+    channel: u8, // From 0, for the status byte
+    hold: Duration,
+    length: Duration,    // Hold and release
+    plan: Vec<(u8, u8)>, // Note and velocity, in the order played
+    done: usize,
+    playing: Option<Playing>,
+}
+
+impl AutoSampler {
+    pub fn start(settings: AutoSampleSettings) -> std::io::Result<Self> {
+        if !cfg!(feature = "midi") {
+            return Err(std::io::Error::new(
+                ErrorKind::Unsupported,
+                "built without the `midi` feature",
+            ));
+        }
+        let plan: Vec<(u8, u8)> = (settings.low_note..=settings.high_note.min(127))
+            .step_by(settings.step.max(1) as usize)
+            .flat_map(|note| {
+                settings
+                    .velocities
+                    .iter()
+                    .map(move |&velocity| (note, velocity.clamp(1, 127)))
+            })
+            .collect();
+        if plan.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "no notes or velocities to play",
+            ));
+        }
+        let hold = Duration::from_secs_f32(settings.hold_seconds.max(0.01));
+        let sampler = AutoSampler {
+            connection: connect(&settings.port)?,
+            channel: settings.channel.clamp(1, 16) - 1,
+            hold,
+            length: hold + Duration::from_secs_f32(settings.release_seconds.max(0.0)),
+            plan,
+            done: 0,
+            playing: None,
+        };
+        println!(
+            "Auto-sampling {} notes on {}",
+            sampler.plan.len(),
+            settings.port
+        );
+        Ok(sampler)
+    }
+
+    // Moves the run along, given how much of the input has been written so far. Returns the
+    // note that has just been captured in full, if any.
+    pub fn poll(&mut self, position: u64) -> Option<Capture> {
+        let now = Instant::now();
+        let Some(playing) = &mut self.playing else {
+            let (note, velocity) = *self.plan.get(self.done)?;
+            self.send(&[0x90 | self.channel, note, velocity]);
+            self.playing = Some(Playing {
+                note,
+                velocity,
+                start: position,
+                started: now,
+                released: false,
+            });
+            return None;
+        };
+        let elapsed = now.duration_since(playing.started);
+        if !playing.released && elapsed >= self.hold {
+            playing.released = true;
+            let note = playing.note;
+            self.send(&[0x80 | self.channel, note, 0]);
+        }
+        if elapsed < self.length {
+            return None;
+        }
+        let playing = self.playing.take()?;
+        self.done += 1;
+        Some(Capture {
+            note: playing.note,
+            velocity: playing.velocity,
+            start: playing.start,
+            end: position,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done >= self.plan.len()
+    }
+
+    // Notes captured and planned
+    #[cfg(feature = "gui")]
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done, self.plan.len())
+    }
+
+    // The note sounding now, and its velocity
+    pub fn playing(&self) -> Option<(u8, u8)> {
+        self.playing
+            .as_ref()
+            .map(|playing| (playing.note, playing.velocity))
+    }
+
+    #[cfg(feature = "midi")]
+    fn send(&mut self, message: &[u8]) {
+        if let Err(e) = self.connection.send(message) {
+            eprintln!("Failed to send MIDI: {}", e);
+        }
+    }
+
+    #[cfg(not(feature = "midi"))]
+    fn send(&mut self, _message: &[u8]) {}
+}
+
+// A run that is stopped part way mustn't leave the synth droning
+impl Drop for AutoSampler {
+    fn drop(&mut self) {
+        if let Some((note, _)) = self.playing() {
+            self.send(&[0x80 | self.channel, note, 0]);
+        }
+        self.send(&[0xb0 | self.channel, ALL_NOTES_OFF, 0]);
+    }
+}
+
+// e.g. "C4" for middle C (note 60), as the tuner names notes
+pub fn note_name(note: u8) -> String {
+    format!("{}{}", NOTE_NAMES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+// The frames from the first to the last that reach `floor_db`, cutting the silence before the
+// synth answered (the MIDI and audio latency) and after its release died away. None when the
+// note never sounded.
+pub fn sounding_frames(samples: &[f32], channels: usize, floor_db: f32) -> Option<(usize, usize)> {
+    let floor = dsp::db_to_gain(floor_db);
+    let channels = channels.max(1);
+    let loud = |frame: &[f32]| dsp::peak(frame) >= floor;
+    let first = samples.chunks_exact(channels).position(loud)?;
+    let last = samples.chunks_exact(channels).rposition(loud)?;
+    Some((first, last + 1))
+}
+
+#[cfg(all(feature = "midi", feature = "gui"))]
+pub fn output_ports() -> Vec<String> {
+    match MidiOutput::new("Rolling Sampler") {
+        Ok(output) => output
+            .ports()
+            .iter()
+            .filter_map(|port| output.port_name(port).ok())
+            .collect(),
+        Err(e) => {
+            eprintln!("MIDI output unavailable: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(all(not(feature = "midi"), feature = "gui"))]
+pub fn output_ports() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(feature = "midi")]
+fn connect(port_name: &str) -> std::io::Result<Connection> {
+    let output = MidiOutput::new("Rolling Sampler").map_err(std::io::Error::other)?;
+    let port = output
+        .ports()
+        .into_iter()
+        .find(|port| output.port_name(port).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("no MIDI output named '{}'", port_name),
+            )
+        })?;
+    output
+        .connect(&port, "rolling-sampler-out")
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+#[cfg(not(feature = "midi"))]
+fn connect(_port_name: &str) -> std::io::Result<Connection> {
+    Ok(())
+}
@@ -0,0 +1,516 @@
+use crate::config::BroadcastTarget;
+#[cfg(feature = "broadcast")]
+use audiopus::coder::Encoder;
+#[cfg(feature = "broadcast")]
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+#[cfg(feature = "broadcast")]
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use rolling_sampler_core::tap::InputTap;
+use std::io::ErrorKind;
+#[cfg(feature = "broadcast")]
+use std::io::{Read, Write};
+#[cfg(feature = "broadcast")]
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "broadcast")]
+use std::time::{Duration, Instant};
+
+// Live stream of the input, so someone elsewhere can listen in while it is captured. The input
+// callback pushes its blocks to a tap as it does for the safety recording, and a thread converts
+// them to 48 kHz, encodes 20 ms Opus packets and pushes them to an Icecast mountpoint (in Ogg) or
+// straight to an RTP endpoint. Opus only: there is no MP3 encoder to build against here.
+
+#[cfg(any(feature = "gui", feature = "broadcast"))]
+const OPUS_RATE: u32 = 48_000; // The rate Opus always runs at inside, and what it's fed
+#[cfg(feature = "broadcast")]
+const PACKET_FRAMES: usize = 960; // 20 ms
+#[cfg(feature = "broadcast")]
+const PACKETS_PER_PAGE: u64 = 5; // Ogg pages are flushed every 100 ms to keep the delay down
+#[cfg(feature = "broadcast")]
+const POLL: Duration = Duration::from_millis(10); // How often the streamer empties the tap
+#[cfg(feature = "broadcast")]
+const RECONNECT: Duration = Duration::from_secs(5);
+#[cfg(feature = "broadcast")]
+const RTP_PAYLOAD_TYPE: u8 = 111; // Dynamic, tied to Opus by the session description
+
+// Most of it is only read by the streamer, which needs the feature
+#[cfg_attr(not(feature = "broadcast"), allow(dead_code))]
+pub struct BroadcastSettings {
+    pub target: BroadcastTarget,
+    pub host: String,
+    pub port: u16,
+    pub mount: String,    // Icecast only
+    pub password: String, // Icecast's source password
+    pub bitrate_kbps: u32,
+    pub channels: u16, // The input's, the first two are streamed
+    pub sample_rate: u32,
+}
+
+impl BroadcastSettings {
+    #[cfg(any(feature = "gui", feature = "broadcast"))]
+    fn stereo(&self) -> bool {
+        self.channels >= 2
+    }
+}
+
+pub struct Broadcaster {
+    tap: Arc<InputTap>,
+    stop: Arc<AtomicBool>,
+    #[cfg(feature = "gui")]
+    connected: Arc<AtomicBool>,
+    #[cfg(feature = "gui")]
+    bytes_sent: Arc<AtomicU64>,
+    #[cfg(feature = "gui")]
+    sdp: Option<String>, // What an RTP listener opens to play the stream
+    streamer: Option<JoinHandle<()>>,
+}
+
+impl Broadcaster {
+    // Takes over the input stream's tap, which has to be from the stream the settings describe
+    pub fn start(settings: BroadcastSettings, tap: Arc<InputTap>) -> std::io::Result<Self> {
+        if !cfg!(feature = "broadcast") {
+            return Err(std::io::Error::new(
+                ErrorKind::Unsupported,
+                "built without the `broadcast` feature",
+            ));
+        }
+        if settings.host.trim().is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "no host to stream to",
+            ));
+        }
+        #[cfg(feature = "gui")]
+        let sdp = (settings.target == BroadcastTarget::Rtp).then(|| session_description(&settings));
+        tap.skip();
+        let stop = Arc::new(AtomicBool::new(false));
+        let connected = Arc::new(AtomicBool::new(false));
+        let bytes_sent = Arc::new(AtomicU64::new(0));
+        let streamer = {
+            let tap = Arc::clone(&tap);
+            let stop = Arc::clone(&stop);
+            let connected = Arc::clone(&connected);
+            let bytes_sent = Arc::clone(&bytes_sent);
+            thread::spawn(move || stream_blocks(settings, &tap, &stop, &connected, &bytes_sent))
+        };
+        tap.set_enabled(true);
+        Ok(Broadcaster {
+            tap,
+            stop,
+            #[cfg(feature = "gui")]
+            connected,
+            #[cfg(feature = "gui")]
+            bytes_sent,
+            #[cfg(feature = "gui")]
+            sdp,
+            streamer: Some(streamer),
+        })
+    }
+
+    // False while (re)connecting to Icecast
+    #[cfg(feature = "gui")]
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn sdp(&self) -> Option<&str> {
+        self.sdp.as_deref()
+    }
+}
+
+impl Drop for Broadcaster {
+    fn drop(&mut self) {
+        // The streamer takes what's left in the tap and ends the stream
+        self.tap.set_enabled(false);
+        self.stop.store(true, Ordering::Release);
+        if let Some(streamer) = self.streamer.take() {
+            let _ = streamer.join();
+        }
+    }
+}
+
+// For `ffplay -protocol_whitelist file,udp,rtp stream.sdp` or VLC. Opus is always described as
+// two channels at 48 kHz, with the parameters saying whether it really is stereo.
+#[cfg(feature = "gui")]
+fn session_description(settings: &BroadcastSettings) -> String {
+    let stereo = settings.stereo() as u8;
+    format!(
+        "v=0\r\no=- 0 0 IN IP4 {host}\r\ns=Rolling Sampler\r\nc=IN IP4 {host}\r\nt=0 0\r\n\
+         m=audio {port} RTP/AVP 111\r\na=rtpmap:111 opus/{rate}/2\r\n\
+         a=fmtp:111 stereo={stereo}; sprop-stereo={stereo}\r\n",
+        host = settings.host.trim(),
+        port = settings.port,
+        rate = OPUS_RATE,
+        stereo = stereo
+    )
+}
+
+#[cfg(feature = "broadcast")]
+fn stream_blocks(
+    settings: BroadcastSettings,
+    tap: &InputTap,
+    stop: &AtomicBool,
+    connected: &AtomicBool,
+    bytes_sent: &AtomicU64,
+) {
+    let channels = if settings.stereo() { 2 } else { 1 };
+    let encoder = Encoder::new(
+        SampleRate::Hz48000,
+        if settings.stereo() {
+            Channels::Stereo
+        } else {
+            Channels::Mono
+        },
+        Application::Audio,
+    )
+    .and_then(|mut encoder| {
+        encoder.set_bitrate(Bitrate::BitsPerSecond(
+            settings.bitrate_kbps.clamp(6, 510) as i32 * 1000,
+        ))?;
+        Ok(encoder)
+    });
+    let encoder = match encoder {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            eprintln!("Failed to start the Opus encoder: {}", e);
+            return;
+        }
+    };
+    let pre_skip = encoder.lookahead().unwrap_or(312) as u16;
+
+    let mut resampler = Resampler::new(settings.channels as usize, channels, settings.sample_rate);
+    let mut pcm = Vec::new();
+    let mut packet = [0u8; 4000];
+    let mut sink: Option<Sink> = None;
+    let mut retry_at = Instant::now();
+    let mut block = Vec::new();
+    loop {
+        // Read before emptying the tap, so nothing pushed before the stop is left behind
+        let stopping = stop.load(Ordering::Acquire);
+        block.clear();
+        if tap.pop(&mut block) == 0 {
+            if stopping {
+                break;
+            }
+            thread::sleep(POLL);
+            continue;
+        }
+        resampler.process(&block, &mut pcm);
+        while pcm.len() >= PACKET_FRAMES * channels {
+            let frame: Vec<f32> = pcm.drain(..PACKET_FRAMES * channels).collect();
+            let len = match encoder.encode_float(&frame, &mut packet) {
+                Ok(len) => len,
+                Err(e) => {
+                    eprintln!("Failed to encode the stream: {}", e);
+                    continue;
+                }
+            };
+            // Packets encoded while disconnected are dropped, listeners only want it live
+            if sink.is_none() && Instant::now() >= retry_at {
+                match Sink::open(&settings, pre_skip) {
+                    Ok(opened) => {
+                        println!("Streaming to {}:{}", settings.host.trim(), settings.port);
+                        connected.store(true, Ordering::Relaxed);
+                        sink = Some(opened);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to connect the stream: {}", e);
+                        retry_at = Instant::now() + RECONNECT;
+                    }
+                }
+            }
+            let Some(open) = &mut sink else {
+                continue;
+            };
+            match open.send(&packet[..len]) {
+                Ok(()) => {
+                    bytes_sent.fetch_add(len as u64, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    eprintln!("Stream dropped, reconnecting: {}", e);
+                    sink = None;
+                    connected.store(false, Ordering::Relaxed);
+                    retry_at = Instant::now() + RECONNECT;
+                }
+            }
+        }
+    }
+    if let Some(open) = sink {
+        open.finish();
+    }
+    connected.store(false, Ordering::Relaxed);
+}
+
+// Never reached, `Broadcaster::start` refuses to run without the feature
+#[cfg(not(feature = "broadcast"))]
+fn stream_blocks(
+    _settings: BroadcastSettings,
+    _tap: &InputTap,
+    _stop: &AtomicBool,
+    _connected: &AtomicBool,
+    _bytes_sent: &AtomicU64,
+) {
+}
+
+// Linear interpolation to 48 kHz, keeping the first one or two channels
+#[cfg(feature = "broadcast")]
+struct Resampler {
+    input_channels: usize,
+    channels: usize,
+    step: f64,         // Input frames per output frame
+    pending: Vec<f32>, // Input frames not yet passed, the first one already started on
+    position: f64,     // Of the next output frame, in frames from the start of `pending`
+}
+
+#[cfg(feature = "broadcast")]
+impl Resampler {
+    fn new(input_channels: usize, channels: usize, sample_rate: u32) -> Self {
+        Resampler {
+            input_channels: input_channels.max(1),
+            channels,
+            step: sample_rate as f64 / OPUS_RATE as f64,
+            pending: Vec::new(),
+            position: 0.0,
+        }
+    }
+
+    fn process(&mut self, block: &[f32], output: &mut Vec<f32>) {
+        for frame in block.chunks_exact(self.input_channels) {
+            self.pending.extend_from_slice(&frame[..self.channels]);
+        }
+        let frames = self.pending.len() / self.channels;
+        while self.position + 1.0 < frames as f64 {
+            let index = self.position as usize;
+            let fraction = (self.position - index as f64) as f32;
+            for channel in 0..self.channels {
+                let a = self.pending[index * self.channels + channel];
+                let b = self.pending[(index + 1) * self.channels + channel];
+                output.push(a + (b - a) * fraction);
+            }
+            self.position += self.step;
+        }
+        let passed = (self.position as usize).min(frames);
+        self.pending.drain(..passed * self.channels);
+        self.position -= passed as f64;
+    }
+}
+
+#[cfg(feature = "broadcast")]
+enum Sink {
+    Icecast {
+        writer: PacketWriter<TcpStream>,
+        serial: u32,
+        granule: u64, // Samples at 48 kHz up to the end of the last packet
+        packets: u64,
+    },
+    Rtp {
+        socket: UdpSocket,
+        sequence: u16,
+        timestamp: u32,
+        ssrc: u32,
+        first: bool,
+    },
+}
+
+#[cfg(feature = "broadcast")]
+impl Sink {
+    fn open(settings: &BroadcastSettings, pre_skip: u16) -> std::io::Result<Sink> {
+        let address = (settings.host.trim(), settings.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "host not found"))?;
+        // Different each time, so listeners can tell a new stream from the old one
+        let random = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.subsec_nanos() ^ since.as_secs() as u32)
+            .unwrap_or(1);
+        match settings.target {
+            BroadcastTarget::Icecast => {
+                let stream = connect_icecast(settings, address)?;
+                let mut writer = PacketWriter::new(stream);
+                writer.write_packet(
+                    opus_head(settings, pre_skip).into_boxed_slice(),
+                    random,
+                    PacketWriteEndInfo::EndPage,
+                    0,
+                )?;
+                writer.write_packet(
+                    opus_tags().into_boxed_slice(),
+                    random,
+                    PacketWriteEndInfo::EndPage,
+                    0,
+                )?;
+                Ok(Sink::Icecast {
+                    writer,
+                    serial: random,
+                    granule: 0,
+                    packets: 0,
+                })
+            }
+            BroadcastTarget::Rtp => {
+                let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+                socket.connect(address)?;
+                Ok(Sink::Rtp {
+                    socket,
+                    sequence: random as u16,
+                    timestamp: random.rotate_left(16),
+                    ssrc: random,
+                    first: true,
+                })
+            }
+        }
+    }
+
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        match self {
+            Sink::Icecast {
+                writer,
+                serial,
+                granule,
+                packets,
+            } => {
+                *granule += PACKET_FRAMES as u64;
+                *packets += 1;
+                let end = if *packets % PACKETS_PER_PAGE == 0 {
+                    PacketWriteEndInfo::EndPage
+                } else {
+                    PacketWriteEndInfo::NormalPacket
+                };
+                writer.write_packet(packet.into(), *serial, end, *granule)
+            }
+            Sink::Rtp {
+                socket,
+                sequence,
+                timestamp,
+                ssrc,
+                first,
+            } => {
+                // RFC 3550 header, with the marker bit on the first packet of the stream
+                let mut datagram = Vec::with_capacity(12 + packet.len());
+                datagram.push(0x80);
+                datagram.push(RTP_PAYLOAD_TYPE | if *first { 0x80 } else { 0 });
+                datagram.extend_from_slice(&sequence.to_be_bytes());
+                datagram.extend_from_slice(&timestamp.to_be_bytes());
+                datagram.extend_from_slice(&ssrc.to_be_bytes());
+                datagram.extend_from_slice(packet);
+                *first = false;
+                *sequence = sequence.wrapping_add(1);
+                *timestamp = timestamp.wrapping_add(PACKET_FRAMES as u32);
+                match socket.send(&datagram) {
+                    // Nobody listening yet isn't a reason to stop
+                    Err(e) if e.kind() == ErrorKind::ConnectionRefused => Ok(()),
+                    result => result.map(|_| ()),
+                }
+            }
+        }
+    }
+
+    // Ends the Ogg stream so Icecast and its listeners know it's over rather than cut off
+    fn finish(self) {
+        if let Sink::Icecast {
+            mut writer,
+            serial,
+            granule,
+            ..
+        } = self
+        {
+            let _ =
+                writer.write_packet(Box::new([]), serial, PacketWriteEndInfo::EndStream, granule);
+        }
+    }
+}
+
+// Icecast 2.4 takes a source as an HTTP PUT whose body never ends
+#[cfg(feature = "broadcast")]
+fn connect_icecast(
+    settings: &BroadcastSettings,
+    address: std::net::SocketAddr,
+) -> std::io::Result<TcpStream> {
+    let mut stream = TcpStream::connect_timeout(&address, RECONNECT)?;
+    stream.set_read_timeout(Some(RECONNECT))?;
+    stream.set_write_timeout(Some(RECONNECT))?;
+    let mount = settings.mount.trim().trim_start_matches('/');
+    write!(
+        stream,
+        "PUT /{} HTTP/1.1\r\nHost: {}:{}\r\nAuthorization: Basic {}\r\n\
+         User-Agent: rolling-sampler\r\nContent-Type: audio/ogg\r\n\
+         Ice-Name: Rolling Sampler\r\nIce-Public: 0\r\nExpect: 100-continue\r\n\r\n",
+        mount,
+        settings.host.trim(),
+        settings.port,
+        base64(format!("source:{}", settings.password).as_bytes())
+    )?;
+
+    // Just the status line matters, the rest of the headers are skipped
+    let mut response = Vec::new();
+    let mut byte = [0u8];
+    while !response.ends_with(b"\r\n\r\n") && response.len() < 4096 {
+        if stream.read(&mut byte)? == 0 {
+            break;
+        }
+        response.push(byte[0]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("100") | Some("200") => Ok(stream),
+        Some("401") => Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            "Icecast refused the source password",
+        )),
+        _ => Err(std::io::Error::other(format!(
+            "Icecast answered '{}'",
+            status
+        ))),
+    }
+}
+
+// RFC 7845 identification header
+#[cfg(feature = "broadcast")]
+fn opus_head(settings: &BroadcastSettings, pre_skip: u16) -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1); // Version
+    head.push(if settings.stereo() { 2 } else { 1 });
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&settings.sample_rate.to_le_bytes()); // The input's, for information
+    head.extend_from_slice(&0i16.to_le_bytes()); // Output gain
+    head.push(0); // Mono or stereo, no channel mapping table
+    head
+}
+
+#[cfg(feature = "broadcast")]
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"rolling-sampler";
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // No comments
+    tags
+}
+
+#[cfg(feature = "broadcast")]
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,90 @@
+use crate::config::Config;
+use clap::Parser;
+use rolling_sampler_core::backend::{AudioBackend, CpalBackend};
+use std::path::{Path, PathBuf};
+
+// Startup options, so a launcher script or an autostart entry can set the app up without
+// touching the settings window. They are applied to the loaded settings before the recorder
+// starts, and are kept like any change made in the app.
+#[derive(Debug, Parser)]
+#[command(
+    name = "rolling-sampler",
+    version,
+    about = "Keeps the last few seconds of audio, ready to grab"
+)]
+pub struct Args {
+    #[arg(long, help = "Input device, by its full name or any part of it")]
+    pub device: Option<String>,
+    #[arg(long, value_parser = parse_buffer_seconds, help = "Seconds of audio kept, 1 to 60")]
+    pub buffer_seconds: Option<f32>,
+    #[arg(long, help = "Folder grabs are saved to, for the active session")]
+    pub save_dir: Option<PathBuf>,
+    #[arg(long, help = "Monitor the input through the output device")]
+    pub monitor: bool,
+    #[arg(long, help = "Open the window minimized")]
+    pub start_minimized: bool,
+    #[arg(long, help = "Run without a window, taking commands on stdin")]
+    pub headless: bool,
+    #[arg(long, help = "Toggle a grab in the running instance, if there is one")]
+    pub grab: bool,
+    #[arg(long, help = "Listen for OSC commands on this UDP port (headless)")]
+    pub osc_port: Option<u16>,
+}
+
+// Same range as the buffer size slider
+fn parse_buffer_seconds(value: &str) -> Result<f32, String> {
+    let seconds: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' isn't a number", value))?;
+    if (1.0..=60.0).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err("the buffer has to be between 1 and 60 seconds".to_owned())
+    }
+}
+
+// A leading ~ isn't expanded when there is no shell in between, as in autostart entries
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+// The input whose name is `wanted`, or failing that the first one containing it, ignoring case
+fn find_input_device(wanted: &str) -> Option<String> {
+    let names = CpalBackend::new().input_devices().ok()?;
+    let wanted_lower = wanted.to_lowercase();
+    names
+        .iter()
+        .find(|name| name.as_str() == wanted)
+        .or_else(|| {
+            names
+                .iter()
+                .find(|name| name.to_lowercase().contains(&wanted_lower))
+        })
+        .cloned()
+}
+
+impl Args {
+    pub fn apply(&self, settings: &mut Config) {
+        if let Some(wanted) = &self.device {
+            match find_input_device(wanted) {
+                Some(name) => {
+                    println!("Using input device {}", name);
+                    settings.input_device = Some(name);
+                }
+                None => eprintln!("No input device matches '{}', using the last one", wanted),
+            }
+        }
+        if let Some(seconds) = self.buffer_seconds {
+            settings.buffer_seconds = seconds;
+        }
+        if let Some(dir) = &self.save_dir {
+            settings.save_path = Some(expand_home(dir).display().to_string());
+        }
+        if self.monitor {
+            settings.monitoring = true;
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use crate::i18n::Language;
+use crate::meter::Ballistics;
+use crate::preset::Preset;
+use crate::session::Session;
+use crate::theme::Theme;
+use dirs::config_dir;
+use rolling_sampler_core::dsp::{EqSettings, GateSettings};
+use rolling_sampler_core::metronome::MetronomeSettings;
+use rolling_sampler_core::monitor::MonitorResampler;
+use rolling_sampler_core::routing::MonitorRouting;
+use rolling_sampler_core::schedule::Schedule;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// Bumped whenever a setting is renamed or changes meaning, with a step in `migrate` to bring
+// older files forward. Files from before versioning count as 0.
+const CONFIG_VERSION: u32 = 1;
+
+// What to do when a grab is stopped before the buffer holds a useful amount of audio
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ShortGrabPolicy {
+    Ask,
+    WaitAndExtend,
+    SaveAnyway,
+}
+
+impl ShortGrabPolicy {
+    #[cfg(feature = "gui")]
+    pub const ALL: [ShortGrabPolicy; 3] = [
+        ShortGrabPolicy::Ask,
+        ShortGrabPolicy::WaitAndExtend,
+        ShortGrabPolicy::SaveAnyway,
+    ];
+
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortGrabPolicy::Ask => "Ask",
+            ShortGrabPolicy::WaitAndExtend => "Wait and extend",
+            ShortGrabPolicy::SaveAnyway => "Save anyway",
+        }
+    }
+}
+
+// Where takes get their timecode from, if anywhere
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TimecodeSource {
+    Off,
+    Mtc, // MIDI Timecode from any MIDI input
+    Ltc, // LTC audio on one of the input's channels
+}
+
+impl TimecodeSource {
+    #[cfg(feature = "gui")]
+    pub const ALL: [TimecodeSource; 3] = [
+        TimecodeSource::Off,
+        TimecodeSource::Mtc,
+        TimecodeSource::Ltc,
+    ];
+
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimecodeSource::Off => "Off",
+            TimecodeSource::Mtc => "MIDI Timecode",
+            TimecodeSource::Ltc => "LTC on an input channel",
+        }
+    }
+}
+
+// Where the live input is streamed to
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BroadcastTarget {
+    Icecast, // An Icecast server's mountpoint, for listeners anywhere
+    Rtp,     // RTP packets straight to one listener or a multicast group
+}
+
+impl BroadcastTarget {
+    #[cfg(feature = "gui")]
+    pub const ALL: [BroadcastTarget; 2] = [BroadcastTarget::Icecast, BroadcastTarget::Rtp];
+
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            BroadcastTarget::Icecast => "Icecast",
+            BroadcastTarget::Rtp => "RTP",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(default)] // Missing means a file from before versioning, not the current version
+    pub version: u32,
+    pub input_device: Option<String>, // By name, like the output device
+    pub buffer_seconds: f32,
+    pub save_path: Option<String>, // The Desktop when not chosen yet
+    pub active_preset: usize,
+    pub active_session: usize,
+    pub arm_threshold_db: f32,
+    pub min_grab_seconds: f32,
+    pub short_grab_policy: ShortGrabPolicy,
+    pub gamepad_grab_button: Option<String>, // Gamepad/footswitch button that toggles a grab
+    pub review_before_save: bool,            // Hold grabs for playback, trimming and accept/discard
+    pub mono_check: bool,                    // Warn about phase cancellation before saving stereo
+    pub name_prompt: bool,                   // Ask for a name before each grab is saved
+    pub remote_enabled: bool,                // Serve the web remote on the LAN
+    pub remote_port: u16,
+    pub link_enabled: bool, // Follow an Ableton Link session for bar-aligned grabs
+    pub link_beats_per_bar: f32, // Link shares beats, not bars, so the bar length is ours
+    pub link_grab_bars: u32, // Bars kept by "Grab Last Bars"
+    pub timecode_source: TimecodeSource,
+    pub ltc_channel: usize,          // Input channel carrying LTC, from 0
+    pub timecode_in_name: bool,      // Add each take's start timecode to its file name
+    pub control_socket: bool,        // Accept text commands on a local unix socket
+    pub script_path: Option<String>, // Rhai script with hooks, see script.rs
+    pub script_silence_db: f32,      // Input below this counts as silence for on_silence
+    pub script_silence_seconds: f32,
+    pub countdown_seconds: u32,    // Lead-in before a delayed grab's cue
+    pub event_sensitivity_db: f32, // How far above the noise floor a sound counts as an event
+    pub vox_threshold_db: f32,
+    pub vox_hang_seconds: f32, // How long the signal has to stay quiet before a VOX take ends
+    pub vox_pre_roll_seconds: f32,
+    pub hit_sensitivity_db: f32, // How sudden a jump in level counts as a hit for auto capture
+    pub hit_floor_db: f32,
+    pub hit_pre_roll_ms: f32,
+    pub hit_length_ms: f32, // Length of each auto-captured one-shot after the hit
+    pub marker_export_seconds: f32, // Length of an export centred on a marker
+    pub hot_folder: Option<String>, // Folder a DAW watches, every grab is copied in
+    pub hot_folder_take_names: bool,
+    pub hot_folder_command: String, // Run after each file is dropped into the hot folder
+    pub post_save_command: String,  // Run on every saved file, with its path for {file}
+    pub channel_names: Vec<String>, // Per input channel, stored in multi-track takes
+    pub disarmed_channels: Vec<usize>, // Input channels left out of takes
+    pub save_stems: bool,           // A mono file per armed channel next to multi-track takes
+    pub mini_mode: bool,            // Just the meters and the grab button
+    pub always_on_top: bool,
+    pub window_size: Option<[f32; 2]>, // Full layout size and position, saved on exit
+    pub window_position: Option<[f32; 2]>,
+    pub theme: Theme,
+    pub language: Language,
+    pub accent_color: Option<[u8; 3]>, // Custom colors on top of the theme, as RGB
+    pub waveform_color: Option<[u8; 3]>,
+    pub background_color: Option<[u8; 3]>,
+    pub waveform_db_scale: bool, // Waveform amplitude in dB instead of linear
+    pub waveform_overlay: bool,  // Channels drawn on top of each other instead of stacked
+    pub hidden_channels: Vec<usize>, // Channels left out of the waveform, counting from 0
+    pub show_spectrogram: bool,
+    pub show_spectrum: bool,
+    pub spectrum_averaging: f32, // Share of the previous spectrum kept each frame
+    pub spectrum_peak_hold: bool,
+    pub monitor_routing: MonitorRouting,
+    pub monitor_custom_routes: Vec<usize>, // Input for each output counting from 1, 0 for none
+    pub monitor_latency_ms: f32,           // Audio held between the input and the monitoring output
+    pub monitor_auto_latency: bool,        // Lower the held audio until just before dropouts
+    pub round_trip_latency_ms: Option<f32>, // Output back to input, as last measured
+    pub monitor_resampler: MonitorResampler,
+    pub monitor_limiter: bool, // Peak limiter at the end of the monitoring path
+    pub monitor_limiter_ceiling_db: f32,
+    pub monitor_eq: bool, // EQ on the monitoring output only, never on what's captured
+    pub monitoring: bool, // Whether monitoring was on when the app last ran
+    pub output_device: Option<String>, // By name, since indexes change as devices come and go
+    pub monitor_gain_db: f32,
+    pub capture_high_pass_hz: u32, // High-pass on the input before it's buffered, 0 for off
+    pub dc_block: bool,            // Remove DC offset from the input before it's buffered
+    pub capture_gate: bool,        // Noise gate the input before it's buffered
+    pub capture_limiter: bool,     // Peak limit the input before it's buffered
+    pub capture_limiter_threshold_db: f32,
+    pub capture_limiter_release_ms: f32,
+    pub tag_pitch: bool,     // Detect each take's note and add it to the metadata
+    pub pitch_in_name: bool, // and to the file name, e.g. `_A#2`
+    pub detect_tempo: bool,  // Detect each grab's BPM and add it to the metadata
+    pub tempo_in_name: bool, // and to the file name, e.g. `_120bpm`
+    pub round_to_bars: bool, // Trim grabs to a whole number of bars at the detected tempo
+    pub detect_key: bool,    // Estimate each grab's key and add it to the metadata
+    pub loop_snap_zero_crossings: bool, // Move loop edges in review to the nearest zero crossing
+    pub loop_crossfade_ms: f32, // Crossfade at the seam of a loop, 0 for none
+    pub punch_pre_roll_seconds: f32, // Played before the punch in, to play along to
+    pub meter_ballistics: Ballistics,
+    pub meter_reference_db: f32, // dBFS marked on the meters as the console's 0
+    pub meter_hold_seconds: f32, // 0 for no peak hold line
+    pub show_tuner: bool,
+    pub tuner_reference_hz: f32,      // Concert pitch for A4
+    pub session_report_on_exit: bool, // Write an HTML report of the session's grabs on quit
+    pub safety_recording: bool,       // Record everything to disk in segments alongside the buffer
+    pub safety_segment_minutes: f32,
+    pub safety_gate: bool, // Only run the safety recording while there is signal
+    pub safety_gate_threshold_db: f32,
+    pub safety_gate_silence_seconds: f32, // Silence before the safety recording pauses
+    pub broadcast_enabled: bool,          // Stream the input live while it's buffered
+    pub broadcast_target: BroadcastTarget,
+    pub broadcast_host: String,
+    pub broadcast_port: u16,
+    pub broadcast_mount: String, // Icecast mountpoint, e.g. /sampler.opus
+    pub broadcast_password: String, // Icecast source password
+    pub broadcast_bitrate_kbps: u32,
+    pub autosample_port: Option<String>, // MIDI output the auto-sampler plays the synth through
+    pub autosample_channel: u8,
+    pub autosample_low_note: u8,
+    pub autosample_high_note: u8,
+    pub autosample_step: u8,            // Semitones between sampled notes
+    pub autosample_velocities: Vec<u8>, // One layer per velocity
+    pub autosample_hold_seconds: f32,
+    pub autosample_release_seconds: f32,
+    pub autosample_floor_db: f32, // Quieter than this is trimmed from each note's ends
+    pub autosample_name: String,  // Of the instrument, for its folder and file names
+    pub write_sfz: bool,          // Patches for auto-sampled notes and sliced hits
+    pub write_dspreset: bool,
+    pub sessions: Vec<Session>, // Tables have to come after plain values in TOML
+    pub presets: Vec<Preset>,
+    pub schedules: Vec<Schedule>,
+    pub capture_gate_settings: GateSettings,
+    pub monitor_eq_settings: EqSettings,
+    pub metronome: MetronomeSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            input_device: None,
+            buffer_seconds: 5.0,
+            save_path: None,
+            active_preset: 0,
+            active_session: 0,
+            arm_threshold_db: -30.0,
+            min_grab_seconds: 0.5,
+            short_grab_policy: ShortGrabPolicy::Ask,
+            gamepad_grab_button: None,
+            review_before_save: false,
+            mono_check: false,
+            name_prompt: false,
+            remote_enabled: false,
+            remote_port: 8765,
+            link_enabled: false,
+            link_beats_per_bar: 4.0,
+            link_grab_bars: 8,
+            timecode_source: TimecodeSource::Off,
+            ltc_channel: 0,
+            timecode_in_name: false,
+            control_socket: false,
+            script_path: None,
+            script_silence_db: -50.0,
+            script_silence_seconds: 30.0,
+            countdown_seconds: 5,
+            event_sensitivity_db: 12.0,
+            vox_threshold_db: -35.0,
+            vox_hang_seconds: 1.5,
+            vox_pre_roll_seconds: 0.3,
+            hit_sensitivity_db: 15.0,
+            hit_floor_db: -40.0,
+            hit_pre_roll_ms: 10.0,
+            hit_length_ms: 1000.0,
+            marker_export_seconds: 30.0,
+            hot_folder: None,
+            hot_folder_take_names: true,
+            hot_folder_command: String::new(),
+            post_save_command: String::new(),
+            channel_names: Vec::new(),
+            disarmed_channels: Vec::new(),
+            save_stems: false,
+            mini_mode: false,
+            always_on_top: false,
+            window_size: None,
+            window_position: None,
+            theme: Theme::Dark,
+            language: Language::from_env(),
+            accent_color: None,
+            waveform_color: None,
+            background_color: None,
+            waveform_db_scale: false,
+            waveform_overlay: false,
+            hidden_channels: Vec::new(),
+            show_spectrogram: false,
+            show_spectrum: false,
+            spectrum_averaging: 0.8,
+            spectrum_peak_hold: true,
+            monitor_routing: MonitorRouting::Direct,
+            monitor_custom_routes: Vec::new(),
+            monitor_latency_ms: 20.0,
+            monitor_auto_latency: false,
+            round_trip_latency_ms: None,
+            monitor_resampler: MonitorResampler::Linear,
+            monitor_limiter: true,
+            monitor_limiter_ceiling_db: -1.0,
+            monitor_eq: false,
+            monitoring: false,
+            output_device: None,
+            monitor_gain_db: 0.0,
+            capture_high_pass_hz: 0,
+            dc_block: false,
+            capture_gate: false,
+            capture_limiter: false,
+            capture_limiter_threshold_db: -3.0,
+            capture_limiter_release_ms: 100.0,
+            tag_pitch: true,
+            pitch_in_name: false,
+            detect_tempo: false,
+            tempo_in_name: true,
+            round_to_bars: false,
+            detect_key: false,
+            loop_snap_zero_crossings: true,
+            loop_crossfade_ms: 10.0,
+            punch_pre_roll_seconds: 2.0,
+            meter_ballistics: Ballistics::Rms,
+            meter_reference_db: -18.0,
+            meter_hold_seconds: 1.5,
+            show_tuner: false,
+            tuner_reference_hz: 440.0,
+            session_report_on_exit: true,
+            safety_recording: false,
+            safety_segment_minutes: 10.0,
+            safety_gate: false,
+            safety_gate_threshold_db: -45.0,
+            safety_gate_silence_seconds: 60.0,
+            broadcast_enabled: false,
+            broadcast_target: BroadcastTarget::Icecast,
+            broadcast_host: "localhost".to_owned(),
+            broadcast_port: 8000,
+            broadcast_mount: "/sampler.opus".to_owned(),
+            broadcast_password: String::new(),
+            broadcast_bitrate_kbps: 96,
+            autosample_port: None,
+            autosample_channel: 1,
+            autosample_low_note: 36,
+            autosample_high_note: 96,
+            autosample_step: 3,
+            autosample_velocities: vec![127],
+            autosample_hold_seconds: 2.0,
+            autosample_release_seconds: 1.5,
+            autosample_floor_db: -60.0,
+            autosample_name: "Synth".to_owned(),
+            write_sfz: true,
+            write_dspreset: false,
+            sessions: Vec::new(), // Filled in from the settings on load
+            presets: vec![Preset::default()],
+            schedules: Vec::new(),
+            capture_gate_settings: GateSettings::default(),
+            monitor_eq_settings: EqSettings::default(),
+            metronome: MetronomeSettings::default(),
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|mut path| {
+        path.push("rolling-sampler");
+        path.push("config.toml");
+        path
+    })
+}
+
+// Bring a config written by an older version up to date, one version at a time, before it is
+// parsed into `Config`
+fn migrate(table: &mut toml::Table) {
+    let mut version = table
+        .get("version")
+        .and_then(|version| version.as_integer())
+        .unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        eprintln!(
+            "Config file is from a newer version ({}), settings it added will be dropped on save",
+            version
+        );
+        return;
+    }
+    while version < CONFIG_VERSION {
+        match version {
+            // Same layout as version 1, whose new settings come from the defaults
+            0 => {}
+            _ => unreachable!("no migration from config version {}", version),
+        }
+        version += 1;
+        println!("Migrated config file to version {}", version);
+    }
+    table.insert("version".to_owned(), toml::Value::Integer(version as i64));
+}
+
+fn parse(contents: &str) -> Result<Config, toml::de::Error> {
+    let mut table: toml::Table = toml::from_str(contents)?;
+    migrate(&mut table);
+    toml::Value::Table(table).try_into()
+}
+
+impl Config {
+    // Fall back to the defaults if the file is missing or can't be parsed. A file that can't be
+    // parsed is kept next to it, so the defaults don't silently replace it on the next save.
+    pub fn load() -> Self {
+        let path = config_path();
+        let mut config: Config = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| match parse(&contents) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    eprintln!("Failed to parse config file: {}", e);
+                    if let Some(path) = &path {
+                        let backup = path.with_extension("toml.bak");
+                        match fs::copy(path, &backup) {
+                            Ok(_) => eprintln!("Kept the old config as {}", backup.display()),
+                            Err(e) => eprintln!("Failed to back up the config file: {}", e),
+                        }
+                    }
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        // There must always be at least one preset to select
+        if config.presets.is_empty() {
+            config.presets.push(Preset::default());
+        }
+        if config.active_preset >= config.presets.len() {
+            config.active_preset = 0;
+        }
+        config.buffer_seconds = config.buffer_seconds.clamp(1.0, 60.0);
+        // Configs from before sessions become the first one, as they are
+        if config.sessions.is_empty() {
+            config.sessions.push(Session::default());
+            config.active_session = 0;
+            config.store_session();
+        }
+        if config.active_session >= config.sessions.len() {
+            config.active_session = 0;
+        }
+        config.version = CONFIG_VERSION; // What it's written back as
+        config
+    }
+
+    pub fn save(&mut self) {
+        self.store_session();
+        let Some(path) = config_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                eprintln!("Failed to create config directory: {}", e);
+                return;
+            }
+        }
+        match toml::to_string_pretty(self) {
+            Ok(contents) => {
+                if let Err(e) = fs::write(&path, contents) {
+                    eprintln!("Failed to write config file: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialise config: {}", e),
+        }
+    }
+
+    pub fn active_preset(&self) -> &Preset {
+        &self.presets[self.active_preset]
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.sessions[self.active_session]
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.sessions[self.active_session]
+    }
+
+    // Copy the live values into the active session's entry
+    pub fn store_session(&mut self) {
+        let (save_path, active_preset) = (self.save_path.clone(), self.active_preset);
+        let (buffer_seconds, hot_folder) = (self.buffer_seconds, self.hot_folder.clone());
+        let session = self.session_mut();
+        session.save_path = save_path;
+        session.active_preset = active_preset;
+        session.buffer_seconds = buffer_seconds;
+        session.hot_folder = hot_folder;
+    }
+
+    // Make another session the active one, keeping the current one's values in its entry
+    pub fn load_session(&mut self, index: usize) {
+        self.store_session();
+        self.active_session = index;
+        let session = self.session().clone();
+        self.save_path = session.save_path;
+        self.active_preset = session.active_preset.min(self.presets.len() - 1);
+        self.buffer_seconds = session.buffer_seconds.clamp(1.0, 60.0);
+        self.hot_folder = session.hot_folder;
+    }
+}
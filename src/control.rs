@@ -0,0 +1,508 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::BufRead;
+use std::net::UdpSocket;
+#[cfg(feature = "gui")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Plain text commands accepted on stdin and the control socket, one per line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    ToggleGrab,
+    GrabBars(u32), // The last whole bars of the Ableton Link session
+    Discard,
+    SwitchAb,
+    Marker,
+    SetBufferSeconds(f32),
+    SetTempo(f32), // Of the metronome
+    // On, off, or None to toggle
+    Monitor(Option<bool>),
+    Arm(Option<bool>),
+    Vox(Option<bool>),
+    Stream(Option<bool>),
+    AutoSample(Option<bool>),
+    Metronome(Option<bool>),
+    Overdub(OverdubAction),
+    MeasureLatency,  // Round trip from the output back to the input
+    Session(String), // Switch to the session of this name, in any case
+    Show,            // Bring the window to the front, e.g. when the app is launched again
+    // An OSC message, looked up in the active preset's bindings before falling back to
+    // `command` (the message read as a text command)
+    Osc { address: String, command: String },
+}
+
+// `overdub` alone plays the bed for another layer, stacking the last one into it first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverdubAction {
+    Pass,
+    SaveLayer,
+    SaveBounce,
+    Stop,
+}
+
+pub const HELP: &str =
+    "grab | grab bars <n> | discard | ab | marker | set buffer <seconds> | set tempo <bpm> | monitor [on|off] | arm [on|off] | vox [on|off] | stream [on|off] | autosample [on|off] | metronome [on|off] | overdub [save|bounce|off] | latency | session <name> | show";
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<String> = line
+            .split_whitespace()
+            .map(|word| word.to_ascii_lowercase())
+            .collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        match words.as_slice() {
+            ["grab"] => Ok(Command::ToggleGrab),
+            ["grab", "bars", bars] => bars
+                .parse()
+                .ok()
+                .filter(|&bars| bars > 0)
+                .map(Command::GrabBars)
+                .ok_or_else(|| format!("invalid number of bars '{}'", bars)),
+            ["discard"] => Ok(Command::Discard),
+            ["ab"] => Ok(Command::SwitchAb),
+            ["marker"] => Ok(Command::Marker),
+            ["show"] => Ok(Command::Show),
+            ["latency"] => Ok(Command::MeasureLatency),
+            ["session", name @ ..] if !name.is_empty() => Ok(Command::Session(name.join(" "))),
+            ["set", "buffer", seconds] => seconds
+                .parse()
+                .map(Command::SetBufferSeconds)
+                .map_err(|_| format!("invalid buffer length '{}'", seconds)),
+            ["set", "tempo", bpm] => bpm
+                .parse()
+                .ok()
+                .filter(|bpm: &f32| (20.0..=300.0).contains(bpm))
+                .map(Command::SetTempo)
+                .ok_or_else(|| format!("invalid tempo '{}', expected 20 to 300 BPM", bpm)),
+            // Without on or off these toggle, which suits a single key or button
+            ["monitor"] => Ok(Command::Monitor(None)),
+            ["arm"] => Ok(Command::Arm(None)),
+            ["vox"] => Ok(Command::Vox(None)),
+            ["stream"] => Ok(Command::Stream(None)),
+            ["autosample"] => Ok(Command::AutoSample(None)),
+            ["metronome"] => Ok(Command::Metronome(None)),
+            ["overdub"] => Ok(Command::Overdub(OverdubAction::Pass)),
+            ["overdub", "save"] => Ok(Command::Overdub(OverdubAction::SaveLayer)),
+            ["overdub", "bounce"] => Ok(Command::Overdub(OverdubAction::SaveBounce)),
+            ["overdub", "off"] => Ok(Command::Overdub(OverdubAction::Stop)),
+            ["monitor", state] => parse_switch(state).map(|on| Command::Monitor(Some(on))),
+            ["arm", state] => parse_switch(state).map(|on| Command::Arm(Some(on))),
+            ["vox", state] => parse_switch(state).map(|on| Command::Vox(Some(on))),
+            ["stream", state] => parse_switch(state).map(|on| Command::Stream(Some(on))),
+            ["autosample", state] => parse_switch(state).map(|on| Command::AutoSample(Some(on))),
+            ["metronome", state] => parse_switch(state).map(|on| Command::Metronome(Some(on))),
+            _ => Err(format!("unknown command '{}', try: {}", line.trim(), HELP)),
+        }
+    }
+}
+
+fn parse_switch(state: &str) -> Result<bool, String> {
+    match state {
+        "on" | "1" | "true" => Ok(true),
+        "off" | "0" | "false" => Ok(false),
+        _ => Err(format!("expected on or off, got '{}'", state)),
+    }
+}
+
+type Waker = Box<dyn Fn() + Send>;
+
+// Reads commands from stdin and, on unix, a local socket, so Stream Deck buttons and shell
+// scripts can drive the app without the network remote, e.g.
+// `echo grab | nc -U /tmp/rolling-sampler.sock`
+pub struct CommandListener {
+    sender: Sender<Command>,
+    commands: Receiver<Command>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    socket: Option<socket::ControlSocket>,
+    instance: Option<socket::ControlSocket>, // Where later launches hand over to this one
+    instance_lock: Option<InstanceLock>,     // Let go of after the socket above is removed
+    osc: Option<OscListener>,
+}
+
+// Held for as long as the app runs, so only one copy opens the devices. It's taken before any of
+// them are, and the instance socket is only removed and bound again while holding it, so a launch
+// that starts before the socket is up can't take over from the first. The OS lets go of it when
+// the process ends, crashed or not: flock on unix, LockFileEx on Windows.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    // None while another instance holds it
+    pub fn acquire() -> std::io::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(instance_dir().join("rolling-sampler.lock"))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(InstanceLock { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+impl CommandListener {
+    pub fn spawn() -> Self {
+        let (sender, commands) = channel();
+        let waker: Arc<Mutex<Option<Waker>>> = Arc::new(Mutex::new(None));
+
+        let stdin_sender = sender.clone();
+        let stdin_waker = Arc::clone(&waker);
+        thread::spawn(move || {
+            // Ends by itself when stdin is closed (e.g. when launched from a desktop shortcut)
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match Command::parse(&line) {
+                    Ok(command) => {
+                        if !deliver(command, &stdin_sender, &stdin_waker) {
+                            return;
+                        }
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        });
+
+        CommandListener {
+            sender,
+            commands,
+            waker,
+            socket: None,
+            instance: None,
+            instance_lock: None,
+            osc: None,
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    pub fn set_socket_enabled(&mut self, enabled: bool) -> std::io::Result<()> {
+        self.socket = None; // Stops any running listener
+        if enabled {
+            self.socket = Some(socket::ControlSocket::start(
+                &default_socket_path(),
+                self.sender.clone(),
+                Arc::clone(&self.waker),
+            )?);
+        }
+        Ok(())
+    }
+
+    // Become the instance later launches send their command to, see `forward_to_running`
+    pub fn claim_instance(&mut self, lock: InstanceLock) -> std::io::Result<()> {
+        self.instance_lock = Some(lock);
+        self.instance = Some(socket::ControlSocket::start(
+            &instance_socket_path(),
+            self.sender.clone(),
+            Arc::clone(&self.waker),
+        )?);
+        Ok(())
+    }
+
+    // Listen for OSC messages on a UDP port, or stop with None
+    pub fn set_osc_port(&mut self, port: Option<u16>) -> std::io::Result<()> {
+        self.osc = None;
+        if let Some(port) = port {
+            self.osc = Some(OscListener::start(
+                port,
+                self.sender.clone(),
+                Arc::clone(&self.waker),
+            )?);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn socket_path(&self) -> Option<&Path> {
+        self.socket.as_ref().map(|socket| socket.path())
+    }
+
+    // Commands received since the last call
+    pub fn poll(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+}
+
+pub fn default_socket_path() -> PathBuf {
+    std::env::temp_dir().join("rolling-sampler.sock")
+}
+
+// Per user where the platform has a runtime folder
+fn instance_dir() -> PathBuf {
+    dirs::runtime_dir().unwrap_or_else(std::env::temp_dir)
+}
+
+// Always on, unlike the control socket
+fn instance_socket_path() -> PathBuf {
+    instance_dir().join("rolling-sampler-instance.sock")
+}
+
+// Hand a command to an instance that is already running. True if one took it, in which case
+// this launch shouldn't open the input a second time.
+pub fn forward_to_running(command: &str) -> bool {
+    socket::send(&instance_socket_path(), command).is_ok_and(|reply| reply == "ok")
+}
+
+// Returns false once the listener has been dropped
+fn deliver(command: Command, sender: &Sender<Command>, waker: &Mutex<Option<Waker>>) -> bool {
+    if sender.send(command).is_err() {
+        return false;
+    }
+    if let Some(wake) = waker.lock().unwrap().as_ref() {
+        wake();
+    }
+    true
+}
+
+// The address of an OSC message, and the address and arguments read as a text command, so
+// `/grab`, `/set/buffer 20.0` and `/monitor 1` work from TouchOSC, Max or a DAW. Bundles aren't
+// supported.
+pub fn parse_osc(packet: &[u8]) -> Option<(String, String)> {
+    let (address, rest) = osc_string(packet)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+    let mut words: Vec<String> = address[1..].split('/').map(str::to_owned).collect();
+
+    // Messages without a type tag string have no arguments
+    let Some((tags, mut data)) = osc_string(rest) else {
+        return Some((address.to_owned(), words.join(" ")));
+    };
+    for tag in tags.strip_prefix(',')?.chars() {
+        let word = match tag {
+            'i' => {
+                let (bytes, rest) = data.split_first_chunk::<4>()?;
+                data = rest;
+                i32::from_be_bytes(*bytes).to_string()
+            }
+            'f' => {
+                let (bytes, rest) = data.split_first_chunk::<4>()?;
+                data = rest;
+                f32::from_be_bytes(*bytes).to_string()
+            }
+            's' => {
+                let (text, rest) = osc_string(data)?;
+                data = rest;
+                text.to_owned()
+            }
+            'T' => "on".to_owned(),
+            'F' => "off".to_owned(),
+            _ => return None,
+        };
+        words.push(word);
+    }
+    Some((address.to_owned(), words.join(" ")))
+}
+
+// A null terminated string padded to a multiple of four bytes, and what follows it
+fn osc_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    let text = std::str::from_utf8(&data[..end]).ok()?;
+    let padded = (end + 4) & !3;
+    Some((text, data.get(padded..).unwrap_or(&[])))
+}
+
+struct OscListener {
+    running: Arc<AtomicBool>,
+}
+
+impl OscListener {
+    fn start(
+        port: u16,
+        sender: Sender<Command>,
+        waker: Arc<Mutex<Option<Waker>>>,
+    ) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        // Wake up now and then to notice when the listener is stopped
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        thread::spawn(move || {
+            let mut packet = [0u8; 1536];
+            while thread_running.load(Ordering::SeqCst) {
+                let Ok(len) = socket.recv(&mut packet) else {
+                    continue;
+                };
+                let Some((address, command)) = parse_osc(&packet[..len]) else {
+                    eprintln!("Ignoring malformed OSC packet");
+                    continue;
+                };
+                // Parsed on the UI side, after the bindings have had a look at it
+                if !deliver(Command::Osc { address, command }, &sender, &waker) {
+                    return;
+                }
+            }
+        });
+
+        println!("Listening for OSC on UDP port {}", port);
+        Ok(OscListener { running })
+    }
+}
+
+impl Drop for OscListener {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use super::{deliver, Command, Waker};
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    // Answer each line on a connection with "ok" or "error: ..."
+    fn handle_lines(
+        reader: impl BufRead,
+        mut writer: impl Write,
+        sender: &Sender<Command>,
+        waker: &Mutex<Option<Waker>>,
+    ) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Command::parse(&line) {
+                Ok(command) => {
+                    if !deliver(command, sender, waker) {
+                        return Ok(());
+                    }
+                    writeln!(writer, "ok")?;
+                }
+                Err(e) => writeln!(writer, "error: {}", e)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Send one command line and wait for the answer
+    pub fn send(path: &Path, command: &str) -> std::io::Result<String> {
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        writeln!(&stream, "{}", command)?;
+        let mut reply = String::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        Ok(reply.trim().to_owned())
+    }
+
+    pub struct ControlSocket {
+        path: PathBuf,
+        running: Arc<AtomicBool>,
+    }
+
+    impl ControlSocket {
+        pub fn start(
+            path: &Path,
+            sender: Sender<Command>,
+            waker: Arc<Mutex<Option<Waker>>>,
+        ) -> std::io::Result<Self> {
+            // Only reached while holding the instance lock, so a file already there was left
+            // behind by a crashed instance, and would make bind fail
+            let _ = std::fs::remove_file(path);
+            let listener = UnixListener::bind(path)?;
+            // Poll for connections so the thread notices when the socket is closed
+            listener.set_nonblocking(true)?;
+
+            let running = Arc::new(AtomicBool::new(true));
+            let thread_running = Arc::clone(&running);
+            thread::spawn(move || {
+                while thread_running.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let sender = sender.clone();
+                            let waker = Arc::clone(&waker);
+                            thread::spawn(move || {
+                                let result = stream.set_nonblocking(false).and_then(|()| {
+                                    let reader = BufReader::new(stream.try_clone()?);
+                                    handle_lines(reader, &stream, &sender, &waker)
+                                });
+                                if let Err(e) = result {
+                                    eprintln!("Control connection error: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(100));
+                        }
+                        Err(e) => eprintln!("Control socket accept failed: {}", e),
+                    }
+                }
+            });
+
+            println!("Listening for commands on {}", path.display());
+            Ok(ControlSocket {
+                path: path.to_owned(),
+                running,
+            })
+        }
+
+        #[cfg(feature = "gui")]
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Drop for ControlSocket {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::SeqCst);
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Windows named pipes would need a platform crate, so only stdin is available there for now.
+// The instance lock still keeps a second launch from starting, it just can't hand anything over.
+#[cfg(not(unix))]
+mod socket {
+    use super::{Command, Waker};
+    use std::path::Path;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+
+    pub struct ControlSocket;
+
+    pub fn send(_path: &Path, _command: &str) -> std::io::Result<String> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the control socket is only available on unix",
+        ))
+    }
+
+    impl ControlSocket {
+        pub fn start(
+            _path: &Path,
+            _sender: Sender<Command>,
+            _waker: Arc<Mutex<Option<Waker>>>,
+        ) -> std::io::Result<Self> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the control socket is only available on unix",
+            ))
+        }
+
+        #[cfg(feature = "gui")]
+        pub fn path(&self) -> &Path {
+            unreachable!()
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::Local;
+use cpal::StreamConfig;
+use rolling_sampler_core::backend::{AudioBackend, CpalBackend};
+use rolling_sampler_core::stats::StreamStats;
+use std::time::{Duration, Instant};
+
+// Share of real time the input callback is busy, averaged over half a second
+#[derive(Default)]
+pub struct CallbackLoad {
+    last: Option<(Instant, u64)>,
+    load: f32,
+}
+
+impl CallbackLoad {
+    pub fn update(&mut self, stats: &StreamStats) -> f32 {
+        let now = Instant::now();
+        let busy = stats.input_busy().as_nanos() as u64;
+        match self.last {
+            Some((then, busy_then)) if now - then >= Duration::from_millis(500) => {
+                let elapsed = (now - then).as_nanos() as f32;
+                self.load = busy.saturating_sub(busy_then) as f32 / elapsed;
+                self.last = Some((now, busy));
+            }
+            Some(_) => {}
+            None => self.last = Some((now, busy)),
+        }
+        self.load
+    }
+}
+
+// Everything about the running engine that goes into the report
+pub struct EngineState<'a> {
+    pub input_devices: &'a [String],
+    pub input_index: usize,
+    pub output_devices: &'a [String],
+    pub output_index: usize,
+    pub config: &'a StreamConfig,
+    pub settings: Vec<(&'static str, String)>,
+}
+
+// Plain text troubleshooting report for pasting into bug reports
+pub fn report(backend: &CpalBackend, state: &EngineState, stats: &StreamStats) -> String {
+    let available: Vec<&str> = cpal::available_hosts().iter().map(|id| id.name()).collect();
+
+    let mut lines = vec![
+        "Rolling Sampler diagnostics".to_owned(),
+        format!("Generated: {}", Local::now().to_rfc3339()),
+        format!(
+            "Version: {} ({} {})",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        ),
+        format!(
+            "Host: {} (available: {})",
+            backend.host_name(),
+            available.join(", ")
+        ),
+    ];
+
+    lines.push(String::new());
+    lines.push("== Input devices ==".to_owned());
+    for (index, device) in state.input_devices.iter().enumerate() {
+        let config = backend
+            .input_config(device)
+            .map(|c| format!("{:?}", c))
+            .unwrap_or_else(|e| e.to_string());
+        lines.push(device_line(device, index == state.input_index, config));
+    }
+
+    lines.push(String::new());
+    lines.push("== Output devices ==".to_owned());
+    for (index, device) in state.output_devices.iter().enumerate() {
+        let config = backend
+            .output_config(device, None)
+            .map(|c| format!("{:?}", c))
+            .unwrap_or_else(|e| e.to_string());
+        lines.push(device_line(device, index == state.output_index, config));
+    }
+
+    lines.push(String::new());
+    lines.push("== Engine ==".to_owned());
+    lines.push(format!(
+        "Input stream: {} Hz, {} channels, buffer {:?}",
+        state.config.sample_rate.0, state.config.channels, state.config.buffer_size
+    ));
+    for (key, value) in &state.settings {
+        lines.push(format!("{}: {}", key, value));
+    }
+
+    lines.push(String::new());
+    lines.push("== Stream health ==".to_owned());
+    lines.push(format!("Input callbacks: {}", stats.input_callbacks()));
+    lines.push(format!("Input xruns: {}", stats.input_xruns()));
+    lines.push(format!("Monitor underruns: {}", stats.monitor_underruns()));
+
+    lines.push(String::new());
+    lines.push("== Recent stream errors ==".to_owned());
+    let errors = stats.recent_errors();
+    if errors.is_empty() {
+        lines.push("(none)".to_owned());
+    }
+    lines.extend(errors);
+
+    lines.join("\n") + "\n"
+}
+
+// The selected device is marked with a star
+fn device_line(name: &str, selected: bool, config: String) -> String {
+    format!("{} {}: {}", if selected { "*" } else { " " }, name, config)
+}
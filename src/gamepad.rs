@@ -0,0 +1,77 @@
+#[cfg(feature = "gamepad")]
+use gilrs::{Button, EventType, Gilrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "gamepad")]
+use std::thread;
+#[cfg(feature = "gamepad")]
+use std::time::Duration;
+
+type Waker = Box<dyn Fn() + Send>;
+
+// Listens for gamepad / USB footswitch button presses on a background thread. Buttons are
+// reported by name (e.g. "South") or by raw code for buttons gilrs doesn't recognise, which is
+// what most footswitches show up as.
+pub struct GamepadListener {
+    presses: Receiver<String>,
+    #[cfg(feature = "gui")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl GamepadListener {
+    pub fn spawn() -> Self {
+        let (sender, presses) = channel();
+        let waker: Arc<Mutex<Option<Waker>>> = Arc::new(Mutex::new(None));
+        listen(sender, Arc::clone(&waker));
+        GamepadListener {
+            presses,
+            #[cfg(feature = "gui")]
+            waker,
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    // Buttons pressed since the last call
+    pub fn poll(&self) -> Vec<String> {
+        self.presses.try_iter().collect()
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn listen(sender: Sender<String>, waker: Arc<Mutex<Option<Waker>>>) {
+    thread::spawn(move || {
+        // Gilrs isn't Send on every platform, so it has to live on this thread
+        let mut gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                eprintln!("Gamepad support unavailable: {}", e);
+                return;
+            }
+        };
+        loop {
+            let Some(event) = gilrs.next_event_blocking(Some(Duration::from_millis(500))) else {
+                continue;
+            };
+            if let EventType::ButtonPressed(button, code) = event.event {
+                let name = match button {
+                    Button::Unknown => format!("Code {}", code),
+                    button => format!("{:?}", button),
+                };
+                if sender.send(name).is_err() {
+                    return; // The listener was dropped
+                }
+                if let Some(wake) = waker.lock().unwrap().as_ref() {
+                    wake();
+                }
+            }
+        }
+    });
+}
+
+// Built without the `gamepad` feature, so no presses ever arrive
+#[cfg(not(feature = "gamepad"))]
+fn listen(_sender: Sender<String>, _waker: Arc<Mutex<Option<Waker>>>) {}
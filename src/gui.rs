@@ -0,0 +1,4629 @@
+// The egui frontend: the main window, the settings and every prompt, drawn over the
+// recorder's state. Builds without the `gui` feature leave all of this out and only run headless.
+
+use super::*;
+use crate::cli::Args;
+use crate::session::TEMPLATE_HELP;
+use eframe::{run_native, App, CreationContext};
+use egui::{CentralPanel, Color32, RichText, ScrollArea, Vec2b};
+use egui_plot::{
+    CoordinatesFormatter, Corner, Line, Plot, PlotBounds, PlotPoint, PlotPoints, PlotUi, Polygon,
+    Text, VLine,
+};
+use rfd::FileDialog;
+
+// Every this many frames make it into the waveform plot
+const PLOT_DOWNSAMPLE: u64 = 10;
+
+const MAX_DETAIL_FRAMES: u64 = 20_000; // Zoomed views narrower than this skip the decimation
+
+// One color per channel in the waveform, repeating after eight
+const CHANNEL_COLORS: [Color32; 8] = [
+    Color32::from_rgb(90, 170, 255),
+    Color32::from_rgb(255, 140, 60),
+    Color32::from_rgb(110, 210, 110),
+    Color32::from_rgb(230, 90, 150),
+    Color32::from_rgb(200, 200, 80),
+    Color32::from_rgb(150, 120, 255),
+    Color32::from_rgb(80, 210, 200),
+    Color32::from_rgb(200, 160, 120),
+];
+
+const MARKER_CLICK_DISTANCE: f32 = 6.0; // Pixels either side of a marker that count as a click on it
+
+const FULL_WINDOW_SIZE: [f32; 2] = [800.0, 445.0];
+
+const MINI_WINDOW_SIZE: [f32; 2] = [260.0, 110.0];
+
+const LIMITER_COLOR: Color32 = Color32::from_rgb(240, 140, 30);
+
+// Window height left for the controls under the waveform, which gets the rest
+const CONTROLS_HEIGHT: f32 = 285.0;
+
+const MIN_WAVEFORM_HEIGHT: f32 = 80.0;
+
+// Quietest level shown on the dB scaled waveform
+const WAVEFORM_FLOOR_DB: f64 = -60.0;
+
+// Height of a sample in the waveform: linear, or on a dB scale from the floor up to full scale
+// (keeping the sign) so quiet material doesn't look like a flat line
+fn waveform_level(sample: f64, floor_db: Option<f64>) -> f64 {
+    match floor_db {
+        None => sample,
+        Some(floor) => {
+            let db = 20.0 * sample.abs().max(1e-9).log10();
+            sample.signum() * ((db - floor) / -floor).clamp(0.0, 1.0)
+        }
+    }
+}
+
+// The theme's waveform color, if one is set, replaces the first channel's
+fn channel_color(settings: &Config, channel: usize) -> Color32 {
+    match settings.waveform_color {
+        Some(color) if channel == 0 => theme::rgb(color),
+        _ => CHANNEL_COLORS[channel % CHANNEL_COLORS.len()],
+    }
+}
+
+// Decimated waveform kept between frames, so each frame only reads the audio written since the
+// last one instead of cloning and decimating the whole buffer
+#[derive(Default)]
+pub struct PlotCache {
+    buffer_id: u64,
+    channels: u64,
+    next: u64,             // Absolute frame of the next frame to decimate
+    points: VecDeque<f32>, // Interleaved like the buffer, one frame per point
+}
+
+impl PlotCache {
+    fn update(&mut self, buffer: &CircularBuffer, channels: usize) {
+        let channels = channels.max(1) as u64;
+        let first_frame = buffer.window_start().div_ceil(channels);
+        if self.buffer_id != buffer.id || self.channels != channels || self.next < first_frame {
+            // A different buffer, or too far behind to catch up: start again
+            self.buffer_id = buffer.id;
+            self.channels = channels;
+            self.points.clear();
+            self.next = first_frame.div_ceil(PLOT_DOWNSAMPLE) * PLOT_DOWNSAMPLE;
+        }
+
+        // Forget points that have rolled out of the window...
+        while !self.points.is_empty() && self.first_frame() < first_frame {
+            self.points.drain(..channels as usize);
+        }
+        // ...and decimate the ones written since the last frame
+        let total_frames = buffer.total_written / channels;
+        while self.next < total_frames {
+            let index = self.next * channels;
+            self.points
+                .extend((index..index + channels).map(|i| buffer.sample_at(i)));
+            self.next += PLOT_DOWNSAMPLE;
+        }
+    }
+
+    fn len(&self) -> u64 {
+        self.points.len() as u64 / self.channels.max(1)
+    }
+
+    fn first_frame(&self) -> u64 {
+        self.next - self.len() * PLOT_DOWNSAMPLE
+    }
+
+    // One channel's samples, positioned in seconds from the start of the buffer
+    fn plot_points(
+        &self,
+        buffer: &CircularBuffer,
+        channel: usize,
+        sample_rate: f64,
+    ) -> Vec<[f64; 2]> {
+        let channels = self.channels.max(1);
+        let start = self
+            .first_frame()
+            .saturating_sub(buffer.window_start() / channels);
+        self.points
+            .iter()
+            .skip(channel)
+            .step_by(channels as usize)
+            .enumerate()
+            .map(|(i, &sample)| {
+                let frame = start + i as u64 * PLOT_DOWNSAMPLE;
+                [frame as f64 / sample_rate, sample as f64]
+            })
+            .collect()
+    }
+}
+
+impl Sampler {
+    fn safety_ui(&mut self, ui: &mut egui::Ui) {
+        let mut restart = false;
+        ui.horizontal(|ui| {
+            restart |= ui
+                .checkbox(&mut self.settings.safety_recording, tr("Record everything"))
+                .changed();
+            ui.label(tr("Segment length (min):"));
+            restart |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.safety_segment_minutes)
+                        .range(1.0..=120.0),
+                )
+                .lost_focus();
+        });
+        ui.horizontal(|ui| {
+            restart |= ui
+                .checkbox(&mut self.settings.safety_gate, tr("Only while there is signal"))
+                .on_hover_text(tr("Start a segment when the input crosses the threshold and pause after a long silence"))
+                .changed();
+            ui.label(tr("Threshold (dB):"));
+            restart |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.safety_gate_threshold_db,
+                    -80.0..=0.0,
+                ))
+                .drag_stopped();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Pause after silence (s):"));
+            restart |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.safety_gate_silence_seconds)
+                        .range(5.0..=3600.0),
+                )
+                .lost_focus();
+        });
+        if restart {
+            self.settings.save();
+            self.set_safety_enabled(self.settings.safety_recording);
+        }
+
+        if let Some(safety) = &self.safety {
+            let state = if safety.is_recording() {
+                tr("Recording")
+            } else {
+                tr("Waiting for signal")
+            };
+            ui.label(tr_fill(
+                "{} ({} segments so far)",
+                &[&state, &safety.segments_written()],
+            ));
+        }
+    }
+
+    fn broadcast_ui(&mut self, ui: &mut egui::Ui) {
+        let mut restart = false;
+        ui.horizontal(|ui| {
+            restart |= ui
+                .checkbox(&mut self.settings.broadcast_enabled, tr("Stream the input"))
+                .changed();
+            ui.label(tr("To:"));
+            egui::ComboBox::from_id_source("Broadcast target")
+                .selected_text(tr(self.settings.broadcast_target.label()))
+                .show_ui(ui, |ui| {
+                    for target in BroadcastTarget::ALL {
+                        restart |= ui
+                            .selectable_value(
+                                &mut self.settings.broadcast_target,
+                                target,
+                                tr(target.label()),
+                            )
+                            .changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Host:"));
+            restart |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.settings.broadcast_host)
+                        .desired_width(160.0),
+                )
+                .lost_focus();
+            ui.label(tr("Port:"));
+            restart |= ui
+                .add(egui::DragValue::new(&mut self.settings.broadcast_port).range(1..=65535))
+                .lost_focus();
+        });
+        if self.settings.broadcast_target == BroadcastTarget::Icecast {
+            ui.horizontal(|ui| {
+                ui.label(tr("Mountpoint:"));
+                restart |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.settings.broadcast_mount)
+                            .desired_width(120.0),
+                    )
+                    .lost_focus();
+                ui.label(tr("Password:"));
+                restart |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.settings.broadcast_password)
+                            .password(true)
+                            .desired_width(100.0),
+                    )
+                    .lost_focus();
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("Opus bitrate (kbps):"));
+            restart |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.broadcast_bitrate_kbps).range(16..=256),
+                )
+                .lost_focus();
+        });
+        if restart {
+            self.settings.save();
+            self.set_broadcast_enabled(self.settings.broadcast_enabled);
+        }
+
+        let Some(broadcast) = &self.broadcast else {
+            return;
+        };
+        let state = if broadcast.is_connected() {
+            tr("Streaming")
+        } else {
+            tr("Connecting...")
+        };
+        let sent = format!("{:.1}", broadcast.bytes_sent() as f64 / 1_000_000.0);
+        ui.label(tr_fill("{} ({} MB sent)", &[&state, &sent]));
+        if let Some(sdp) = broadcast.sdp() {
+            if ui
+                .button(tr("Copy SDP"))
+                .on_hover_text(tr(
+                    "Save it as a .sdp file for the listener to open in VLC or ffplay",
+                ))
+                .clicked()
+            {
+                let sdp = sdp.to_owned();
+                ui.output_mut(|output| output.copied_text = sdp);
+                self.toasts.note("Session description copied");
+            }
+        }
+        ui.ctx()
+            .request_repaint_after(std::time::Duration::from_secs(1));
+    }
+
+    fn name_prompt(&mut self, ctx: &egui::Context) {
+        let Some((take, name)) = &mut self.pending_name else {
+            return;
+        };
+        let problem = take_name_problem(take, name);
+        let mut save = false;
+        let mut keep = false;
+        egui::Window::new(tr("Name Take"))
+            .id(egui::Id::new("Name Take"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let seconds = format!("{:.1}", take.duration_seconds());
+                ui.label(tr_fill("{} s grabbed", &[&seconds]));
+                let response = ui.text_edit_singleline(name);
+                if !response.has_focus() && !response.lost_focus() {
+                    response.request_focus();
+                }
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if let Some(problem) = &problem {
+                    ui.colored_label(Color32::RED, problem);
+                }
+                ui.horizontal(|ui| {
+                    save = ui
+                        .add_enabled(problem.is_none(), egui::Button::new(tr("Save")))
+                        .clicked()
+                        || (enter && problem.is_none());
+                    keep = ui.button(tr_fill("Keep {}", &[&take.name])).clicked();
+                });
+            });
+
+        if !(save || keep) {
+            return;
+        }
+        if let Some((take, name)) = self.pending_name.take() {
+            if save {
+                self.save_named(take, &name);
+            } else {
+                self.save_take(take);
+            }
+        }
+    }
+
+    // Called once the app is about to quit
+    // Keep the window's place and its full layout size for the next run
+    fn remember_window(&mut self, ctx: &egui::Context) {
+        let (inner, outer) = ctx.input(|i| (i.viewport().inner_rect, i.viewport().outer_rect));
+        let size = if self.settings.mini_mode {
+            self.full_window_size
+        } else {
+            inner.map(|rect| rect.size())
+        };
+        if let Some(size) = size {
+            self.settings.window_size = Some(size.into());
+        }
+        if let Some(outer) = outer {
+            self.settings.window_position = Some(outer.min.into());
+        }
+        self.settings.save();
+    }
+
+    fn spectrum_settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("Averaging:"));
+        if ui
+            .add(egui::Slider::new(
+                &mut self.settings.spectrum_averaging,
+                0.0..=0.98,
+            ))
+            .drag_stopped()
+        {
+            self.settings.save();
+        }
+        if ui
+            .checkbox(&mut self.settings.spectrum_peak_hold, tr("Peak hold"))
+            .changed()
+        {
+            self.settings.save();
+        }
+        if ui.button(tr("Reset Peak")).clicked() {
+            self.spectrum.reset_peak();
+        }
+    }
+
+    // Amplitude scale, plus show/hide for each channel and whether they share one lane
+    fn waveform_options_ui(&mut self, ui: &mut egui::Ui) {
+        let channels = self.recorder.config.channels as usize;
+        ui.horizontal(|ui| {
+            let mut changed = ui
+                .checkbox(&mut self.settings.waveform_db_scale, tr("dB scale"))
+                .on_hover_text(tr_fill(
+                    "Show levels from {} dBFS up, so quiet material is visible",
+                    &[&WAVEFORM_FLOOR_DB],
+                ))
+                .changed();
+            if channels > 1 {
+                changed |= ui
+                    .checkbox(&mut self.settings.waveform_overlay, tr("Overlay channels"))
+                    .changed();
+                for channel in 0..channels {
+                    let mut shown = !self.settings.hidden_channels.contains(&channel);
+                    let label = RichText::new(format!("{} {}", tr("Ch"), channel + 1))
+                        .color(channel_color(&self.settings, channel));
+                    if ui.checkbox(&mut shown, label).changed() {
+                        if shown {
+                            self.settings.hidden_channels.retain(|&c| c != channel);
+                        } else {
+                            self.settings.hidden_channels.push(channel);
+                        }
+                        changed = true;
+                    }
+                }
+            }
+            if changed {
+                self.settings.save();
+            }
+
+            // How far back a grab can reach right now
+            let coverage = {
+                let buffer = self.recorder.buffer.lock().unwrap();
+                let samples_per_second = self.recorder.config.channels.max(1) as f32
+                    * self.recorder.config.sample_rate.0 as f32;
+                let filled = buffer.current_size as f32 / samples_per_second;
+                if self.recorder.state.is_grabbing() {
+                    tr_fill("{} s captured", &[&format!("{:.0}", filled)])
+                } else {
+                    let history = buffer.max_size as f32 / samples_per_second;
+                    tr_fill(
+                        "{} s / {} s buffered",
+                        &[&format!("{:.0}", filled), &format!("{:.0}", history)],
+                    )
+                }
+            };
+            ui.label(coverage);
+        });
+    }
+
+    fn clip_log_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let level = 20.0 * rolling_sampler_core::meter::CLIP_LEVEL.log10();
+            ui.label(tr_fill(
+                "{} clip events (at or above {} dBFS)",
+                &[&self.meters.clip_log.len(), &format!("{:.2}", level)],
+            ));
+            if ui.button(tr("Reset Lights")).clicked() {
+                self.meters.reset_clip_leds();
+            }
+            if ui.button(tr("Clear Log")).clicked() {
+                self.meters.clip_log.clear();
+            }
+        });
+        ScrollArea::vertical()
+            .id_source("Clip Log")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                // Newest first
+                for event in self.meters.clip_log.iter().rev() {
+                    ui.label(tr_fill(
+                        "{}  Ch {}  {} samples",
+                        &[
+                            &event.time.format("%H:%M:%S%.3f"),
+                            &(event.channel + 1),
+                            &event.samples,
+                        ],
+                    ));
+                }
+            });
+    }
+
+    fn session_report_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(
+                    &mut self.settings.session_report_on_exit,
+                    tr("Write a report when quitting"),
+                )
+                .changed()
+            {
+                self.settings.save();
+            }
+            if ui.button(tr("Write Report Now")).clicked() {
+                self.write_session_report();
+            }
+        });
+        ui.label(tr_fill(
+            "{} grabs saved since {}",
+            &[&self.history.len(), &self.session_started.format("%H:%M")],
+        ));
+    }
+
+    // This session's grabs, newest first, with playback and file management
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if !self.history.is_empty() {
+                take_filter_ui(ui, &mut self.history_filter);
+            }
+            if ui
+                .button(tr("Search a Folder..."))
+                .on_hover_text(tr(
+                    "Find takes saved before, by their ratings, tags and metadata",
+                ))
+                .clicked()
+            {
+                let mut dialog = FileDialog::new();
+                if let Some(save_path) = &self.save_path {
+                    dialog = dialog.set_directory(save_path);
+                }
+                if let Some(dir) = dialog.pick_folder() {
+                    self.open_library(dir);
+                }
+            }
+        });
+        if self.history.is_empty() {
+            ui.label(tr("Grabs saved this session will be listed here."));
+            return;
+        }
+        if matches!(&self.history_playback, Some((_, playback)) if playback.is_finished()) {
+            self.history_playback = None;
+        }
+        // Files can't be touched while a writer thread may still be working on them
+        self.collect_saves();
+        let saving = self.recorder.is_saving();
+
+        let mut play = None;
+        let mut stop = false;
+        let mut rename = None;
+        let mut delete = None;
+        let mut edit = None;
+        ScrollArea::vertical()
+            .id_source("History")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for (index, item) in self.history.iter_mut().enumerate().rev() {
+                    if !self.history_filter.matches(item) {
+                        continue;
+                    }
+                    ui.horizontal_wrapped(|ui| {
+                        let progress = match &self.history_playback {
+                            Some((i, playback)) if *i == index => Some(
+                                playback.position() as f32 / playback.end_frame().max(1) as f32,
+                            ),
+                            _ => None,
+                        };
+                        let thumbnail = item.thumbnail(ui.ctx());
+                        if let Some(from) = thumbnail_ui(ui, thumbnail, progress) {
+                            match &self.history_playback {
+                                Some((i, playback)) if *i == index => {
+                                    playback.seek((from * playback.end_frame() as f32) as usize)
+                                }
+                                _ if !saving => play = Some((index, from)),
+                                _ => {}
+                            }
+                        }
+                        match &mut self.renaming {
+                            Some((renaming, name)) if *renaming == index => {
+                                let response = ui.text_edit_singleline(name);
+                                if ui.button(tr("Save")).clicked()
+                                    || (response.lost_focus()
+                                        && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                                {
+                                    rename = Some((index, name.clone()));
+                                }
+                                if ui.button(tr("Cancel")).clicked() {
+                                    self.renaming = None;
+                                }
+                            }
+                            _ => {
+                                let key = item
+                                    .metadata
+                                    .iter()
+                                    .find(|(field, _)| field == "key")
+                                    .map(|(_, key)| format!(", {}", key))
+                                    .unwrap_or_default();
+                                ui.label(format!(
+                                    "{} ({:.1} s{})",
+                                    item.name, item.duration_seconds, key
+                                ));
+                                if let Some(analysis) = &item.analysis {
+                                    ui.label(RichText::new(analysis.summary()).small().weak());
+                                }
+                            }
+                        }
+                        // The sidecar they're kept in is written with the file
+                        ui.add_enabled_ui(!saving, |ui| {
+                            if let Some(change) =
+                                rating_and_tags_ui(ui, item, &mut self.editing_tags)
+                            {
+                                edit = Some((item.path.clone(), change));
+                            }
+                        });
+
+                        let playing = matches!(&self.history_playback, Some((i, _)) if *i == index);
+                        if playing {
+                            stop = ui.button(tr("Stop")).clicked();
+                        } else if ui
+                            .add_enabled(!saving, egui::Button::new(tr("Play")))
+                            .clicked()
+                        {
+                            play = Some((index, 0.0));
+                        }
+                        if ui
+                            .add_enabled(!saving, egui::Button::new(tr("Rename")))
+                            .clicked()
+                        {
+                            self.renaming = Some((index, item.name.clone()));
+                        }
+                        if ui.button(tr("Reveal")).clicked() {
+                            if let Err(e) = history::reveal(&item.path) {
+                                self.toasts.error(RecorderError::Other(format!(
+                                    "Failed to open the file manager: {}",
+                                    e
+                                )));
+                            }
+                        }
+                        if self.confirm_delete == Some(index) {
+                            if ui
+                                .button(RichText::new(tr("Really delete?")).color(Color32::RED))
+                                .clicked()
+                            {
+                                delete = Some(index);
+                            }
+                        } else if ui
+                            .add_enabled(!saving, egui::Button::new(tr("Delete")))
+                            .clicked()
+                        {
+                            self.confirm_delete = Some(index);
+                        }
+                    });
+                }
+            });
+
+        if stop {
+            self.history_playback = None;
+        } else if let Some((index, from)) = play {
+            self.audition(index, from);
+        }
+        if let Some((path, change)) = edit {
+            self.edit_take(&path, change);
+        }
+        if let Some((index, name)) = rename {
+            match self.history[index].rename(&name) {
+                Ok(()) => self.renaming = None,
+                Err(e) => self.toasts.error(RecorderError::Other(format!(
+                    "Failed to rename {}: {}",
+                    self.history[index].name, e
+                ))),
+            }
+        }
+        if let Some(index) = delete {
+            self.confirm_delete = None;
+            if matches!(&self.history_playback, Some((i, _)) if *i == index) {
+                self.history_playback = None;
+            }
+            match self.history[index].delete() {
+                Ok(()) => {
+                    println!("Deleted {}", self.history[index].path.display());
+                    self.renaming = None;
+                    self.remove_history_item(index);
+                }
+                Err(e) => self.toasts.error(RecorderError::Other(format!(
+                    "Failed to delete {}: {}",
+                    self.history[index].name, e
+                ))),
+            }
+        }
+    }
+
+    fn library_window(&mut self, ctx: &egui::Context) {
+        let Some(library) = &mut self.library else {
+            return;
+        };
+        if matches!(&library.playback, Some((_, playback)) if playback.is_finished()) {
+            library.playback = None;
+        }
+        let mut open = true;
+        let mut rescan = false;
+        let mut play = None;
+        let mut edit = None;
+        egui::Window::new(tr("Take Library"))
+            .id(egui::Id::new("Take Library"))
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(library.dir.display().to_string());
+                    rescan = ui.small_button(tr("Rescan")).clicked();
+                });
+                ui.horizontal(|ui| take_filter_ui(ui, &mut self.history_filter));
+                let shown: Vec<usize> = (0..library.items.len())
+                    .filter(|&index| self.history_filter.matches(&library.items[index]))
+                    .collect();
+                ui.label(tr_fill(
+                    "{} of {} takes",
+                    &[&shown.len(), &library.items.len()],
+                ));
+                ScrollArea::vertical()
+                    .id_source("Library")
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for index in shown {
+                            let item = &mut library.items[index];
+                            ui.horizontal_wrapped(|ui| {
+                                let progress = match &library.playback {
+                                    Some((i, playback)) if *i == index => Some(
+                                        playback.position() as f32
+                                            / playback.end_frame().max(1) as f32,
+                                    ),
+                                    _ => None,
+                                };
+                                let thumbnail = item.thumbnail(ui.ctx());
+                                if let Some(from) = thumbnail_ui(ui, thumbnail, progress) {
+                                    match &library.playback {
+                                        Some((i, playback)) if *i == index => playback
+                                            .seek((from * playback.end_frame() as f32) as usize),
+                                        _ => play = Some((index, from)),
+                                    }
+                                }
+                                ui.label(format!(
+                                    "{} ({:.1} s, {})",
+                                    item.name,
+                                    item.duration_seconds,
+                                    item.saved_at.format("%Y-%m-%d")
+                                ));
+                                if let Some(change) =
+                                    rating_and_tags_ui(ui, item, &mut self.editing_tags)
+                                {
+                                    edit = Some((item.path.clone(), change));
+                                }
+                                if ui.small_button(tr("Reveal")).clicked() {
+                                    if let Err(e) = history::reveal(&item.path) {
+                                        self.toasts.error(RecorderError::Other(format!(
+                                            "Failed to open the file manager: {}",
+                                            e
+                                        )));
+                                    }
+                                }
+                            });
+                        }
+                    });
+                if library.playback.is_some() {
+                    ui.ctx().request_repaint(); // Keep the playhead moving
+                }
+            });
+
+        if !open {
+            self.library = None;
+            return;
+        }
+        if rescan {
+            let dir = library.dir.clone();
+            self.open_library(dir);
+        } else if let Some((index, from)) = play {
+            let path = library.items[index].path.clone();
+            library.playback = None;
+            if let Some(playback) = self.play_file(&path, from) {
+                if let Some(library) = &mut self.library {
+                    library.playback = Some((index, playback));
+                }
+            }
+        }
+        if let Some((path, change)) = edit {
+            self.edit_take(&path, change);
+        }
+    }
+
+    fn ab_compare_ui(&mut self, ui: &mut egui::Ui) {
+        if self.history.len() < 2 {
+            ui.label(tr("Save at least two grabs to compare them."));
+            return;
+        }
+        let previous_pick = self.ab_pick;
+        for (slot, label) in ["A", "B"].into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", label));
+                let selected = self.ab_pick[slot]
+                    .and_then(|index| self.history.get(index))
+                    .map(|item| item.name.clone())
+                    .unwrap_or_else(|| tr("Choose a take").to_owned());
+                egui::ComboBox::from_id_source(format!("AbPick{}", label))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        // Newest first
+                        for (index, item) in self.history.iter().enumerate().rev() {
+                            ui.selectable_value(
+                                &mut self.ab_pick[slot],
+                                Some(index),
+                                format!("{} ({:.1} s)", item.name, item.duration_seconds),
+                            );
+                        }
+                    });
+            });
+        }
+        if self.ab_pick != previous_pick {
+            self.ab_playback = None;
+        }
+
+        let mut start = false;
+        let mut stop = false;
+        ui.horizontal(|ui| match &self.ab_playback {
+            Some(ab) => {
+                stop = ui.button(tr("Stop")).clicked();
+                let hearing = if ab.is_playing_b() { "B" } else { "A" };
+                if ui
+                    .button(tr_fill("Hearing {} (switch)", &[&hearing]))
+                    .on_hover_text(self.shortcut_hint("ab"))
+                    .clicked()
+                {
+                    ab.switch();
+                }
+                let offset = format!("{:+.1}", ab.b_offset_db);
+                ui.label(tr_fill("B is {} dB against A, level matched", &[&offset]));
+            }
+            None => {
+                let ready = self.ab_pick.iter().all(Option::is_some);
+                start = ui
+                    .add_enabled(ready, egui::Button::new(tr("Play A/B")))
+                    .clicked();
+            }
+        });
+        if stop {
+            self.ab_playback = None;
+        } else if start {
+            self.start_ab();
+        }
+    }
+
+    fn hot_folder_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            if ui.button(tr("Select Hot Folder")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.settings.hot_folder = Some(path.display().to_string());
+                    changed = true;
+                }
+            }
+            match &self.settings.hot_folder {
+                Some(dir) => {
+                    ui.label(dir.as_str());
+                    if ui.small_button("✖").clicked() {
+                        self.settings.hot_folder = None;
+                        changed = true;
+                    }
+                }
+                None => {
+                    ui.label(tr("Off"));
+                }
+            }
+        });
+        changed |= ui
+            .checkbox(
+                &mut self.settings.hot_folder_take_names,
+                tr("Name files Take 001, Take 002..."),
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label(tr("Run after each drop:"));
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.settings.hot_folder_command)
+                        .hint_text(tr("command, file in $ROLLING_SAMPLER_FILE")),
+                )
+                .lost_focus();
+        });
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    fn post_save_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Run after each save:"));
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.settings.post_save_command)
+                        .hint_text(tr("e.g. rclone copy {file} remote:samples")),
+                )
+                .on_hover_text(
+                    tr("{file} is replaced by the saved file's path, which is added at the end if it isn't used"),
+                )
+                .lost_focus()
+            {
+                self.settings.save();
+            }
+        });
+    }
+
+    // Names and arming for each input channel, e.g. every mic of a rehearsal room coming in
+    // through one interface or an aggregate device
+    fn tracks_ui(&mut self, ui: &mut egui::Ui) {
+        let channels = self.recorder.config.channels as usize;
+        if self.settings.channel_names.len() < channels {
+            self.settings.channel_names.resize(channels, String::new());
+        }
+        let mut changed = false;
+        egui::Grid::new("Tracks").show(ui, |ui| {
+            for channel in 0..channels {
+                let mut armed = !self.settings.disarmed_channels.contains(&channel);
+                if ui
+                    .checkbox(&mut armed, format!("{}", channel + 1))
+                    .on_hover_text(tr("Armed channels are saved, the rest left out"))
+                    .changed()
+                {
+                    if armed {
+                        self.settings.disarmed_channels.retain(|&c| c != channel);
+                    } else {
+                        self.settings.disarmed_channels.push(channel);
+                    }
+                    changed = true;
+                }
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut self.settings.channel_names[channel])
+                            .hint_text(format!("{}{}", tr("Ch"), channel + 1))
+                            .desired_width(160.0),
+                    )
+                    .lost_focus();
+                ui.end_row();
+            }
+        });
+        changed |= ui
+            .checkbox(
+                &mut self.settings.save_stems,
+                tr("Also save a mono file per channel"),
+            )
+            .on_hover_text(tr(
+                "In a folder next to each take, named after the channels",
+            ))
+            .changed();
+        ui.label(
+            RichText::new(tr(
+                "Channel names go into each file's iXML track list, for DAWs to split it by",
+            ))
+            .italics(),
+        );
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    fn markers_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button(tr("Drop Marker"))
+                .on_hover_text(self.shortcut_hint("marker"))
+                .clicked()
+            {
+                self.drop_marker();
+            }
+            ui.label(tr("Export length around a marker (s):"));
+            if ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.marker_export_seconds)
+                        .range(1.0..=120.0),
+                )
+                .changed()
+            {
+                self.settings.save();
+            }
+        });
+
+        if self.recorder.markers.is_empty() {
+            ui.label(tr("No markers in the buffer."));
+            return;
+        }
+
+        let now = Local::now();
+        let mut around = None;
+        for marker in &self.recorder.markers {
+            ui.horizontal(|ui| {
+                let ago = (now - marker.time).num_milliseconds() as f64 / 1000.0;
+                ui.label(tr_fill(
+                    "Marker {} at {} ({} ago)",
+                    &[
+                        &marker.number,
+                        &marker.time.format("%H:%M:%S"),
+                        &format_time(ago),
+                    ],
+                ));
+                if ui.small_button(tr("Export Around")).clicked() {
+                    around = Some(marker.number);
+                }
+            });
+        }
+        if let Some(number) = around {
+            self.export_around_marker(number);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Export from marker")).on_hover_text(tr(
+                "Or click a marker on the waveform for the start, right click for the end",
+            ));
+            ui.add(
+                egui::DragValue::new(&mut self.marker_range.0)
+                    .range(1..=self.settings.session().marker_count),
+            );
+            ui.label(tr("to marker"));
+            ui.add(
+                egui::DragValue::new(&mut self.marker_range.1)
+                    .range(1..=self.settings.session().marker_count),
+            );
+            if ui.button(tr("Export Range")).clicked() {
+                self.export_marker_range(self.marker_range.0, self.marker_range.1);
+            }
+        });
+        if self.recorder.pending_exports() > 0 {
+            ui.label(
+                RichText::new(tr_fill(
+                    "{} export(s) waiting for audio after the marker...",
+                    &[&self.recorder.pending_exports()],
+                ))
+                .italics(),
+            );
+        }
+    }
+
+    fn hit_capture_ui(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.hit_capture.is_some();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut enabled, tr("Auto-capture hits"))
+                .on_hover_text(tr("Save a short one-shot around every detected transient"))
+                .changed()
+            {
+                changed = true;
+            }
+            ui.label(tr("Sensitivity (dB):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.hit_sensitivity_db,
+                    3.0..=30.0,
+                ))
+                .drag_stopped();
+            ui.label(tr("Floor (dB):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.hit_floor_db,
+                    -70.0..=0.0,
+                ))
+                .drag_stopped();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Pre-roll (ms):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.hit_pre_roll_ms,
+                    0.0..=100.0,
+                ))
+                .drag_stopped();
+            ui.label(tr("Length (ms):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.hit_length_ms,
+                    50.0..=5000.0,
+                ))
+                .drag_stopped();
+        });
+        if changed {
+            self.settings.save();
+            // Rebuild the detector with the new settings
+            self.set_hit_capture_enabled(enabled);
+        }
+        if let Some(capture) = &self.hit_capture {
+            ui.label(RichText::new(tr_fill("{} hits captured", &[&capture.count])).italics());
+        }
+    }
+
+    fn poll_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        // Space and Enter already press whichever button has focus
+        let focused = ctx.memory(|memory| memory.focused().is_some());
+        let keys: Vec<egui::Key> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        repeat: false,
+                        ..
+                    } => Some(*key),
+                    _ => None,
+                })
+                .filter(|key| !(focused && matches!(key, egui::Key::Space | egui::Key::Enter)))
+                .collect()
+        });
+        for key in keys {
+            self.handle_trigger(Trigger::Key(key.name().to_owned()));
+        }
+    }
+
+    // Tooltip naming the active preset's triggers for a command, e.g. "Shortcut: Key Space"
+    fn shortcut_hint(&self, command: &str) -> String {
+        let triggers: Vec<String> = self
+            .settings
+            .active_preset()
+            .bindings
+            .iter()
+            .filter(|binding| {
+                binding
+                    .command
+                    .split_whitespace()
+                    .next()
+                    .map(str::to_ascii_lowercase)
+                    == Some(command.to_owned())
+            })
+            .map(|binding| binding.trigger.label())
+            .collect();
+        if triggers.is_empty() {
+            tr("No shortcut, add one under Preset & Metadata").to_owned()
+        } else {
+            format!("{} {}", tr("Shortcut:"), triggers.join(", "))
+        }
+    }
+
+    fn control_ui(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.commands.socket_path().is_some();
+        if ui
+            .checkbox(&mut enabled, tr("Listen on a local control socket"))
+            .changed()
+        {
+            self.settings.control_socket = enabled;
+            self.settings.save();
+            self.set_control_socket_enabled(enabled);
+        }
+        if let Some(path) = self.commands.socket_path() {
+            ui.label(format!("echo grab | nc -U {}", path.display()));
+        }
+        ui.label(
+            RichText::new(format!(
+                "{} {}",
+                tr("Commands (also on stdin):"),
+                control::HELP
+            ))
+            .italics(),
+        );
+    }
+
+    #[cfg(feature = "scripting")]
+    fn script_ui(&mut self, ui: &mut egui::Ui) {
+        let mut load = false;
+        ui.horizontal(|ui| {
+            if ui.button(tr("Select Script")).clicked() {
+                if let Some(path) = FileDialog::new().add_filter("Rhai", &["rhai"]).pick_file() {
+                    self.settings.script_path = Some(path.display().to_string());
+                    load = true;
+                }
+            }
+            match &self.settings.script_path {
+                Some(path) => {
+                    ui.label(path.as_str());
+                    if ui.small_button("⟳").on_hover_text(tr("Reload")).clicked() {
+                        load = true;
+                    }
+                    if ui.small_button("✖").clicked() {
+                        self.settings.script_path = None;
+                        load = true;
+                    }
+                }
+                None => {
+                    ui.label(tr("Off"));
+                }
+            }
+        });
+        if load {
+            self.settings.save();
+            self.load_script();
+        }
+        if let Some(script) = &self.script {
+            let hooks = script.hooks();
+            ui.label(if hooks.is_empty() {
+                tr("No hooks defined").to_owned()
+            } else {
+                format!("{} {}", tr("Hooks:"), hooks.join(", "))
+            });
+        }
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("on_silence after (s):"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.script_silence_seconds)
+                        .range(1.0..=3600.0),
+                )
+                .lost_focus();
+            ui.label(tr("below (dB):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.script_silence_db,
+                    -90.0..=0.0,
+                ))
+                .drag_stopped();
+        });
+        if changed {
+            self.settings.save();
+        }
+        ui.label(
+            RichText::new(tr_fill(
+                "Hooks: {}. Calls: command(\"grab\"), tag(key, value), notify(text), osc_send(\"host:port\", \"/address\", [args]). The file is reloaded when it changes.",
+                &[&script::HOOKS.join(", ")],
+            ))
+            .italics(),
+        );
+    }
+
+    fn timecode_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Timecode from:"));
+            egui::ComboBox::from_id_source("Timecode source")
+                .selected_text(tr(self.settings.timecode_source.label()))
+                .show_ui(ui, |ui| {
+                    for source in TimecodeSource::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.timecode_source,
+                                source,
+                                tr(source.label()),
+                            )
+                            .changed();
+                    }
+                });
+            if self.settings.timecode_source == TimecodeSource::Ltc {
+                ui.label(tr("Channel:"));
+                let mut channel = self.settings.ltc_channel + 1;
+                let channels = self.recorder.config.channels.max(1) as usize;
+                if ui
+                    .add(egui::DragValue::new(&mut channel).range(1..=channels))
+                    .changed()
+                {
+                    self.settings.ltc_channel = channel - 1;
+                    self.ltc = None; // Decode the new channel from scratch
+                    changed = true;
+                }
+            }
+        });
+        if changed {
+            self.timecode = None;
+        }
+        if self.settings.timecode_source == TimecodeSource::Off {
+            if changed {
+                self.settings.save();
+            }
+            return;
+        }
+        match self.current_timecode() {
+            Some(timecode) => {
+                ui.label(RichText::new(timecode.to_string()).monospace().size(18.0));
+                ui.ctx().request_repaint(); // Keep the frames running
+            }
+            None => {
+                ui.label(tr("Waiting for timecode..."));
+            }
+        }
+        changed |= ui
+            .checkbox(
+                &mut self.settings.timecode_in_name,
+                tr("Add the start timecode to file names"),
+            )
+            .changed();
+        ui.label(
+            RichText::new(tr(
+                "Takes get their start timecode as a BWF time reference and in the metadata",
+            ))
+            .italics(),
+        );
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    // Plays a range of notes on a hardware synth over MIDI and saves each one from the input
+    fn auto_sampler_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(sampler) = &self.auto_sampler {
+            let (done, total) = sampler.progress();
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                        .desired_width(200.0)
+                        .text(tr_fill("{} of {}", &[&done, &total])),
+                );
+                if let Some((note, velocity)) = sampler.playing() {
+                    ui.label(tr_fill(
+                        "Playing {} at velocity {}",
+                        &[&autosample::note_name(note), &velocity],
+                    ));
+                }
+            });
+            if ui.button(tr("Stop")).clicked() {
+                self.set_auto_sampler_running(false);
+            }
+            ui.ctx().request_repaint(); // Notes are timed on the UI thread
+            return;
+        }
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("MIDI output:"));
+            egui::ComboBox::from_id_source("Auto-sampler output")
+                .selected_text(
+                    self.settings
+                        .autosample_port
+                        .as_deref()
+                        .unwrap_or(tr("None")),
+                )
+                .show_ui(ui, |ui| {
+                    for port in &self.midi_outputs {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.autosample_port,
+                                Some(port.clone()),
+                                port,
+                            )
+                            .changed();
+                    }
+                });
+            if ui
+                .small_button("⟳")
+                .on_hover_text(tr("Look for outputs again"))
+                .clicked()
+            {
+                self.midi_outputs = autosample::output_ports();
+            }
+            ui.label(tr("Channel:"));
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.settings.autosample_channel).range(1..=16))
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Notes:"));
+            for note in [
+                &mut self.settings.autosample_low_note,
+                &mut self.settings.autosample_high_note,
+            ] {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(note)
+                            .range(0..=127)
+                            .custom_formatter(|n, _| autosample::note_name(n as u8)),
+                    )
+                    .changed();
+            }
+            ui.label(tr("every"));
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.settings.autosample_step).range(1..=12))
+                .changed();
+            ui.label(tr("semitones"));
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Velocities:"));
+            let velocities = &mut self.settings.autosample_velocities;
+            for velocity in velocities.iter_mut() {
+                changed |= ui
+                    .add(egui::DragValue::new(velocity).range(1..=127))
+                    .changed();
+            }
+            if velocities.len() > 1 && ui.small_button("-").clicked() {
+                velocities.pop();
+                changed = true;
+            }
+            if ui
+                .small_button("+")
+                .on_hover_text(tr("Another velocity layer"))
+                .clicked()
+            {
+                let softest = velocities.last().copied().unwrap_or(127);
+                velocities.push((softest / 2).max(1));
+                changed = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Hold (s):"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.autosample_hold_seconds)
+                        .range(0.05..=30.0)
+                        .speed(0.05),
+                )
+                .changed();
+            ui.label(tr("Release (s):"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.autosample_release_seconds)
+                        .range(0.0..=30.0)
+                        .speed(0.05),
+                )
+                .changed();
+            ui.label(tr("Trim below (dB):"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.autosample_floor_db)
+                        .range(-96.0..=-20.0),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Instrument:"));
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.settings.autosample_name)
+                        .desired_width(160.0),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Patches:")).on_hover_text(tr(
+                "Written for the notes of each run, and for Slice to Hits",
+            ));
+            changed |= ui.checkbox(&mut self.settings.write_sfz, "SFZ").changed();
+            changed |= ui
+                .checkbox(&mut self.settings.write_dspreset, "DecentSampler")
+                .changed();
+        });
+        if changed {
+            self.settings.save();
+        }
+        let notes = (self.settings.autosample_low_note..=self.settings.autosample_high_note)
+            .step_by(self.settings.autosample_step.max(1) as usize)
+            .count()
+            * self.settings.autosample_velocities.len();
+        let seconds = notes as f32
+            * (self.settings.autosample_hold_seconds + self.settings.autosample_release_seconds);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(notes > 0, egui::Button::new(tr("Start")))
+                .on_hover_text(self.shortcut_hint("autosample"))
+                .clicked()
+            {
+                self.set_auto_sampler_running(true);
+            }
+            ui.label(tr_fill(
+                "{} notes, about {} minutes",
+                &[&notes, &(seconds / 60.0).ceil()],
+            ));
+        });
+        ui.label(
+            RichText::new(
+                tr("Each note is saved into a folder named after the instrument, trimmed to where it sounds"),
+            )
+            .italics(),
+        );
+    }
+
+    fn overdub_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(layering) = &self.layering else {
+            let bed = self
+                .last_grab
+                .as_ref()
+                .map(|last_grab| last_grab.take.name.clone());
+            ui.label(tr(
+                "Plays a grab back through the output while you record a new layer over it.",
+            ));
+            if ui
+                .add_enabled(bed.is_some(), egui::Button::new(tr("Layer Over Last Grab")))
+                .on_hover_text(bed.unwrap_or_else(|| tr("Grab something first").to_owned()))
+                .clicked()
+            {
+                self.start_overdub(false);
+            }
+            if self.settings.round_trip_latency_ms.is_none() {
+                ui.label(tr("Measure the round-trip latency (under Devices & Monitoring) so layers line up exactly."));
+            }
+            return;
+        };
+        ui.label(format!("{} {}", tr("Bed:"), layering.bed.name));
+        if let Some(pass) = &layering.pass {
+            let progress = pass.progress().min(1.0);
+            let number = layering.passes + 1;
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .desired_width(200.0)
+                        .text(tr_fill("Pass {}", &[&number])),
+                );
+                if ui.button(tr("Cancel")).clicked() {
+                    if let Some(layering) = &mut self.layering {
+                        layering.pass = None;
+                    }
+                }
+            });
+            ui.ctx().request_repaint();
+            return;
+        }
+        let has_layer = layering.layer.is_some();
+        ui.horizontal(|ui| {
+            if has_layer {
+                if ui.button(tr("Save Layer")).clicked() {
+                    self.save_overdub(false);
+                }
+                if ui
+                    .button(tr("Save Bounce"))
+                    .on_hover_text(tr("The bed and the new layer mixed together"))
+                    .clicked()
+                {
+                    self.save_overdub(true);
+                }
+                if ui
+                    .button(tr("Layer Again"))
+                    .on_hover_text(tr(
+                        "Mix this layer into the bed and record another over both",
+                    ))
+                    .clicked()
+                {
+                    self.start_overdub(true);
+                }
+            }
+            let again = if has_layer {
+                tr("Redo")
+            } else {
+                tr("Record Layer")
+            };
+            if ui.button(again).clicked() {
+                self.start_overdub(false);
+            }
+            if ui.button(tr("Done")).clicked() {
+                self.layering = None;
+            }
+        });
+    }
+
+    fn metronome_ui(&mut self, ui: &mut egui::Ui) {
+        let mut running = self.metronome_running();
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut running, tr("Click"))
+                .on_hover_text(tr("Plays in the monitoring output only, never in what's recorded. Starts monitoring if it's off."))
+                .changed()
+            {
+                self.set_metronome_running(running);
+            }
+            if self.metronome_running() {
+                let beat = self.recorder.metronome_beat.load(Ordering::Relaxed);
+                ui.label(self.settings.metronome.describe_beat(beat));
+                ui.ctx().request_repaint(); // Keep the beat moving
+            }
+        });
+        let link_bpm = self.link_bpm();
+        let metronome = &mut self.settings.metronome;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Tempo:"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut metronome.bpm)
+                        .range(20.0..=300.0)
+                        .speed(0.2)
+                        .suffix(" BPM"),
+                )
+                .changed();
+            if let Some(bpm) = link_bpm {
+                if ui.small_button(tr("From Link")).clicked() {
+                    metronome.bpm = (bpm * 10.0).round() / 10.0;
+                    changed = true;
+                }
+            }
+            ui.label(tr("Beats per bar:"));
+            changed |= ui
+                .add(egui::DragValue::new(&mut metronome.beats_per_bar).range(1..=16))
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Count-in:"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut metronome.count_in_bars)
+                        .range(0..=4)
+                        .suffix(" bars"),
+                )
+                .changed();
+            changed |= ui
+                .checkbox(&mut metronome.after_count_in, tr("Keep clicking"))
+                .on_hover_text(tr("Off to hear only the count-in"))
+                .changed();
+            ui.label(tr("Level:"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut metronome.level_db)
+                        .range(-40.0..=0.0)
+                        .speed(0.2)
+                        .suffix(" dB"),
+                )
+                .changed();
+        });
+        if changed {
+            self.settings.save();
+            self.update_metronome();
+        }
+    }
+
+    #[cfg(feature = "link")]
+    fn link_ui(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.link.is_some();
+        if ui
+            .checkbox(&mut enabled, tr("Follow Ableton Link"))
+            .on_hover_text(tr(
+                "Takes the tempo and bar lines from a DAW or drum machine on the network",
+            ))
+            .changed()
+        {
+            self.settings.link_enabled = enabled;
+            self.settings.save();
+            self.set_link_enabled(enabled);
+        }
+        let Some(link) = &self.link else {
+            return;
+        };
+        let status = link.status();
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            match status.timeline {
+                Some(timeline) => {
+                    let beats_per_bar = self.settings.link_beats_per_bar.max(1.0) as f64;
+                    let beat = timeline.beat_at(Instant::now());
+                    ui.label(tr_fill(
+                        "{} BPM, bar {}, beat {}, {} peers",
+                        &[
+                            &format!("{:.1}", timeline.bpm()),
+                            &((beat / beats_per_bar).floor() as i64 + 1),
+                            &((beat.rem_euclid(beats_per_bar)).floor() as i64 + 1),
+                            &status.peers,
+                        ],
+                    ));
+                    ui.ctx().request_repaint(); // Keep the beat moving
+                }
+                None if status.peers > 0 => {
+                    ui.label(tr("Measuring the session's clock..."));
+                }
+                None => {
+                    ui.label(tr("No Link session found"));
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Beats per bar:"));
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.settings.link_beats_per_bar).range(1.0..=16.0))
+                .changed();
+            ui.label(tr("Bars:"));
+            changed |= ui
+                .add(egui::DragValue::new(&mut self.settings.link_grab_bars).range(1..=64))
+                .changed();
+            let grab = ui.add_enabled(
+                status.timeline.is_some() && !self.recorder.state.is_grabbing(),
+                egui::Button::new(tr("Grab Last Bars")),
+            );
+            if grab.clicked() {
+                self.grab_bars(self.settings.link_grab_bars);
+            }
+        });
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    fn remote_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut enabled = self.remote.is_some();
+            if ui.checkbox(&mut enabled, tr("Enable web remote")).changed() {
+                self.settings.remote_enabled = enabled;
+                self.settings.save();
+                self.set_remote_enabled(enabled);
+            }
+            ui.label(tr("Port:"));
+            let response = ui.add_enabled(
+                self.remote.is_none(),
+                egui::DragValue::new(&mut self.settings.remote_port).range(1024..=65535),
+            );
+            if response.changed() {
+                self.settings.save();
+            }
+        });
+        if let Some(remote) = &self.remote {
+            let url = remote.url();
+            ui.horizontal(|ui| {
+                ui.label(tr("Open on your phone:"));
+                ui.hyperlink(url);
+            });
+        }
+    }
+
+    fn devices_ui(&mut self, ui: &mut egui::Ui) {
+        // Device selection dropdown - can't centre this because it isn't an atomic widget 🤷
+        ui.horizontal(|ui| {
+            ui.label(tr("Input Device:"));
+            let current_input_device_index = self.recorder.input_index; // Store the current device index for later comparison
+            egui::ComboBox::from_id_source("Device") // Using an ID instead of a label
+                .selected_text(self.input_device().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (idx, device) in self.recorder.input_devices.iter().enumerate() {
+                        ui.selectable_value(&mut self.recorder.input_index, idx, device);
+                    }
+                });
+            // Check if the selected device has changed
+            if current_input_device_index != self.recorder.input_index {
+                self.settings.input_device = self.input_device().ok().map(str::to_owned);
+                self.settings.save();
+                // Start recording with new device, which also moves monitoring over to it
+                self.start_recording();
+            }
+            if ui
+                .button(tr("Rescan"))
+                .on_hover_text(tr("Look for devices plugged in since the app started"))
+                .clicked()
+            {
+                self.rescan_devices();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Input High-Pass:"));
+            let label = |hz: u32| match hz {
+                0 => tr("Off").to_owned(),
+                hz => format!("{} Hz", hz),
+            };
+            let mut changed = false;
+            egui::ComboBox::from_id_source("high_pass")
+                .selected_text(label(self.settings.capture_high_pass_hz))
+                .show_ui(ui, |ui| {
+                    for hz in HIGH_PASS_CHOICES {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.capture_high_pass_hz,
+                                hz,
+                                label(hz),
+                            )
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(tr("Filters out handling rumble and plosives before they're recorded"));
+            if changed {
+                self.recorder.high_pass_hz
+                    .store(self.settings.capture_high_pass_hz, Ordering::Relaxed);
+                self.settings.save();
+            }
+            if ui
+                .checkbox(&mut self.settings.dc_block, tr("Remove DC Offset"))
+                .on_hover_text(tr("For interfaces that add a constant bias, which eats headroom and upsets normalizing"))
+                .changed()
+            {
+                self.recorder.dc_block.store(self.settings.dc_block, Ordering::Relaxed);
+                self.settings.save();
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut changed = ui
+                .checkbox(&mut self.settings.capture_gate, tr("Noise Gate on Input"))
+                .on_hover_text(tr("Gate the input before it's buffered. Add a gate to the preset's processing to gate only what's saved instead."))
+                .changed();
+            ui.add_enabled_ui(self.settings.capture_gate, |ui| {
+                changed |= gate_settings_ui(ui, &mut self.settings.capture_gate_settings);
+            });
+            if changed {
+                self.recorder.capture_gate.set_enabled(self.settings.capture_gate);
+                self.recorder.capture_gate
+                    .set(self.settings.capture_gate_settings.into());
+                self.settings.save();
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut changed = ui
+                .checkbox(&mut self.settings.capture_limiter, tr("Limiter on Input"))
+                .on_hover_text(tr(
+                    "Holds sudden loud moments under the threshold so the capture doesn't clip",
+                ))
+                .changed();
+            let response = ui.add_enabled(
+                self.settings.capture_limiter,
+                egui::DragValue::new(&mut self.settings.capture_limiter_threshold_db)
+                    .range(-30.0..=0.0)
+                    .speed(0.1)
+                    .suffix(" dBFS"),
+            );
+            changed |= response.drag_stopped() || response.lost_focus();
+            let response = ui
+                .add_enabled(
+                    self.settings.capture_limiter,
+                    egui::DragValue::new(&mut self.settings.capture_limiter_release_ms)
+                        .range(10.0..=1000.0)
+                        .speed(1.0)
+                        .prefix(tr("release "))
+                        .suffix(" ms"),
+                )
+                .on_hover_text(tr(
+                    "How quickly the level comes back up after being held down",
+                ));
+            changed |= response.drag_stopped() || response.lost_focus();
+            if self.settings.capture_limiter {
+                limiter_indicator(ui, self.capture_reduction_db);
+            }
+            if changed {
+                self.recorder
+                    .capture_limiter
+                    .set_enabled(self.settings.capture_limiter);
+                self.recorder.capture_limiter.set([
+                    self.settings.capture_limiter_threshold_db,
+                    self.settings.capture_limiter_release_ms,
+                ]);
+                self.settings.save();
+            }
+        });
+
+        // Output Device Selection
+        ui.horizontal(|ui| {
+            ui.label(tr("Output Device:"));
+            let current_output_device_index = self.recorder.output_index;
+            egui::ComboBox::from_id_source("OutputDevice")
+                .selected_text(self.output_device().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (idx, device) in self.recorder.output_devices.iter().enumerate() {
+                        ui.selectable_value(&mut self.recorder.output_index, idx, device);
+                    }
+                });
+            if current_output_device_index != self.recorder.output_index {
+                self.settings.output_device = self.output_device().ok().map(str::to_owned);
+                self.settings.save();
+            }
+            // Carry on monitoring through the new device rather than the old one
+            if current_output_device_index != self.recorder.output_index
+                && self.recorder.is_monitoring()
+            {
+                println!(
+                    "Moving monitoring to {}",
+                    self.settings.output_device.as_deref().unwrap_or_default()
+                );
+                self.start_monitoring();
+            }
+        });
+
+        self.routing_ui(ui);
+
+        // Add a checkbox to enable/disable monitoring
+        let mut monitoring = self.recorder.is_monitoring();
+        if ui
+            .checkbox(&mut monitoring, tr("Enable Monitoring"))
+            .on_hover_text(self.shortcut_hint("monitor"))
+            .changed()
+        {
+            if monitoring {
+                self.start_monitoring();
+            } else {
+                self.stop_monitoring();
+            }
+        }
+    }
+
+    fn routing_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Monitor Routing:"));
+            egui::ComboBox::from_id_source("monitor_routing")
+                .selected_text(tr(self.settings.monitor_routing.label()))
+                .show_ui(ui, |ui| {
+                    for routing in MonitorRouting::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.monitor_routing,
+                                routing,
+                                tr(routing.label()),
+                            )
+                            .changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Monitor Gain:"));
+            // Applied live, no need to reopen the stream
+            if ui
+                .add(
+                    egui::Slider::new(&mut self.settings.monitor_gain_db, -30.0..=12.0)
+                        .suffix(" dB"),
+                )
+                .changed()
+            {
+                self.recorder.monitor_gain.store(
+                    dsp::db_to_gain(self.settings.monitor_gain_db).to_bits(),
+                    Ordering::Relaxed,
+                );
+                self.settings.save();
+            }
+        });
+        ui.horizontal(|ui| {
+            // Applied live like the gain, and only to what you hear
+            let eq = &mut self.settings.monitor_eq_settings;
+            let mut eq_changed = ui
+                .checkbox(&mut self.settings.monitor_eq, tr("Monitor EQ"))
+                .on_hover_text(tr("Hear how the sound would sit after EQ. Only the monitoring is affected, never what's recorded."))
+                .changed();
+            ui.add_enabled_ui(self.settings.monitor_eq, |ui| {
+                for (label, gain) in [
+                    ("Low", &mut eq.low_db),
+                    ("Mid", &mut eq.mid_db),
+                    ("High", &mut eq.high_db),
+                ] {
+                    ui.label(tr(label));
+                    eq_changed |= ui
+                        .add(
+                            egui::DragValue::new(gain)
+                                .range(-15.0..=15.0)
+                                .speed(0.1)
+                                .suffix(" dB"),
+                        )
+                        .changed();
+                }
+                if ui.small_button(tr("Flat")).clicked() {
+                    *eq = EqSettings::default();
+                    eq_changed = true;
+                }
+            });
+            if eq_changed {
+                self.recorder.monitor_eq.set_enabled(self.settings.monitor_eq);
+                self.recorder.monitor_eq.set(self.settings.monitor_eq_settings.into());
+                self.settings.save();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Monitor Resampler:"));
+            egui::ComboBox::from_id_source("monitor_resampler")
+                .selected_text(tr(self.settings.monitor_resampler.label()))
+                .show_ui(ui, |ui| {
+                    for resampler in MonitorResampler::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.monitor_resampler,
+                                resampler,
+                                tr(resampler.label()),
+                            )
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(tr("Converts to the output rate and follows drift between the two clocks, better ones add delay"));
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Monitor Latency:"));
+            let response = ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.monitor_latency_ms)
+                        .range(2.0..=200.0)
+                        .speed(0.5)
+                        .suffix(" ms"),
+                )
+                .on_hover_text(tr("Audio held back to ride out scheduling jitter, lower is tighter"));
+            // Reopening the stream on every step of a drag would stutter, so wait for the release
+            changed |= response.drag_stopped() || response.lost_focus();
+            changed |= ui
+                .checkbox(&mut self.settings.monitor_auto_latency, tr("Minimize automatically"))
+                .on_hover_text(
+                    tr("Start from this latency, lower it while playback stays clean and raise it after a dropout"),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.settings.monitor_limiter, tr("Headphone Limiter"))
+                .on_hover_text(tr(
+                    "Catches feedback and hot transients before they reach your ears",
+                ))
+                .changed();
+            ui.label(tr("Ceiling:"));
+            let response = ui.add_enabled(
+                self.settings.monitor_limiter,
+                egui::DragValue::new(&mut self.settings.monitor_limiter_ceiling_db)
+                    .range(-30.0..=0.0)
+                    .speed(0.1)
+                    .suffix(" dBFS"),
+            );
+            changed |= response.drag_stopped() || response.lost_focus();
+            if self.settings.monitor_limiter && self.recorder.is_monitoring() {
+                limiter_indicator(ui, self.monitor_reduction_db);
+            }
+        });
+        ui.horizontal(|ui| {
+            if self.round_trip.is_some() {
+                ui.spinner();
+                ui.label(tr("Listening for the clicks..."));
+                ui.ctx().request_repaint();
+            } else if ui
+                .button(tr("Measure Round-Trip Latency"))
+                .on_hover_text(
+                    tr("Plays a few clicks out of the output and times them coming back on the input. Connect an output to an input with a cable first, or put a mic by a speaker."),
+                )
+                .clicked()
+            {
+                self.start_latency_test();
+            }
+            if let Some(ms) = self.settings.round_trip_latency_ms {
+                ui.label(format!("{} {:.1} ms", tr("Last measured:"), ms));
+            }
+        });
+        if let Some(latency) = self.monitor_latency() {
+            let ms = [
+                latency.total_ms(),
+                latency.input_ms,
+                latency.fifo_ms,
+                latency.output_ms,
+                latency.resampler_ms,
+                latency.limiter_ms,
+            ]
+            .map(|ms| format!("{:.1}", ms));
+            ui.label(tr_fill(
+                "Measured: {} ms (input {} + FIFO {} + output {} + resampler {} + limiter {})",
+                &[&ms[0], &ms[1], &ms[2], &ms[3], &ms[4], &ms[5]],
+            ));
+            if self.settings.monitor_auto_latency {
+                ui.label(format!(
+                    "{} {:.1} ms",
+                    tr("FIFO target now"),
+                    latency.target_ms
+                ));
+            }
+        }
+        if self.settings.monitor_routing == MonitorRouting::Custom {
+            let outputs = match &self.recorder.output_config {
+                Some(config) => config.channels as usize,
+                None => self
+                    .output_device()
+                    .ok()
+                    .and_then(|device| self.recorder.backend.output_config(device, None).ok())
+                    .map_or(2, |config| config.channels as usize),
+            };
+            let inputs = self.recorder.config.channels as usize;
+            let routes = &mut self.settings.monitor_custom_routes;
+            if routes.len() < outputs {
+                // New outputs start out straight through
+                let start = routes.len();
+                routes.extend((start..outputs).map(
+                    |output| {
+                        if output < inputs {
+                            output + 1
+                        } else {
+                            0
+                        }
+                    },
+                ));
+            }
+            ui.horizontal_wrapped(|ui| {
+                for (output, input) in routes.iter_mut().take(outputs).enumerate() {
+                    let label = |input: usize| match input {
+                        0 => tr("Off").to_owned(),
+                        n => tr_fill("In {}", &[&n]),
+                    };
+                    egui::ComboBox::from_id_source(("monitor_route", output))
+                        .selected_text(tr_fill("Out {}: {}", &[&(output + 1), &label(*input)]))
+                        .show_ui(ui, |ui| {
+                            for choice in 0..=inputs {
+                                changed |=
+                                    ui.selectable_value(input, choice, label(choice)).changed();
+                            }
+                        });
+                }
+            });
+        }
+        if changed {
+            self.settings.save();
+            // The routing, resampler, latency and limiter are fixed when the output stream is built
+            if self.recorder.is_monitoring() {
+                self.start_monitoring();
+            }
+        }
+    }
+
+    fn meter_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Meters:"));
+            egui::ComboBox::from_id_source("meter_ballistics")
+                .selected_text(tr(self.settings.meter_ballistics.label()))
+                .show_ui(ui, |ui| {
+                    for ballistics in Ballistics::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.meter_ballistics,
+                                ballistics,
+                                tr(ballistics.label()),
+                            )
+                            .changed();
+                    }
+                });
+            ui.label(tr("Reference:"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.meter_reference_db)
+                        .range(-30.0..=0.0)
+                        .suffix(" dBFS"),
+                )
+                .on_hover_text(tr("The level your console reads as 0, e.g. -18 dBFS for EBU or -20 dBFS for SMPTE"))
+                .changed();
+            ui.label(tr("Peak hold:"));
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.meter_hold_seconds)
+                        .range(0.0..=10.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                )
+                .changed();
+        });
+        if changed {
+            self.apply_meter_settings();
+            self.settings.save();
+        }
+    }
+
+    fn display_ui(&mut self, ui: &mut egui::Ui) {
+        self.meter_settings_ui(ui);
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.settings.show_spectrogram, tr("Spectrogram"))
+                .changed()
+            {
+                self.settings.save();
+            }
+            if ui
+                .checkbox(&mut self.settings.show_spectrum, tr("Spectrum"))
+                .changed()
+            {
+                self.settings.save();
+            }
+        });
+        if self.settings.show_spectrum {
+            ui.horizontal(|ui| self.spectrum_settings_ui(ui));
+        }
+        ui.horizontal(|ui| {
+            let mut changed = ui
+                .checkbox(&mut self.settings.show_tuner, tr("Tuner"))
+                .changed();
+            ui.label("A4 =");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut self.settings.tuner_reference_hz)
+                        .range(415.0..=466.0)
+                        .suffix(" Hz"),
+                )
+                .changed();
+            if changed {
+                self.settings.save();
+            }
+        });
+    }
+
+    // Configuration lives in its own window, leaving the main panel for the meters, the
+    // waveform and grabbing
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.settings_open;
+        egui::Window::new(tr("Settings"))
+            .id(egui::Id::new("Settings"))
+            .open(&mut open)
+            .default_width(560.0)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.collapsing(tr("Devices & Monitoring"), |ui| {
+                    self.devices_ui(ui);
+                });
+                ui.collapsing(tr("Session"), |ui| {
+                    self.session_ui(ui);
+                });
+                ui.collapsing(tr("Save Folder"), |ui| {
+                    // File path selection button
+                    if ui.button(tr("Select Save Folder")).clicked() {
+                        self.open_file_dialog(); // Open the native file dialog
+                    }
+
+                    if let Some(path) = &self.save_path {
+                        ui.label(format!("{} {}", tr("Selected Folder:"), path));
+                    }
+                    self.post_save_ui(ui);
+                });
+                ui.collapsing(tr("Tracks"), |ui| {
+                    self.tracks_ui(ui);
+                });
+                ui.collapsing(tr("Display"), |ui| {
+                    self.display_ui(ui);
+                });
+                ui.collapsing(tr("Preset & Metadata"), |ui| {
+                    self.preset_ui(ui);
+                });
+
+                ui.collapsing(tr("Timecode"), |ui| {
+                    self.timecode_ui(ui);
+                });
+                ui.collapsing(tr("Grab Options"), |ui| {
+                    self.short_grab_settings_ui(ui);
+                    self.hit_capture_ui(ui);
+                    if ui
+                        .checkbox(
+                            &mut self.settings.review_before_save,
+                            tr("Review each grab before saving"),
+                        )
+                        .changed()
+                    {
+                        self.settings.save();
+                    }
+                    if ui
+                        .checkbox(
+                            &mut self.settings.name_prompt,
+                            tr("Ask for a name after each grab"),
+                        )
+                        .changed()
+                    {
+                        self.settings.save();
+                    }
+                    self.gamepad_ui(ui);
+                    if ui
+                        .checkbox(
+                            &mut self.settings.mono_check,
+                            tr("Warn about phase cancellation in stereo grabs"),
+                        )
+                        .changed()
+                    {
+                        self.settings.save();
+                    }
+                    ui.horizontal(|ui| {
+                        let mut changed = ui
+                            .checkbox(&mut self.settings.detect_tempo, tr("Detect the tempo of each grab"))
+                            .changed();
+                        ui.add_enabled_ui(self.settings.detect_tempo, |ui| {
+                            changed |= ui
+                                .checkbox(&mut self.settings.tempo_in_name, tr("in the file name"))
+                                .changed();
+                            changed |= ui
+                                .checkbox(&mut self.settings.round_to_bars, tr("Round to whole bars"))
+                                .on_hover_text(tr("Trim the start of the grab so it's a whole number of 4/4 bars long"))
+                                .changed();
+                        });
+                        if changed {
+                            self.settings.save();
+                        }
+                    });
+                    if ui
+                        .checkbox(&mut self.settings.detect_key, tr("Detect the key of each grab"))
+                        .on_hover_text(tr("Written to the metadata and shown in the take history, for melodic ideas"))
+                        .changed()
+                    {
+                        self.settings.save();
+                    }
+                    ui.horizontal(|ui| {
+                        let mut changed = ui
+                            .checkbox(&mut self.settings.tag_pitch, tr("Tag each take with its note"))
+                            .on_hover_text(
+                                tr("Written to the metadata for samplers that key map automatically"),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add_enabled(
+                                self.settings.tag_pitch,
+                                egui::Checkbox::new(
+                                    &mut self.settings.pitch_in_name,
+                                    tr("in the file name too"),
+                                ),
+                            )
+                            .changed();
+                        if changed {
+                            self.settings.save();
+                        }
+                    });
+                });
+
+                ui.collapsing(tr("DAW Hot Folder"), |ui| {
+                    ui.label(
+                        RichText::new(tr("Every saved grab is also dropped into this folder:"))
+                            .italics(),
+                    );
+                    self.hot_folder_ui(ui);
+                });
+
+                ui.collapsing(tr("Safety Recording"), |ui| {
+                    ui.label(
+                        RichText::new(tr(
+                            "Continuous segments in the save folder, separate from grabs",
+                        ))
+                        .italics(),
+                    );
+                    self.safety_ui(ui);
+                });
+
+                ui.collapsing(tr("Live Stream"), |ui| {
+                    ui.label(
+                        RichText::new(tr(
+                            "Opus to an Icecast server or an RTP listener, so others can listen in",
+                        ))
+                        .italics(),
+                    );
+                    self.broadcast_ui(ui);
+                });
+
+                ui.collapsing(tr("Web Remote"), |ui| {
+                    self.remote_ui(ui);
+                });
+
+                ui.collapsing(tr("Command Interface"), |ui| {
+                    self.control_ui(ui);
+                });
+
+                #[cfg(feature = "scripting")]
+                ui.collapsing(tr("Scripting"), |ui| {
+                    self.script_ui(ui);
+                });
+
+                ui.collapsing(tr("Appearance"), |ui| {
+                    self.appearance_ui(ui);
+                });
+
+                ui.collapsing(tr("Scheduled Grabs"), |ui| {
+                    ui.label(
+                        RichText::new(tr("Save the rolling buffer at a set time or interval:"))
+                            .italics(),
+                    );
+                    self.schedules_ui(ui);
+                });
+
+                ui.collapsing(tr("Diagnostics"), |ui| {
+                    self.diagnostics_ui(ui);
+                });
+            });
+        self.settings_open = open;
+    }
+
+    // Start/Stop Recording button
+    fn grab_button_ui(&mut self, ui: &mut egui::Ui) {
+        let record_button_text = if self.short_grab == Some(ShortGrab::Extending) {
+            tr("Extending...")
+        } else if self.recorder.state.is_grabbing() {
+            tr("Stop Grab")
+        } else {
+            tr("Start Grab")
+        };
+
+        if ui
+            .add_enabled(
+                self.short_grab.is_none(),
+                egui::Button::new(record_button_text).min_size([100.0, 40.0].into()),
+            )
+            .on_hover_text(self.shortcut_hint("grab"))
+            .clicked()
+        {
+            if self.recorder.state.is_grabbing() {
+                println!("Stop button clicked");
+            } else {
+                println!("Start grab button clicked");
+            }
+            self.toggle_grab();
+        }
+    }
+
+    // For a second launch: show the window wherever it is, over whatever is in front of it
+    pub fn bring_to_front(&self) {
+        let Some(ctx) = &self.repaint_ctx else {
+            return;
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+
+    // Shrink the window to the mini layout, or grow it back to the size it had before
+    fn set_mini_mode(&mut self, ctx: &egui::Context, mini: bool) {
+        if mini {
+            self.full_window_size = ctx.input(|i| i.viewport().inner_rect.map(|rect| rect.size()));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(MINI_WINDOW_SIZE.into()));
+        } else {
+            let size = self
+                .full_window_size
+                .take()
+                .or(self.settings.window_size.map(egui::Vec2::from))
+                .unwrap_or(FULL_WINDOW_SIZE.into());
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+        }
+        self.settings.mini_mode = mini;
+        self.settings.save();
+    }
+
+    fn set_always_on_top(&mut self, ctx: &egui::Context, on_top: bool) {
+        let level = if on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+        self.settings.always_on_top = on_top;
+        self.settings.save();
+    }
+
+    // Just the meters and the grab button, small enough to sit in a corner over a DAW
+    fn mini_ui(&mut self, ctx: &egui::Context) {
+        CentralPanel::default().show(ctx, |ui| {
+            self.meters.update(&self.recorder.meter_tap);
+            ui.horizontal(|ui| {
+                self.meters.ui(ui, ui.available_height());
+                ui.vertical(|ui| {
+                    self.grab_button_ui(ui);
+                    let status = {
+                        let buffer = self.recorder.buffer.lock().unwrap();
+                        let frames_per_second = self.recorder.config.channels.max(1) as f32
+                            * self.recorder.config.sample_rate.0 as f32;
+                        if self.recorder.state.is_grabbing() {
+                            let seconds = (buffer.total_written - buffer.static_start) as f32
+                                / frames_per_second;
+                            RichText::new(tr_fill("Grabbing +{} s", &[&format!("{:.1}", seconds)]))
+                                .color(Color32::from_rgb(230, 40, 40))
+                        } else {
+                            let seconds = buffer.current_size as f32 / frames_per_second;
+                            RichText::new(tr_fill("{} s buffered", &[&format!("{:.0}", seconds)]))
+                        }
+                    };
+                    ui.label(status);
+                    ui.horizontal(|ui| {
+                        if ui.small_button(tr("Expand")).clicked() {
+                            self.set_mini_mode(ctx, false);
+                        }
+                        let mut on_top = self.settings.always_on_top;
+                        if ui.checkbox(&mut on_top, tr("On top")).changed() {
+                            self.set_always_on_top(ctx, on_top);
+                        }
+                    });
+                });
+            });
+        });
+    }
+
+    fn countdown_ui(&mut self, ui: &mut egui::Ui) {
+        let now = Instant::now();
+        match (self.countdown_cue, self.countdown_capture_time()) {
+            (Some(cue), Some(capture)) => {
+                let status = if now < cue {
+                    format!("{} {}", tr("Get ready..."), (cue - now).as_secs() + 1)
+                } else {
+                    let seconds = format!("{:.1}", (capture - now).as_secs_f32());
+                    tr_fill("Play now! Saving in {} s", &[&seconds])
+                };
+                ui.label(RichText::new(status).size(24.0).strong());
+                if ui.button(tr("Cancel Delayed Grab")).clicked() {
+                    self.countdown_cue = None;
+                }
+            }
+            _ => {
+                ui.horizontal(|ui| {
+                    let hint = "Count down, then grab so the rolling window is centred on \
+                                what you play when the countdown ends";
+                    if ui
+                        .button(tr("Grab After Countdown"))
+                        .on_hover_text(hint)
+                        .clicked()
+                    {
+                        let delay =
+                            std::time::Duration::from_secs(self.settings.countdown_seconds as u64);
+                        self.countdown_cue = Some(now + delay);
+                    }
+                    let response = ui.add(
+                        egui::DragValue::new(&mut self.settings.countdown_seconds)
+                            .range(0..=60)
+                            .suffix(" s"),
+                    );
+                    if response.changed() {
+                        self.settings.save();
+                    }
+                });
+            }
+        }
+    }
+
+    fn appearance_ui(&mut self, ui: &mut egui::Ui) {
+        let mut on_top = self.settings.always_on_top;
+        if ui
+            .checkbox(&mut on_top, tr("Keep the window above other windows"))
+            .changed()
+        {
+            self.set_always_on_top(ui.ctx(), on_top);
+        }
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Theme:"));
+            egui::ComboBox::from_id_source("theme")
+                .selected_text(tr(self.settings.theme.label()))
+                .show_ui(ui, |ui| {
+                    for theme in Theme::ALL {
+                        changed |= ui
+                            .selectable_value(&mut self.settings.theme, theme, tr(theme.label()))
+                            .changed();
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Language:"));
+            egui::ComboBox::from_id_source("language")
+                .selected_text(self.settings.language.label())
+                .show_ui(ui, |ui| {
+                    for language in Language::ALL {
+                        if ui
+                            .selectable_value(
+                                &mut self.settings.language,
+                                language,
+                                language.label(),
+                            )
+                            .changed()
+                        {
+                            i18n::set_language(language);
+                            self.settings.save();
+                        }
+                    }
+                });
+        });
+
+        // Each color follows the theme unless a custom one is picked
+        let colors = [
+            (
+                "Custom accent",
+                &mut self.settings.accent_color,
+                [0, 120, 200],
+            ),
+            (
+                "Custom waveform",
+                &mut self.settings.waveform_color,
+                [90, 170, 255],
+            ),
+            (
+                "Custom background",
+                &mut self.settings.background_color,
+                [20, 20, 20],
+            ),
+        ];
+        for (label, color, default) in colors {
+            ui.horizontal(|ui| {
+                let mut custom = color.is_some();
+                if ui.checkbox(&mut custom, tr(label)).changed() {
+                    *color = custom.then_some(default);
+                    changed = true;
+                }
+                if let Some(rgb) = color {
+                    changed |= ui.color_edit_button_srgb(rgb).changed();
+                }
+            });
+        }
+
+        if changed {
+            theme::apply(ui.ctx(), &self.settings);
+            self.settings.save();
+        }
+    }
+
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button(tr("Copy Diagnostics"))
+                .on_hover_text(tr("Copy device and stream details for a bug report"))
+                .clicked()
+            {
+                let report = self.diagnostics_report();
+                ui.output_mut(|output| output.copied_text = report);
+            }
+            if ui.button(tr("Save Diagnostics...")).clicked() {
+                if let Some(path) = FileDialog::new()
+                    .set_file_name("rolling-sampler-diagnostics.txt")
+                    .save_file()
+                {
+                    match std::fs::write(&path, self.diagnostics_report()) {
+                        Ok(()) => self
+                            .toasts
+                            .note(format!("Diagnostics saved to {}", path.display())),
+                        Err(e) => self.toasts.error(RecorderError::Other(format!(
+                            "Failed to save diagnostics: {}",
+                            e
+                        ))),
+                    }
+                }
+            }
+        });
+    }
+
+    // One line summary of the engine and its health along the bottom of the window
+    fn status_bar_ui(&mut self, ui: &mut egui::Ui) {
+        let load = self.callback_load.update(&self.recorder.stream_stats);
+        self.recorder
+            .stream_stats
+            .monitor_limiter
+            .update_display(&mut self.monitor_reduction_db);
+        self.recorder
+            .stream_stats
+            .capture_limiter
+            .update_display(&mut self.capture_reduction_db);
+        ui.horizontal_wrapped(|ui| {
+            if let RecorderState::Error(e) = &self.recorder.state {
+                ui.colored_label(Color32::RED, tr("Input failed"))
+                    .on_hover_text(e);
+                ui.separator();
+            }
+            let input = self.input_device().unwrap_or(tr("(no input device)"));
+            ui.label(format!(
+                "{}  {} Hz  {} ch",
+                input, self.recorder.config.sample_rate.0, self.recorder.config.channels
+            ));
+            // Only worth mentioning from about -60 dBFS up
+            let offset = f32::from_bits(self.recorder.dc_offset.load(Ordering::Relaxed));
+            if offset.abs() > 0.001 {
+                let text = format!("DC {:+.2}%", offset * 100.0);
+                let hover = if self.settings.dc_block {
+                    tr("DC offset on the input, being removed")
+                } else {
+                    tr("DC offset on the input, turn on Remove DC Offset in Settings to take it out")
+                };
+                if self.settings.dc_block {
+                    ui.label(text).on_hover_text(hover);
+                } else {
+                    ui.colored_label(Color32::YELLOW, text).on_hover_text(hover);
+                }
+            }
+            if let Some(output) = &self.recorder.output_config {
+                let resampling = output.sample_rate != self.recorder.config.sample_rate;
+                ui.separator();
+                ui.label(format!(
+                    "{} {} Hz{}",
+                    tr("Monitoring at"),
+                    output.sample_rate.0,
+                    if resampling { tr(" (resampled)") } else { "" }
+                ));
+                if let Some(latency) = self.monitor_latency() {
+                    // Around 10 ms is where playing along starts to feel late
+                    let total = latency.total_ms();
+                    let color = if total > 15.0 {
+                        Color32::YELLOW
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.colored_label(color, format!("{:.1} ms", total))
+                        .on_hover_text(tr("Monitoring latency from input to output"));
+                }
+                if self.monitor_reduction_db > 0.5 {
+                    ui.colored_label(
+                        LIMITER_COLOR,
+                        format!("{} -{:.1} dB", tr("Limiting"), self.monitor_reduction_db),
+                    );
+                }
+            }
+            if self.capture_reduction_db > 0.5 {
+                ui.separator();
+                ui.colored_label(
+                    LIMITER_COLOR,
+                    format!("{} -{:.1} dB", tr("Input limiting"), self.capture_reduction_db),
+                )
+                .on_hover_text(tr(
+                    "The capture limiter is holding the input under its threshold",
+                ));
+            }
+            ui.separator();
+            let load_color = if load > 0.7 {
+                Color32::RED
+            } else {
+                ui.visuals().text_color()
+            };
+            ui.colored_label(load_color, format!("CPU {:.0}%", load * 100.0))
+                .on_hover_text(tr("Share of real time spent in the input callback"));
+
+            let xruns = self.recorder.stream_stats.input_xruns();
+            let underruns = self.recorder.stream_stats.monitor_underruns();
+            ui.separator();
+            let health = tr_fill("Xruns {}  Underruns {}", &[&xruns, &underruns]);
+            if xruns + underruns > 0 {
+                ui.colored_label(Color32::YELLOW, health);
+            } else {
+                ui.label(health);
+            }
+            if let Some(error) = self.recorder.stream_stats.last_error() {
+                ui.separator();
+                ui.colored_label(
+                    Color32::RED,
+                    tr_fill(
+                        "{} stream errors",
+                        &[&self.recorder.stream_stats.error_count()],
+                    ),
+                )
+                .on_hover_text(error);
+            }
+        });
+    }
+
+    fn open_file_dialog(&mut self) {
+        if let Some(path) = FileDialog::new().pick_folder() {
+            // Store the selected directory path
+            println!("Save directory selected: {}", path.display());
+            self.save_path = Some(path.display().to_string());
+            self.settings.save_path = self.save_path.clone();
+            self.settings.save();
+            if self.safety.is_some() {
+                self.set_safety_enabled(true);
+            }
+        }
+    }
+
+    fn arm_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let hint =
+                format!(
+                "{}\n{}",
+                tr("Keep the buffer empty until the input crosses the threshold, then start the grab at the hit"),
+                self.shortcut_hint("arm")
+            );
+            if ui
+                .checkbox(&mut self.arm_enabled, tr("Wait for signal"))
+                .on_hover_text(hint)
+                .changed()
+            {
+                self.sync_arming();
+            }
+
+            ui.label(tr("Threshold (dB):"));
+            let response = ui.add(egui::Slider::new(
+                &mut self.settings.arm_threshold_db,
+                -60.0..=0.0,
+            ));
+            if response.drag_stopped() {
+                self.sync_arming();
+                self.settings.save();
+            }
+        });
+
+        if self.recorder.buffer.lock().unwrap().is_armed() {
+            ui.label(RichText::new(tr("Armed - waiting for signal...")).italics());
+        }
+    }
+
+    fn vox_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.vox_enabled, tr("Voice activated (VOX)"))
+                .on_hover_text(format!(
+                    "{}\n{}",
+                    tr("Grab automatically while there is signal, one file per utterance"),
+                    self.shortcut_hint("vox")
+                ))
+                .changed();
+            ui.label(tr("Threshold (dB):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.vox_threshold_db,
+                    -60.0..=0.0,
+                ))
+                .drag_stopped();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Hang time (s):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.vox_hang_seconds,
+                    0.2..=10.0,
+                ))
+                .drag_stopped();
+            ui.label(tr("Pre-roll (s):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.vox_pre_roll_seconds,
+                    0.0..=2.0,
+                ))
+                .drag_stopped();
+        });
+
+        if changed {
+            self.settings.save();
+            // Straight away unless an utterance is being recorded
+            self.sync_arming();
+        }
+        if self.vox_enabled && !self.recorder.state.is_grabbing() {
+            ui.label(RichText::new(tr("VOX listening...")).italics());
+        }
+    }
+
+    fn short_grab_settings_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Minimum grab length (s):"));
+            changed |= ui
+                .add(egui::Slider::new(
+                    &mut self.settings.min_grab_seconds,
+                    0.0..=5.0,
+                ))
+                .drag_stopped();
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("When a grab is shorter:"));
+            egui::ComboBox::from_id_source("ShortGrabPolicy")
+                .selected_text(tr(self.settings.short_grab_policy.label()))
+                .show_ui(ui, |ui| {
+                    for policy in ShortGrabPolicy::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.short_grab_policy,
+                                policy,
+                                tr(policy.label()),
+                            )
+                            .changed();
+                    }
+                });
+        });
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    fn schedules_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove_index = None;
+        let buffer_seconds = self.settings.buffer_seconds;
+
+        for (idx, schedule) in self.settings.schedules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.checkbox(&mut schedule.enabled, "").changed();
+                egui::ComboBox::from_id_source(("ScheduleKind", idx))
+                    .width(80.0)
+                    .selected_text(match schedule.kind {
+                        ScheduleKind::At => tr("At"),
+                        ScheduleKind::Every => tr("Every"),
+                    })
+                    .show_ui(ui, |ui| {
+                        changed |= ui
+                            .selectable_value(&mut schedule.kind, ScheduleKind::At, tr("At"))
+                            .changed();
+                        changed |= ui
+                            .selectable_value(&mut schedule.kind, ScheduleKind::Every, tr("Every"))
+                            .changed();
+                    });
+                match schedule.kind {
+                    ScheduleKind::At => {
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut schedule.time)
+                                    .hint_text("HH:MM")
+                                    .desired_width(50.0),
+                            )
+                            .lost_focus();
+                    }
+                    ScheduleKind::Every => {
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut schedule.interval_minutes)
+                                    .range(1..=24 * 60)
+                                    .suffix(" min"),
+                            )
+                            .changed();
+                    }
+                }
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut schedule.capture_seconds)
+                            .range(0.0..=3600.0)
+                            .prefix(tr("last "))
+                            .suffix(" s"),
+                    )
+                    .on_hover_text(tr(
+                        "Seconds saved from the end of the buffer, 0 for all of it",
+                    ))
+                    .changed();
+
+                match self.recorder.scheduler.next_fire(idx) {
+                    Some(next) => ui.label(format!("{} {}", tr("next:"), next.format("%H:%M:%S"))),
+                    None => ui.label(tr("inactive")),
+                };
+                if ui.small_button("✖").clicked() {
+                    remove_index = Some(idx);
+                }
+            });
+            if let Err(e) = schedule.check(buffer_seconds) {
+                ui.colored_label(Color32::RED, e);
+            }
+        }
+        if let Some(idx) = remove_index {
+            self.settings.schedules.remove(idx);
+            changed = true;
+        }
+        if ui.button(tr("Add Schedule")).clicked() {
+            self.settings.schedules.push(Schedule::default());
+            changed = true;
+        }
+
+        if changed {
+            self.recorder
+                .scheduler
+                .set_schedules(self.settings.schedules.clone());
+            self.settings.save();
+        }
+    }
+
+    fn gamepad_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Gamepad/footswitch grab button:"));
+            if self.learning_gamepad_button {
+                ui.label(RichText::new(tr("press a button...")).italics());
+                if ui.button(tr("Cancel")).clicked() {
+                    self.learning_gamepad_button = false;
+                }
+            } else {
+                ui.label(
+                    self.settings
+                        .gamepad_grab_button
+                        .as_deref()
+                        .unwrap_or(tr("none")),
+                );
+                if ui.button(tr("Learn")).clicked() {
+                    self.learning_gamepad_button = true;
+                }
+                if self.settings.gamepad_grab_button.is_some() && ui.button(tr("Clear")).clicked() {
+                    self.settings.gamepad_grab_button = None;
+                    self.settings.save();
+                }
+            }
+        });
+    }
+
+    fn review_prompt(&mut self, ctx: &egui::Context) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        let take = &review.take;
+        let channels = take.channels.max(1) as usize;
+        let sample_rate = take.sample_rate as f64;
+        let frames = take.frames();
+        if review.playback.as_ref().is_some_and(|p| p.is_finished()) {
+            review.playback = None;
+        }
+
+        let mut play = false;
+        let mut seek_to = None;
+        let mut accept = false;
+        let mut discard = false;
+        let mut start_punch = false;
+        let mut undo_punch = false;
+        let mut settings_changed = false;
+        egui::Window::new(tr("Review Grab"))
+            .id(egui::Id::new("Review Grab"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("{} ({:.1} s)", take.name, take.duration_seconds()));
+                if !self.reviews_waiting.is_empty() {
+                    ui.label(tr_fill("{} more waiting", &[&self.reviews_waiting.len()]));
+                }
+
+                let step = (frames / 4000).max(1);
+                let points: Vec<[f64; 2]> = take
+                    .samples
+                    .chunks(channels)
+                    .enumerate()
+                    .step_by(step)
+                    .map(|(i, frame)| [i as f64 / sample_rate, frame[0] as f64])
+                    .collect();
+                let (start, end) = (
+                    review.trim.0 as f64 / sample_rate,
+                    review.trim.1 as f64 / sample_rate,
+                );
+                let playhead = review
+                    .playback
+                    .as_ref()
+                    .map(|p| p.position() as f64 / sample_rate);
+                let punch = review
+                    .punch
+                    .map(|(from, to)| (from as f64 / sample_rate, to as f64 / sample_rate));
+                let clicked = Plot::new("Review Plot")
+                    .width(500.0)
+                    .view_aspect(4.0)
+                    .show_axes([true, false])
+                    .show_grid(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        // Shade what the trim cuts off
+                        let cut = egui::Color32::from_rgba_unmultiplied(0, 0, 0, 120);
+                        let total = frames as f64 / sample_rate;
+                        for (from, to) in [(0.0, start), (end, total)] {
+                            plot_ui.polygon(
+                                Polygon::new(vec![
+                                    [from, -1.0],
+                                    [to, -1.0],
+                                    [to, 1.0],
+                                    [from, 1.0],
+                                ])
+                                .fill_color(cut)
+                                .stroke(egui::Stroke::NONE),
+                            );
+                        }
+                        if let Some((from, to)) = punch {
+                            plot_ui.polygon(
+                                Polygon::new(vec![[from, -1.0], [to, -1.0], [to, 1.0], [from, 1.0]])
+                                    .fill_color(egui::Color32::from_rgba_unmultiplied(200, 40, 40, 60))
+                                    .stroke(egui::Stroke::NONE),
+                            );
+                            plot_ui.vline(VLine::new(from).color(egui::Color32::RED));
+                            plot_ui.vline(VLine::new(to).color(egui::Color32::RED));
+                        }
+                        plot_ui.line(Line::new(PlotPoints::new(points)));
+                        plot_ui.vline(VLine::new(start).color(egui::Color32::YELLOW));
+                        plot_ui.vline(VLine::new(end).color(egui::Color32::YELLOW));
+                        if let Some(playhead) = playhead {
+                            plot_ui.vline(VLine::new(playhead).color(egui::Color32::WHITE));
+                        }
+                        // Click to play from there
+                        if plot_ui.response().clicked() {
+                            plot_ui.pointer_coordinate().map(|point| point.x)
+                        } else {
+                            None
+                        }
+                    })
+                    .inner;
+                if let Some(seconds) = clicked {
+                    let frame = ((seconds * sample_rate).max(0.0) as usize)
+                        .clamp(review.trim.0, review.trim.1);
+                    match &review.playback {
+                        Some(playback) => playback.seek(frame),
+                        None => seek_to = Some(frame),
+                    }
+                }
+
+                // Trim handles
+                let (mut start, mut end) = review.trim;
+                ui.horizontal(|ui| {
+                    ui.label(tr("Start:"));
+                    ui.add(
+                        egui::Slider::new(&mut start, 0..=frames)
+                            .custom_formatter(|frame, _| format_time(frame / sample_rate)),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("End:"));
+                    ui.add(
+                        egui::Slider::new(&mut end, 0..=frames)
+                            .custom_formatter(|frame, _| format_time(frame / sample_rate)),
+                    );
+                });
+                review.trim = (start.min(end), end.max(start));
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut review.as_loop, tr("Loop"))
+                        .on_hover_text(tr("Crossfade the end into the audio before the start so the part loops cleanly"));
+                    ui.add_enabled_ui(review.as_loop, |ui| {
+                        settings_changed |= ui
+                            .checkbox(
+                                &mut self.settings.loop_snap_zero_crossings,
+                                tr("Snap to zero crossings"),
+                            )
+                            .changed();
+                        ui.label(tr("Crossfade:"));
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.settings.loop_crossfade_ms)
+                                .range(0.0..=500.0)
+                                .suffix(" ms"),
+                        );
+                        settings_changed |= response.drag_stopped() || response.lost_focus();
+                    });
+                });
+                if review.as_loop && self.settings.loop_snap_zero_crossings {
+                    let snap = |frame| {
+                        let search = (sample_rate * LOOP_SNAP_SECONDS) as usize;
+                        dsp::nearest_zero_crossing(&take.samples, channels, frame, search)
+                    };
+                    review.trim = (snap(review.trim.0), snap(review.trim.1));
+                }
+
+                ui.horizontal(|ui| {
+                    let mut punch_on = review.punch.is_some();
+                    if ui
+                        .checkbox(&mut punch_on, tr("Punch"))
+                        .on_hover_text(tr("Replace a part of the take by playing it again, along to the rest of the take"))
+                        .changed()
+                    {
+                        // The middle third of what's kept, to be moved onto the mistake
+                        let third = (review.trim.1 - review.trim.0) / 3;
+                        review.punch =
+                            punch_on.then_some((review.trim.0 + third, review.trim.1 - third));
+                        review.punching = None;
+                    }
+                    if punch_on {
+                        ui.label(tr("Pre-roll:"));
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.settings.punch_pre_roll_seconds)
+                                .range(0.0..=10.0)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        );
+                        settings_changed |= response.drag_stopped() || response.lost_focus();
+                    }
+                });
+                if let Some((mut punch_in, mut punch_out)) = review.punch {
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Punch In:"));
+                        ui.add(
+                            egui::Slider::new(&mut punch_in, 0..=frames)
+                                .custom_formatter(|frame, _| format_time(frame / sample_rate)),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Punch Out:"));
+                        ui.add(
+                            egui::Slider::new(&mut punch_out, 0..=frames)
+                                .custom_formatter(|frame, _| format_time(frame / sample_rate)),
+                        );
+                    });
+                    review.punch = Some((punch_in.min(punch_out), punch_out.max(punch_in)));
+                    ui.horizontal(|ui| match &review.punching {
+                        Some((pass, _)) => {
+                            ui.add(
+                                egui::ProgressBar::new(pass.progress().min(1.0))
+                                    .desired_width(200.0)
+                                    .text(tr("Punching")),
+                            );
+                            if ui.button(tr("Cancel")).clicked() {
+                                review.punching = None;
+                            }
+                            ui.ctx().request_repaint();
+                        }
+                        None => {
+                            start_punch = ui.button(tr("Punch In")).clicked();
+                            if review.unpunched.is_some() {
+                                undo_punch = ui.button(tr("Undo Punch")).clicked();
+                            }
+                        }
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    let playing = review.playback.is_some();
+                    if ui
+                        .button(if playing { tr("Stop") } else { tr("Play") })
+                        .clicked()
+                    {
+                        play = !playing;
+                        review.playback = None;
+                        review.punching = None;
+                    }
+                    if ui.button(tr("Accept")).clicked() {
+                        accept = true;
+                    }
+                    if ui.button(tr("Discard")).clicked() {
+                        discard = true;
+                    }
+                });
+            });
+
+        if let (true, Some(review)) = (play || seek_to.is_some(), &self.review) {
+            let playback = self.recorder.play(
+                Arc::clone(&review.take.samples),
+                review.take.channels as usize,
+                review.take.sample_rate,
+                seek_to.unwrap_or(review.trim.0),
+                review.trim.1,
+            );
+            match playback {
+                Ok(playback) => {
+                    if let Some(review) = &mut self.review {
+                        review.playback = Some(playback);
+                    }
+                }
+                Err(e) => self.toasts.error(e.context("Failed to start playback")),
+            }
+        }
+        if settings_changed {
+            self.settings.save();
+        }
+        if start_punch {
+            self.start_punch();
+        }
+        if undo_punch {
+            if let Some(review) = &mut self.review {
+                if let Some(take) = review.unpunched.take() {
+                    review.take = take;
+                    println!("Punch undone");
+                }
+            }
+        }
+        if accept || discard {
+            let Some(review) = self.review.take() else {
+                return;
+            };
+            if accept {
+                println!("Grab accepted");
+                self.finish_take(review.kept(self.settings.loop_crossfade_ms));
+            } else {
+                println!("Grab discarded after review");
+            }
+            self.review = self.reviews_waiting.pop_front();
+        }
+    }
+
+    fn mono_check_prompt(&mut self, ctx: &egui::Context) {
+        let Some((take, check)) = &self.pending_mono_check else {
+            return;
+        };
+        let better = if check.better_channel == 0 {
+            tr("left")
+        } else {
+            tr("right")
+        };
+        let message = tr_fill(
+            "The stereo grab loses {} dB when summed to mono (correlation {}). The channels are probably out of phase.",
+            &[
+                &format!("{:.1}", check.mono_loss_db),
+                &format!("{:.2}", check.correlation),
+            ],
+        );
+        let file_name = take
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut choice = None;
+        egui::Window::new(tr("Mono Compatibility"))
+            .id(egui::Id::new("Mono Compatibility"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(file_name);
+                ui.label(message);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Save as is")).clicked() {
+                        choice = Some(MonoCheckChoice::SaveAsIs);
+                    }
+                    if ui.button(tr("Flip right polarity")).clicked() {
+                        choice = Some(MonoCheckChoice::FlipPolarity);
+                    }
+                    if ui.button(tr_fill("Save mono ({})", &[&better])).clicked() {
+                        choice = Some(MonoCheckChoice::Mono);
+                    }
+                    if ui.button(tr("Discard")).clicked() {
+                        choice = Some(MonoCheckChoice::Discard);
+                    }
+                });
+            });
+
+        let Some(choice) = choice else {
+            return;
+        };
+        let Some((mut take, check)) = self.pending_mono_check.take() else {
+            return;
+        };
+        match choice {
+            MonoCheckChoice::SaveAsIs => {}
+            MonoCheckChoice::FlipPolarity => take.flip_polarity(1),
+            MonoCheckChoice::Mono => take.keep_channel(check.better_channel),
+            MonoCheckChoice::Discard => {
+                println!("Grab discarded");
+                return;
+            }
+        }
+        self.name_take(take);
+    }
+
+    fn last_grab_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(last_grab) = &mut self.last_grab else {
+            ui.label(tr("No grabs yet."));
+            return;
+        };
+        let take = &last_grab.take;
+        let channels = take.channels.max(1) as usize;
+        let sample_rate = take.sample_rate as f64;
+
+        let loudness = match last_grab.loudness {
+            Some(lufs) => format!("{:.1} LUFS", lufs),
+            None => tr("loudness unmeasured").to_owned(),
+        };
+        ui.label(tr_fill(
+            "{} ({} s) - {} events - {}",
+            &[
+                &take.name,
+                &format!("{:.1}", take.duration_seconds()),
+                &last_grab.events.len(),
+                &loudness,
+            ],
+        ));
+
+        // Mono overview of the grab with the events shaded
+        let step = (take.frames() / 4000).max(1);
+        let points: Vec<[f64; 2]> = take
+            .samples
+            .chunks(channels)
+            .enumerate()
+            .step_by(step)
+            .map(|(i, frame)| [i as f64 / sample_rate, frame[0] as f64])
+            .collect();
+        let focus = last_grab.focus.take();
+        Plot::new("Last Grab Plot")
+            .view_aspect(6.0)
+            .show_axes([true, false])
+            .show_grid(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                for event in &last_grab.events {
+                    let (start, end) = (
+                        event.start as f64 / sample_rate,
+                        event.end as f64 / sample_rate,
+                    );
+                    plot_ui.polygon(
+                        Polygon::new(vec![[start, -1.0], [end, -1.0], [end, 1.0], [start, 1.0]])
+                            .fill_color(egui::Color32::from_rgba_unmultiplied(255, 200, 0, 30))
+                            .stroke(egui::Stroke::NONE),
+                    );
+                }
+                plot_ui.line(Line::new(PlotPoints::new(points)));
+                if let Some((start, end)) = focus {
+                    plot_ui.set_plot_bounds(PlotBounds::from_min_max([start, -1.0], [end, 1.0]));
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Event sensitivity (dB above floor):"));
+            let response = ui.add(egui::Slider::new(
+                &mut self.settings.event_sensitivity_db,
+                3.0..=40.0,
+            ));
+            if response.drag_stopped() {
+                last_grab.events = analysis::detect_events(
+                    &last_grab.take.samples,
+                    channels,
+                    last_grab.take.sample_rate,
+                    self.settings.event_sensitivity_db,
+                );
+                self.settings.save();
+            }
+        });
+
+        let mut export = None;
+        ScrollArea::vertical()
+            .id_source("Events")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (idx, event) in last_grab.events.iter().enumerate() {
+                    let (start, end) = (
+                        event.start as f64 / sample_rate,
+                        event.end as f64 / sample_rate,
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label(tr_fill(
+                            "#{} {} - {} ({} s, peak {} dB)",
+                            &[
+                                &(idx + 1),
+                                &format_time(start),
+                                &format_time(end),
+                                &format!("{:.2}", end - start),
+                                &format!("{:.1}", event.peak_db),
+                            ],
+                        ));
+                        if ui.small_button(tr("Show")).clicked() {
+                            // Leave a little context around the event
+                            let margin = (end - start) * 0.25;
+                            last_grab.focus = Some((start - margin, end + margin));
+                        }
+                        if ui.small_button(tr("Export")).clicked() {
+                            export = Some((idx, *event));
+                        }
+                    });
+                }
+            });
+        let mut slice = false;
+        ui.horizontal(|ui| {
+            if ui.button(tr("Show All")).clicked() {
+                last_grab.focus = Some((0.0, last_grab.take.duration_seconds()));
+            }
+            slice = ui
+                .button(tr("Slice to Hits"))
+                .on_hover_text(
+                    tr("Save every hit as its own trimmed one-shot in a subfolder, using the hit capture sensitivity and floor, with a kit patch mapping them from C1 up"),
+                )
+                .clicked();
+        });
+        let mut sliced = Vec::new();
+        if slice {
+            let take = &last_grab.take;
+            let slices = analysis::slice_hits(
+                &take.samples,
+                channels,
+                take.sample_rate,
+                self.settings.hit_sensitivity_db,
+                dsp::db_to_gain(self.settings.hit_floor_db),
+            );
+            sliced = slices
+                .iter()
+                .enumerate()
+                .map(|(index, &(start, end))| take.slice(start, end, index + 1))
+                .collect();
+            self.toasts.note(format!(
+                "Sliced {} hits into {}_slices",
+                sliced.len(),
+                take.name
+            ));
+        }
+
+        if let Some((idx, event)) = export {
+            let take =
+                last_grab
+                    .take
+                    .excerpt(event.start, event.end, &format!("event{:02}", idx + 1));
+            self.save_take(take);
+        }
+        self.write_slice_patches(&sliced);
+        for take in sliced {
+            self.save_take(take);
+        }
+    }
+
+    // Offer back what the last run saved from the buffer when it crashed
+    fn recovery_prompt(&mut self, ctx: &egui::Context) {
+        if self.recovered.is_empty() {
+            return;
+        }
+        egui::Window::new(tr("Recovered Audio"))
+            .id(egui::Id::new("Recovered Audio"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(tr(
+                    "The app closed unexpectedly. The rolling buffer was saved as:",
+                ));
+                for path in &self.recovered {
+                    ui.monospace(path.file_name().unwrap_or_default().to_string_lossy());
+                }
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Restore to save folder")).clicked() {
+                        match self.save_dir() {
+                            Ok(save_dir) => {
+                                for path in std::mem::take(&mut self.recovered) {
+                                    match recovery::restore(&path, &save_dir) {
+                                        Ok(target) => self
+                                            .toasts
+                                            .note(format!("Restored {}", target.display())),
+                                        Err(e) => self.toasts.error(RecorderError::Save(format!(
+                                            "Failed to restore {}: {}",
+                                            path.display(),
+                                            e
+                                        ))),
+                                    }
+                                }
+                            }
+                            Err(e) => self.toasts.error(e),
+                        }
+                    }
+                    if ui.button(tr("Discard")).clicked() {
+                        for path in std::mem::take(&mut self.recovered) {
+                            if let Err(e) = std::fs::remove_file(&path) {
+                                eprintln!("Failed to delete {}: {}", path.display(), e);
+                            }
+                        }
+                    }
+                });
+            });
+    }
+
+    fn short_grab_prompt(&mut self, ctx: &egui::Context) {
+        if self.short_grab != Some(ShortGrab::Prompting) {
+            return;
+        }
+        let captured_ms =
+            self.buffered_frames() as f32 * 1000.0 / self.recorder.config.sample_rate.0 as f32;
+
+        egui::Window::new(tr("Short Grab"))
+            .id(egui::Id::new("Short Grab"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(tr_fill(
+                    "Only {} ms of audio has been captured.",
+                    &[&format!("{:.0}", captured_ms)],
+                ));
+                ui.horizontal(|ui| {
+                    let seconds = format!("{:.1}", self.settings.min_grab_seconds);
+                    let extend_label = tr_fill("Wait until {} s", &[&seconds]);
+                    if ui.button(extend_label).clicked() {
+                        self.short_grab = Some(ShortGrab::Extending);
+                    }
+                    if ui.button(tr("Save anyway")).clicked() {
+                        self.short_grab = None;
+                        self.grab_recording();
+                    }
+                    if ui.button(tr("Discard")).clicked() {
+                        self.discard_grab();
+                    }
+                });
+            });
+    }
+
+    // Picking, adding and naming sessions. Switching goes through `switch_session`, since it
+    // brings the folder, settings and history along.
+    fn session_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(tr("Session:"));
+            let mut selected = self.settings.active_session;
+            egui::ComboBox::from_id_source("Session")
+                .selected_text(self.settings.session().name.clone())
+                .show_ui(ui, |ui| {
+                    for (idx, session) in self.settings.sessions.iter().enumerate() {
+                        ui.selectable_value(&mut selected, idx, &session.name);
+                    }
+                });
+            if ui.button(tr("New")).clicked() {
+                // Starts from the current settings, with its own numbering
+                self.settings.store_session();
+                let mut session = self.settings.session().clone();
+                session.name = self.free_session_name(usize::MAX, "Session");
+                session.take_counter = 0;
+                session.marker_count = 0;
+                self.settings.sessions.push(session);
+                selected = self.settings.sessions.len() - 1;
+            }
+            // Always keep at least one session around, and only drop ones that aren't in use
+            if self.settings.sessions.len() > 1 && ui.button(tr("Delete")).clicked() {
+                let removed = self.settings.active_session;
+                let other = if removed == 0 { 1 } else { 0 };
+                self.switch_session(other);
+                if self.settings.active_session == other {
+                    let name = self.settings.sessions.remove(removed).name;
+                    self.parked_sessions.remove(&name);
+                    if self.settings.active_session > removed {
+                        self.settings.active_session -= 1;
+                    }
+                    selected = self.settings.active_session;
+                    changed = true;
+                }
+            }
+            if selected != self.settings.active_session {
+                self.switch_session(selected);
+            }
+        });
+
+        let active = self.settings.active_session;
+        ui.horizontal(|ui| {
+            ui.label(tr("Name:"));
+            let name = &mut self.settings.sessions[active].name;
+            if ui.text_edit_singleline(name).lost_focus() {
+                changed = true;
+            }
+        });
+        if changed {
+            // Other sessions' history is kept by name, so names have to stay unique
+            let name = self.settings.session().name.trim().to_owned();
+            let name =
+                self.free_session_name(active, if name.is_empty() { "Session" } else { &name });
+            self.settings.session_mut().name = name;
+        }
+
+        let session = self.settings.session_mut();
+        ui.horizontal(|ui| {
+            ui.label(tr("Take names:"));
+            changed |= ui
+                .text_edit_singleline(&mut session.naming_template)
+                .on_hover_text(TEMPLATE_HELP)
+                .lost_focus();
+            ui.label(tr_fill("{} takes", &[&session.take_counter]));
+            if ui.small_button(tr("Reset")).clicked() {
+                session.take_counter = 0;
+                changed = true;
+            }
+        });
+        ui.label(
+            RichText::new(tr(
+                "Save folder, preset, buffer length and hot folder belong to the session",
+            ))
+            .italics(),
+        );
+
+        if changed {
+            self.settings.save();
+        }
+    }
+
+    // `base`, or `base` numbered if another session than `skip` already has that name
+    fn free_session_name(&self, skip: usize, base: &str) -> String {
+        let taken = |name: &str| {
+            self.settings
+                .sessions
+                .iter()
+                .enumerate()
+                .any(|(idx, session)| idx != skip && session.name == name)
+        };
+        let mut name = base.to_owned();
+        let mut count = 1;
+        while taken(&name) {
+            count += 1;
+            name = format!("{} {}", base, count);
+        }
+        name
+    }
+
+    fn preset_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Preset:"));
+            let previous_preset = self.settings.active_preset;
+            egui::ComboBox::from_id_source("Preset")
+                .selected_text(self.settings.active_preset().name.clone())
+                .show_ui(ui, |ui| {
+                    for (idx, preset) in self.settings.presets.iter().enumerate() {
+                        ui.selectable_value(&mut self.settings.active_preset, idx, &preset.name);
+                    }
+                });
+            if previous_preset != self.settings.active_preset {
+                // Binding indices belong to the old preset
+                self.learning_binding = None;
+                changed = true;
+            }
+
+            if ui.button(tr("New")).clicked() {
+                let preset = Preset {
+                    name: format!("Preset {}", self.settings.presets.len() + 1),
+                    ..Default::default()
+                };
+                self.settings.presets.push(preset);
+                self.settings.active_preset = self.settings.presets.len() - 1;
+                changed = true;
+            }
+            if ui
+                .button(tr("Add Performance"))
+                .on_hover_text(tr("Only Space to grab and Escape to discard"))
+                .clicked()
+            {
+                self.settings.presets.push(Preset::performance());
+                self.settings.active_preset = self.settings.presets.len() - 1;
+                changed = true;
+            }
+            if ui
+                .button(tr("Add Vinyl Sampling"))
+                .on_hover_text(tr(
+                    "De-click, gentle high-pass and normalization for turntables",
+                ))
+                .clicked()
+            {
+                self.settings.presets.push(Preset::vinyl_sampling());
+                self.settings.active_preset = self.settings.presets.len() - 1;
+                changed = true;
+            }
+            // Always keep at least one preset around
+            if self.settings.presets.len() > 1 && ui.button(tr("Delete")).clicked() {
+                self.settings.presets.remove(self.settings.active_preset);
+                self.settings.active_preset = 0;
+                changed = true;
+            }
+        });
+
+        let preset = &mut self.settings.presets[self.settings.active_preset];
+        ui.horizontal(|ui| {
+            ui.label(tr("Name:"));
+            changed |= ui.text_edit_singleline(&mut preset.name).lost_focus();
+        });
+
+        ui.label(
+            RichText::new(tr("Metadata written to every grab made with this preset:")).italics(),
+        );
+        let mut remove_index = None;
+        for (idx, field) in preset.metadata.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut field.key)
+                            .hint_text(tr("key"))
+                            .desired_width(120.0),
+                    )
+                    .lost_focus();
+                ui.label("=");
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut field.value).hint_text(tr("value")))
+                    .lost_focus();
+                if ui.small_button("✖").clicked() {
+                    remove_index = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_index {
+            preset.metadata.remove(idx);
+            changed = true;
+        }
+        if ui.button(tr("Add Field")).clicked() {
+            preset.metadata.push(MetadataField::default());
+            changed = true;
+        }
+
+        ui.label(
+            RichText::new(tr("Processing applied to each grab before it is saved:")).italics(),
+        );
+        let mut remove_index = None;
+        for (idx, stage) in preset.export_stages.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}.", idx + 1));
+                let slider = match stage {
+                    ExportStage::Gate(gate) => {
+                        changed |= gate_settings_ui(ui, gate);
+                        if ui.small_button("✖").clicked() {
+                            remove_index = Some(idx);
+                        }
+                        return;
+                    }
+                    ExportStage::Channels { mode } => {
+                        egui::ComboBox::from_id_source(("channel_mode", idx))
+                            .selected_text(tr(mode.label()))
+                            .show_ui(ui, |ui| {
+                                for choice in ChannelMode::ALL {
+                                    changed |= ui
+                                        .selectable_value(mode, choice, tr(choice.label()))
+                                        .changed();
+                                }
+                            });
+                        if ui.small_button("✖").clicked() {
+                            remove_index = Some(idx);
+                        }
+                        return;
+                    }
+                    ExportStage::Stretch {
+                        source_bpm,
+                        target_bpm,
+                    } => {
+                        ui.label(tr("Time-stretch from"));
+                        let response = ui.add(
+                            egui::DragValue::new(source_bpm)
+                                .range(0.0..=300.0)
+                                .speed(0.1)
+                                .custom_formatter(|bpm, _| {
+                                    if bpm > 0.0 {
+                                        format!("{:.1} BPM", bpm)
+                                    } else {
+                                        tr("detected").to_owned()
+                                    }
+                                }),
+                        );
+                        changed |= response.drag_stopped() || response.lost_focus();
+                        ui.label(tr("to"));
+                        let response = ui.add(
+                            egui::DragValue::new(target_bpm)
+                                .range(20.0..=300.0)
+                                .speed(0.1)
+                                .suffix(" BPM"),
+                        );
+                        changed |= response.drag_stopped() || response.lost_focus();
+                        if ui.small_button("✖").clicked() {
+                            remove_index = Some(idx);
+                        }
+                        return;
+                    }
+                    ExportStage::DeClick { strength } => {
+                        egui::Slider::new(strength, 0.0..=1.0).text(tr("De-click strength"))
+                    }
+                    ExportStage::HighPass { cutoff_hz } => {
+                        egui::Slider::new(cutoff_hz, 10.0..=200.0)
+                            .logarithmic(true)
+                            .suffix(" Hz")
+                            .text(tr("High-pass"))
+                    }
+                    ExportStage::Normalize { peak_db } => egui::Slider::new(peak_db, -24.0..=0.0)
+                        .suffix(" dBFS")
+                        .text(tr("Normalize peak")),
+                    ExportStage::Loudness { lufs } => egui::Slider::new(lufs, -36.0..=-6.0)
+                        .suffix(" LUFS")
+                        .text(tr("Loudness (put a Normalize after it to catch peaks)")),
+                };
+                changed |= ui.add(slider).drag_stopped();
+                if ui.small_button("✖").clicked() {
+                    remove_index = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_index {
+            preset.export_stages.remove(idx);
+            changed = true;
+        }
+        ui.horizontal(|ui| {
+            for stage in ExportStage::DEFAULTS {
+                if ui.button(format!("+ {}", tr(stage.name()))).clicked() {
+                    preset.export_stages.push(stage);
+                    changed = true;
+                }
+            }
+        });
+
+        ui.label(RichText::new(tr("Also render as extra files:")).italics());
+        ui.horizontal(|ui| {
+            for variant in ExportVariant::DEFAULTS {
+                let mut enabled = preset.export_variants.contains(&variant);
+                let label = format!("{} (_{})", tr(&variant.name()), variant.suffix());
+                if ui.checkbox(&mut enabled, label).changed() {
+                    if enabled {
+                        preset.export_variants.push(variant);
+                    } else {
+                        preset.export_variants.retain(|v| *v != variant);
+                    }
+                    changed = true;
+                }
+            }
+        });
+
+        ui.label(RichText::new(tr("Controls while this preset is active:")).italics());
+        let mut remove_index = None;
+        let mut learn_index = None;
+        for (idx, binding) in preset.bindings.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let learning = self.learning_binding == Some(idx);
+                if learning {
+                    ui.label(RichText::new(tr("Press a key, button or pad...")).italics());
+                } else {
+                    ui.label(binding.trigger.label());
+                }
+                if ui
+                    .small_button(if learning { tr("Cancel") } else { tr("Learn") })
+                    .clicked()
+                {
+                    learn_index = Some(idx);
+                }
+                ui.label(tr("runs"));
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut binding.command)
+                            .hint_text(tr("command"))
+                            .desired_width(160.0),
+                    )
+                    .on_hover_text(control::HELP)
+                    .lost_focus();
+                if ui.small_button("✖").clicked() {
+                    remove_index = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = remove_index {
+            preset.bindings.remove(idx);
+            self.learning_binding = None;
+            changed = true;
+        }
+        ui.horizontal(|ui| {
+            if ui.button(tr("Add Binding")).clicked() {
+                preset
+                    .bindings
+                    .push(Binding::new(Trigger::Key(String::new()), "grab"));
+                learn_index = Some(preset.bindings.len() - 1);
+                changed = true;
+            }
+            if ui
+                .button(tr("Default Shortcuts"))
+                .on_hover_text(tr(
+                    "Replace these bindings with the default keyboard shortcuts",
+                ))
+                .clicked()
+            {
+                preset.bindings = Binding::defaults();
+                self.learning_binding = None;
+                changed = true;
+            }
+        });
+        if let Some(idx) = learn_index {
+            self.learning_binding = if self.learning_binding == Some(idx) {
+                None
+            } else {
+                Some(idx)
+            };
+        }
+
+        if changed {
+            self.settings.save();
+        }
+    }
+}
+
+impl App for Sampler {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        // Repaint the UI to update the plot
+        ctx.request_repaint_after(std::time::Duration::from_millis(33));
+
+        self.poll_auto_grab();
+        self.poll_short_grab();
+        self.poll_schedules();
+        self.poll_gamepad();
+        self.poll_remote();
+        self.poll_commands();
+        self.poll_countdown();
+        self.poll_timecode();
+        self.poll_hits();
+        self.poll_markers();
+        self.poll_midi();
+        self.poll_keys(ctx);
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.remember_window(ctx);
+            self.on_close();
+        }
+        let signal = self.quit_signal.load(Ordering::SeqCst);
+        if signal != 0 {
+            println!("Quitting on signal {}", signal);
+            self.remember_window(ctx);
+            self.on_close(); // Waits for the take writers
+                             // and lets the safety recording and stream finish their files
+            self.set_safety_enabled(false);
+            self.set_broadcast_enabled(false);
+            std::process::exit(128 + signal);
+        }
+        self.poll_vox();
+        #[cfg(feature = "scripting")]
+        self.poll_script();
+        self.poll_auto_sampler();
+        self.poll_latency_test();
+        self.poll_overdub();
+        self.poll_punch();
+        self.recovery_prompt(ctx);
+        self.short_grab_prompt(ctx);
+        self.mono_check_prompt(ctx);
+        self.name_prompt(ctx);
+        self.review_prompt(ctx);
+        self.library_window(ctx);
+        self.toasts.ui(ctx);
+        if self
+            .scrub
+            .as_ref()
+            .is_some_and(|(_, playback)| playback.is_finished())
+        {
+            self.scrub = None;
+        }
+
+        if self.settings.mini_mode {
+            self.mini_ui(ctx);
+            return;
+        }
+        self.settings_window(ctx);
+
+        egui::TopBottomPanel::bottom("Status Bar").show(ctx, |ui| {
+            self.status_bar_ui(ui);
+        });
+
+        CentralPanel::default().show(ctx, |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(10.0); // Add some space at the top
+
+                ui.vertical_centered(|ui| {
+                    // ui.add_space(10.0); // Add some space at the top
+                    let panel_width = ui.available_width();
+
+                    // Center the contents inside the horizontal layout
+                    ui.vertical_centered(|ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            if ui.button(tr("Settings")).clicked() {
+                                self.settings_open = !self.settings_open;
+                            }
+                            if ui
+                                .button(tr("Mini Mode"))
+                                .on_hover_text(tr("Shrink to just the meters and the grab button"))
+                                .clicked()
+                            {
+                                self.set_mini_mode(ctx, true);
+                            }
+                        });
+                        match &self.save_path {
+                            Some(path) => {
+                                ui.label(
+                                    RichText::new(format!("{} {}", tr("Saving to"), path)).small(),
+                                );
+                            }
+                            None => {
+                                ui.colored_label(
+                                    Color32::YELLOW,
+                                    tr("Pick a save folder in Settings before grabbing"),
+                                );
+                            }
+                        }
+
+                        self.waveform_options_ui(ui);
+
+                        // Plot the waveform
+                        if let Ok(buffer) = self.recorder.buffer.lock() {
+                            let channels = self.recorder.config.channels.max(1) as usize;
+                            self.plot_cache.update(&buffer, channels);
+                            // x is in seconds from the start of the history, so the axis reads
+                            // as a time ruler
+                            let sample_rate = self.recorder.config.sample_rate.0 as f64;
+                            // A single channel is always shown, as there is no toggle for it
+                            let visible: Vec<usize> = (0..channels)
+                                .filter(|channel| {
+                                    channels == 1
+                                        || !self.settings.hidden_channels.contains(channel)
+                                })
+                                .collect();
+                            let overlay = self.settings.waveform_overlay;
+                            let floor_db =
+                                self.settings.waveform_db_scale.then_some(WAVEFORM_FLOOR_DB);
+                            // Stacked channels each get a lane two units high, first at the top
+                            let lanes = if overlay { 1 } else { visible.len().max(1) };
+                            let lane_offset = |lane: usize| {
+                                if overlay {
+                                    0.0
+                                } else {
+                                    2.0 * (lanes - 1 - lane) as f64
+                                }
+                            };
+
+                            // Zoom and pan only once the buffer has stopped moving, and go back
+                            // to the whole history whenever the mode changes
+                            let zoomable = buffer.is_static_mode;
+                            let reset_view = zoomable != self.plot_was_static;
+                            self.plot_was_static = zoomable;
+                            // While the buffer is still filling, the audio sits at the right and
+                            // the part of the history it can't reach yet is grayed out
+                            let history_seconds =
+                                buffer.max_size as f64 / channels as f64 / sample_rate;
+                            let unfilled = if zoomable {
+                                0.0
+                            } else {
+                                buffer.max_size.saturating_sub(buffer.current_size) as f64
+                                    / channels as f64
+                                    / sample_rate
+                            };
+                            let coordinates = if zoomable {
+                                CoordinatesFormatter::new(|point, _| format!("{:.3} s", point.x))
+                            } else {
+                                CoordinatesFormatter::new(|_, _| String::new())
+                            };
+
+                            // Plot the waveform with the level meters alongside
+                            self.meters.update(&self.recorder.meter_tap);
+                            let spacing = ui.spacing().item_spacing.x;
+                            let mut meters_width = self.meters.width() + spacing;
+                            let monitoring = self.recorder.is_monitoring();
+                            if monitoring {
+                                self.monitor_meters.update(&self.recorder.monitor_meter_tap);
+                                meters_width += self.monitor_meters.width() + 2.0 * spacing;
+                            }
+                            let plot_width = ui.available_width() - meters_width;
+                            // The waveform takes whatever height the controls leave
+                            let plot_height = (ctx.screen_rect().height() - CONTROLS_HEIGHT)
+                                .clamp(MIN_WAVEFORM_HEIGHT, plot_width.max(MIN_WAVEFORM_HEIGHT));
+                            ui.horizontal(|ui| {
+                                let mut plot = Plot::new("Rolling Waveform Plot")
+                                    .width(plot_width)
+                                    .height(plot_height)
+                                    .auto_bounds(Vec2b::new(true, false))
+                                    .show_axes([zoomable, false])
+                                    .show_grid([zoomable, false])
+                                    .show_background(false)
+                                    .allow_zoom([zoomable, false])
+                                    .allow_drag([zoomable, false])
+                                    .allow_scroll([zoomable, false])
+                                    .sharp_grid_lines(true)
+                                    .label_formatter(|_, point| format!("{:.3} s", point.x))
+                                    .coordinates_formatter(Corner::LeftBottom, coordinates);
+                                if !overlay {
+                                    // Fixed full scale lanes so the channels don't overlap
+                                    plot = plot.include_y(-1.0).include_y(2.0 * lanes as f64 - 1.0);
+                                }
+                                if !zoomable {
+                                    plot = plot.include_x(0.0).include_x(history_seconds);
+                                }
+                                if reset_view {
+                                    plot = plot.reset();
+                                }
+                                let ui_text_color = ui.visuals().strong_text_color();
+                                let clicked_marker = plot.show(ui, |plot_ui: &mut PlotUi| {
+                                    if unfilled > 0.0 {
+                                        let top = 2.0 * lanes as f64 - 1.0;
+                                        plot_ui.polygon(
+                                            Polygon::new(vec![
+                                                [0.0, -1.0],
+                                                [unfilled, -1.0],
+                                                [unfilled, top],
+                                                [0.0, top],
+                                            ])
+                                            .fill_color(
+                                                Color32::from_gray(128).gamma_multiply(0.15),
+                                            )
+                                            .stroke(egui::Stroke::NONE),
+                                        );
+                                    }
+                                    let bounds = plot_ui.plot_bounds();
+                                    let frames = buffer.current_size as u64 / channels as u64;
+                                    let first = (bounds.min()[0] * sample_rate).max(0.0) as u64;
+                                    let last = ((bounds.max()[0] * sample_rate).max(0.0) as u64)
+                                        .min(frames);
+                                    // Zoomed in far enough to draw every sample
+                                    let detailed = zoomable
+                                        && first < last
+                                        && last - first <= MAX_DETAIL_FRAMES;
+                                    let start = buffer.window_start() / channels as u64;
+                                    for (lane, &channel) in visible.iter().enumerate() {
+                                        let offset = lane_offset(lane);
+                                        let points = if detailed {
+                                            (first..last)
+                                                .map(|frame| {
+                                                    let index = (start + frame) * channels as u64
+                                                        + channel as u64;
+                                                    [
+                                                        frame as f64 / sample_rate,
+                                                        buffer.sample_at(index) as f64,
+                                                    ]
+                                                })
+                                                .collect()
+                                        } else {
+                                            self.plot_cache.plot_points(
+                                                &buffer,
+                                                channel,
+                                                sample_rate,
+                                            )
+                                        };
+                                        let points = points
+                                            .into_iter()
+                                            .map(|[x, y]| {
+                                                [x + unfilled, waveform_level(y, floor_db) + offset]
+                                            })
+                                            .collect();
+                                        plot_ui.line(
+                                            Line::new(PlotPoints::new(points))
+                                                .color(channel_color(&self.settings, channel))
+                                                .name(format!("{} {}", tr("Ch"), channel + 1)),
+                                        );
+                                    }
+
+                                    // Positions of absolute sample indices and wall clock times
+                                    let top = if overlay {
+                                        1.0
+                                    } else {
+                                        2.0 * lanes as f64 - 1.0
+                                    };
+                                    let window_start = buffer.window_start();
+                                    let x_of_index = |index: u64| {
+                                        index.saturating_sub(window_start) as f64
+                                            / channels as f64
+                                            / sample_rate
+                                            + unfilled
+                                    };
+                                    let newest = x_of_index(buffer.total_written);
+                                    let now = Local::now();
+                                    let x_of_time = |time: DateTime<Local>| {
+                                        newest - (now - time).num_milliseconds() as f64 / 1000.0
+                                    };
+
+                                    for clip in &self.meters.clip_log {
+                                        let x = x_of_time(clip.time);
+                                        if x >= unfilled {
+                                            plot_ui.vline(
+                                                VLine::new(x)
+                                                    .color(Color32::RED.gamma_multiply(0.6))
+                                                    .width(1.0),
+                                            );
+                                        }
+                                    }
+
+                                    // The selected export range shaded between its markers
+                                    let (from, to) = self.marker_range;
+                                    let range =
+                                        self.recorder.marker(from).zip(self.recorder.marker(to));
+                                    if let Some((from, to)) = range {
+                                        let (a, b) = (x_of_index(from.index), x_of_index(to.index));
+                                        plot_ui.polygon(
+                                            Polygon::new(vec![
+                                                [a, -1.0],
+                                                [b, -1.0],
+                                                [b, top],
+                                                [a, top],
+                                            ])
+                                            .fill_color(Color32::from_rgba_unmultiplied(
+                                                255, 200, 0, 25,
+                                            ))
+                                            .stroke(egui::Stroke::NONE),
+                                        );
+                                    }
+
+                                    let marker_color = Color32::from_rgb(255, 200, 0);
+                                    for marker in self.recorder.markers.iter().filter(|marker| {
+                                        marker.index >= window_start
+                                            && marker.index <= buffer.total_written
+                                    }) {
+                                        let x = x_of_index(marker.index);
+                                        plot_ui.vline(VLine::new(x).color(marker_color).width(1.5));
+                                        plot_ui.text(
+                                            Text::new(
+                                                PlotPoint::new(x, top),
+                                                format!(" M{}", marker.number),
+                                            )
+                                            .color(marker_color)
+                                            .anchor(egui::Align2::LEFT_TOP),
+                                        );
+                                    }
+
+                                    if let Some((first, playback)) = &self.scrub {
+                                        let index =
+                                            first + playback.position() as u64 * channels as u64;
+                                        if index >= window_start {
+                                            plot_ui.vline(
+                                                VLine::new(x_of_index(index))
+                                                    .color(ui_text_color)
+                                                    .width(1.5),
+                                            );
+                                        }
+                                    }
+
+                                    // Clicking a marker makes it the start of the export range,
+                                    // right clicking makes it the end
+                                    let response = plot_ui.response();
+                                    let (clicked, secondary) =
+                                        (response.clicked(), response.secondary_clicked());
+                                    let marker = response
+                                        .interact_pointer_pos()
+                                        .filter(|_| clicked || secondary)
+                                        .and_then(|pointer| {
+                                            self.recorder
+                                                .markers
+                                                .iter()
+                                                .map(|marker| {
+                                                    let x = plot_ui
+                                                        .screen_from_plot(PlotPoint::new(
+                                                            x_of_index(marker.index),
+                                                            0.0,
+                                                        ))
+                                                        .x;
+                                                    (marker.number, (x - pointer.x).abs())
+                                                })
+                                                .filter(|(_, distance)| {
+                                                    *distance <= MARKER_CLICK_DISTANCE
+                                                })
+                                                .min_by(|a, b| a.1.total_cmp(&b.1))
+                                                .map(|(number, _)| (number, secondary))
+                                        });
+
+                                    // Anywhere else on the rolling history plays from there, and
+                                    // dragging scrubs
+                                    let started = clicked || response.drag_started();
+                                    let scrub = if zoomable || marker.is_some() {
+                                        None
+                                    } else if started || response.dragged() {
+                                        plot_ui.pointer_coordinate().map(|point| {
+                                            let seconds = (point.x - unfilled).max(0.0);
+                                            (
+                                                window_start / channels as u64
+                                                    + (seconds * sample_rate) as u64,
+                                                started,
+                                            )
+                                        })
+                                    } else {
+                                        None
+                                    };
+                                    (marker, scrub)
+                                });
+                                match clicked_marker.inner.0 {
+                                    Some((number, false)) => self.marker_range.0 = number,
+                                    Some((number, true)) => self.marker_range.1 = number,
+                                    None => {}
+                                }
+                                match (clicked_marker.inner.1, &self.scrub) {
+                                    (Some((frame, false)), Some((first, playback))) => {
+                                        playback
+                                            .seek(frame.saturating_sub(first / channels as u64)
+                                                as usize);
+                                    }
+                                    (Some((frame, _)), _) => {
+                                        // Play a copy of the history, as the buffer keeps rolling
+                                        let first = buffer.window_start();
+                                        let samples =
+                                            buffer.copy_range(first, buffer.total_written);
+                                        let start = frame.saturating_sub(first / channels as u64);
+                                        let frames = samples.len() / channels;
+                                        match self.recorder.play(
+                                            Arc::new(samples),
+                                            channels,
+                                            self.recorder.config.sample_rate.0,
+                                            start as usize,
+                                            frames,
+                                        ) {
+                                            Ok(playback) => self.scrub = Some((first, playback)),
+                                            Err(e) => self
+                                                .toasts
+                                                .error(e.context("Failed to start playback")),
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                                // Right click away from a marker stops it
+                                if clicked_marker.response.secondary_clicked()
+                                    && clicked_marker.inner.0.is_none()
+                                {
+                                    self.scrub = None;
+                                }
+                                if zoomable {
+                                    let post_roll = (buffer.total_written - buffer.static_start)
+                                        as f64
+                                        / channels as f64
+                                        / sample_rate;
+                                    grabbing_overlay(ui, clicked_marker.response.rect, post_roll);
+                                }
+                                self.meters.ui(ui, plot_height);
+                                if monitoring {
+                                    // Set apart from the input meters
+                                    ui.add_space(spacing);
+                                    self.monitor_meters.ui(ui, plot_height);
+                                }
+                            });
+                            if self.settings.show_spectrogram {
+                                self.spectrogram.update(
+                                    &buffer,
+                                    self.recorder.config.channels as usize,
+                                    self.recorder.config.sample_rate.0,
+                                );
+                                ui.horizontal(|ui| {
+                                    self.spectrogram
+                                        .ui(ui, egui::vec2(plot_width, plot_width / 6.0));
+                                });
+                            }
+                            if self.settings.show_spectrum {
+                                self.spectrum.update(
+                                    &buffer,
+                                    self.recorder.config.channels as usize,
+                                    self.recorder.config.sample_rate.0,
+                                    self.settings.spectrum_averaging,
+                                );
+                                ui.horizontal(|ui| {
+                                    self.spectrum.ui(
+                                        ui,
+                                        plot_width,
+                                        self.settings.spectrum_peak_hold,
+                                    );
+                                });
+                            }
+                            if self.settings.show_tuner {
+                                self.tuner.update(
+                                    &buffer,
+                                    self.recorder.config.channels as usize,
+                                    self.recorder.config.sample_rate.0,
+                                );
+                                self.tuner
+                                    .ui(ui, plot_width, self.settings.tuner_reference_hz);
+                            }
+                        }
+
+                        ui.label(
+                            RichText::new(tr(
+                                "Choose how much past audio to include in the recording:",
+                            ))
+                            .italics(),
+                        );
+
+                        // Leaves room for the label and value on narrow windows
+                        let desired_width = (panel_width * 0.8).min(panel_width - 200.0).max(60.0);
+                        ui.style_mut().spacing.slider_width = desired_width;
+
+                        let max_buffer_seconds = 60.0; // Maximum 60 seconds for the slider
+                        let mut new_buffer_size_seconds = self.settings.buffer_seconds;
+
+                        ui.horizontal(|ui| {
+                            ui.label(tr("Buffer Size (s):")); // Text label before the slider
+                            let response = ui.add(egui::Slider::new(
+                                &mut new_buffer_size_seconds,
+                                1.0..=max_buffer_seconds,
+                            ));
+
+                            if response.drag_stopped()
+                                && new_buffer_size_seconds != self.settings.buffer_seconds
+                            {
+                                self.set_buffer_seconds(new_buffer_size_seconds);
+                            }
+                        });
+
+                        ui.add_space(20.0); // Add some space between the slider and the button
+
+                        ui.collapsing(tr("Markers"), |ui| {
+                            self.markers_ui(ui);
+                        });
+
+                        #[cfg(feature = "link")]
+                        ui.collapsing(tr("Ableton Link"), |ui| {
+                            self.link_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Metronome"), |ui| {
+                            self.metronome_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Overdub"), |ui| {
+                            self.overdub_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Auto-Sampler"), |ui| {
+                            self.auto_sampler_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Clip Log"), |ui| {
+                            self.clip_log_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Take History"), |ui| {
+                            self.history_ui(ui);
+                        });
+
+                        ui.collapsing(tr("A/B Compare"), |ui| {
+                            self.ab_compare_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Last Grab & Events"), |ui| {
+                            self.last_grab_ui(ui);
+                        });
+
+                        ui.collapsing(tr("Session Report"), |ui| {
+                            ui.label(
+                                RichText::new(tr(
+                                    "An HTML page listing every grab, saved in the save folder:",
+                                ))
+                                .italics(),
+                            );
+                            self.session_report_ui(ui);
+                        });
+
+                        self.arm_ui(ui);
+                        self.vox_ui(ui);
+
+                        ui.add_space(20.0); // Add some space between the path selector and the button
+                        self.grab_button_ui(ui);
+
+                        ui.add_space(10.0);
+                        self.countdown_ui(ui);
+                    });
+                });
+            });
+        });
+    }
+}
+
+// A take's waveform thumbnail in the theme's accent color, returning where it was clicked as a
+// fraction of its width
+fn thumbnail_ui(
+    ui: &mut egui::Ui,
+    thumbnail: &egui::TextureHandle,
+    playhead: Option<f32>,
+) -> Option<f32> {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(120.0, 24.0), egui::Sense::click());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    painter.image(
+        thumbnail.id(),
+        rect,
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+        ui.visuals().selection.bg_fill,
+    );
+    if let Some(playhead) = playhead {
+        painter.vline(
+            rect.left() + playhead.clamp(0.0, 1.0) * rect.width(),
+            rect.y_range(),
+            egui::Stroke::new(1.5, ui.visuals().strong_text_color()),
+        );
+    }
+    response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked())
+        .map(|pos| (pos.x - rect.left()) / rect.width())
+}
+
+fn take_filter_ui(ui: &mut egui::Ui, filter: &mut TakeFilter) {
+    ui.label(tr("Search:"));
+    ui.add(egui::TextEdit::singleline(&mut filter.search).desired_width(140.0))
+        .on_hover_text(tr("Words in the name, tags or metadata, or #tag for a tag"));
+    egui::ComboBox::from_id_source(ui.id().with("Min rating"))
+        .selected_text(match filter.min_rating {
+            0 => "Any rating".to_owned(),
+            stars => format!("{}+", "★".repeat(stars as usize)),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter.min_rating, 0, tr("Any rating"));
+            for stars in 1..=5u8 {
+                ui.selectable_value(
+                    &mut filter.min_rating,
+                    stars,
+                    format!("{}+", "★".repeat(stars as usize)),
+                );
+            }
+        });
+}
+
+// Stars to click and the tags, which open in place for editing. Returns what was changed.
+fn rating_and_tags_ui(
+    ui: &mut egui::Ui,
+    item: &HistoryItem,
+    editing: &mut Option<(PathBuf, String)>,
+) -> Option<TakeEdit> {
+    let mut edit = None;
+    for star in 1..=5u8 {
+        let text = if star <= item.rating { "★" } else { "☆" };
+        if ui
+            .add(egui::Button::new(text).frame(false).small())
+            .clicked()
+        {
+            // Clicking the rating it already has clears it
+            let rating = if star == item.rating { 0 } else { star };
+            edit = Some(TakeEdit::Rating(rating));
+        }
+    }
+    match editing {
+        Some((path, text)) if *path == item.path => {
+            let response = ui.add(
+                egui::TextEdit::singleline(text)
+                    .hint_text(tr("tag, another tag"))
+                    .desired_width(140.0),
+            );
+            if ui.small_button(tr("Save")).clicked()
+                || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+            {
+                edit = Some(TakeEdit::Tags(history::parse_tags(text)));
+                *editing = None;
+            } else if ui.small_button(tr("Cancel")).clicked() {
+                *editing = None;
+            }
+        }
+        _ => {
+            for tag in &item.tags {
+                ui.label(RichText::new(format!("#{}", tag)).small().weak());
+            }
+            if ui
+                .small_button(tr("Tags"))
+                .on_hover_text(tr(
+                    "Edit the tags, kept with the rating in the take's sidecar",
+                ))
+                .clicked()
+            {
+                *editing = Some((item.path.clone(), item.tags.join(", ")));
+            }
+        }
+    }
+    edit
+}
+
+// Threshold, attack, hold and release, returning true once an edit is finished
+fn gate_settings_ui(ui: &mut egui::Ui, gate: &mut GateSettings) -> bool {
+    let mut changed = false;
+    ui.label(tr("Gate"));
+    for (value, range, suffix) in [
+        (&mut gate.threshold_db, -90.0..=0.0, " dBFS"),
+        (&mut gate.attack_ms, 0.1..=50.0, " ms attack"),
+        (&mut gate.hold_ms, 0.0..=1000.0, " ms hold"),
+        (&mut gate.release_ms, 5.0..=2000.0, " ms release"),
+    ] {
+        let response = ui.add(
+            egui::DragValue::new(value)
+                .range(range)
+                .speed(0.5)
+                .suffix(suffix),
+        );
+        changed |= response.drag_stopped() || response.lost_focus();
+    }
+    changed
+}
+
+// Gain reduction as a bar growing from the left, up to 20 dB
+fn limiter_indicator(ui: &mut egui::Ui, reduction_db: f32) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(80.0, 10.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let fraction = (reduction_db / 20.0).clamp(0.0, 1.0);
+    let bar =
+        egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * fraction, rect.height()));
+    painter.rect_filled(bar, 2.0, LIMITER_COLOR);
+    response.on_hover_text(format!("{} {:.1} dB", tr("Gain reduction"), reduction_db));
+}
+
+// Pulsing red frame and wash over the waveform while a grab is running, with how long it has
+// been capturing since the grab started
+fn grabbing_overlay(ui: &egui::Ui, rect: egui::Rect, post_roll_seconds: f64) {
+    let time = ui.input(|i| i.time);
+    let pulse = 0.6 + 0.4 * (time * std::f64::consts::TAU).sin() as f32;
+    let red = Color32::from_rgb(230, 40, 40);
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, red.gamma_multiply(0.08 * pulse));
+    painter.rect_stroke(
+        rect.expand(1.0),
+        2.0,
+        egui::Stroke::new(3.0, red.gamma_multiply(pulse)),
+    );
+    painter.text(
+        rect.left_top() + egui::vec2(8.0, 6.0),
+        egui::Align2::LEFT_TOP,
+        format!("{}  +{}", tr("GRABBING"), format_time(post_roll_seconds)),
+        egui::FontId::monospace(14.0),
+        red,
+    );
+}
+
+// Format seconds as "mm:ss.mmm"
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0);
+    format!("{:02}:{:06.3}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+// Opens the main window, restoring its size and position
+pub fn run(args: &Args, settings: Config, instance: InstanceLock) -> Result<(), Box<dyn Error>> {
+    let app_name = "Rolling Sampler";
+    let start_minimized = args.start_minimized;
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(settings.window_size.unwrap_or(FULL_WINDOW_SIZE))
+        .with_min_inner_size(MINI_WINDOW_SIZE);
+    if let Some(position) = settings.window_position {
+        viewport = viewport.with_position(position);
+    }
+    let native_options = eframe::NativeOptions {
+        viewport,
+        ..Default::default()
+    };
+    let app_creator =
+        move |cc: &CreationContext| -> Result<Box<dyn App>, Box<dyn Error + Send + Sync>> {
+            let mut sampler = Sampler::new(settings, instance);
+            let ctx = cc.egui_ctx.clone();
+            if let Err(e) = recovery::flush_on_signals(
+                Arc::clone(&sampler.recorder.buffer),
+                Arc::clone(&sampler.quit_signal),
+                move || ctx.request_repaint(),
+            ) {
+                eprintln!("Failed to listen for signals: {}", e);
+            }
+            sampler.repaint_ctx = Some(cc.egui_ctx.clone());
+            theme::apply(&cc.egui_ctx, &sampler.settings);
+            i18n::set_language(sampler.settings.language);
+            if sampler.settings.mini_mode {
+                let size = egui::ViewportCommand::InnerSize(MINI_WINDOW_SIZE.into());
+                cc.egui_ctx.send_viewport_cmd(size);
+            }
+            if sampler.settings.always_on_top {
+                let level = egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop);
+                cc.egui_ctx.send_viewport_cmd(level);
+            }
+            if start_minimized {
+                cc.egui_ctx
+                    .send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            }
+
+            // Make sure scheduled, gamepad and command grabs are handled even when nothing else
+            // triggers a repaint
+            let ctx = cc.egui_ctx.clone();
+            sampler
+                .recorder
+                .scheduler
+                .set_waker(move || ctx.request_repaint());
+            let ctx = cc.egui_ctx.clone();
+            sampler.gamepad.set_waker(move || ctx.request_repaint());
+            let ctx = cc.egui_ctx.clone();
+            sampler.midi.set_waker(move || ctx.request_repaint());
+            let ctx = cc.egui_ctx.clone();
+            sampler.commands.set_waker(move || ctx.request_repaint());
+            if sampler.settings.remote_enabled {
+                sampler.set_remote_enabled(true);
+            }
+            if sampler.settings.control_socket {
+                sampler.set_control_socket_enabled(true);
+            }
+
+            Ok(Box::new(sampler))
+        };
+    run_native(app_name, native_options, Box::new(app_creator))?;
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use crate::cli::Args;
+use crate::config::{Config, ShortGrabPolicy};
+use crate::control::InstanceLock;
+use crate::Sampler;
+use rolling_sampler_core::state::RecorderState;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+// The recorder without a window, for a Raspberry Pi or a server. Grabs are triggered with text
+// commands on stdin or the control socket, OSC (--osc-port), SIGUSR1, schedules or a
+// gamepad, and status is printed to the terminal.
+pub fn run(args: &Args, settings: Config, instance: InstanceLock) -> Result<(), Box<dyn Error>> {
+    let mut sampler = Sampler::new(settings, instance);
+    // Left where they are, for someone to collect
+    for path in std::mem::take(&mut sampler.recovered) {
+        println!("Audio recovered from a crash is in {}", path.display());
+    }
+    // Nobody is there to answer a prompt, so anything that would ask saves straight away
+    sampler.settings.review_before_save = false;
+    sampler.settings.mono_check = false;
+    sampler.settings.name_prompt = false;
+    if sampler.settings.short_grab_policy == ShortGrabPolicy::Ask {
+        sampler.settings.short_grab_policy = ShortGrabPolicy::SaveAnyway;
+    }
+    if sampler.settings.remote_enabled {
+        sampler.set_remote_enabled(true);
+    }
+    if sampler.settings.control_socket {
+        sampler.set_control_socket_enabled(true);
+    }
+    if let Err(e) = sampler.commands.set_osc_port(args.osc_port) {
+        return Err(format!("Failed to listen for OSC: {}", e).into());
+    }
+
+    let grab_signal = Arc::new(AtomicBool::new(false));
+    let quit = Arc::new(AtomicBool::new(false));
+    register_signals(&grab_signal, &quit)?;
+
+    println!("Rolling Sampler running headless");
+    println!(
+        "Saving to {}",
+        sampler.save_path.as_deref().unwrap_or("(no save folder)")
+    );
+    println!("Type a command and press enter: {}", crate::control::HELP);
+    #[cfg(unix)]
+    println!("Or send SIGUSR1 to grab: kill -USR1 {}", std::process::id());
+
+    let mut last_status = Instant::now();
+    while !quit.load(Ordering::SeqCst) {
+        if grab_signal.swap(false, Ordering::SeqCst) {
+            println!("Grab toggled by signal");
+            sampler.toggle_grab();
+        }
+        sampler.poll_auto_grab();
+        sampler.poll_short_grab();
+        sampler.poll_schedules();
+        sampler.poll_gamepad();
+        sampler.poll_midi();
+        sampler.poll_remote();
+        sampler.poll_commands();
+        sampler.poll_countdown();
+        sampler.poll_timecode();
+        sampler.poll_hits();
+        sampler.poll_markers();
+        sampler.poll_vox();
+        sampler.meters.update(&sampler.recorder.meter_tap);
+        #[cfg(feature = "scripting")]
+        sampler.poll_script();
+        sampler.poll_auto_sampler();
+        sampler.poll_latency_test();
+        sampler.poll_overdub();
+
+        if last_status.elapsed() >= STATUS_INTERVAL {
+            println!("{}", status_line(&sampler));
+            last_status = Instant::now();
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // Save a grab that is still running rather than losing it
+    if sampler.recorder.state.is_grabbing() {
+        println!("Saving the grab in progress before exiting");
+        sampler.grab_recording();
+    }
+    sampler.on_close();
+    println!("Stopped");
+    Ok(())
+}
+
+fn status_line(sampler: &Sampler) -> String {
+    let recorder = &sampler.recorder;
+    let channels = recorder.config.channels.max(1) as f32;
+    let rate = recorder.config.sample_rate.0 as f32;
+    let peak = f32::from_bits(recorder.input_peak.load(Ordering::Relaxed));
+    let peak_db = 20.0 * peak.max(1e-6).log10();
+    let buffer = recorder.buffer.lock().unwrap();
+    let state = match &recorder.state {
+        RecorderState::Grabbing => format!(
+            "grabbing {:.1} s",
+            buffer.static_buffer.len() as f32 / channels / rate
+        ),
+        RecorderState::Rolling | RecorderState::Saving => format!(
+            "rolling {:.1}/{:.1} s",
+            buffer.current_size as f32 / channels / rate,
+            buffer.max_size as f32 / channels / rate
+        ),
+        RecorderState::Idle => "no input".to_owned(),
+        RecorderState::Error(e) => format!("input failed: {}", e),
+    };
+    format!(
+        "[status] {} | peak {:.1} dBFS | {} clip events",
+        state,
+        peak_db,
+        sampler.meters.clip_log.len()
+    )
+}
+
+#[cfg(unix)]
+fn register_signals(grab: &Arc<AtomicBool>, quit: &Arc<AtomicBool>) -> std::io::Result<()> {
+    use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1};
+    signal_hook::flag::register(SIGUSR1, Arc::clone(grab))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(quit))?;
+    signal_hook::flag::register(SIGTERM, Arc::clone(quit))?;
+    Ok(())
+}
+
+// No SIGUSR1 on Windows, and Ctrl+C ends the process as usual
+#[cfg(not(unix))]
+fn register_signals(_grab: &Arc<AtomicBool>, _quit: &Arc<AtomicBool>) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,444 @@
+use chrono::{DateTime, Local};
+use hound::{SampleFormat, WavReader};
+use rolling_sampler_core::export::Take;
+use rolling_sampler_core::metadata::TakeAnalysis;
+#[cfg(feature = "gui")]
+use rolling_sampler_core::metadata::{self, sidecar_path};
+use rolling_sampler_core::playback::Audio;
+#[cfg(feature = "gui")]
+use std::fs;
+#[cfg(feature = "gui")]
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use std::process::Command;
+
+#[cfg(feature = "gui")]
+const ENVELOPE_COLUMNS: usize = 240;
+#[cfg(feature = "gui")]
+const THUMBNAIL_HEIGHT: usize = 48; // Pixels, drawn at half size so it stays sharp on HiDPI
+
+#[cfg(feature = "gui")]
+pub enum TakeEdit {
+    Rating(u8), // 0 to clear it
+    Tags(Vec<String>),
+}
+
+// A file saved during this session, or found in a folder by its sidecar
+#[derive(Clone)]
+pub struct HistoryItem {
+    pub name: String,
+    pub path: PathBuf,
+    pub duration_seconds: f64,
+    pub preset: String,
+    pub metadata: Vec<(String, String)>,
+    pub saved_at: DateTime<Local>,
+    pub analysis: Option<TakeAnalysis>, // Filled in once the writer thread has finished
+    #[cfg(feature = "gui")]
+    pub rating: u8, // Stars, 0 for unrated
+    #[cfg(feature = "gui")]
+    pub tags: Vec<String>,
+    #[cfg(feature = "gui")]
+    envelope: Vec<(f32, f32)>, // Min/max over all channels, for the thumbnail, read when first shown
+    #[cfg(feature = "gui")]
+    thumbnail: Option<egui::TextureHandle>, // Rendered the first time it's shown
+}
+
+impl HistoryItem {
+    pub fn from_take(take: &Take) -> Self {
+        HistoryItem {
+            name: take.name.clone(),
+            path: take.path.clone(),
+            duration_seconds: take.output_duration_seconds(),
+            preset: take.preset.clone(),
+            metadata: take.metadata.clone(),
+            saved_at: Local::now(),
+            analysis: None,
+            #[cfg(feature = "gui")]
+            rating: 0,
+            #[cfg(feature = "gui")]
+            tags: Vec::new(),
+            #[cfg(feature = "gui")]
+            envelope: envelope(&take.samples, take.channels as usize),
+            #[cfg(feature = "gui")]
+            thumbnail: None,
+        }
+    }
+
+    // From a sidecar written when the take was saved, None if there isn't one
+    #[cfg(feature = "gui")]
+    pub fn from_sidecar(wav_path: &Path) -> Option<Self> {
+        let sidecar = metadata::read_sidecar(wav_path).ok()?;
+        let saved_at = DateTime::parse_from_rfc3339(&sidecar.created)
+            .map(|time| time.with_timezone(&Local))
+            .unwrap_or_default();
+        Some(HistoryItem {
+            name: wav_path.file_stem()?.to_string_lossy().into_owned(),
+            path: wav_path.to_path_buf(),
+            duration_seconds: sidecar.duration_seconds,
+            preset: sidecar.preset,
+            metadata: sidecar.metadata.into_iter().collect(),
+            saved_at,
+            analysis: sidecar.analysis,
+            #[cfg(feature = "gui")]
+            rating: sidecar.rating.unwrap_or(0),
+            #[cfg(feature = "gui")]
+            tags: sidecar.tags,
+            #[cfg(feature = "gui")]
+            envelope: Vec::new(),
+            #[cfg(feature = "gui")]
+            thumbnail: None,
+        })
+    }
+
+    // Saved to the sidecar, which has to have been written already
+    #[cfg(feature = "gui")]
+    pub fn apply(&mut self, edit: TakeEdit) -> io::Result<()> {
+        let mut sidecar = metadata::read_sidecar(&self.path)?;
+        match edit {
+            TakeEdit::Rating(rating) => {
+                sidecar.rating = Some(rating).filter(|&stars| stars > 0);
+                metadata::write_sidecar(&self.path, &sidecar)?;
+                self.rating = rating;
+            }
+            TakeEdit::Tags(tags) => {
+                sidecar.tags = tags.clone();
+                metadata::write_sidecar(&self.path, &sidecar)?;
+                self.tags = tags;
+            }
+        }
+        Ok(())
+    }
+    // The envelope as a white on transparent image, to be tinted when drawn
+    #[cfg(feature = "gui")]
+    pub fn thumbnail(&mut self, ctx: &egui::Context) -> &egui::TextureHandle {
+        if self.thumbnail.is_none() && self.envelope.is_empty() {
+            // Found in a folder, so the audio hasn't been read yet
+            if let Ok(audio) = read_wav(&self.path) {
+                self.envelope = envelope(&audio.samples, audio.channels);
+            }
+        }
+        self.thumbnail.get_or_insert_with(|| {
+            let mut image = egui::ColorImage::new(
+                [self.envelope.len().max(1), THUMBNAIL_HEIGHT],
+                egui::Color32::TRANSPARENT,
+            );
+            let row = |s: f32| {
+                let fraction = (1.0 - s.clamp(-1.0, 1.0)) / 2.0;
+                ((fraction * THUMBNAIL_HEIGHT as f32) as usize).min(THUMBNAIL_HEIGHT - 1)
+            };
+            for (x, &(low, high)) in self.envelope.iter().enumerate() {
+                for y in row(high)..=row(low) {
+                    image[(x, y)] = egui::Color32::WHITE;
+                }
+            }
+            ctx.load_texture(
+                format!("thumbnail {}", self.path.display()),
+                image,
+                egui::TextureOptions::LINEAR,
+            )
+        })
+    }
+
+    // Renames the file and its sidecar, keeping them in the same folder
+    #[cfg(feature = "gui")]
+    pub fn rename(&mut self, name: &str) -> io::Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a valid file name",
+            ));
+        }
+        let path = self.path.with_file_name(format!("{}.wav", name));
+        if path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            ));
+        }
+        fs::rename(&self.path, &path)?;
+        let sidecar = sidecar_path(&self.path);
+        if sidecar.exists() {
+            fs::rename(sidecar, sidecar_path(&path))?;
+        }
+        self.name = name.to_owned();
+        self.path = path;
+        Ok(())
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn delete(&self) -> io::Result<()> {
+        fs::remove_file(&self.path)?;
+        let sidecar = sidecar_path(&self.path);
+        if sidecar.exists() {
+            fs::remove_file(sidecar)?;
+        }
+        Ok(())
+    }
+}
+
+// Every take with a sidecar in a folder and the folders under it, newest first. Only the folder
+// itself has to be readable: anything under it that can't be read is skipped.
+#[cfg(feature = "gui")]
+pub fn scan_folder(dir: &Path) -> io::Result<Vec<HistoryItem>> {
+    let mut items = Vec::new();
+    let mut folders = vec![dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let entries = match fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(e) if folder == dir => return Err(e),
+            Err(e) => {
+                eprintln!("Skipping {} while scanning: {}", folder.display(), e);
+                continue;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    eprintln!(
+                        "Skipping a file in {} while scanning: {}",
+                        folder.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if path.is_dir() {
+                folders.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+            {
+                items.extend(HistoryItem::from_sidecar(&path));
+            }
+        }
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.saved_at));
+    Ok(items)
+}
+
+// Comma separated, e.g. "chorus-idea, guitar"
+#[cfg(feature = "gui")]
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
+
+// What the history and library lists are narrowed to. Every word of the search has to turn up
+// in the name, preset, tags or metadata, except that `#word` has to be a whole tag.
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct TakeFilter {
+    pub search: String,
+    pub min_rating: u8,
+}
+
+#[cfg(feature = "gui")]
+impl TakeFilter {
+    pub fn matches(&self, item: &HistoryItem) -> bool {
+        if item.rating < self.min_rating {
+            return false;
+        }
+        let search = self.search.to_lowercase();
+        search
+            .split_whitespace()
+            .all(|word| match word.strip_prefix('#') {
+                Some(tag) => item.tags.iter().any(|t| t.to_lowercase() == tag),
+                None => std::iter::once(&item.name)
+                    .chain([&item.preset])
+                    .chain(&item.tags)
+                    .chain(item.metadata.iter().map(|(_, value)| value))
+                    .any(|text| text.to_lowercase().contains(word)),
+            })
+    }
+}
+
+#[cfg(feature = "gui")]
+fn envelope(samples: &[f32], channels: usize) -> Vec<(f32, f32)> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let per_column = frames.div_ceil(ENVELOPE_COLUMNS).max(1) * channels;
+    samples
+        .chunks(per_column)
+        .map(|column| {
+            column.iter().fold((0.0f32, 0.0f32), |(low, high), &s| {
+                (low.min(s), high.max(s))
+            })
+        })
+        .collect()
+}
+
+// Show the file in the platform's file manager, selected where that is possible
+#[cfg(feature = "gui")]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else if cfg!(windows) {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    } else {
+        // Most Linux file managers can't be asked to select a file, so open its folder
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(Path::new(".")));
+        command
+    };
+    command.spawn().map(|_| ())
+}
+
+pub fn read_wav(path: &Path) -> Result<Audio, hound::Error> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok(Audio {
+        samples,
+        channels: spec.channels as usize,
+        sample_rate: spec.sample_rate,
+    })
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use rolling_sampler_core::metadata::Sidecar;
+
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rolling-sampler-history-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // A take as the writer thread leaves it, a WAV with its sidecar
+    fn saved_take(path: &Path, created: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+        let sidecar = Sidecar {
+            file: path.file_name().unwrap().to_string_lossy().into_owned(),
+            created: created.to_owned(),
+            duration_seconds: 2.5,
+            preset: "Vinyl Sampling".to_owned(),
+            metadata: [("source".to_owned(), "vinyl".to_owned())].into(),
+            ..Sidecar::default()
+        };
+        metadata::write_sidecar(path, &sidecar).unwrap();
+    }
+
+    fn item(name: &str, rating: u8, tags: &[&str]) -> HistoryItem {
+        HistoryItem {
+            name: name.to_owned(),
+            path: PathBuf::from(format!("{}.wav", name)),
+            duration_seconds: 1.0,
+            preset: "Default".to_owned(),
+            metadata: vec![("mic".to_owned(), "U87".to_owned())],
+            saved_at: Local::now(),
+            analysis: None,
+            rating,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            envelope: Vec::new(),
+            #[cfg(feature = "gui")]
+            thumbnail: None,
+        }
+    }
+
+    #[test]
+    fn tags_are_split_on_commas_without_repeats() {
+        assert_eq!(
+            parse_tags(" chorus-idea, guitar,,Guitar , live take "),
+            ["chorus-idea", "guitar", "live take"]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+
+    #[test]
+    fn filter_matches_words_tags_and_rating() {
+        let take = item("Riff 3", 4, &["chorus-idea", "Guitar"]);
+        let matches = |search: &str, min_rating: u8| {
+            TakeFilter {
+                search: search.to_owned(),
+                min_rating,
+            }
+            .matches(&take)
+        };
+        assert!(matches("", 0));
+        assert!(matches("riff", 0));
+        assert!(matches("u87 default", 0)); // Metadata and preset
+        assert!(matches("chorus", 0)); // Part of a tag, as a plain word
+        assert!(matches("#guitar", 4));
+        assert!(!matches("#chorus", 0)); // Not a whole tag
+        assert!(!matches("riff bass", 0)); // Every word has to be found
+        assert!(!matches("riff", 5));
+    }
+
+    #[test]
+    fn edits_are_kept_in_the_sidecar() {
+        let dir = temp_dir("edit");
+        let path = dir.join("Take.wav");
+        saved_take(&path, "2024-05-01T12:00:00+00:00");
+        let mut take = HistoryItem::from_sidecar(&path).unwrap();
+        assert_eq!((take.rating, take.tags.len()), (0, 0));
+
+        take.apply(TakeEdit::Rating(5)).unwrap();
+        take.apply(TakeEdit::Tags(parse_tags("chorus-idea, guitar")))
+            .unwrap();
+        let read_back = HistoryItem::from_sidecar(&path).unwrap();
+        assert_eq!(read_back.rating, 5);
+        assert_eq!(read_back.tags, ["chorus-idea", "guitar"]);
+        assert_eq!(read_back.preset, "Vinyl Sampling");
+        assert_eq!(
+            read_back.metadata,
+            [("source".to_owned(), "vinyl".to_owned())]
+        );
+
+        // Clearing the rating leaves it out of the file
+        take.apply(TakeEdit::Rating(0)).unwrap();
+        assert_eq!(metadata::read_sidecar(&path).unwrap().rating, None);
+        assert_eq!(HistoryItem::from_sidecar(&path).unwrap().rating, 0);
+
+        // A take whose sidecar is gone can't be edited, or found
+        fs::remove_file(sidecar_path(&path)).unwrap();
+        assert!(take.apply(TakeEdit::Rating(3)).is_err());
+        assert!(HistoryItem::from_sidecar(&path).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn scanning_finds_takes_in_subfolders_newest_first() {
+        let dir = temp_dir("scan");
+        saved_take(&dir.join("Old.wav"), "2024-05-01T12:00:00+00:00");
+        saved_take(&dir.join("Slices/New.WAV"), "2024-06-01T12:00:00+00:00");
+        fs::write(dir.join("Unsaved.wav"), b"").unwrap(); // No sidecar
+        fs::write(dir.join("Notes.txt"), b"").unwrap();
+
+        let names: Vec<String> = scan_folder(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(names, ["New", "Old"]);
+        assert!(scan_folder(&dir.join("Missing")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
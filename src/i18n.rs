@@ -0,0 +1,1126 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use std::fmt::Display;
+#[cfg(feature = "gui")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// UI text is written in English in the code and looked up here for other languages, so a
+// missing translation just shows the English
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    German,
+    Spanish,
+}
+
+#[cfg(feature = "gui")]
+static CURRENT: AtomicUsize = AtomicUsize::new(0); // Index into Language::ALL
+
+impl Language {
+    #[cfg(feature = "gui")]
+    pub const ALL: [Language; 3] = [Language::English, Language::German, Language::Spanish];
+
+    // Each language names itself, so it can be found whatever language is showing
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+            Language::Spanish => "Español",
+        }
+    }
+
+    // The system language if there is a translation for it, for a first run
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        match locale.get(..2) {
+            Some("de") => Language::German,
+            Some("es") => Language::Spanish,
+            _ => Language::English,
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn table(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => &[],
+            Language::German => GERMAN,
+            Language::Spanish => SPANISH,
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn set_language(language: Language) {
+    let index = Language::ALL
+        .iter()
+        .position(|&l| l == language)
+        .unwrap_or(0);
+    CURRENT.store(index, Ordering::Relaxed);
+}
+
+#[cfg(feature = "gui")]
+pub fn language() -> Language {
+    Language::ALL[CURRENT.load(Ordering::Relaxed)]
+}
+
+// The current language's version of an English UI string
+#[cfg(feature = "gui")]
+pub fn tr(text: &str) -> &str {
+    language()
+        .table()
+        .iter()
+        .find(|(english, _)| *english == text)
+        .map_or(text, |(_, translated)| translated)
+}
+
+// `tr` for text with `{}` placeholders, filled in order from `args` once it's translated, so
+// each language can put them where its word order wants them
+#[cfg(feature = "gui")]
+pub fn tr_fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::new();
+    for (index, part) in tr(text).split("{}").enumerate() {
+        if let Some(arg) = index.checked_sub(1).and_then(|index| args.get(index)) {
+            filled += &arg.to_string();
+        }
+        filled += part;
+    }
+    filled
+}
+
+#[cfg(feature = "gui")]
+const GERMAN: &[(&str, &str)] = &[
+    ("Settings", "Einstellungen"),
+    ("Mini Mode", "Mini-Modus"),
+    (
+        "Shrink to just the meters and the grab button",
+        "Nur Pegelanzeigen und Aufnahmeknopf zeigen",
+    ),
+    ("Saving to", "Speichern in"),
+    (
+        "Pick a save folder in Settings before grabbing",
+        "Vor dem Aufnehmen in den Einstellungen einen Speicherordner wählen",
+    ),
+    (
+        "Choose how much past audio to include in the recording:",
+        "Wie viel zurückliegendes Audio in die Aufnahme kommt:",
+    ),
+    ("Buffer Size (s):", "Puffergröße (s):"),
+    ("Markers", "Marker"),
+    ("Metronome", "Metronom"),
+    ("Overdub", "Overdub"),
+    ("Take Library", "Aufnahmearchiv"),
+    ("Auto-Sampler", "Auto-Sampler"),
+    ("Clip Log", "Übersteuerungsprotokoll"),
+    ("Take History", "Aufnahmeverlauf"),
+    ("A/B Compare", "A/B-Vergleich"),
+    ("Last Grab & Events", "Letzte Aufnahme & Ereignisse"),
+    ("Session Report", "Sitzungsbericht"),
+    (
+        "An HTML page listing every grab, saved in the save folder:",
+        "Eine HTML-Seite mit allen Aufnahmen, im Speicherordner abgelegt:",
+    ),
+    ("Extending...", "Verlängern..."),
+    ("Stop Grab", "Aufnahme stoppen"),
+    ("Start Grab", "Aufnahme starten"),
+    ("Devices & Monitoring", "Geräte & Abhören"),
+    ("Session", "Sitzung"),
+    ("Save Folder", "Speicherordner"),
+    ("Tracks", "Spuren"),
+    ("Select Save Folder", "Speicherordner wählen"),
+    ("Selected Folder:", "Gewählter Ordner:"),
+    ("Display", "Anzeige"),
+    ("Preset & Metadata", "Preset & Metadaten"),
+    ("Grab Options", "Aufnahmeoptionen"),
+    (
+        "Review each grab before saving",
+        "Jede Aufnahme vor dem Speichern prüfen",
+    ),
+    (
+        "Ask for a name after each grab",
+        "Nach jeder Aufnahme nach einem Namen fragen",
+    ),
+    (
+        "Warn about phase cancellation in stereo grabs",
+        "Vor Phasenauslöschung in Stereoaufnahmen warnen",
+    ),
+    ("DAW Hot Folder", "DAW-Hot-Folder"),
+    (
+        "Every saved grab is also dropped into this folder:",
+        "Jede gespeicherte Aufnahme landet auch in diesem Ordner:",
+    ),
+    ("Safety Recording", "Sicherheitsaufnahme"),
+    (
+        "Continuous segments in the save folder, separate from grabs",
+        "Fortlaufende Abschnitte im Speicherordner, getrennt von den Aufnahmen",
+    ),
+    ("Live Stream", "Live-Stream"),
+    ("Scripting", "Skripte"),
+    (
+        "Opus to an Icecast server or an RTP listener, so others can listen in",
+        "Opus an einen Icecast-Server oder RTP-Empfänger, damit andere mithören können",
+    ),
+    ("Web Remote", "Web-Fernbedienung"),
+    ("Command Interface", "Befehlsschnittstelle"),
+    ("Appearance", "Darstellung"),
+    ("Scheduled Grabs", "Geplante Aufnahmen"),
+    (
+        "Save the rolling buffer at a set time or interval:",
+        "Den Puffer zu einer festen Zeit oder in Abständen speichern:",
+    ),
+    ("Diagnostics", "Diagnose"),
+    (
+        "Keep the window above other windows",
+        "Fenster über anderen Fenstern halten",
+    ),
+    ("Theme:", "Design:"),
+    ("Dark", "Dunkel"),
+    ("Light", "Hell"),
+    ("Stage", "Bühne"),
+    ("Language:", "Sprache:"),
+    (
+        "Grabs saved this session will be listed here.",
+        "Die in dieser Sitzung gespeicherten Aufnahmen erscheinen hier.",
+    ),
+    ("Play", "Abspielen"),
+    ("Stop", "Stopp"),
+    ("Save", "Speichern"),
+    ("Cancel", "Abbrechen"),
+    ("Rename", "Umbenennen"),
+    ("Reveal", "Im Ordner zeigen"),
+    ("Delete", "Löschen"),
+    ("Really delete?", "Wirklich löschen?"),
+    ("Review Grab", "Aufnahme prüfen"),
+    ("Start:", "Anfang:"),
+    ("End:", "Ende:"),
+    ("Accept", "Übernehmen"),
+    ("Discard", "Verwerfen"),
+    ("Record everything", "Alles aufnehmen"),
+    ("Segment length (min):", "Abschnittslänge (min):"),
+    ("Only while there is signal", "Nur solange ein Signal anliegt"),
+    ("Start a segment when the input crosses the threshold and pause after a long silence", "Einen Abschnitt beginnen, wenn der Eingang die Schwelle überschreitet, und nach langer Stille pausieren"),
+    ("Threshold (dB):", "Schwelle (dB):"),
+    ("Pause after silence (s):", "Pause nach Stille (s):"),
+    ("Recording", "Nimmt auf"),
+    ("Waiting for signal", "Wartet auf Signal"),
+    ("{} ({} segments so far)", "{} (bisher {} Abschnitte)"),
+    ("Stream the input", "Eingang streamen"),
+    ("To:", "An:"),
+    ("Host:", "Host:"),
+    ("Port:", "Port:"),
+    ("Mountpoint:", "Mountpoint:"),
+    ("Password:", "Passwort:"),
+    ("Opus bitrate (kbps):", "Opus-Bitrate (kbit/s):"),
+    ("Streaming", "Sendet"),
+    ("Connecting...", "Verbinde..."),
+    ("{} ({} MB sent)", "{} ({} MB gesendet)"),
+    ("Copy SDP", "SDP kopieren"),
+    ("Save it as a .sdp file for the listener to open in VLC or ffplay", "Als .sdp-Datei speichern, die der Empfänger in VLC oder ffplay öffnet"),
+    ("Name Take", "Aufnahme benennen"),
+    ("{} s grabbed", "{} s aufgenommen"),
+    ("Keep {}", "{} behalten"),
+    ("Averaging:", "Mittelung:"),
+    ("Peak hold", "Spitzen halten"),
+    ("Reset Peak", "Spitze zurücksetzen"),
+    ("dB scale", "dB-Skala"),
+    ("Show levels from {} dBFS up, so quiet material is visible", "Pegel ab {} dBFS zeigen, damit leises Material sichtbar ist"),
+    ("Overlay channels", "Kanäle überlagern"),
+    ("Ch", "K"),
+    ("{} s captured", "{} s erfasst"),
+    ("{} s / {} s buffered", "{} s / {} s gepuffert"),
+    ("{} clip events (at or above {} dBFS)", "{} Übersteuerungen (bei oder über {} dBFS)"),
+    ("Reset Lights", "Anzeigen zurücksetzen"),
+    ("Clear Log", "Protokoll leeren"),
+    ("{}  Ch {}  {} samples", "{}  K {}  {} Samples"),
+    ("Write a report when quitting", "Beim Beenden einen Bericht schreiben"),
+    ("Write Report Now", "Bericht jetzt schreiben"),
+    ("{} grabs saved since {}", "{} Aufnahmen seit {} gespeichert"),
+    ("Search a Folder...", "Ordner durchsuchen..."),
+    ("Find takes saved before, by their ratings, tags and metadata", "Früher gespeicherte Aufnahmen nach Bewertung, Tags und Metadaten finden"),
+    ("Rescan", "Neu suchen"),
+    ("{} of {} takes", "{} von {} Aufnahmen"),
+    ("Save at least two grabs to compare them.", "Mindestens zwei Aufnahmen speichern, um sie zu vergleichen."),
+    ("Choose a take", "Aufnahme wählen"),
+    ("Hearing {} (switch)", "Zu hören: {} (wechseln)"),
+    ("B is {} dB against A, level matched", "B liegt {} dB gegenüber A, pegelangeglichen"),
+    ("Play A/B", "A/B abspielen"),
+    ("Select Hot Folder", "Hot Folder wählen"),
+    ("Off", "Aus"),
+    ("Name files Take 001, Take 002...", "Dateien Take 001, Take 002... benennen"),
+    ("Run after each drop:", "Nach jeder Ablage ausführen:"),
+    ("command, file in $ROLLING_SAMPLER_FILE", "Befehl, Datei in $ROLLING_SAMPLER_FILE"),
+    ("Run after each save:", "Nach jedem Speichern ausführen:"),
+    ("e.g. rclone copy {file} remote:samples", "z. B. rclone copy {file} remote:samples"),
+    ("{file} is replaced by the saved file's path, which is added at the end if it isn't used", "{file} wird durch den Pfad der gespeicherten Datei ersetzt, der sonst ans Ende angehängt wird"),
+    ("Armed channels are saved, the rest left out", "Scharfgeschaltete Kanäle werden gespeichert, der Rest weggelassen"),
+    ("Also save a mono file per channel", "Zusätzlich eine Mono-Datei pro Kanal speichern"),
+    ("In a folder next to each take, named after the channels", "In einem Ordner neben jeder Aufnahme, nach den Kanälen benannt"),
+    ("Channel names go into each file's iXML track list, for DAWs to split it by", "Kanalnamen kommen in die iXML-Spurliste jeder Datei, damit DAWs danach aufteilen können"),
+    ("Drop Marker", "Marker setzen"),
+    ("Export length around a marker (s):", "Exportlänge um einen Marker (s):"),
+    ("No markers in the buffer.", "Keine Marker im Puffer."),
+    ("Marker {} at {} ({} ago)", "Marker {} um {} (vor {})"),
+    ("Export Around", "Umgebung exportieren"),
+    ("Export from marker", "Exportieren von Marker"),
+    ("Or click a marker on the waveform for the start, right click for the end", "Oder einen Marker in der Wellenform für den Anfang anklicken, Rechtsklick für das Ende"),
+    ("to marker", "bis Marker"),
+    ("Export Range", "Bereich exportieren"),
+    ("{} export(s) waiting for audio after the marker...", "{} Export(e) warten auf Audio nach dem Marker..."),
+    ("Auto-capture hits", "Anschläge automatisch aufnehmen"),
+    ("Save a short one-shot around every detected transient", "Um jeden erkannten Transienten ein kurzes One-Shot speichern"),
+    ("Sensitivity (dB):", "Empfindlichkeit (dB):"),
+    ("Floor (dB):", "Untergrenze (dB):"),
+    ("Pre-roll (ms):", "Vorlauf (ms):"),
+    ("Length (ms):", "Länge (ms):"),
+    ("{} hits captured", "{} Anschläge aufgenommen"),
+    ("No shortcut, add one under Preset & Metadata", "Kein Kürzel, unter Preset & Metadaten hinzufügen"),
+    ("Shortcut:", "Kürzel:"),
+    ("Listen on a local control socket", "Auf einem lokalen Steuer-Socket lauschen"),
+    ("Commands (also on stdin):", "Befehle (auch über stdin):"),
+    ("Select Script", "Skript wählen"),
+    ("Reload", "Neu laden"),
+    ("No hooks defined", "Keine Hooks definiert"),
+    ("Hooks:", "Hooks:"),
+    ("on_silence after (s):", "on_silence nach (s):"),
+    ("below (dB):", "unter (dB):"),
+    ("Hooks: {}. Calls: command(\"grab\"), tag(key, value), notify(text), osc_send(\"host:port\", \"/address\", [args]). The file is reloaded when it changes.", "Hooks: {}. Aufrufe: command(\"grab\"), tag(key, value), notify(text), osc_send(\"host:port\", \"/address\", [args]). Die Datei wird bei Änderungen neu geladen."),
+    ("Timecode from:", "Timecode von:"),
+    ("Channel:", "Kanal:"),
+    ("Waiting for timecode...", "Warte auf Timecode..."),
+    ("Add the start timecode to file names", "Start-Timecode an Dateinamen anhängen"),
+    ("Takes get their start timecode as a BWF time reference and in the metadata", "Aufnahmen erhalten ihren Start-Timecode als BWF-Zeitreferenz und in den Metadaten"),
+    ("{} of {}", "{} von {}"),
+    ("Playing {} at velocity {}", "Spiele {} mit Anschlagstärke {}"),
+    ("MIDI output:", "MIDI-Ausgang:"),
+    ("None", "Keiner"),
+    ("Look for outputs again", "Erneut nach Ausgängen suchen"),
+    ("Notes:", "Noten:"),
+    ("every", "alle"),
+    ("semitones", "Halbtöne"),
+    ("Velocities:", "Anschlagstärken:"),
+    ("Another velocity layer", "Weitere Anschlagstufe"),
+    ("Hold (s):", "Halten (s):"),
+    ("Release (s):", "Ausklang (s):"),
+    ("Trim below (dB):", "Unterhalb abschneiden (dB):"),
+    ("Instrument:", "Instrument:"),
+    ("Patches:", "Patches:"),
+    ("Written for the notes of each run, and for Slice to Hits", "Für die Noten jedes Durchlaufs und für In Anschläge zerlegen geschrieben"),
+    ("Start", "Start"),
+    ("{} notes, about {} minutes", "{} Noten, etwa {} Minuten"),
+    ("Each note is saved into a folder named after the instrument, trimmed to where it sounds", "Jede Note wird in einem nach dem Instrument benannten Ordner gespeichert, auf den klingenden Teil gekürzt"),
+    ("Plays a grab back through the output while you record a new layer over it.", "Spielt eine Aufnahme über den Ausgang ab, während du eine neue Schicht darüber aufnimmst."),
+    ("Layer Over Last Grab", "Über letzte Aufnahme schichten"),
+    ("Grab something first", "Zuerst etwas aufnehmen"),
+    ("Measure the round-trip latency (under Devices & Monitoring) so layers line up exactly.", "Die Round-Trip-Latenz messen (unter Geräte & Abhören), damit die Schichten genau passen."),
+    ("Bed:", "Grundlage:"),
+    ("Pass {}", "Durchgang {}"),
+    ("Save Layer", "Schicht speichern"),
+    ("Save Bounce", "Mischung speichern"),
+    ("The bed and the new layer mixed together", "Grundlage und neue Schicht zusammengemischt"),
+    ("Layer Again", "Erneut schichten"),
+    ("Mix this layer into the bed and record another over both", "Diese Schicht in die Grundlage mischen und eine weitere über beide aufnehmen"),
+    ("Redo", "Wiederholen"),
+    ("Record Layer", "Schicht aufnehmen"),
+    ("Done", "Fertig"),
+    ("Click", "Klick"),
+    ("Plays in the monitoring output only, never in what's recorded. Starts monitoring if it's off.", "Nur im Abhörausgang zu hören, nie in der Aufnahme. Startet das Abhören, falls es aus ist."),
+    ("Tempo:", "Tempo:"),
+    ("From Link", "Von Link"),
+    ("Beats per bar:", "Schläge pro Takt:"),
+    ("Count-in:", "Einzähler:"),
+    ("Keep clicking", "Weiter klicken"),
+    ("Off to hear only the count-in", "Aus, um nur den Einzähler zu hören"),
+    ("Level:", "Pegel:"),
+    ("Follow Ableton Link", "Ableton Link folgen"),
+    ("Takes the tempo and bar lines from a DAW or drum machine on the network", "Übernimmt Tempo und Taktgrenzen von einer DAW oder Drumcomputer im Netzwerk"),
+    ("{} BPM, bar {}, beat {}, {} peers", "{} BPM, Takt {}, Schlag {}, {} Teilnehmer"),
+    ("Measuring the session's clock...", "Messe den Takt der Sitzung..."),
+    ("No Link session found", "Keine Link-Sitzung gefunden"),
+    ("Bars:", "Takte:"),
+    ("Grab Last Bars", "Letzte Takte aufnehmen"),
+    ("Enable web remote", "Web-Fernbedienung aktivieren"),
+    ("Open on your phone:", "Auf dem Telefon öffnen:"),
+    ("Input Device:", "Eingangsgerät:"),
+    ("Look for devices plugged in since the app started", "Nach seit dem Start angeschlossenen Geräten suchen"),
+    ("Input High-Pass:", "Eingangs-Hochpass:"),
+    ("Filters out handling rumble and plosives before they're recorded", "Filtert Griffgeräusche und Plosive heraus, bevor sie aufgenommen werden"),
+    ("Remove DC Offset", "Gleichspannungsversatz entfernen"),
+    ("For interfaces that add a constant bias, which eats headroom and upsets normalizing", "Für Interfaces mit konstantem Versatz, der Headroom kostet und das Normalisieren stört"),
+    ("Noise Gate on Input", "Noise Gate am Eingang"),
+    ("Gate the input before it's buffered. Add a gate to the preset's processing to gate only what's saved instead.", "Den Eingang vor dem Puffern gaten. Um nur das Gespeicherte zu gaten, stattdessen ein Gate zur Bearbeitung des Presets hinzufügen."),
+    ("Limiter on Input", "Limiter am Eingang"),
+    ("Holds sudden loud moments under the threshold so the capture doesn't clip", "Hält plötzliche laute Stellen unter der Schwelle, damit die Aufnahme nicht übersteuert"),
+    ("release ", "Rückkehr "),
+    ("How quickly the level comes back up after being held down", "Wie schnell der Pegel nach dem Absenken zurückkommt"),
+    ("Output Device:", "Ausgangsgerät:"),
+    ("Enable Monitoring", "Abhören aktivieren"),
+    ("Monitor Routing:", "Abhör-Routing:"),
+    ("Monitor Gain:", "Abhörpegel:"),
+    ("Monitor EQ", "Abhör-EQ"),
+    ("Hear how the sound would sit after EQ. Only the monitoring is affected, never what's recorded.", "Hören, wie der Klang nach dem EQ sitzt. Nur das Abhören ist betroffen, nie die Aufnahme."),
+    ("Flat", "Neutral"),
+    ("Monitor Resampler:", "Abhör-Resampler:"),
+    ("Converts to the output rate and follows drift between the two clocks, better ones add delay", "Wandelt in die Ausgangsrate und folgt der Drift zwischen beiden Takten, bessere fügen Verzögerung hinzu"),
+    ("Monitor Latency:", "Abhörlatenz:"),
+    ("Audio held back to ride out scheduling jitter, lower is tighter", "Zurückgehaltenes Audio gegen Planungs-Jitter, weniger ist direkter"),
+    ("Minimize automatically", "Automatisch minimieren"),
+    ("Start from this latency, lower it while playback stays clean and raise it after a dropout", "Mit dieser Latenz beginnen, sie senken, solange die Wiedergabe sauber bleibt, und nach einem Aussetzer erhöhen"),
+    ("Headphone Limiter", "Kopfhörer-Limiter"),
+    ("Catches feedback and hot transients before they reach your ears", "Fängt Rückkopplungen und laute Transienten ab, bevor sie deine Ohren erreichen"),
+    ("Ceiling:", "Obergrenze:"),
+    ("Listening for the clicks...", "Horche auf die Klicks..."),
+    ("Measure Round-Trip Latency", "Round-Trip-Latenz messen"),
+    ("Plays a few clicks out of the output and times them coming back on the input. Connect an output to an input with a cable first, or put a mic by a speaker.", "Spielt einige Klicks über den Ausgang und misst, wann sie am Eingang zurückkommen. Vorher einen Ausgang per Kabel mit einem Eingang verbinden oder ein Mikrofon vor einen Lautsprecher stellen."),
+    ("Last measured:", "Zuletzt gemessen:"),
+    ("Measured: {} ms (input {} + FIFO {} + output {} + resampler {} + limiter {})", "Gemessen: {} ms (Eingang {} + FIFO {} + Ausgang {} + Resampler {} + Limiter {})"),
+    ("FIFO target now", "FIFO-Ziel jetzt"),
+    ("In {}", "Ein {}"),
+    ("Out {}: {}", "Aus {}: {}"),
+    ("Meters:", "Pegelanzeigen:"),
+    ("Reference:", "Referenz:"),
+    ("The level your console reads as 0, e.g. -18 dBFS for EBU or -20 dBFS for SMPTE", "Der Pegel, den dein Pult als 0 anzeigt, z. B. -18 dBFS für EBU oder -20 dBFS für SMPTE"),
+    ("Peak hold:", "Spitzen halten:"),
+    ("Spectrogram", "Spektrogramm"),
+    ("Spectrum", "Spektrum"),
+    ("Tuner", "Stimmgerät"),
+    ("Timecode", "Timecode"),
+    ("Detect the tempo of each grab", "Tempo jeder Aufnahme erkennen"),
+    ("in the file name", "im Dateinamen"),
+    ("Round to whole bars", "Auf ganze Takte runden"),
+    ("Trim the start of the grab so it's a whole number of 4/4 bars long", "Den Anfang der Aufnahme kürzen, sodass sie eine ganze Zahl von 4/4-Takten lang ist"),
+    ("Detect the key of each grab", "Tonart jeder Aufnahme erkennen"),
+    ("Written to the metadata and shown in the take history, for melodic ideas", "In die Metadaten geschrieben und im Aufnahmeverlauf gezeigt, für melodische Ideen"),
+    ("Tag each take with its note", "Jede Aufnahme mit ihrer Note versehen"),
+    ("Written to the metadata for samplers that key map automatically", "In die Metadaten geschrieben, für Sampler mit automatischer Tastenzuordnung"),
+    ("in the file name too", "auch im Dateinamen"),
+    ("Grabbing +{} s", "Nimmt auf +{} s"),
+    ("{} s buffered", "{} s gepuffert"),
+    ("Expand", "Erweitern"),
+    ("On top", "Im Vordergrund"),
+    ("Get ready...", "Bereit machen..."),
+    ("Play now! Saving in {} s", "Jetzt spielen! Speichern in {} s"),
+    ("Cancel Delayed Grab", "Verzögerte Aufnahme abbrechen"),
+    ("Grab After Countdown", "Nach Countdown aufnehmen"),
+    ("Copy Diagnostics", "Diagnose kopieren"),
+    ("Copy device and stream details for a bug report", "Geräte- und Stream-Details für einen Fehlerbericht kopieren"),
+    ("Save Diagnostics...", "Diagnose speichern..."),
+    ("Input failed", "Eingang ausgefallen"),
+    ("(no input device)", "(kein Eingangsgerät)"),
+    ("DC offset on the input, being removed", "Gleichspannungsversatz am Eingang, wird entfernt"),
+    ("DC offset on the input, turn on Remove DC Offset in Settings to take it out", "Gleichspannungsversatz am Eingang, zum Entfernen Gleichspannungsversatz entfernen in den Einstellungen einschalten"),
+    ("Monitoring at", "Abhören mit"),
+    (" (resampled)", " (umgerechnet)"),
+    ("Monitoring latency from input to output", "Abhörlatenz vom Eingang zum Ausgang"),
+    ("Limiting", "Begrenzt"),
+    ("Input limiting", "Eingang begrenzt"),
+    ("The capture limiter is holding the input under its threshold", "Der Aufnahme-Limiter hält den Eingang unter seiner Schwelle"),
+    ("Share of real time spent in the input callback", "Anteil der Echtzeit im Eingangs-Callback"),
+    ("Xruns {}  Underruns {}", "Xruns {}  Unterläufe {}"),
+    ("{} stream errors", "{} Stream-Fehler"),
+    ("Keep the buffer empty until the input crosses the threshold, then start the grab at the hit", "Den Puffer leer lassen, bis der Eingang die Schwelle überschreitet, dann die Aufnahme beim Anschlag beginnen"),
+    ("Wait for signal", "Auf Signal warten"),
+    ("Armed - waiting for signal...", "Scharf - warte auf Signal..."),
+    ("Voice activated (VOX)", "Sprachgesteuert (VOX)"),
+    ("Grab automatically while there is signal, one file per utterance", "Automatisch aufnehmen, solange ein Signal anliegt, eine Datei pro Äußerung"),
+    ("Hang time (s):", "Haltezeit (s):"),
+    ("Pre-roll (s):", "Vorlauf (s):"),
+    ("VOX listening...", "VOX hört zu..."),
+    ("Minimum grab length (s):", "Minimale Aufnahmelänge (s):"),
+    ("When a grab is shorter:", "Wenn eine Aufnahme kürzer ist:"),
+    ("At", "Um"),
+    ("Every", "Alle"),
+    ("last ", "letzte "),
+    ("Seconds saved from the end of the buffer, 0 for all of it", "Vom Ende des Puffers gespeicherte Sekunden, 0 für alles"),
+    ("next:", "nächste:"),
+    ("inactive", "inaktiv"),
+    ("Add Schedule", "Zeitplan hinzufügen"),
+    ("Gamepad/footswitch grab button:", "Gamepad-/Fußschalter-Aufnahmetaste:"),
+    ("press a button...", "Taste drücken..."),
+    ("none", "keine"),
+    ("Learn", "Anlernen"),
+    ("Clear", "Leeren"),
+    ("{} more waiting", "{} weitere warten"),
+    ("Loop", "Schleife"),
+    ("Crossfade the end into the audio before the start so the part loops cleanly", "Das Ende in das Audio vor dem Anfang überblenden, damit der Teil sauber loopt"),
+    ("Snap to zero crossings", "An Nulldurchgängen ausrichten"),
+    ("Crossfade:", "Überblendung:"),
+    ("Punch", "Punch"),
+    ("Replace a part of the take by playing it again, along to the rest of the take", "Einen Teil der Aufnahme ersetzen, indem er zum Rest der Aufnahme neu eingespielt wird"),
+    ("Pre-roll:", "Vorlauf:"),
+    ("Punch In:", "Punch-In:"),
+    ("Punch Out:", "Punch-Out:"),
+    ("Punching", "Punch läuft"),
+    ("Punch In", "Punch-In"),
+    ("Undo Punch", "Punch rückgängig"),
+    ("left", "links"),
+    ("right", "rechts"),
+    ("The stereo grab loses {} dB when summed to mono (correlation {}). The channels are probably out of phase.", "Die Stereoaufnahme verliert {} dB, wenn sie zu Mono summiert wird (Korrelation {}). Die Kanäle sind wahrscheinlich phasenverdreht."),
+    ("Mono Compatibility", "Monokompatibilität"),
+    ("Save as is", "So speichern"),
+    ("Flip right polarity", "Polarität rechts drehen"),
+    ("Save mono ({})", "Mono speichern ({})"),
+    ("No grabs yet.", "Noch keine Aufnahmen."),
+    ("loudness unmeasured", "Lautheit nicht gemessen"),
+    ("{} ({} s) - {} events - {}", "{} ({} s) - {} Ereignisse - {}"),
+    ("Event sensitivity (dB above floor):", "Ereignisempfindlichkeit (dB über Untergrenze):"),
+    ("#{} {} - {} ({} s, peak {} dB)", "#{} {} - {} ({} s, Spitze {} dB)"),
+    ("Show", "Zeigen"),
+    ("Export", "Exportieren"),
+    ("Show All", "Alle zeigen"),
+    ("Slice to Hits", "In Anschläge zerlegen"),
+    ("Save every hit as its own trimmed one-shot in a subfolder, using the hit capture sensitivity and floor, with a kit patch mapping them from C1 up", "Jeden Anschlag als eigenes gekürztes One-Shot in einem Unterordner speichern, mit Empfindlichkeit und Untergrenze der Anschlagaufnahme und einem Kit-Patch, der sie ab C1 verteilt"),
+    ("Recovered Audio", "Wiederhergestelltes Audio"),
+    ("The app closed unexpectedly. The rolling buffer was saved as:", "Die App wurde unerwartet beendet. Der Puffer wurde gespeichert als:"),
+    ("Restore to save folder", "In den Speicherordner übernehmen"),
+    ("Short Grab", "Kurze Aufnahme"),
+    ("Only {} ms of audio has been captured.", "Es wurden erst {} ms Audio aufgenommen."),
+    ("Wait until {} s", "Bis {} s warten"),
+    ("Save anyway", "Trotzdem speichern"),
+    ("Session:", "Sitzung:"),
+    ("New", "Neu"),
+    ("Name:", "Name:"),
+    ("Take names:", "Aufnahmenamen:"),
+    ("{} takes", "{} Aufnahmen"),
+    ("Reset", "Zurücksetzen"),
+    ("Save folder, preset, buffer length and hot folder belong to the session", "Speicherordner, Preset, Pufferlänge und Hot Folder gehören zur Sitzung"),
+    ("Preset:", "Preset:"),
+    ("Add Performance", "Performance hinzufügen"),
+    ("Only Space to grab and Escape to discard", "Nur Leertaste zum Aufnehmen und Escape zum Verwerfen"),
+    ("Add Vinyl Sampling", "Vinyl-Sampling hinzufügen"),
+    ("De-click, gentle high-pass and normalization for turntables", "Entknacksen, sanfter Hochpass und Normalisierung für Plattenspieler"),
+    ("Metadata written to every grab made with this preset:", "Metadaten für jede Aufnahme mit diesem Preset:"),
+    ("key", "Schlüssel"),
+    ("value", "Wert"),
+    ("Add Field", "Feld hinzufügen"),
+    ("Processing applied to each grab before it is saved:", "Bearbeitung jeder Aufnahme vor dem Speichern:"),
+    ("Time-stretch from", "Zeitdehnung von"),
+    ("detected", "erkannt"),
+    ("to", "auf"),
+    ("De-click strength", "Entknackstärke"),
+    ("High-pass", "Hochpass"),
+    ("Normalize peak", "Spitze normalisieren"),
+    ("Loudness (put a Normalize after it to catch peaks)", "Lautheit (danach Normalisieren einfügen, um Spitzen abzufangen)"),
+    ("Also render as extra files:", "Zusätzlich als weitere Dateien erzeugen:"),
+    ("Controls while this preset is active:", "Steuerung, solange dieses Preset aktiv ist:"),
+    ("Press a key, button or pad...", "Taste, Knopf oder Pad drücken..."),
+    ("runs", "führt aus"),
+    ("command", "Befehl"),
+    ("Add Binding", "Belegung hinzufügen"),
+    ("Default Shortcuts", "Standardkürzel"),
+    ("Replace these bindings with the default keyboard shortcuts", "Diese Belegungen durch die Standard-Tastenkürzel ersetzen"),
+    ("Ableton Link", "Ableton Link"),
+    ("Search:", "Suche:"),
+    ("Words in the name, tags or metadata, or #tag for a tag", "Wörter in Name, Tags oder Metadaten, oder #tag für einen Tag"),
+    ("Any rating", "Jede Bewertung"),
+    ("tag, another tag", "Tag, weiterer Tag"),
+    ("Tags", "Tags"),
+    ("Edit the tags, kept with the rating in the take's sidecar", "Tags bearbeiten, die mit der Bewertung in der Begleitdatei der Aufnahme liegen"),
+    ("Gate", "Gate"),
+    ("Gain reduction", "Pegelreduktion"),
+    ("GRABBING", "AUFNAHME"),
+    ("Direct", "Direkt"),
+    ("Mono to all outputs", "Mono auf alle Ausgänge"),
+    ("Swap left/right", "Links/rechts tauschen"),
+    ("Custom", "Eigene"),
+    ("Low-latency linear", "Linear mit geringer Latenz"),
+    ("Balanced", "Ausgewogen"),
+    ("HQ sinc", "HQ-Sinc"),
+    ("Ask", "Fragen"),
+    ("Wait and extend", "Warten und verlängern"),
+    ("MIDI Timecode", "MIDI-Timecode"),
+    ("LTC on an input channel", "LTC auf einem Eingangskanal"),
+    ("Icecast", "Icecast"),
+    ("RTP", "RTP"),
+    ("Peak", "Spitze"),
+    ("RMS", "RMS"),
+    ("VU", "VU"),
+    ("Sum to mono", "Zu Mono summieren"),
+    ("L/R to mid/side", "L/R zu Mitte/Seite"),
+    ("Mid/side to L/R", "Mitte/Seite zu L/R"),
+    ("De-click", "Entknacksen"),
+    ("Normalize", "Normalisieren"),
+    ("Noise gate", "Noise Gate"),
+    ("Loudness", "Lautheit"),
+    ("Time-stretch", "Zeitdehnung"),
+    ("Channels", "Kanäle"),
+    ("Reversed", "Rückwärts"),
+    ("Half speed", "Halbe Geschwindigkeit"),
+    ("Double speed", "Doppelte Geschwindigkeit"),
+    ("Low", "Tiefen"),
+    ("Mid", "Mitten"),
+    ("High", "Höhen"),
+    ("Custom accent", "Eigene Akzentfarbe"),
+    ("Custom waveform", "Eigene Wellenformfarbe"),
+    ("Custom background", "Eigener Hintergrund"),
+];
+
+#[cfg(feature = "gui")]
+const SPANISH: &[(&str, &str)] = &[
+    ("Settings", "Ajustes"),
+    ("Mini Mode", "Modo mini"),
+    (
+        "Shrink to just the meters and the grab button",
+        "Reducir a los medidores y el botón de captura",
+    ),
+    ("Saving to", "Guardando en"),
+    (
+        "Pick a save folder in Settings before grabbing",
+        "Elige una carpeta de guardado en Ajustes antes de capturar",
+    ),
+    (
+        "Choose how much past audio to include in the recording:",
+        "Elige cuánto audio anterior incluir en la grabación:",
+    ),
+    ("Buffer Size (s):", "Tamaño del búfer (s):"),
+    ("Markers", "Marcadores"),
+    ("Metronome", "Metrónomo"),
+    ("Overdub", "Sobregrabación"),
+    ("Take Library", "Biblioteca de tomas"),
+    ("Auto-Sampler", "Muestreo automático"),
+    ("Clip Log", "Registro de saturación"),
+    ("Take History", "Historial de tomas"),
+    ("A/B Compare", "Comparación A/B"),
+    ("Last Grab & Events", "Última captura y eventos"),
+    ("Session Report", "Informe de sesión"),
+    (
+        "An HTML page listing every grab, saved in the save folder:",
+        "Una página HTML con todas las capturas, guardada en la carpeta de guardado:",
+    ),
+    ("Extending...", "Alargando..."),
+    ("Stop Grab", "Detener captura"),
+    ("Start Grab", "Iniciar captura"),
+    ("Devices & Monitoring", "Dispositivos y monitorización"),
+    ("Session", "Sesión"),
+    ("Save Folder", "Carpeta de guardado"),
+    ("Tracks", "Pistas"),
+    ("Select Save Folder", "Elegir carpeta de guardado"),
+    ("Selected Folder:", "Carpeta elegida:"),
+    ("Display", "Visualización"),
+    ("Preset & Metadata", "Preajuste y metadatos"),
+    ("Grab Options", "Opciones de captura"),
+    (
+        "Review each grab before saving",
+        "Revisar cada captura antes de guardarla",
+    ),
+    (
+        "Ask for a name after each grab",
+        "Pedir un nombre después de cada captura",
+    ),
+    (
+        "Warn about phase cancellation in stereo grabs",
+        "Avisar de cancelación de fase en capturas estéreo",
+    ),
+    ("DAW Hot Folder", "Carpeta de intercambio del DAW"),
+    (
+        "Every saved grab is also dropped into this folder:",
+        "Cada captura guardada se copia también en esta carpeta:",
+    ),
+    ("Safety Recording", "Grabación de seguridad"),
+    (
+        "Continuous segments in the save folder, separate from grabs",
+        "Segmentos continuos en la carpeta de guardado, aparte de las capturas",
+    ),
+    ("Live Stream", "Transmisión en directo"),
+    ("Scripting", "Scripts"),
+    (
+        "Opus to an Icecast server or an RTP listener, so others can listen in",
+        "Opus a un servidor Icecast o a un receptor RTP, para que otros puedan escuchar",
+    ),
+    ("Web Remote", "Control remoto web"),
+    ("Command Interface", "Interfaz de comandos"),
+    ("Appearance", "Apariencia"),
+    ("Scheduled Grabs", "Capturas programadas"),
+    (
+        "Save the rolling buffer at a set time or interval:",
+        "Guardar el búfer a una hora fija o a intervalos:",
+    ),
+    ("Diagnostics", "Diagnóstico"),
+    (
+        "Keep the window above other windows",
+        "Mantener la ventana encima de las demás",
+    ),
+    ("Theme:", "Tema:"),
+    ("Dark", "Oscuro"),
+    ("Light", "Claro"),
+    ("Stage", "Escenario"),
+    ("Language:", "Idioma:"),
+    (
+        "Grabs saved this session will be listed here.",
+        "Las capturas guardadas en esta sesión aparecerán aquí.",
+    ),
+    ("Play", "Reproducir"),
+    ("Stop", "Detener"),
+    ("Save", "Guardar"),
+    ("Cancel", "Cancelar"),
+    ("Rename", "Renombrar"),
+    ("Reveal", "Mostrar en carpeta"),
+    ("Delete", "Eliminar"),
+    ("Really delete?", "¿Eliminar de verdad?"),
+    ("Review Grab", "Revisar captura"),
+    ("Start:", "Inicio:"),
+    ("End:", "Fin:"),
+    ("Accept", "Aceptar"),
+    ("Discard", "Descartar"),
+    ("Record everything", "Grabar todo"),
+    ("Segment length (min):", "Duración del segmento (min):"),
+    ("Only while there is signal", "Solo mientras haya señal"),
+    ("Start a segment when the input crosses the threshold and pause after a long silence", "Empezar un segmento cuando la entrada supere el umbral y pausar tras un silencio largo"),
+    ("Threshold (dB):", "Umbral (dB):"),
+    ("Pause after silence (s):", "Pausa tras silencio (s):"),
+    ("Recording", "Grabando"),
+    ("Waiting for signal", "Esperando señal"),
+    ("{} ({} segments so far)", "{} ({} segmentos hasta ahora)"),
+    ("Stream the input", "Transmitir la entrada"),
+    ("To:", "A:"),
+    ("Host:", "Servidor:"),
+    ("Port:", "Puerto:"),
+    ("Mountpoint:", "Punto de montaje:"),
+    ("Password:", "Contraseña:"),
+    ("Opus bitrate (kbps):", "Tasa de bits Opus (kbps):"),
+    ("Streaming", "Transmitiendo"),
+    ("Connecting...", "Conectando..."),
+    ("{} ({} MB sent)", "{} ({} MB enviados)"),
+    ("Copy SDP", "Copiar SDP"),
+    ("Save it as a .sdp file for the listener to open in VLC or ffplay", "Guárdalo como archivo .sdp para que el oyente lo abra en VLC o ffplay"),
+    ("Name Take", "Nombrar toma"),
+    ("{} s grabbed", "{} s capturados"),
+    ("Keep {}", "Conservar {}"),
+    ("Averaging:", "Promediado:"),
+    ("Peak hold", "Retener picos"),
+    ("Reset Peak", "Reiniciar pico"),
+    ("dB scale", "Escala en dB"),
+    ("Show levels from {} dBFS up, so quiet material is visible", "Mostrar niveles desde {} dBFS, para que se vea el material suave"),
+    ("Overlay channels", "Superponer canales"),
+    ("Ch", "Can"),
+    ("{} s captured", "{} s capturados"),
+    ("{} s / {} s buffered", "{} s / {} s en el búfer"),
+    ("{} clip events (at or above {} dBFS)", "{} saturaciones (en o por encima de {} dBFS)"),
+    ("Reset Lights", "Reiniciar indicadores"),
+    ("Clear Log", "Borrar registro"),
+    ("{}  Ch {}  {} samples", "{}  Can {}  {} muestras"),
+    ("Write a report when quitting", "Escribir un informe al salir"),
+    ("Write Report Now", "Escribir informe ahora"),
+    ("{} grabs saved since {}", "{} capturas guardadas desde las {}"),
+    ("Search a Folder...", "Buscar en una carpeta..."),
+    ("Find takes saved before, by their ratings, tags and metadata", "Encontrar tomas guardadas antes por su valoración, etiquetas y metadatos"),
+    ("Rescan", "Volver a buscar"),
+    ("{} of {} takes", "{} de {} tomas"),
+    ("Save at least two grabs to compare them.", "Guarda al menos dos capturas para compararlas."),
+    ("Choose a take", "Elige una toma"),
+    ("Hearing {} (switch)", "Escuchando {} (cambiar)"),
+    ("B is {} dB against A, level matched", "B está a {} dB respecto a A, con nivel igualado"),
+    ("Play A/B", "Reproducir A/B"),
+    ("Select Hot Folder", "Elegir carpeta de intercambio"),
+    ("Off", "Apagado"),
+    ("Name files Take 001, Take 002...", "Nombrar los archivos Take 001, Take 002..."),
+    ("Run after each drop:", "Ejecutar tras cada copia:"),
+    ("command, file in $ROLLING_SAMPLER_FILE", "comando, archivo en $ROLLING_SAMPLER_FILE"),
+    ("Run after each save:", "Ejecutar tras cada guardado:"),
+    ("e.g. rclone copy {file} remote:samples", "p. ej. rclone copy {file} remote:samples"),
+    ("{file} is replaced by the saved file's path, which is added at the end if it isn't used", "{file} se sustituye por la ruta del archivo guardado, que se añade al final si no se usa"),
+    ("Armed channels are saved, the rest left out", "Se guardan los canales armados, el resto se omite"),
+    ("Also save a mono file per channel", "Guardar también un archivo mono por canal"),
+    ("In a folder next to each take, named after the channels", "En una carpeta junto a cada toma, con el nombre de los canales"),
+    ("Channel names go into each file's iXML track list, for DAWs to split it by", "Los nombres de canal van a la lista de pistas iXML de cada archivo, para que los DAW la dividan"),
+    ("Drop Marker", "Poner marcador"),
+    ("Export length around a marker (s):", "Duración a exportar alrededor de un marcador (s):"),
+    ("No markers in the buffer.", "No hay marcadores en el búfer."),
+    ("Marker {} at {} ({} ago)", "Marcador {} a las {} (hace {})"),
+    ("Export Around", "Exportar alrededor"),
+    ("Export from marker", "Exportar desde el marcador"),
+    ("Or click a marker on the waveform for the start, right click for the end", "O haz clic en un marcador de la forma de onda para el inicio, clic derecho para el final"),
+    ("to marker", "hasta el marcador"),
+    ("Export Range", "Exportar rango"),
+    ("{} export(s) waiting for audio after the marker...", "{} exportación(es) esperando el audio tras el marcador..."),
+    ("Auto-capture hits", "Capturar golpes automáticamente"),
+    ("Save a short one-shot around every detected transient", "Guardar un one-shot corto alrededor de cada transitorio detectado"),
+    ("Sensitivity (dB):", "Sensibilidad (dB):"),
+    ("Floor (dB):", "Suelo (dB):"),
+    ("Pre-roll (ms):", "Preroll (ms):"),
+    ("Length (ms):", "Duración (ms):"),
+    ("{} hits captured", "{} golpes capturados"),
+    ("No shortcut, add one under Preset & Metadata", "Sin atajo, añade uno en Preajuste y metadatos"),
+    ("Shortcut:", "Atajo:"),
+    ("Listen on a local control socket", "Escuchar en un socket de control local"),
+    ("Commands (also on stdin):", "Comandos (también por stdin):"),
+    ("Select Script", "Elegir script"),
+    ("Reload", "Recargar"),
+    ("No hooks defined", "No hay hooks definidos"),
+    ("Hooks:", "Hooks:"),
+    ("on_silence after (s):", "on_silence tras (s):"),
+    ("below (dB):", "por debajo de (dB):"),
+    ("Hooks: {}. Calls: command(\"grab\"), tag(key, value), notify(text), osc_send(\"host:port\", \"/address\", [args]). The file is reloaded when it changes.", "Hooks: {}. Llamadas: command(\"grab\"), tag(key, value), notify(text), osc_send(\"host:port\", \"/address\", [args]). El archivo se recarga cuando cambia."),
+    ("Timecode from:", "Código de tiempo desde:"),
+    ("Channel:", "Canal:"),
+    ("Waiting for timecode...", "Esperando código de tiempo..."),
+    ("Add the start timecode to file names", "Añadir el código de tiempo inicial a los nombres de archivo"),
+    ("Takes get their start timecode as a BWF time reference and in the metadata", "Las tomas llevan su código de tiempo inicial como referencia BWF y en los metadatos"),
+    ("{} of {}", "{} de {}"),
+    ("Playing {} at velocity {}", "Tocando {} con velocidad {}"),
+    ("MIDI output:", "Salida MIDI:"),
+    ("None", "Ninguna"),
+    ("Look for outputs again", "Buscar salidas de nuevo"),
+    ("Notes:", "Notas:"),
+    ("every", "cada"),
+    ("semitones", "semitonos"),
+    ("Velocities:", "Velocidades:"),
+    ("Another velocity layer", "Otra capa de velocidad"),
+    ("Hold (s):", "Mantener (s):"),
+    ("Release (s):", "Liberación (s):"),
+    ("Trim below (dB):", "Recortar por debajo de (dB):"),
+    ("Instrument:", "Instrumento:"),
+    ("Patches:", "Patches:"),
+    ("Written for the notes of each run, and for Slice to Hits", "Se escriben para las notas de cada pasada y para Dividir en golpes"),
+    ("Start", "Iniciar"),
+    ("{} notes, about {} minutes", "{} notas, unos {} minutos"),
+    ("Each note is saved into a folder named after the instrument, trimmed to where it sounds", "Cada nota se guarda en una carpeta con el nombre del instrumento, recortada a donde suena"),
+    ("Plays a grab back through the output while you record a new layer over it.", "Reproduce una captura por la salida mientras grabas una nueva capa encima."),
+    ("Layer Over Last Grab", "Grabar capa sobre la última captura"),
+    ("Grab something first", "Captura algo primero"),
+    ("Measure the round-trip latency (under Devices & Monitoring) so layers line up exactly.", "Mide la latencia de ida y vuelta (en Dispositivos y monitorización) para que las capas encajen exactamente."),
+    ("Bed:", "Base:"),
+    ("Pass {}", "Pasada {}"),
+    ("Save Layer", "Guardar capa"),
+    ("Save Bounce", "Guardar mezcla"),
+    ("The bed and the new layer mixed together", "La base y la nueva capa mezcladas"),
+    ("Layer Again", "Otra capa"),
+    ("Mix this layer into the bed and record another over both", "Mezclar esta capa en la base y grabar otra sobre ambas"),
+    ("Redo", "Repetir"),
+    ("Record Layer", "Grabar capa"),
+    ("Done", "Hecho"),
+    ("Click", "Clic"),
+    ("Plays in the monitoring output only, never in what's recorded. Starts monitoring if it's off.", "Suena solo en la salida de monitorización, nunca en lo grabado. Activa la monitorización si está apagada."),
+    ("Tempo:", "Tempo:"),
+    ("From Link", "Desde Link"),
+    ("Beats per bar:", "Tiempos por compás:"),
+    ("Count-in:", "Cuenta previa:"),
+    ("Keep clicking", "Seguir con el clic"),
+    ("Off to hear only the count-in", "Apagado para oír solo la cuenta previa"),
+    ("Level:", "Nivel:"),
+    ("Follow Ableton Link", "Seguir Ableton Link"),
+    ("Takes the tempo and bar lines from a DAW or drum machine on the network", "Toma el tempo y los compases de un DAW o caja de ritmos en la red"),
+    ("{} BPM, bar {}, beat {}, {} peers", "{} BPM, compás {}, tiempo {}, {} participantes"),
+    ("Measuring the session's clock...", "Midiendo el reloj de la sesión..."),
+    ("No Link session found", "No se encontró ninguna sesión Link"),
+    ("Bars:", "Compases:"),
+    ("Grab Last Bars", "Capturar los últimos compases"),
+    ("Enable web remote", "Activar el control remoto web"),
+    ("Open on your phone:", "Abrir en el móvil:"),
+    ("Input Device:", "Dispositivo de entrada:"),
+    ("Look for devices plugged in since the app started", "Buscar dispositivos conectados desde que se inició la aplicación"),
+    ("Input High-Pass:", "Paso alto de entrada:"),
+    ("Filters out handling rumble and plosives before they're recorded", "Filtra ruidos de manipulación y oclusivas antes de grabarlos"),
+    ("Remove DC Offset", "Quitar el desplazamiento de CC"),
+    ("For interfaces that add a constant bias, which eats headroom and upsets normalizing", "Para interfaces que añaden un desplazamiento constante, que resta margen y estropea la normalización"),
+    ("Noise Gate on Input", "Puerta de ruido en la entrada"),
+    ("Gate the input before it's buffered. Add a gate to the preset's processing to gate only what's saved instead.", "Aplica la puerta a la entrada antes del búfer. Para aplicarla solo a lo guardado, añade una puerta al procesado del preajuste."),
+    ("Limiter on Input", "Limitador en la entrada"),
+    ("Holds sudden loud moments under the threshold so the capture doesn't clip", "Mantiene los momentos fuertes repentinos bajo el umbral para que la captura no sature"),
+    ("release ", "liberación "),
+    ("How quickly the level comes back up after being held down", "Qué tan rápido vuelve a subir el nivel tras ser reducido"),
+    ("Output Device:", "Dispositivo de salida:"),
+    ("Enable Monitoring", "Activar monitorización"),
+    ("Monitor Routing:", "Enrutado de monitorización:"),
+    ("Monitor Gain:", "Ganancia de monitorización:"),
+    ("Monitor EQ", "EQ de monitorización"),
+    ("Hear how the sound would sit after EQ. Only the monitoring is affected, never what's recorded.", "Escucha cómo quedaría el sonido tras el EQ. Solo afecta a la monitorización, nunca a lo grabado."),
+    ("Flat", "Plano"),
+    ("Monitor Resampler:", "Remuestreador de monitorización:"),
+    ("Converts to the output rate and follows drift between the two clocks, better ones add delay", "Convierte a la frecuencia de salida y sigue la deriva entre ambos relojes, los mejores añaden retardo"),
+    ("Monitor Latency:", "Latencia de monitorización:"),
+    ("Audio held back to ride out scheduling jitter, lower is tighter", "Audio retenido para absorber el jitter de planificación, menos es más ajustado"),
+    ("Minimize automatically", "Minimizar automáticamente"),
+    ("Start from this latency, lower it while playback stays clean and raise it after a dropout", "Empezar con esta latencia, bajarla mientras la reproducción sea limpia y subirla tras un corte"),
+    ("Headphone Limiter", "Limitador de auriculares"),
+    ("Catches feedback and hot transients before they reach your ears", "Frena realimentaciones y transitorios fuertes antes de que lleguen a tus oídos"),
+    ("Ceiling:", "Techo:"),
+    ("Listening for the clicks...", "Escuchando los clics..."),
+    ("Measure Round-Trip Latency", "Medir latencia de ida y vuelta"),
+    ("Plays a few clicks out of the output and times them coming back on the input. Connect an output to an input with a cable first, or put a mic by a speaker.", "Reproduce unos clics por la salida y mide cuándo vuelven por la entrada. Conecta antes una salida a una entrada con un cable, o pon un micrófono junto a un altavoz."),
+    ("Last measured:", "Última medición:"),
+    ("Measured: {} ms (input {} + FIFO {} + output {} + resampler {} + limiter {})", "Medido: {} ms (entrada {} + FIFO {} + salida {} + remuestreador {} + limitador {})"),
+    ("FIFO target now", "Objetivo del FIFO ahora"),
+    ("In {}", "Ent {}"),
+    ("Out {}: {}", "Sal {}: {}"),
+    ("Meters:", "Medidores:"),
+    ("Reference:", "Referencia:"),
+    ("The level your console reads as 0, e.g. -18 dBFS for EBU or -20 dBFS for SMPTE", "El nivel que tu consola marca como 0, p. ej. -18 dBFS para EBU o -20 dBFS para SMPTE"),
+    ("Peak hold:", "Retención de picos:"),
+    ("Spectrogram", "Espectrograma"),
+    ("Spectrum", "Espectro"),
+    ("Tuner", "Afinador"),
+    ("Timecode", "Código de tiempo"),
+    ("Detect the tempo of each grab", "Detectar el tempo de cada captura"),
+    ("in the file name", "en el nombre del archivo"),
+    ("Round to whole bars", "Redondear a compases completos"),
+    ("Trim the start of the grab so it's a whole number of 4/4 bars long", "Recortar el inicio de la captura para que dure un número entero de compases de 4/4"),
+    ("Detect the key of each grab", "Detectar la tonalidad de cada captura"),
+    ("Written to the metadata and shown in the take history, for melodic ideas", "Se escribe en los metadatos y se muestra en el historial de tomas, para ideas melódicas"),
+    ("Tag each take with its note", "Etiquetar cada toma con su nota"),
+    ("Written to the metadata for samplers that key map automatically", "Se escribe en los metadatos para samplers que asignan teclas automáticamente"),
+    ("in the file name too", "también en el nombre del archivo"),
+    ("Grabbing +{} s", "Capturando +{} s"),
+    ("{} s buffered", "{} s en el búfer"),
+    ("Expand", "Ampliar"),
+    ("On top", "Encima"),
+    ("Get ready...", "Prepárate..."),
+    ("Play now! Saving in {} s", "¡Toca ahora! Guardando en {} s"),
+    ("Cancel Delayed Grab", "Cancelar captura diferida"),
+    ("Grab After Countdown", "Capturar tras la cuenta atrás"),
+    ("Copy Diagnostics", "Copiar diagnóstico"),
+    ("Copy device and stream details for a bug report", "Copiar los detalles del dispositivo y del flujo para un informe de error"),
+    ("Save Diagnostics...", "Guardar diagnóstico..."),
+    ("Input failed", "Fallo en la entrada"),
+    ("(no input device)", "(sin dispositivo de entrada)"),
+    ("DC offset on the input, being removed", "Desplazamiento de CC en la entrada, se está quitando"),
+    ("DC offset on the input, turn on Remove DC Offset in Settings to take it out", "Desplazamiento de CC en la entrada, activa Quitar el desplazamiento de CC en Ajustes para eliminarlo"),
+    ("Monitoring at", "Monitorizando a"),
+    (" (resampled)", " (remuestreado)"),
+    ("Monitoring latency from input to output", "Latencia de monitorización de la entrada a la salida"),
+    ("Limiting", "Limitando"),
+    ("Input limiting", "Limitando la entrada"),
+    ("The capture limiter is holding the input under its threshold", "El limitador de captura mantiene la entrada bajo su umbral"),
+    ("Share of real time spent in the input callback", "Proporción del tiempo real empleada en la rutina de entrada"),
+    ("Xruns {}  Underruns {}", "Xruns {}  Vaciados {}"),
+    ("{} stream errors", "{} errores de flujo"),
+    ("Keep the buffer empty until the input crosses the threshold, then start the grab at the hit", "Mantener el búfer vacío hasta que la entrada supere el umbral y empezar la captura en el golpe"),
+    ("Wait for signal", "Esperar señal"),
+    ("Armed - waiting for signal...", "Armado - esperando señal..."),
+    ("Voice activated (VOX)", "Activado por voz (VOX)"),
+    ("Grab automatically while there is signal, one file per utterance", "Capturar automáticamente mientras haya señal, un archivo por intervención"),
+    ("Hang time (s):", "Tiempo de espera (s):"),
+    ("Pre-roll (s):", "Preroll (s):"),
+    ("VOX listening...", "VOX escuchando..."),
+    ("Minimum grab length (s):", "Duración mínima de captura (s):"),
+    ("When a grab is shorter:", "Cuando una captura es más corta:"),
+    ("At", "A las"),
+    ("Every", "Cada"),
+    ("last ", "últimos "),
+    ("Seconds saved from the end of the buffer, 0 for all of it", "Segundos guardados del final del búfer, 0 para todo"),
+    ("next:", "siguiente:"),
+    ("inactive", "inactiva"),
+    ("Add Schedule", "Añadir programación"),
+    ("Gamepad/footswitch grab button:", "Botón de captura del mando/pedal:"),
+    ("press a button...", "pulsa un botón..."),
+    ("none", "ninguno"),
+    ("Learn", "Aprender"),
+    ("Clear", "Borrar"),
+    ("{} more waiting", "{} más en espera"),
+    ("Loop", "Bucle"),
+    ("Crossfade the end into the audio before the start so the part loops cleanly", "Fundir el final con el audio anterior al inicio para que la parte haga bucle limpio"),
+    ("Snap to zero crossings", "Ajustar a cruces por cero"),
+    ("Crossfade:", "Fundido:"),
+    ("Punch", "Pinchazo"),
+    ("Replace a part of the take by playing it again, along to the rest of the take", "Sustituir una parte de la toma tocándola de nuevo sobre el resto de la toma"),
+    ("Pre-roll:", "Preroll:"),
+    ("Punch In:", "Entrada:"),
+    ("Punch Out:", "Salida:"),
+    ("Punching", "Pinchando"),
+    ("Punch In", "Pinchar"),
+    ("Undo Punch", "Deshacer pinchazo"),
+    ("left", "izquierdo"),
+    ("right", "derecho"),
+    ("The stereo grab loses {} dB when summed to mono (correlation {}). The channels are probably out of phase.", "La captura estéreo pierde {} dB al sumarla a mono (correlación {}). Es probable que los canales estén en contrafase."),
+    ("Mono Compatibility", "Compatibilidad mono"),
+    ("Save as is", "Guardar tal cual"),
+    ("Flip right polarity", "Invertir la polaridad derecha"),
+    ("Save mono ({})", "Guardar mono ({})"),
+    ("No grabs yet.", "Aún no hay capturas."),
+    ("loudness unmeasured", "sonoridad sin medir"),
+    ("{} ({} s) - {} events - {}", "{} ({} s) - {} eventos - {}"),
+    ("Event sensitivity (dB above floor):", "Sensibilidad de eventos (dB sobre el suelo):"),
+    ("#{} {} - {} ({} s, peak {} dB)", "#{} {} - {} ({} s, pico {} dB)"),
+    ("Show", "Mostrar"),
+    ("Export", "Exportar"),
+    ("Show All", "Mostrar todo"),
+    ("Slice to Hits", "Dividir en golpes"),
+    ("Save every hit as its own trimmed one-shot in a subfolder, using the hit capture sensitivity and floor, with a kit patch mapping them from C1 up", "Guardar cada golpe como un one-shot recortado en una subcarpeta, con la sensibilidad y el suelo de la captura de golpes y un patch de kit que los asigna desde C1"),
+    ("Recovered Audio", "Audio recuperado"),
+    ("The app closed unexpectedly. The rolling buffer was saved as:", "La aplicación se cerró inesperadamente. El búfer se guardó como:"),
+    ("Restore to save folder", "Restaurar en la carpeta de guardado"),
+    ("Short Grab", "Captura corta"),
+    ("Only {} ms of audio has been captured.", "Solo se han capturado {} ms de audio."),
+    ("Wait until {} s", "Esperar hasta {} s"),
+    ("Save anyway", "Guardar de todos modos"),
+    ("Session:", "Sesión:"),
+    ("New", "Nueva"),
+    ("Name:", "Nombre:"),
+    ("Take names:", "Nombres de toma:"),
+    ("{} takes", "{} tomas"),
+    ("Reset", "Reiniciar"),
+    ("Save folder, preset, buffer length and hot folder belong to the session", "La carpeta de guardado, el preajuste, la duración del búfer y la carpeta de intercambio pertenecen a la sesión"),
+    ("Preset:", "Preajuste:"),
+    ("Add Performance", "Añadir Actuación"),
+    ("Only Space to grab and Escape to discard", "Solo Espacio para capturar y Escape para descartar"),
+    ("Add Vinyl Sampling", "Añadir Muestreo de vinilo"),
+    ("De-click, gentle high-pass and normalization for turntables", "Eliminación de clics, paso alto suave y normalización para tocadiscos"),
+    ("Metadata written to every grab made with this preset:", "Metadatos escritos en cada captura hecha con este preajuste:"),
+    ("key", "clave"),
+    ("value", "valor"),
+    ("Add Field", "Añadir campo"),
+    ("Processing applied to each grab before it is saved:", "Procesado aplicado a cada captura antes de guardarla:"),
+    ("Time-stretch from", "Estirar el tiempo de"),
+    ("detected", "detectado"),
+    ("to", "a"),
+    ("De-click strength", "Intensidad de eliminación de clics"),
+    ("High-pass", "Paso alto"),
+    ("Normalize peak", "Normalizar pico"),
+    ("Loudness (put a Normalize after it to catch peaks)", "Sonoridad (pon un Normalizar detrás para contener los picos)"),
+    ("Also render as extra files:", "Generar también como archivos extra:"),
+    ("Controls while this preset is active:", "Controles mientras este preajuste está activo:"),
+    ("Press a key, button or pad...", "Pulsa una tecla, botón o pad..."),
+    ("runs", "ejecuta"),
+    ("command", "comando"),
+    ("Add Binding", "Añadir asignación"),
+    ("Default Shortcuts", "Atajos predeterminados"),
+    ("Replace these bindings with the default keyboard shortcuts", "Sustituir estas asignaciones por los atajos de teclado predeterminados"),
+    ("Ableton Link", "Ableton Link"),
+    ("Search:", "Buscar:"),
+    ("Words in the name, tags or metadata, or #tag for a tag", "Palabras del nombre, las etiquetas o los metadatos, o #etiqueta para una etiqueta"),
+    ("Any rating", "Cualquier valoración"),
+    ("tag, another tag", "etiqueta, otra etiqueta"),
+    ("Tags", "Etiquetas"),
+    ("Edit the tags, kept with the rating in the take's sidecar", "Editar las etiquetas, guardadas con la valoración en el archivo adjunto de la toma"),
+    ("Gate", "Puerta"),
+    ("Gain reduction", "Reducción de ganancia"),
+    ("GRABBING", "CAPTURANDO"),
+    ("Direct", "Directo"),
+    ("Mono to all outputs", "Mono a todas las salidas"),
+    ("Swap left/right", "Intercambiar izquierda/derecha"),
+    ("Custom", "Personalizado"),
+    ("Low-latency linear", "Lineal de baja latencia"),
+    ("Balanced", "Equilibrado"),
+    ("HQ sinc", "Sinc de alta calidad"),
+    ("Ask", "Preguntar"),
+    ("Wait and extend", "Esperar y alargar"),
+    ("MIDI Timecode", "Código de tiempo MIDI"),
+    ("LTC on an input channel", "LTC en un canal de entrada"),
+    ("Icecast", "Icecast"),
+    ("RTP", "RTP"),
+    ("Peak", "Pico"),
+    ("RMS", "RMS"),
+    ("VU", "VU"),
+    ("Sum to mono", "Sumar a mono"),
+    ("L/R to mid/side", "L/R a medio/lateral"),
+    ("Mid/side to L/R", "Medio/lateral a L/R"),
+    ("De-click", "Eliminar clics"),
+    ("Normalize", "Normalizar"),
+    ("Noise gate", "Puerta de ruido"),
+    ("Loudness", "Sonoridad"),
+    ("Time-stretch", "Estiramiento de tiempo"),
+    ("Channels", "Canales"),
+    ("Reversed", "Invertido"),
+    ("Half speed", "Media velocidad"),
+    ("Double speed", "Doble velocidad"),
+    ("Low", "Graves"),
+    ("Mid", "Medios"),
+    ("High", "Agudos"),
+    ("Custom accent", "Color de acento propio"),
+    ("Custom waveform", "Color de forma de onda propio"),
+    ("Custom background", "Fondo propio"),
+];
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use crate::config::{BroadcastTarget, ShortGrabPolicy, TimecodeSource};
+    use crate::meter::Ballistics;
+    use crate::theme::Theme;
+    use rolling_sampler_core::export::{ChannelMode, ExportStage, ExportVariant};
+    use rolling_sampler_core::monitor::MonitorResampler;
+    use rolling_sampler_core::routing::MonitorRouting;
+
+    // Every literal the window passes to `tr` or `tr_fill`, unescaped like the compiler would
+    fn gui_keys() -> Vec<String> {
+        let source = include_str!("gui.rs");
+        let mut keys = Vec::new();
+        for call in ["tr(\"", "tr_fill(\""] {
+            for (start, _) in source.match_indices(call) {
+                let before = source[..start].chars().next_back();
+                if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let mut key = String::new();
+                let mut chars = source[start + call.len()..].chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => key.extend(chars.next()),
+                        _ => key.push(c),
+                    }
+                }
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    // Labels that reach `tr` through a variable rather than as a literal
+    fn other_keys() -> Vec<String> {
+        let mut keys: Vec<String> = ["Low", "Mid", "High"]
+            .into_iter()
+            .chain(["Custom accent", "Custom waveform", "Custom background"])
+            .chain(Theme::ALL.iter().map(|theme| theme.label()))
+            .chain(Ballistics::ALL.iter().map(|ballistics| ballistics.label()))
+            .chain(ShortGrabPolicy::ALL.iter().map(|policy| policy.label()))
+            .chain(TimecodeSource::ALL.iter().map(|source| source.label()))
+            .chain(BroadcastTarget::ALL.iter().map(|target| target.label()))
+            .chain(MonitorRouting::ALL.iter().map(|routing| routing.label()))
+            .chain(
+                MonitorResampler::ALL
+                    .iter()
+                    .map(|resampler| resampler.label()),
+            )
+            .chain(ChannelMode::ALL.iter().map(|mode| mode.label()))
+            .chain(ExportStage::DEFAULTS.iter().map(|stage| stage.name()))
+            .map(str::to_owned)
+            .collect();
+        keys.extend(ExportVariant::DEFAULTS.iter().map(|variant| variant.name()));
+        keys
+    }
+
+    #[test]
+    fn every_label_is_translated() {
+        let keys = gui_keys();
+        assert!(keys.len() > 300, "only found {} keys in gui.rs", keys.len());
+        for key in keys.iter().chain(&other_keys()) {
+            for language in [Language::German, Language::Spanish] {
+                assert!(
+                    language.table().iter().any(|(english, _)| english == key),
+                    "{:?} has no {} translation",
+                    key,
+                    language.label()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tables_match() {
+        for language in [Language::German, Language::Spanish] {
+            let table = language.table();
+            for (index, (english, translated)) in table.iter().enumerate() {
+                assert!(
+                    !table[..index].iter().any(|(other, _)| other == english),
+                    "{:?} is in the {} table twice",
+                    english,
+                    language.label()
+                );
+                assert_eq!(
+                    english.matches("{}").count(),
+                    translated.matches("{}").count(),
+                    "{:?} has different placeholders in {}",
+                    english,
+                    language.label()
+                );
+            }
+        }
+        let german: Vec<_> = GERMAN.iter().map(|(english, _)| english).collect();
+        let spanish: Vec<_> = SPANISH.iter().map(|(english, _)| english).collect();
+        assert_eq!(german, spanish);
+    }
+}
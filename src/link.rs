@@ -0,0 +1,323 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Follows an Ableton Link session on the LAN for its tempo and beat grid, so grabs can be cut
+// on bar lines. It only listens: peers announce themselves and their timeline over multicast,
+// and pinging one of them gives the offset between our clock and the session's shared one.
+// Nothing is announced back, so the app never changes the session's tempo and doesn't show up
+// as a peer in the DAW.
+
+const MULTICAST: Ipv4Addr = Ipv4Addr::new(224, 76, 78, 75);
+const DISCOVERY_PORT: u16 = 20808;
+const DISCOVERY_HEADER: &[u8; 8] = b"_asdp_v\x01";
+const MEASUREMENT_HEADER: &[u8; 8] = b"_link_v\x01";
+const BYE_BYE: u8 = 3; // Discovery message types, the others (alive, response) carry a state
+const PING: u8 = 1; // Measurement message types
+const PONG: u8 = 2;
+const PINGS: usize = 5; // Round trips per measurement, the median is kept
+const REMEASURE: Duration = Duration::from_secs(10); // The clocks drift apart slowly
+const POLL: Duration = Duration::from_millis(50);
+
+type NodeId = [u8; 8];
+
+// Microseconds on our own clock, the host time the session's clock is measured against
+fn host_micros(at: Instant) -> i64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    let start = *START.get_or_init(Instant::now);
+    if at >= start {
+        at.duration_since(start).as_micros() as i64
+    } else {
+        -(start.duration_since(at).as_micros() as i64)
+    }
+}
+
+// A session's beat grid: the beat at a time on the shared clock, and how fast it moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RawTimeline {
+    micros_per_beat: i64,
+    beat_origin: i64, // Millionths of a beat
+    time_origin: i64, // Shared clock microseconds
+}
+
+// A session's beat grid on our clock
+#[derive(Clone, Copy, Debug)]
+pub struct Timeline {
+    raw: RawTimeline,
+    ghost_offset: i64, // Shared clock minus our clock, in microseconds
+}
+
+impl Timeline {
+    pub fn bpm(&self) -> f64 {
+        60_000_000.0 / self.raw.micros_per_beat as f64
+    }
+
+    pub fn beat_at(&self, at: Instant) -> f64 {
+        let ghost = host_micros(at) + self.ghost_offset;
+        (self.raw.beat_origin as f64
+            + (ghost - self.raw.time_origin) as f64 * 1_000_000.0 / self.raw.micros_per_beat as f64)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinkStatus {
+    #[cfg(feature = "gui")]
+    pub peers: usize, // In the session being followed
+    pub timeline: Option<Timeline>, // Once a peer has been heard and measured
+}
+
+struct Peer {
+    session: NodeId,
+    timeline: RawTimeline,
+    endpoint: Option<SocketAddrV4>, // Where it answers pings
+    heard: Instant,
+    expires: Instant,
+}
+
+pub struct LinkFollower {
+    status: Arc<Mutex<LinkStatus>>,
+    running: Arc<AtomicBool>,
+}
+
+impl LinkFollower {
+    pub fn start() -> std::io::Result<Self> {
+        // Other Link apps on this machine listen on the same port
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT).into())?;
+        socket.join_multicast_v4(&MULTICAST, &Ipv4Addr::UNSPECIFIED)?;
+        let discovery: UdpSocket = socket.into();
+        discovery.set_read_timeout(Some(POLL))?;
+        let measurement = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        measurement.set_nonblocking(true)?;
+
+        let follower = LinkFollower {
+            status: Arc::new(Mutex::new(LinkStatus::default())),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let status = Arc::clone(&follower.status);
+        let running = Arc::clone(&follower.running);
+        thread::spawn(move || {
+            let mut listener = Listener::default();
+            while running.load(Ordering::SeqCst) {
+                listener.receive(&discovery, &measurement);
+                listener.measure(&measurement);
+                *status.lock().unwrap() = listener.status();
+            }
+        });
+        println!("Following Ableton Link sessions on the network");
+        Ok(follower)
+    }
+
+    pub fn status(&self) -> LinkStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+impl Drop for LinkFollower {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+struct Listener {
+    peers: HashMap<NodeId, Peer>,
+    session: Option<NodeId>,
+    offsets: Vec<i64>, // From the pongs of the measurement in progress
+    ghost_offset: Option<i64>,
+    measured: Option<Instant>,
+    pings_sent: usize,
+}
+
+impl Listener {
+    fn receive(&mut self, discovery: &UdpSocket, measurement: &UdpSocket) {
+        let mut packet = [0u8; 512];
+        match discovery.recv_from(&mut packet) {
+            Ok((len, SocketAddr::V4(from))) => self.on_discovery(&packet[..len], from),
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => eprintln!("Link discovery failed: {}", e),
+        }
+        while let Ok(len) = measurement.recv(&mut packet) {
+            self.on_pong(&packet[..len]);
+        }
+
+        let now = Instant::now();
+        self.peers.retain(|_, peer| peer.expires > now);
+        // Follow the session with the most peers, as a new peer joining would
+        let mut sizes: HashMap<NodeId, usize> = HashMap::new();
+        for peer in self.peers.values() {
+            *sizes.entry(peer.session).or_default() += 1;
+        }
+        let session = sizes
+            .into_iter()
+            .max_by_key(|&(session, size)| (size, std::cmp::Reverse(session)))
+            .map(|(session, _)| session);
+        if session != self.session {
+            self.session = session;
+            self.ghost_offset = None;
+            self.measured = None;
+        }
+    }
+
+    // Alive and response messages: header, sender, then the sender's state as tagged entries
+    fn on_discovery(&mut self, packet: &[u8], from: SocketAddrV4) {
+        let Some(rest) = packet.strip_prefix(DISCOVERY_HEADER) else {
+            return;
+        };
+        if rest.len() < 12 {
+            return;
+        }
+        let (kind, ttl) = (rest[0], rest[1]);
+        let id: NodeId = rest[4..12].try_into().unwrap();
+        if kind == BYE_BYE {
+            self.peers.remove(&id);
+            return;
+        }
+        let (mut session, mut timeline, mut endpoint) = (None, None, None);
+        for (key, value) in entries(&rest[12..]) {
+            match &key {
+                b"sess" if value.len() >= 8 => session = value[..8].try_into().ok(),
+                b"tmln" if value.len() >= 24 => {
+                    timeline = Some(RawTimeline {
+                        micros_per_beat: read_i64(&value[0..8]),
+                        beat_origin: read_i64(&value[8..16]),
+                        time_origin: read_i64(&value[16..24]),
+                    })
+                }
+                b"mep4" if value.len() >= 6 => {
+                    let ip = Ipv4Addr::new(value[0], value[1], value[2], value[3]);
+                    let port = u16::from_be_bytes([value[4], value[5]]);
+                    // Peers on this machine may announce an address we can't reach them on
+                    let ip = if ip.is_unspecified() { *from.ip() } else { ip };
+                    endpoint = Some(SocketAddrV4::new(ip, port));
+                }
+                _ => {}
+            }
+        }
+        let (Some(session), Some(timeline)) = (session, timeline) else {
+            return;
+        };
+        if timeline.micros_per_beat <= 0 {
+            return;
+        }
+        self.peers.insert(
+            id,
+            Peer {
+                session,
+                timeline,
+                endpoint,
+                heard: Instant::now(),
+                expires: Instant::now() + Duration::from_secs(ttl.max(1) as u64),
+            },
+        );
+    }
+
+    // Answers carry the session's clock when the ping was answered and our send time echoed,
+    // so the offset is taken at the middle of the round trip
+    fn on_pong(&mut self, packet: &[u8]) {
+        let Some(rest) = packet.strip_prefix(MEASUREMENT_HEADER) else {
+            return;
+        };
+        if rest.first() != Some(&PONG) {
+            return;
+        }
+        let (mut ghost, mut sent) = (None, None);
+        for (key, value) in entries(&rest[1..]) {
+            match &key {
+                b"__gt" if value.len() >= 8 => ghost = Some(read_i64(value)),
+                b"hst_" if value.len() >= 8 => sent = Some(read_i64(value)),
+                _ => {}
+            }
+        }
+        if let (Some(ghost), Some(sent)) = (ghost, sent) {
+            let now = host_micros(Instant::now());
+            self.offsets.push(ghost - (sent + now) / 2);
+        }
+        if self.offsets.len() >= PINGS {
+            self.offsets.sort_unstable();
+            self.ghost_offset = Some(self.offsets[self.offsets.len() / 2]);
+            self.offsets.clear();
+        }
+    }
+
+    // Ping a peer of the followed session, a few times in a row, every so often
+    fn measure(&mut self, measurement: &UdpSocket) {
+        let Some(session) = self.session else {
+            return;
+        };
+        if self.measured.is_some_and(|at| at.elapsed() < REMEASURE) {
+            return;
+        }
+        let Some(endpoint) = self
+            .peers
+            .values()
+            .filter(|peer| peer.session == session)
+            .find_map(|peer| peer.endpoint)
+        else {
+            return;
+        };
+        if self.pings_sent == 0 {
+            self.offsets.clear();
+        }
+        let mut ping = MEASUREMENT_HEADER.to_vec();
+        ping.push(PING);
+        ping.extend_from_slice(b"hst_");
+        ping.extend_from_slice(&8u32.to_be_bytes());
+        ping.extend_from_slice(&host_micros(Instant::now()).to_be_bytes());
+        if let Err(e) = measurement.send_to(&ping, endpoint) {
+            eprintln!("Link measurement failed: {}", e);
+        }
+        self.pings_sent += 1;
+        if self.pings_sent >= PINGS {
+            self.pings_sent = 0;
+            self.measured = Some(Instant::now());
+        }
+    }
+
+    fn status(&self) -> LinkStatus {
+        let Some(session) = self.session else {
+            return LinkStatus::default();
+        };
+        let peers = || self.peers.values().filter(|peer| peer.session == session);
+        LinkStatus {
+            #[cfg(feature = "gui")]
+            peers: peers().count(),
+            // The newest announcement has the latest tempo change
+            timeline: peers()
+                .max_by_key(|peer| peer.heard)
+                .zip(self.ghost_offset)
+                .map(|(peer, ghost_offset)| Timeline {
+                    raw: peer.timeline,
+                    ghost_offset,
+                }),
+        }
+    }
+}
+
+// The key/length/value entries of a message's payload, all big endian
+fn entries(mut payload: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        if payload.len() < 8 {
+            return None;
+        }
+        let key: [u8; 4] = payload[..4].try_into().unwrap();
+        let len = u32::from_be_bytes(payload[4..8].try_into().unwrap()) as usize;
+        let value = payload.get(8..8 + len)?;
+        payload = &payload[8 + len..];
+        Some((key, value))
+    })
+}
+
+fn read_i64(bytes: &[u8]) -> i64 {
+    i64::from_be_bytes(bytes[..8].try_into().unwrap())
+}
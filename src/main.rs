@@ -1,48 +1,323 @@
-use chrono::Utc;
-use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::{BufferSize, Device, SampleFormat, StreamConfig};
+mod autosample;
+mod broadcast;
+mod cli;
+mod config;
+mod control;
+#[cfg(feature = "gui")]
+mod diagnostics;
+mod gamepad;
+#[cfg(feature = "gui")]
+mod gui;
+mod headless;
+mod history;
+mod i18n;
+#[cfg(feature = "link")]
+mod link;
+mod meter;
+mod midi;
+mod preset;
+mod recovery;
+mod remote;
+mod report;
+mod safety;
+#[cfg(feature = "scripting")]
+mod script;
+mod session;
+#[cfg(feature = "gui")]
+mod spectrogram;
+#[cfg(feature = "gui")]
+mod spectrum;
+mod theme;
+mod toast;
+#[cfg(feature = "gui")]
+mod tuner;
+
+use autosample::{AutoSampleSettings, AutoSampler, Capture};
+use broadcast::{BroadcastSettings, Broadcaster};
+use chrono::{DateTime, Duration, Local, Utc};
+use clap::Parser;
+use config::{Config, ShortGrabPolicy, TimecodeSource};
+use control::{Command, CommandListener, InstanceLock, OverdubAction};
 use dirs::home_dir;
-use eframe::{run_native, App, CreationContext};
-use egui::{CentralPanel, RichText, Vec2b};
-use egui_plot::{CoordinatesFormatter, Corner, Line, Plot, PlotPoints, PlotUi};
-use hound::{SampleFormat as HoundSampleFormat, WavSpec, WavWriter};
-use rfd::FileDialog;
-use rubato::{
-    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
-};
-use std::collections::VecDeque;
+use gamepad::GamepadListener;
+#[cfg(feature = "gui")]
+use gui::PlotCache;
+use history::HistoryItem;
+#[cfg(feature = "link")]
+use link::LinkFollower;
+use meter::Meters;
+use midi::MidiListener;
+use preset::Trigger;
+use remote::{RemoteCommand, RemoteServer, RemoteStatus};
+use rolling_sampler_core::analysis::{self, MonoCheck};
+use rolling_sampler_core::backend::CpalBackend;
+use rolling_sampler_core::dsp::{self, OnsetDetector};
+use rolling_sampler_core::export::Take;
+use rolling_sampler_core::hotfolder::HotFolder;
+use rolling_sampler_core::metadata::TakeAnalysis;
+use rolling_sampler_core::overdub::Overdub;
+use rolling_sampler_core::patch::{self, PatchFormat, Zone};
+use rolling_sampler_core::playback::{AbPlayback, Playback};
+use rolling_sampler_core::recorder::{Arming, Marker, MonitorSettings, Recorder, VoxSettings};
+use rolling_sampler_core::roundtrip::RoundTripTest;
+use rolling_sampler_core::timecode::{LtcDecoder, Timecode};
+use rolling_sampler_core::{pitch, CircularBuffer, RecorderError};
+use safety::{Gate, SafetyRecorder, SafetySettings};
+#[cfg(feature = "scripting")]
+use script::{Script, ScriptAction};
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::sync::Mutex;
-
-struct Recorder {
-    is_grabbing: Arc<AtomicBool>,
-    sample_buffer: Arc<Mutex<CircularBuffer>>,
-    input_stream: Option<cpal::Stream>,
-    config: StreamConfig,
-    buffer_size: Arc<Mutex<usize>>,
+use std::time::Instant;
+use toast::Toasts;
+#[cfg(feature = "gui")]
+use {
+    config::BroadcastTarget,
+    diagnostics::{CallbackLoad, EngineState},
+    history::{read_wav, TakeEdit, TakeFilter},
+    i18n::{tr, tr_fill, Language},
+    meter::Ballistics,
+    preset::{Binding, MetadataField, Preset},
+    rolling_sampler_core::analysis::Event,
+    rolling_sampler_core::backend::AudioBackend,
+    rolling_sampler_core::dsp::{EqSettings, GateSettings},
+    rolling_sampler_core::export::{ChannelMode, ExportStage, ExportVariant},
+    rolling_sampler_core::monitor::{MonitorLatency, MonitorResampler},
+    rolling_sampler_core::routing::MonitorRouting,
+    rolling_sampler_core::schedule::{Schedule, ScheduleKind},
+    rolling_sampler_core::state::RecorderState,
+    spectrogram::Spectrogram,
+    spectrum::SpectrumAnalyzer,
+    std::sync::atomic::AtomicI32,
+    theme::Theme,
+    tuner::Tuner,
+};
+
+struct Sampler {
     save_path: Option<String>,
-    input_devices: Vec<Device>,          // Store available input devices
-    current_input_device_index: usize,   // Store the index of the selected device
-    is_monitoring: Arc<AtomicBool>,      // New flag to track if monitoring is active
-    output_stream: Option<cpal::Stream>, // Optional output stream for monitoring
-    output_devices: Vec<Device>,         // Output devices (new field for audio output)
-    current_output_device_index: usize,  // Store the index of the selected output device
-    monitoring_buffers: Arc<Mutex<Vec<VecDeque<f32>>>>, // One VecDeque per channel
-    resampler: Option<SincFixedIn<f32>>,
-    resample_buffers: Vec<Vec<f32>>, // One buffer per channel for resampled data
+    settings: Config,                // Persisted user settings (presets etc.)
+    arm_enabled: bool,               // Wait for the input to cross a threshold before buffering
+    short_grab: Option<ShortGrab>, // Set while a too-short grab is waiting on the user or on audio
+    gamepad: GamepadListener,      // Gamepad/footswitch buttons that can trigger grabs
+    learning_gamepad_button: bool, // The next button pressed becomes the grab button
+    midi: MidiListener,            // Notes and controllers that can be bound to commands
+    learning_binding: Option<usize>, // The next key/button/message becomes this binding's trigger
+    pending_mono_check: Option<(Take, MonoCheck)>, // Stereo take held back by a phase warning
+    pending_name: Option<(Take, String)>, // Take waiting to be named, and the name typed so far
+    meters: Meters,                // Meter ballistics, updated each frame
+    #[cfg(feature = "gui")]
+    monitor_meters: Meters,
+    #[cfg(feature = "gui")]
+    monitor_reduction_db: f32, // Gain reduction shown for the monitoring limiter
+    #[cfg(feature = "gui")]
+    capture_reduction_db: f32, // and for the capture limiter
+    remote: Option<RemoteServer>, // Web remote, when enabled
+    #[cfg(feature = "link")]
+    link: Option<LinkFollower>, // Ableton Link session on the network, when enabled
+    #[cfg(feature = "gui")]
+    repaint_ctx: Option<egui::Context>, // Used to wake the UI from background threads
+    last_grab: Option<LastGrab>,  // The most recent grab, kept for browsing its events
+    vox_enabled: bool,            // Voice activated recording, one file per utterance
+    #[cfg(feature = "gui")]
+    callback_load: CallbackLoad,
+    toasts: Toasts,
+    commands: CommandListener, // Text commands from stdin / the control socket
+    countdown_cue: Option<Instant>, // When a delayed grab tells the player to start
+    #[cfg(feature = "gui")]
+    plot_cache: PlotCache, // Decimated waveform, updated incrementally
+    #[cfg(feature = "gui")]
+    plot_was_static: bool, // Mode the waveform was last drawn in, to reset the zoom
+    #[cfg(feature = "gui")]
+    spectrogram: Spectrogram, // STFT of the history, updated incrementally when shown
+    #[cfg(feature = "gui")]
+    spectrum: SpectrumAnalyzer, // Live spectrum of the newest input, when shown
+    #[cfg(feature = "gui")]
+    tuner: Tuner, // Pitch of the newest input, when shown
+    hit_capture: Option<HitCapture>, // Saves a one-shot around every hit, when enabled
+    timecode: Option<TimecodeReference>, // Latest timecode received, when a source is chosen
+    ltc: Option<LtcInput>,
+    #[cfg(feature = "scripting")]
+    script: Option<Script>, // The user's hooks, when a script is chosen
+    #[cfg(feature = "gui")]
+    marker_range: (usize, usize), // Marker numbers picked for a range export
+    review: Option<Review>, // Grab waiting to be accepted before it is written
+    reviews_waiting: VecDeque<Review>, // Later grabs, reviewed in turn once that one is decided
+    layering: Option<Layering>, // Overdubbing over a grab, while on
+    // The devices, streams and rolling buffer. Declared before the safety recorder and the
+    // broadcaster so the input's senders to them are gone when they wait for their writers.
+    recorder: Recorder<CpalBackend>,
+    safety: Option<SafetyRecorder>, // Segmented recording of everything, when enabled
+    broadcast: Option<Broadcaster>, // Live stream of the input, when enabled
+    auto_sampler: Option<AutoSampler>, // Playing through a synth's notes, while running
+    auto_samples: Vec<(u8, u8, PathBuf)>, // Note, velocity and file of each saved, for the patch
+    #[cfg(feature = "gui")]
+    midi_outputs: Vec<String>, // For the auto-sampler, listed at startup and on refresh
+    round_trip: Option<(RoundTripTest, bool)>, // Latency test running, and if it paused monitoring
+    history: Vec<HistoryItem>,      // Everything saved this session, oldest first
+    session_started: DateTime<Local>,
+    parked_sessions: HashMap<String, ParkedSession>, // Other sessions' history, by session name
+    ab_pick: [Option<usize>; 2],                     // History items chosen as A and B
+    ab_playback: Option<AbPlayback>,
+    history_playback: Option<(usize, Playback)>, // History item being auditioned
+    #[cfg(feature = "gui")]
+    history_filter: TakeFilter, // Narrows the history and the library
+    #[cfg(feature = "gui")]
+    editing_tags: Option<(PathBuf, String)>, // Take whose tags are being edited, and the text
+    #[cfg(feature = "gui")]
+    library: Option<Library>, // Takes in a folder being looked through
+    #[cfg(feature = "gui")]
+    scrub: Option<(u64, Playback)>, // Copy of the rolling history being auditioned, by first index
+    renaming: Option<(usize, String)>,           // History item being renamed, and the new name
+    confirm_delete: Option<usize>,               // History item waiting for a second click
+    #[cfg(feature = "gui")]
+    full_window_size: Option<egui::Vec2>, // Window size to go back to after mini mode
+    #[cfg(feature = "gui")]
+    settings_open: bool,
+    recovered: Vec<PathBuf>, // Buffer copies left by a crash, waiting to be restored or discarded
+    #[cfg(feature = "gui")]
+    quit_signal: Arc<AtomicI32>, // Signal that asked to quit, 0 until one does
+}
+
+// A grab held back for review, with the part to keep in frames
+struct Review {
+    take: Take,
+    #[cfg(feature = "gui")]
+    trim: (usize, usize),
+    #[cfg(feature = "gui")]
+    as_loop: bool, // Keep the trimmed part as a loop, with a crossfade at the seam
+    #[cfg(feature = "gui")]
+    playback: Option<Playback>,
+    #[cfg(feature = "gui")]
+    punch: Option<(usize, usize)>, // Punch in and out frames, while punching is on
+    #[cfg(feature = "gui")]
+    punching: Option<(Overdub, usize)>, // Pass under way, and the frame it plays from
+    #[cfg(feature = "gui")]
+    unpunched: Option<Take>, // The take before the last punch, to undo it
+}
+
+#[cfg(feature = "gui")]
+impl Review {
+    fn kept(&self, crossfade_ms: f32) -> Take {
+        let (start, end) = self.trim;
+        if self.as_loop {
+            let fade_frames = (crossfade_ms / 1000.0 * self.take.sample_rate as f32) as usize;
+            self.take.looped(start, end, fade_frames)
+        } else {
+            self.take.trimmed(start, end)
+        }
+    }
+}
+
+// Layers recorded over a grab, one pass at a time, looper style: each new pass can stack the
+// last layer into the bed it plays over
+struct Layering {
+    base: String, // Name of the grab it started from, for the layers' names
+    bed: Take,    // What the next pass plays over
+    pass: Option<Overdub>,
+    layer: Option<Take>, // From the last pass, the same length as the bed
+    saved: bool,         // The last layer has been saved, on its own or bounced
+    passes: usize,
+}
+
+impl Layering {
+    // The bed with the last layer mixed in
+    fn bounce(&self) -> Option<Take> {
+        let layer = self.layer.as_ref()?;
+        let mut bounce = layer.clone();
+        bounce.samples = Arc::new(
+            self.bed
+                .samples
+                .iter()
+                .zip(layer.samples.iter())
+                .map(|(bed, layer)| bed + layer)
+                .collect(),
+        );
+        bounce.rename(&format!("{}_bounce{}", self.base, self.passes));
+        Some(bounce)
+    }
 }
 
-struct CircularBuffer {
-    circular_buffer: Vec<f32>,
-    static_buffer: Vec<f32>,
-    max_size: usize,
-    write_pos: usize,
-    current_size: usize,
-    is_static_mode: bool,
+// Takes found in a folder by their sidecars, e.g. to find an old one by its rating and tags
+#[cfg(feature = "gui")]
+struct Library {
+    dir: PathBuf,
+    items: Vec<HistoryItem>, // Newest first
+    playback: Option<(usize, Playback)>,
 }
 
+// What a session that isn't active had going when it was switched away from, picked up again
+// when switching back
+struct ParkedSession {
+    history: Vec<HistoryItem>,
+    markers: Vec<Marker>,
+    started: DateTime<Local>,
+}
+
+// A timecode and the frame of the buffer it fell on (see `CircularBuffer::total_written`, here
+// in frames). Times before and after it are counted on from there, so takes still get a time
+// when the timecode stops, as a recorder jam-synced to it would.
+struct TimecodeReference {
+    timecode: Timecode,
+    frame: u64,
+    arrived: Option<Instant>, // For MIDI Timecode, which arrives with a time rather than a frame
+}
+
+// Decodes LTC from one channel as audio arrives in the buffer
+struct LtcInput {
+    decoder: LtcDecoder,
+    buffer_id: u64,
+    next: u64,  // Absolute index of the next sample to feed
+    start: u64, // Frame the decoder's first sample was
+}
+
+// Auto capture of one-shots: watches the audio arriving in the buffer for hits and saves a short
+// take around each one once enough audio after it has come in
+struct HitCapture {
+    detector: OnsetDetector,
+    buffer_id: u64,
+    next: u64, // Absolute index (see `CircularBuffer::total_written`) of the next frame
+    pending: Vec<u64>, // Hits still waiting for the rest of their one-shot
+    count: usize,
+}
+
+// The most recent grab with the events detected in it
+struct LastGrab {
+    take: Take,
+    #[cfg(feature = "gui")]
+    events: Vec<Event>,
+    #[cfg(feature = "gui")]
+    loudness: Option<f32>, // Integrated, in LUFS
+    #[cfg(feature = "gui")]
+    focus: Option<(f64, f64)>, // Range in seconds the plot should jump to on the next frame
+}
+
+// Answers to the mono compatibility warning
+#[cfg(feature = "gui")]
+enum MonoCheckChoice {
+    SaveAsIs,
+    FlipPolarity,
+    Mono,
+    Discard,
+}
+
+// A grab that was stopped before reaching the minimum length
+#[derive(Clone, Copy, PartialEq)]
+enum ShortGrab {
+    Prompting, // Asking the user what to do
+    Extending, // Still capturing until the minimum length is reached
+}
+
+#[cfg(feature = "gui")]
+const LOOP_SNAP_SECONDS: f64 = 0.01; // How far a loop edge may move to reach a zero crossing
+#[cfg(feature = "gui")]
+const PUNCH_POST_ROLL_SECONDS: f32 = 1.0; // Played after the punch out, so it isn't cut off
+#[cfg(feature = "gui")]
+const PUNCH_FADE_MS: f32 = 5.0; // Crossfade at each end of a punch
+#[cfg(feature = "gui")]
+const HIGH_PASS_CHOICES: [u32; 4] = [0, 40, 80, 120]; // Capture high-pass cutoffs, 0 for off
+const INSTANCE_RETRIES: u32 = 10; // Tries at handing over to an instance that's still starting
 fn get_file_safe_timestamp() -> String {
     // Get the current time in UTC
     let now = Utc::now();
@@ -51,684 +326,2293 @@ fn get_file_safe_timestamp() -> String {
     now.format("%Y-%m-%d_%H-%M-%S").to_string()
 }
 
-impl Recorder {
-    fn new(initial_buffer_size: usize) -> Self {
-        let host = cpal::default_host();
-        let input_devices: Vec<Device> = host.input_devices().unwrap().collect(); // Fetch available devices
-
-        let current_input_device_index = 0; // Set default to the first device
-        let input_device = input_devices[current_input_device_index].clone();
-        let config = input_device
-            .default_input_config()
-            .expect("Failed to get default input config");
-        let config: StreamConfig = config.into();
-        let initial_buffer_size =
-            initial_buffer_size * config.sample_rate.0 as usize * config.channels as usize;
-
-        // Get available output devices for live monitoring
-        let output_devices: Vec<Device> = host.output_devices().unwrap().collect();
-        let current_output_device_index = 0; // Set default to the first device
-        let initial_monitoring_capacity = config.sample_rate.0 as usize * 4; // Example: 4 second buffer
-
-        let num_channels = config.channels as usize;
-        let monitoring_buffers = Arc::new(Mutex::new(vec![
-            VecDeque::with_capacity(
-                initial_monitoring_capacity
-            );
-            num_channels
-        ]));
-        let resample_buffers = vec![Vec::new(); num_channels];
-
-        // Resolve the Desktop path and convert it to a String
-        let save_path: Option<String> = home_dir().and_then(|mut path| {
-            path.push("Desktop");
-            path.to_str().map(|s| s.to_owned())
-        });
+// Asking to follow a Link session in a build that can't, like the stream without `broadcast`
+#[cfg(not(feature = "link"))]
+fn link_not_built() -> RecorderError {
+    RecorderError::Other("Failed to join Ableton Link: built without the `link` feature".to_owned())
+}
+
+// Why a typed take name can't be used, if it can't
+fn take_name_problem(take: &Take, name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Some("The name can't be empty".to_owned());
+    }
+    if name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+        return Some("The name can't contain / \\ : * ? \" < > |".to_owned());
+    }
+    let path = take.path.with_file_name(format!("{}.wav", name));
+    if name != take.name && path.exists() {
+        return Some(format!("{}.wav already exists", name));
+    }
+    None
+}
+
+// Timestamps only have one second resolution, so number takes that land in the same second
+// (e.g. short VOX utterances) instead of overwriting each other
+fn unique_take_name(save_dir: &Path, base: String) -> String {
+    let mut name = base.clone();
+    let mut count = 1;
+    while save_dir.join(format!("{}.wav", name)).exists() {
+        count += 1;
+        name = format!("{}_{}", base, count);
+    }
+    name
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Where grabs go until a folder is chosen
+fn default_save_path() -> Option<String> {
+    home_dir().and_then(|mut path| {
+        path.push("Desktop");
+        path.to_str().map(|s| s.to_owned())
+    })
+}
+
+impl Sampler {
+    fn new(settings: Config, instance: InstanceLock) -> Self {
+        let mut recorder = Recorder::new(CpalBackend::new(), settings.buffer_seconds);
+        // Missing or broken devices are reported once the app is up rather than stopping it
+        // from opening, so they can be plugged in and rescanned. The ones used last time are
+        // picked again if they're still there.
+        let listed = recorder.list_devices(
+            settings.input_device.as_deref(),
+            settings.output_device.as_deref(),
+        );
+        recorder.scheduler.set_schedules(settings.schedules.clone());
+
+        // The folder chosen last time, otherwise the Desktop
+        let save_path: Option<String> = settings.save_path.clone().or_else(default_save_path);
 
-        let mut recorder = Recorder {
-            is_grabbing: Arc::new(AtomicBool::new(false)),
-            sample_buffer: Arc::new(Mutex::new(CircularBuffer::new(initial_buffer_size))),
-            input_stream: None,
-            config,
-            buffer_size: Arc::new(Mutex::new(initial_buffer_size)),
+        let mut sampler = Sampler {
             save_path,
-            input_devices,
-            current_input_device_index,
-            is_monitoring: Arc::new(AtomicBool::new(false)),
-            output_stream: None,
-            current_output_device_index, // Initially, no output device selected
-            output_devices,              // Initialize with available output devices
-            monitoring_buffers,
-            resampler: None,
-            resample_buffers,
-        };
-
-        recorder.start_recording();
+            settings,
+            arm_enabled: false,
+            short_grab: None,
+            gamepad: GamepadListener::spawn(),
+            learning_gamepad_button: false,
+            midi: MidiListener::spawn(),
+            learning_binding: None,
+            pending_mono_check: None,
+            pending_name: None,
+            meters: Meters::default(),
+            #[cfg(feature = "gui")]
+            monitor_meters: Meters::new("Monitor output"),
+            #[cfg(feature = "gui")]
+            monitor_reduction_db: 0.0,
+            #[cfg(feature = "gui")]
+            capture_reduction_db: 0.0,
+            remote: None,
+            #[cfg(feature = "link")]
+            link: None,
+            #[cfg(feature = "gui")]
+            repaint_ctx: None,
+            last_grab: None,
+            vox_enabled: false,
+            #[cfg(feature = "gui")]
+            callback_load: CallbackLoad::default(),
+            toasts: Toasts::default(),
+            commands: CommandListener::spawn(),
+            countdown_cue: None,
+            #[cfg(feature = "gui")]
+            plot_cache: PlotCache::default(),
+            #[cfg(feature = "gui")]
+            plot_was_static: false,
+            #[cfg(feature = "gui")]
+            spectrogram: Spectrogram::default(),
+            #[cfg(feature = "gui")]
+            spectrum: SpectrumAnalyzer::default(),
+            #[cfg(feature = "gui")]
+            tuner: Tuner::default(),
+            hit_capture: None,
+            timecode: None,
+            ltc: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "gui")]
+            marker_range: (1, 2),
+            review: None,
+            reviews_waiting: VecDeque::new(),
+            layering: None,
+            recorder,
+            safety: None,
+            broadcast: None,
+            auto_sampler: None,
+            auto_samples: Vec::new(),
+            #[cfg(feature = "gui")]
+            midi_outputs: autosample::output_ports(),
+            round_trip: None,
+            history: Vec::new(),
+            session_started: Local::now(),
+            parked_sessions: HashMap::new(),
+            ab_pick: [None, None],
+            ab_playback: None,
+            history_playback: None,
+            #[cfg(feature = "gui")]
+            history_filter: TakeFilter::default(),
+            #[cfg(feature = "gui")]
+            editing_tags: None,
+            #[cfg(feature = "gui")]
+            library: None,
+            #[cfg(feature = "gui")]
+            scrub: None,
+            renaming: None,
+            confirm_delete: None,
+            #[cfg(feature = "gui")]
+            full_window_size: None,
+            #[cfg(feature = "gui")]
+            settings_open: false,
+            recovered: recovery::pending(),
+            #[cfg(feature = "gui")]
+            quit_signal: Arc::new(AtomicI32::new(0)),
+        };
+
+        if let Err(e) = listed {
+            sampler.toasts.error(e.context("can't list the devices"));
+        }
+        if let Err(e) = sampler.commands.claim_instance(instance) {
+            eprintln!(
+                "Failed to open the instance socket, later launches won't find this one: {}",
+                e
+            );
+        }
+        sampler
+            .recorder
+            .stream_stats
+            .report_to(sampler.toasts.sender());
+        recovery::install_panic_hook(Arc::clone(&sampler.recorder.buffer));
+        sampler.apply_capture_settings();
+        sampler.apply_meter_settings();
+        if sampler.settings.link_enabled {
+            sampler.set_link_enabled(true);
+        }
+        sampler.load_script();
+        sampler.start_recording();
+        // Pick up where the last session left off
+        if sampler.settings.monitoring {
+            sampler.start_monitoring();
+        }
+        sampler
+    }
+
+    // The selected devices' names, or an error when there aren't any
+    #[cfg(feature = "gui")]
+    fn input_device(&self) -> Result<&str, RecorderError> {
+        self.recorder.input_device()
+    }
+
+    #[cfg(feature = "gui")]
+    fn output_device(&self) -> Result<&str, RecorderError> {
+        self.recorder.output_device()
+    }
+
+    // List the devices again, e.g. after plugging one in, keeping the same ones selected
+    #[cfg(feature = "gui")]
+    fn rescan_devices(&mut self) {
+        let input = self.input_device().ok().map(str::to_owned);
+        let output = self.output_device().ok().map(str::to_owned);
+        if let Err(e) = self
+            .recorder
+            .list_devices(input.as_deref(), output.as_deref())
+        {
+            self.toasts.error(e.context("can't list the devices"));
+            return;
+        }
+        self.toasts.note(format!(
+            "Found {} input and {} output devices",
+            self.recorder.input_devices.len(),
+            self.recorder.output_devices.len()
+        ));
+        if !self.recorder.has_input() {
+            self.start_recording();
+        }
+    }
+
+    // Passes the capture chain settings to the input callback, which picks them up live
+    fn apply_capture_settings(&self) {
+        let recorder = &self.recorder;
+        let settings = &self.settings;
+        recorder
+            .high_pass_hz
+            .store(settings.capture_high_pass_hz, Ordering::Relaxed);
+        recorder
+            .dc_block
+            .store(settings.dc_block, Ordering::Relaxed);
+        recorder.capture_gate.set_enabled(settings.capture_gate);
         recorder
+            .capture_gate
+            .set(settings.capture_gate_settings.into());
+        recorder
+            .capture_limiter
+            .set_enabled(settings.capture_limiter);
+        recorder.capture_limiter.set([
+            settings.capture_limiter_threshold_db,
+            settings.capture_limiter_release_ms,
+        ]);
+        recorder.monitor_gain.store(
+            dsp::db_to_gain(settings.monitor_gain_db).to_bits(),
+            Ordering::Relaxed,
+        );
+        recorder.monitor_eq.set_enabled(settings.monitor_eq);
+        recorder.monitor_eq.set(settings.monitor_eq_settings.into());
+        recorder.metronome.set(settings.metronome.into());
     }
 
+    // (Re)open the selected input with a buffer of the chosen length, and move everything fed
+    // from it over
     fn start_recording(&mut self) {
-        // Get the currently selected device
-        let input_device = self.input_devices[self.current_input_device_index].clone();
+        self.recorder.buffer_seconds = self.settings.buffer_seconds;
+        if let Err(e) = self.recorder.start_input() {
+            self.toasts.error(e);
+            return;
+        }
 
-        // Fetch the latest configuration
-        let config = input_device
-            .default_input_config()
-            .expect("Failed to get default input config");
-        let config: StreamConfig = config.into();
-        self.config = config;
-        let sample_format = input_device.default_input_config().unwrap().sample_format();
+        // The segments have to follow the new stream's channels and rate
+        self.set_safety_enabled(self.settings.safety_recording);
+        self.set_broadcast_enabled(self.settings.broadcast_enabled);
+        // and monitoring has to read from the new stream's FIFO
+        if self.recorder.is_monitoring() {
+            self.start_monitoring();
+        }
+    }
 
-        println!(
-            "Input Stream Config - Sample Rate: {}, Channels: {}",
-            self.config.sample_rate.0, self.config.channels
-        );
+    fn set_safety_enabled(&mut self, enabled: bool) {
+        // The old writer stops taking from the tap and finishes its segment first
+        self.safety = None;
+        if !enabled {
+            return;
+        }
+        let Some(save_path) = &self.save_path else {
+            self.toasts.error(RecorderError::Other(
+                "Select a save folder before starting the safety recording".to_owned(),
+            ));
+            return;
+        };
+        let gate = self.settings.safety_gate.then(|| Gate {
+            threshold: 10f32.powf(self.settings.safety_gate_threshold_db / 20.0),
+            silence_seconds: self.settings.safety_gate_silence_seconds,
+        });
+        let settings = SafetySettings {
+            dir: Path::new(save_path).join("Safety Recordings"),
+            channels: self.recorder.config.channels,
+            sample_rate: self.recorder.config.sample_rate.0,
+            segment_seconds: self.settings.safety_segment_minutes * 60.0,
+            gate,
+        };
+        let tap = Arc::clone(&self.recorder.safety_tap);
+        match SafetyRecorder::start(settings, tap, self.toasts.sender()) {
+            Ok(safety) => self.safety = Some(safety),
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to start the safety recording: {}",
+                e
+            ))),
+        }
+    }
+
+    fn set_broadcast_enabled(&mut self, enabled: bool) {
+        // The old streamer stops taking from the tap and ends its stream first
+        self.broadcast = None;
+        if !enabled {
+            return;
+        }
+        let settings = BroadcastSettings {
+            target: self.settings.broadcast_target,
+            host: self.settings.broadcast_host.clone(),
+            port: self.settings.broadcast_port,
+            mount: self.settings.broadcast_mount.clone(),
+            password: self.settings.broadcast_password.clone(),
+            bitrate_kbps: self.settings.broadcast_bitrate_kbps,
+            channels: self.recorder.config.channels,
+            sample_rate: self.recorder.config.sample_rate.0,
+        };
+        match Broadcaster::start(settings, Arc::clone(&self.recorder.broadcast_tap)) {
+            Ok(broadcast) => self.broadcast = Some(broadcast),
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to start the stream: {}",
+                e
+            ))),
+        }
+    }
 
-        self.reset_buffer(); // Reset the buffer before starting a new recording
-        let sample_buffer = Arc::clone(&self.sample_buffer);
-
-        // Reinitialize monitoring buffers
-        self.reset_monitoring_buffers();
-
-        let is_monitoring = Arc::clone(&self.is_monitoring);
-        let num_channels = self.config.channels as usize;
-        let monitoring_buffers = Arc::clone(&self.monitoring_buffers);
-
-        let stream = match sample_format {
-            SampleFormat::F32 => {
-                input_device.build_input_stream(
-                    &self.config,
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        // Write to sample_buffer
-                        {
-                            let mut buffer = sample_buffer.lock().unwrap();
-                            buffer.add_samples(data);
-                        }
-
-                        // If monitoring is enabled, distribute samples to per-channel buffers
-                        if is_monitoring.load(Ordering::SeqCst) {
-                            let mut m_buffers = monitoring_buffers.lock().unwrap();
-                            for (i, &sample) in data.iter().enumerate() {
-                                let channel = i % num_channels;
-                                let m_buffer = &mut m_buffers[channel];
-                                if m_buffer.len() == m_buffer.capacity() {
-                                    m_buffer.pop_front();
-                                }
-                                m_buffer.push_back(sample);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-            }
-            _ => panic!("Unsupported sample format"),
-        }
-        .unwrap();
-
-        // Stop the previous stream if it exists
-        if let Some(old_stream) = self.input_stream.take() {
-            drop(old_stream);
-        }
-
-        self.input_stream = Some(stream);
-        self.is_grabbing.store(false, Ordering::SeqCst);
+    fn metronome_running(&self) -> bool {
+        self.recorder.metronome.enabled()
     }
 
-    fn grab_recording(&mut self) {
-        self.is_grabbing.store(false, Ordering::SeqCst);
-        if let Some(stream) = self.input_stream.take() {
-            drop(stream); // This drops the stream and stops recording
+    // The click only goes to the monitoring output, so that comes on with it. Each start counts
+    // in afresh.
+    fn set_metronome_running(&mut self, running: bool) {
+        if running == self.metronome_running() {
+            return;
+        }
+        if running && !self.recorder.is_monitoring() {
+            self.start_monitoring();
+            if !self.recorder.is_monitoring() {
+                return; // Its error has been shown
+            }
+        }
+        self.recorder
+            .set_metronome(running, self.settings.metronome);
+        if running {
+            println!("Metronome at {:.1} BPM", self.settings.metronome.bpm);
+        }
+    }
+
+    // Passes changed settings to a running click, which carries on from the same beat
+    fn update_metronome(&self) {
+        self.recorder.metronome.set(self.settings.metronome.into());
+    }
+
+    // Plays the bed once to record a layer over it, starting from the last grab. With `stack`
+    // the last layer is mixed into the bed first, otherwise it's thrown away for another go.
+    fn start_overdub(&mut self, stack: bool) {
+        if !self.recorder.has_input() {
+            self.toasts.error(RecorderError::Other(
+                "Start the input before overdubbing".to_owned(),
+            ));
+            return;
+        }
+        if self.layering.is_none() {
+            let Some(last_grab) = &self.last_grab else {
+                self.toasts.error(RecorderError::Other(
+                    "Grab something to layer over first".to_owned(),
+                ));
+                return;
+            };
+            let bed = last_grab.take.clone();
+            let config = &self.recorder.config;
+            if bed.channels != config.channels || bed.sample_rate != config.sample_rate.0 {
+                self.toasts.error(RecorderError::Other(
+                    "The input has changed since the last grab, so it can't be layered over"
+                        .to_owned(),
+                ));
+                return;
+            }
+            self.layering = Some(Layering {
+                base: bed.name.clone(),
+                bed,
+                pass: None,
+                layer: None,
+                saved: false,
+                passes: 0,
+            });
+        }
+        let Some(layering) = &mut self.layering else {
+            return;
+        };
+        if stack {
+            if let Some(bounce) = layering.bounce() {
+                layering.bed = bounce;
+            }
+        }
+        layering.layer = None;
+        layering.pass = None; // Stops a pass already playing
+        let bed = layering.bed.clone();
+        let latency_ms = self.settings.round_trip_latency_ms.unwrap_or(0.0);
+        match self.recorder.overdub(&bed, latency_ms, "overdub") {
+            Ok(pass) => {
+                println!("Overdubbing over {}", bed.name);
+                if let Some(layering) = &mut self.layering {
+                    layering.pass = Some(pass);
+                }
+            }
+            Err(e) => self.toasts.error(e.context("Failed to play the bed")),
         }
+    }
 
-        let buffer = self.sample_buffer.lock().unwrap();
+    fn poll_overdub(&mut self) {
+        let Some(layering) = &mut self.layering else {
+            return;
+        };
+        let Some(pass) = &mut layering.pass else {
+            return;
+        };
+        let polled = pass.poll(&self.recorder.buffer.lock().unwrap());
+        let samples = match polled {
+            Ok(Some(samples)) => samples,
+            Ok(None) => return,
+            Err(e) => {
+                layering.pass = None;
+                self.toasts
+                    .error(RecorderError::Other(format!("Overdub stopped: {}", e)));
+                return;
+            }
+        };
+        layering.pass = None;
+        layering.passes += 1;
+        let name = format!("{}_layer{}", layering.base, layering.passes);
+        let bed = layering.bed.clone();
+        match self.new_take(name, samples, None) {
+            Ok(mut layer) => {
+                layer
+                    .metadata
+                    .push(("layered_over".to_owned(), bed.name.clone()));
+                // Still in time with the bed
+                layer.metadata.extend(
+                    bed.metadata
+                        .iter()
+                        .filter(|(key, _)| key == "bpm" || key == "time_signature")
+                        .cloned(),
+                );
+                println!("Captured {}", layer.name);
+                self.toasts.note(format!(
+                    "Captured {}: save it, bounce it, or layer again",
+                    layer.name
+                ));
+                if let Some(layering) = &mut self.layering {
+                    layering.layer = Some(layer);
+                    layering.saved = false;
+                }
+            }
+            Err(e) => self.toasts.error(e),
+        }
+    }
 
-        // Determine the number of samples in the buffer
-        let num_samples = buffer.current_size;
-        let num_channels = self.config.channels as usize;
+    // Saves the last layer on its own, or mixed with the bed it was played over
+    fn save_overdub(&mut self, bounce: bool) {
+        let take = self.layering.as_ref().and_then(|layering| {
+            if bounce {
+                layering.bounce()
+            } else {
+                layering.layer.clone()
+            }
+        });
+        match take {
+            Some(take) => {
+                self.toasts.note(format!("Saving {}", take.name));
+                self.save_take(take);
+                if let Some(layering) = &mut self.layering {
+                    layering.saved = true;
+                }
+            }
+            None => self.toasts.note("No layer to save yet"),
+        }
+    }
 
-        println!(
-            "Recorded shape: ({}, {})",
-            num_samples / num_channels,
-            num_channels
+    // Plays the take under review from the pre-roll before the punch in to just after the punch
+    // out, recording the input to go in between them
+    #[cfg(feature = "gui")]
+    fn start_punch(&mut self) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        let Some((punch_in, punch_out)) = review.punch else {
+            return;
+        };
+        review.playback = None;
+        review.punching = None;
+        let take = &review.take;
+        if !self.recorder.has_input() {
+            self.toasts.error(RecorderError::Other(
+                "Start the input before punching in".to_owned(),
+            ));
+            return;
+        }
+        let config = &self.recorder.config;
+        if take.channels != config.channels || take.sample_rate != config.sample_rate.0 {
+            self.toasts.error(RecorderError::Other(
+                "The input has changed since this grab, so it can't be punched into".to_owned(),
+            ));
+            return;
+        }
+        let rate = take.sample_rate as f32;
+        let from = punch_in.saturating_sub((self.settings.punch_pre_roll_seconds * rate) as usize);
+        let to = (punch_out + (PUNCH_POST_ROLL_SECONDS * rate) as usize).min(take.frames());
+        let cue = take.trimmed(from, to);
+        let latency_ms = self.settings.round_trip_latency_ms.unwrap_or(0.0);
+        match self.recorder.overdub(&cue, latency_ms, "punch") {
+            Ok(pass) => {
+                println!("Punching in from frame {} to {}", punch_in, punch_out);
+                if let Some(review) = &mut self.review {
+                    review.punching = Some((pass, from));
+                }
+            }
+            Err(e) => self
+                .toasts
+                .error(e.context("Failed to play the take for punching in")),
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn poll_punch(&mut self) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        let (Some((pass, from)), Some((punch_in, punch_out))) =
+            (&mut review.punching, review.punch)
+        else {
+            return;
+        };
+        let from = *from;
+        let input = match pass.poll(&self.recorder.buffer.lock().unwrap()) {
+            Ok(Some(input)) => input,
+            Ok(None) => return,
+            Err(e) => {
+                review.punching = None;
+                self.toasts
+                    .error(RecorderError::Other(format!("Punch stopped: {}", e)));
+                return;
+            }
+        };
+        review.punching = None;
+        let fade_frames = (PUNCH_FADE_MS / 1000.0 * review.take.sample_rate as f32) as usize;
+        let punched = review
+            .take
+            .punched(&input, from, punch_in, punch_out, fade_frames);
+        review.unpunched = Some(std::mem::replace(&mut review.take, punched));
+        println!("Punched in");
+        self.toasts
+            .note("Punched in: play it back, undo it, or punch again");
+    }
+
+    fn set_auto_sampler_running(&mut self, running: bool) {
+        self.auto_sampler = None; // Lets go of any note still sounding
+        self.write_auto_sample_patches(); // For what a run stopped part way got through
+        if !running {
+            return;
+        }
+        let Some(port) = self.settings.autosample_port.clone() else {
+            self.toasts.error(RecorderError::Other(
+                "Choose a MIDI output for the auto-sampler first".to_owned(),
+            ));
+            return;
+        };
+        let settings = AutoSampleSettings {
+            port,
+            channel: self.settings.autosample_channel,
+            low_note: self.settings.autosample_low_note,
+            high_note: self.settings.autosample_high_note,
+            step: self.settings.autosample_step,
+            velocities: self.settings.autosample_velocities.clone(),
+            hold_seconds: self.settings.autosample_hold_seconds,
+            release_seconds: self.settings.autosample_release_seconds,
+        };
+        match AutoSampler::start(settings) {
+            Ok(sampler) => self.auto_sampler = Some(sampler),
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to start the auto-sampler: {}",
+                e
+            ))),
+        }
+    }
+
+    fn poll_auto_sampler(&mut self) {
+        let Some(sampler) = &mut self.auto_sampler else {
+            return;
+        };
+        let position = self.recorder.buffer.lock().unwrap().total_written;
+        let capture = sampler.poll(position);
+        let finished = sampler.is_finished();
+        if let Some(capture) = capture {
+            self.save_auto_sample(capture);
+        }
+        if finished {
+            self.set_auto_sampler_running(false);
+            println!("Auto-sampling finished");
+            self.toasts.note("Auto-sampling finished");
+        }
+    }
+
+    // One note of the auto-sampler, trimmed to where it sounds and saved into the instrument's
+    // folder as e.g. Synth_C#3_v100
+    fn save_auto_sample(&mut self, capture: Capture) {
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let (start, samples) = {
+            let buffer = self.recorder.buffer.lock().unwrap();
+            let start = (capture.start / channels * channels).max(buffer.window_start());
+            let end = (capture.end / channels * channels).min(buffer.total_written);
+            (start, buffer.copy_range(start, end.max(start)))
+        };
+        let instrument: String = self
+            .settings
+            .autosample_name
+            .trim()
+            .chars()
+            .filter(|c| !['/', '\\', ':', '*', '?', '"', '<', '>', '|'].contains(c))
+            .collect();
+        let instrument = if instrument.is_empty() {
+            "Synth".to_owned()
+        } else {
+            instrument
+        };
+        let note = autosample::note_name(capture.note);
+        let name = format!("{}_{}_v{:03}", instrument, note, capture.velocity);
+        let Some((first, last)) = autosample::sounding_frames(
+            &samples,
+            channels as usize,
+            self.settings.autosample_floor_db,
+        ) else {
+            self.toasts
+                .note(format!("Nothing came back for {}, skipped", name));
+            return;
+        };
+        let samples = samples[first * channels as usize..last * channels as usize].to_vec();
+        println!("Auto-sampled {}", name);
+        match self.new_take(name.clone(), samples, None) {
+            Ok(mut take) => {
+                let folder = take.path.with_file_name(&instrument);
+                take.path = folder.join("take.wav");
+                take.rename(&unique_take_name(&folder, name));
+                // The note played is known, so it replaces any that was detected
+                take.metadata
+                    .retain(|(key, _)| key != "note" && key != "pitch_hz");
+                take.metadata.push(("note".to_owned(), note));
+                take.metadata
+                    .push(("midi_note".to_owned(), capture.note.to_string()));
+                take.metadata
+                    .push(("velocity".to_owned(), capture.velocity.to_string()));
+                self.stamp_timecode(&mut take, start + first as u64 * channels);
+                self.auto_samples
+                    .push((capture.note, capture.velocity, take.path.clone()));
+                self.save_take(take);
+            }
+            Err(e) => self.toasts.error(e),
+        }
+    }
+
+    // The instrument for the notes the auto-sampler saved, in the instrument's folder
+    fn write_auto_sample_patches(&mut self) {
+        let samples = std::mem::take(&mut self.auto_samples);
+        let Some(dir) = samples.first().and_then(|(_, _, path)| path.parent()) else {
+            return;
+        };
+        let zones = patch::multisample_zones(
+            &samples
+                .iter()
+                .map(|(note, velocity, path)| (*note, *velocity, file_name(path)))
+                .collect::<Vec<_>>(),
         );
-        println!("Sample rate: {}", self.config.sample_rate.0);
+        self.write_patches(dir, &file_name(dir), &zones, false);
+    }
+
+    // A drum kit of hits sliced out of a take, one per key, in the slices' folder
+    #[cfg(feature = "gui")]
+    fn write_slice_patches(&mut self, slices: &[Take]) {
+        let Some(dir) = slices.first().and_then(|slice| slice.path.parent()) else {
+            return;
+        };
+        let files: Vec<String> = slices.iter().map(|slice| file_name(&slice.path)).collect();
+        let zones = patch::chromatic_zones(&files, patch::KIT_FIRST_KEY);
+        self.write_patches(dir, &file_name(dir), &zones, true);
+    }
 
-        // Prepare WAV writer specifications based on the buffer content
-        let spec = WavSpec {
-            channels: self.config.channels,
-            sample_rate: self.config.sample_rate.0,
-            bits_per_sample: 32, // Assuming f32 for this example
-            sample_format: HoundSampleFormat::Float,
+    // Each kind of patch chosen in the settings, named after the folder of samples it plays
+    fn write_patches(&mut self, dir: &Path, name: &str, zones: &[Zone], one_shot: bool) {
+        let formats = [
+            (self.settings.write_sfz, PatchFormat::Sfz),
+            (self.settings.write_dspreset, PatchFormat::DecentSampler),
+        ];
+        for (_, format) in formats.into_iter().filter(|(on, _)| *on) {
+            match patch::write_patch(dir, name, format, zones, one_shot) {
+                Ok(path) => println!("Wrote the patch {}", path.display()),
+                Err(e) => self.toasts.error(RecorderError::Save(format!(
+                    "Failed to write the {} patch: {}",
+                    format.extension(),
+                    e
+                ))),
+            }
+        }
+    }
+
+    fn grab_recording(&mut self) {
+        // Without a save folder the grab carries on, so nothing is lost while one is picked
+        let save_dir = match self.save_dir() {
+            Ok(save_dir) => save_dir,
+            Err(e) => {
+                self.toasts.error(e);
+                return;
+            }
+        };
+        if let Some(grabbed) = self.recorder.grab() {
+            self.save_grab(&save_dir, grabbed);
+        }
+    }
+
+    // Turn a stopped grab into a take, analyse it and hand it on for review or to be written
+    fn save_grab(&mut self, save_dir: &Path, grabbed: CircularBuffer) {
+        let preset = self.settings.active_preset().name.clone();
+        let name = unique_take_name(save_dir, self.settings.session_mut().take_name(&preset));
+        self.settings.save(); // Keeps the take counter
+        let start = grabbed.total_written - grabbed.static_buffer.len() as u64;
+        let mut take = match self.new_take(name, grabbed.static_buffer, grabbed.onset_time) {
+            Ok(take) => take,
+            Err(e) => {
+                self.toasts.error(e);
+                return;
+            }
         };
+        self.stamp_timecode(&mut take, start);
+        #[cfg(feature = "link")]
+        let following_link = self.link.is_some();
+        #[cfg(not(feature = "link"))]
+        let following_link = false;
+        if self.settings.detect_tempo || following_link || self.metronome_running() {
+            self.tag_tempo(&mut take);
+        }
+        if self.settings.detect_key {
+            if let Some((key, score)) = analysis::detect_key(
+                &take.samples,
+                take.channels as usize,
+                take.sample_rate,
+                self.settings.tuner_reference_hz,
+            ) {
+                println!("Detected key {} (correlation {:.2})", key, score);
+                take.metadata.push(("key".to_owned(), key));
+            }
+        }
+
+        // Flag takes that clipped while they were being captured
+        self.meters.update(&self.recorder.meter_tap);
+        let end = Local::now();
+        let start = end - Duration::milliseconds((take.duration_seconds() * 1000.0) as i64);
+        let clips = self.meters.clips_between(start, end);
+        if !clips.is_empty() {
+            let samples: u64 = clips.iter().map(|clip| clip.samples).sum();
+            self.toasts.note(format!(
+                "Warning: {} clipped {} samples ({} clip events)",
+                take.name,
+                samples,
+                clips.len()
+            ));
+            take.metadata
+                .push(("clipped_samples".to_owned(), samples.to_string()));
+        }
+        #[cfg(feature = "scripting")]
+        if !self.script_on_grab(&mut take) {
+            return;
+        }
 
-        let filepath = format!(
-            "{}/{}.wav",
-            self.save_path.as_ref().unwrap(),
-            get_file_safe_timestamp()
+        let events = analysis::detect_events(
+            &take.samples,
+            take.channels as usize,
+            take.sample_rate,
+            self.settings.event_sensitivity_db,
         );
-        let mut writer = WavWriter::create(filepath, spec).expect("Failed to create WAV writer");
+        println!("Detected {} events in the grab", events.len());
+        #[cfg(feature = "gui")]
+        let loudness =
+            analysis::integrated_loudness(&take.samples, take.channels as usize, take.sample_rate);
+        self.last_grab = Some(LastGrab {
+            take: take.clone(),
+            #[cfg(feature = "gui")]
+            events,
+            #[cfg(feature = "gui")]
+            loudness,
+            #[cfg(feature = "gui")]
+            focus: None,
+        });
 
-        // Save buffer
-        for &sample in buffer.static_buffer.iter().take(buffer.current_size) {
-            writer.write_sample(sample).unwrap();
+        if self.settings.review_before_save {
+            #[cfg(feature = "gui")]
+            let trim = (0, take.frames());
+            let review = Review {
+                take,
+                #[cfg(feature = "gui")]
+                trim,
+                #[cfg(feature = "gui")]
+                as_loop: false,
+                #[cfg(feature = "gui")]
+                playback: None,
+                #[cfg(feature = "gui")]
+                punch: None,
+                #[cfg(feature = "gui")]
+                punching: None,
+                #[cfg(feature = "gui")]
+                unpunched: None,
+            };
+            // A take still waiting on a decision keeps it, the new one waits its turn
+            if self.review.is_some() {
+                self.toasts.note(format!(
+                    "{} will be up for review after the current grab",
+                    review.take.name
+                ));
+                self.reviews_waiting.push_back(review);
+            } else {
+                self.review = Some(review);
+            }
+            return;
         }
+        self.finish_take(take);
+    }
 
-        writer.finalize().expect("Failed to finalize WAV writer");
+    fn save_take(&mut self, take: Take) {
+        self.history.push(HistoryItem::from_take(&take));
+        self.collect_saves();
+        self.recorder.save(take, self.toasts.sender());
+    }
 
-        println!("Recording saved!");
+    // Block until every take handed to a writer thread is on disk
+    fn wait_for_saves(&mut self) {
+        for (path, analysis) in self.recorder.wait_for_saves() {
+            self.set_analysis(&path, analysis);
+        }
+    }
 
-        // Replace the buffer with a new one rather than clearing the old one
-        drop(buffer); // Unlock the mutex before replacing the buffer
+    // Pick up the analysis from writer threads that have finished
+    fn collect_saves(&mut self) {
+        for (path, analysis) in self.recorder.collect_saves() {
+            self.set_analysis(&path, analysis);
+        }
+    }
 
-        // Restart recording with a fresh stream
-        self.start_recording();
+    fn set_analysis(&mut self, path: &Path, analysis: TakeAnalysis) {
+        if let Some(item) = self.history.iter_mut().find(|item| item.path == path) {
+            item.analysis = Some(analysis);
+        }
     }
 
-    fn update_buffer_size(&mut self, new_size: usize) {
-        {
-            // Update the buffer size in the Arc<Mutex<usize>>
-            let mut buffer_size = self.buffer_size.lock().unwrap();
-            *buffer_size = new_size;
-            // The lock (buffer_size) will be dropped at the end of this scope
+    // Last checks before a take is written
+    fn finish_take(&mut self, take: Take) {
+        if self.settings.mono_check {
+            if let Some(check) = analysis::mono_compatibility(&take.samples, take.channels as usize)
+            {
+                if check.has_cancellation() {
+                    println!(
+                        "Phase cancellation detected: mono sum is {:.1} dB down",
+                        check.mono_loss_db
+                    );
+                    // Don't lose a take that is still waiting on an answer
+                    if let Some((previous, _)) = self.pending_mono_check.replace((take, check)) {
+                        self.name_take(previous);
+                    }
+                    return;
+                }
+            }
         }
+        self.name_take(take);
+    }
 
-        // Reset the buffer after the lock on buffer_size has been released
-        self.reset_buffer();
+    // Hold the take for the naming prompt if it is on, otherwise save it as it is
+    fn name_take(&mut self, take: Take) {
+        if !self.settings.name_prompt {
+            self.save_take(take);
+            return;
+        }
+        let name = take.name.clone();
+        // A take still waiting on a name is saved under whatever has been typed so far
+        if let Some((previous, typed)) = self.pending_name.replace((take, name)) {
+            self.save_named(previous, &typed);
+        }
     }
 
-    fn open_file_dialog(&mut self) {
-        if let Some(path) = FileDialog::new().pick_folder() {
-            // Store the selected directory path
-            self.save_path = Some(path.display().to_string());
-            println!(
-                "Save directory selected: {}",
-                self.save_path.as_ref().unwrap()
-            );
+    // Save under the typed name, or the original one if that can't be used
+    fn save_named(&mut self, mut take: Take, name: &str) {
+        match take_name_problem(&take, name) {
+            None => {
+                if let Some(last_grab) = &mut self.last_grab {
+                    if last_grab.take.path == take.path {
+                        last_grab.take.rename(name.trim());
+                    }
+                }
+                take.rename(name.trim());
+            }
+            Some(problem) => self
+                .toasts
+                .note(format!("Keeping the name {}: {}", take.name, problem)),
         }
+        self.save_take(take);
     }
 
-    fn reset_buffer(&mut self) {
-        // Lock the current buffer size to reuse it
-        let new_buffer_size = *self.buffer_size.lock().unwrap();
+    fn save_dir(&self) -> Result<PathBuf, RecorderError> {
+        self.save_path
+            .as_ref()
+            .map(PathBuf::from)
+            .ok_or_else(|| RecorderError::Save("pick a save folder in Settings first".to_owned()))
+    }
+
+    // A take in the save folder set up with the active preset
+    fn new_take(
+        &self,
+        name: String,
+        samples: Vec<f32>,
+        onset_time: Option<DateTime<Utc>>,
+    ) -> Result<Take, RecorderError> {
+        let save_dir = self.save_dir()?;
+        let preset = self.settings.active_preset();
+        let mut metadata = preset.metadata_pairs();
+        let mut name = name;
+        // The note for samplers to key map by
+        if self.settings.tag_pitch {
+            if let Some((note, hz)) = pitch::take_pitch(
+                &samples,
+                self.recorder.config.channels as usize,
+                self.recorder.config.sample_rate.0,
+                self.settings.tuner_reference_hz,
+            ) {
+                println!("Detected pitch {} ({:.1} Hz)", note, hz);
+                if self.settings.pitch_in_name {
+                    name = format!("{}_{}", name, note);
+                }
+                metadata.push(("note".to_owned(), note));
+                metadata.push(("pitch_hz".to_owned(), format!("{:.1}", hz)));
+            }
+        }
+        Ok(Take {
+            path: save_dir.join(format!("{}.wav", name)),
+            name,
+            samples: Arc::new(samples),
+            channels: self.recorder.config.channels,
+            sample_rate: self.recorder.config.sample_rate.0,
+            preset: preset.name.clone(),
+            metadata,
+            onset_time,
+            stages: preset.export_stages.clone(),
+            channel_fix: None,
+            variants: preset.export_variants.clone(),
+            variant: None,
+            hot_folder: self.settings.hot_folder.as_ref().map(|dir| HotFolder {
+                dir: PathBuf::from(dir),
+                take_names: self.settings.hot_folder_take_names,
+                notify_command: self.settings.hot_folder_command.clone(),
+            }),
+            time_reference: None, // See `stamp_timecode`
+            post_save_command: Some(self.settings.post_save_command.clone())
+                .filter(|command| !command.trim().is_empty()),
+            channel_names: self.settings.channel_names.clone(),
+            armed_channels: self.armed_channels(),
+            stems: self.settings.save_stems,
+        })
+    }
 
-        // Replace the old buffer with a new one
-        self.sample_buffer = Arc::new(Mutex::new(CircularBuffer::new(new_buffer_size)));
+    // The input channels going into takes, or None for all of them. Disarming every channel
+    // would leave nothing to save, so that counts as all too.
+    fn armed_channels(&self) -> Option<Vec<usize>> {
+        let channels = self.recorder.config.channels as usize;
+        let armed: Vec<usize> = (0..channels)
+            .filter(|channel| !self.settings.disarmed_channels.contains(channel))
+            .collect();
+        Some(armed).filter(|armed| !armed.is_empty() && armed.len() < channels)
     }
 
-    fn start_monitoring(&mut self) {
-        let output_device = self.output_devices[self.current_output_device_index].clone();
-        let config = output_device.default_output_config().unwrap();
-        let sample_format = config.sample_format();
-        let config: StreamConfig = config.into();
-        let output_config = StreamConfig {
-            channels: config.channels,
-            sample_rate: config.sample_rate,
-            buffer_size: BufferSize::Fixed(512), // Adjust this value as needed
+    // Adds the grab's tempo to its metadata (and name), and trims it to whole bars if asked.
+    // The oldest audio is the part that goes, since a grab ends at the moment it was wanted.
+    fn tag_tempo(&self, take: &mut Take) {
+        // The click being played to is what the take is in time with, then a Link session's
+        // tempo, which is exact, then detection
+        let metronome = self.metronome_running().then_some(self.settings.metronome);
+        let link_bpm = self.link_bpm();
+        let known_bpm = metronome.map(|settings| settings.bpm).or(link_bpm);
+        let Some(bpm) = known_bpm.or_else(|| {
+            analysis::detect_tempo(&take.samples, take.channels as usize, take.sample_rate)
+        }) else {
+            return;
         };
+        println!("Tempo {:.1} BPM", bpm);
+        take.metadata
+            .push(("bpm".to_owned(), format!("{:.1}", bpm)));
+        if let Some(settings) = metronome {
+            take.metadata.push((
+                "time_signature".to_owned(),
+                format!("{}/4", settings.beats_per_bar),
+            ));
+        }
+        if self.settings.round_to_bars {
+            let beats_per_bar = match metronome {
+                Some(settings) => settings.beats_per_bar as f32,
+                None if link_bpm.is_some() => self.settings.link_beats_per_bar,
+                None => 4.0,
+            };
+            let bar_frames = (beats_per_bar * 60.0 / bpm * take.sample_rate as f32) as usize;
+            let frames = take.frames();
+            let mut bars = (frames as f32 / bar_frames as f32).round() as usize;
+            if bars * bar_frames > frames {
+                bars -= 1; // Can't make up audio that was never captured
+            }
+            if bars > 0 {
+                *take = take.trimmed(frames - bars * bar_frames, frames);
+                take.metadata.push(("bars".to_owned(), bars.to_string()));
+            }
+        }
+        if self.settings.tempo_in_name {
+            take.rename(&format!("{}_{:.0}bpm", take.name, bpm));
+        }
+    }
 
-        let num_output_channels = config.channels as usize;
-        let num_input_channels = self.config.channels as usize;
+    #[cfg(feature = "gui")]
+    fn set_hit_capture_enabled(&mut self, enabled: bool) {
+        let count = self.hit_capture.as_ref().map_or(0, |capture| capture.count);
+        self.hit_capture = enabled.then(|| HitCapture {
+            detector: OnsetDetector::new(
+                self.recorder.config.sample_rate.0,
+                self.settings.hit_sensitivity_db,
+                dsp::db_to_gain(self.settings.hit_floor_db),
+            ),
+            buffer_id: u64::MAX, // Picks up the current buffer on the next poll
+            next: 0,
+            pending: Vec::new(),
+            count,
+        });
+    }
 
-        // Initialize resampler only if sample rates differ
-        let input_sample_rate = self.config.sample_rate.0 as f64;
-        let output_sample_rate = config.sample_rate.0 as f64;
+    fn poll_timecode(&mut self) {
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let rate = self.recorder.config.sample_rate.0 as f64;
+        match self.settings.timecode_source {
+            TimecodeSource::Off => {
+                self.timecode = None;
+                self.ltc = None;
+            }
+            TimecodeSource::Mtc => {
+                self.ltc = None;
+                let Some((timecode, arrived)) = self.midi.timecode() else {
+                    return;
+                };
+                if self
+                    .timecode
+                    .as_ref()
+                    .is_some_and(|t| t.arrived == Some(arrived))
+                {
+                    return;
+                }
+                let now = self.recorder.buffer.lock().unwrap().total_written / channels;
+                let ago = (arrived.elapsed().as_secs_f64() * rate) as u64;
+                self.timecode = Some(TimecodeReference {
+                    timecode,
+                    frame: now.saturating_sub(ago),
+                    arrived: Some(arrived),
+                });
+            }
+            TimecodeSource::Ltc => {
+                let channel = self.settings.ltc_channel.min(channels as usize - 1) as u64;
+                let buffer = self.recorder.buffer.lock().unwrap();
+                let ltc = self.ltc.get_or_insert_with(|| LtcInput {
+                    decoder: LtcDecoder::new(self.recorder.config.sample_rate.0),
+                    buffer_id: u64::MAX,
+                    next: 0,
+                    start: 0,
+                });
+                // A new buffer, or audio that rolled out before it was read: start decoding
+                // again from the newest audio, which takes a frame to lock on to
+                if ltc.buffer_id != buffer.id || ltc.next < buffer.window_start() {
+                    ltc.decoder = LtcDecoder::new(self.recorder.config.sample_rate.0);
+                    ltc.buffer_id = buffer.id;
+                    ltc.next = buffer.total_written / channels * channels;
+                    ltc.start = ltc.next / channels;
+                }
+                let (from, to) = (ltc.next, buffer.total_written / channels * channels);
+                let samples = (from..to)
+                    .step_by(channels as usize)
+                    .map(|index| buffer.sample_at(index + channel));
+                if let Some((timecode, position)) = ltc.decoder.feed(samples) {
+                    self.timecode = Some(TimecodeReference {
+                        timecode,
+                        frame: ltc.start + position,
+                        arrived: None,
+                    });
+                }
+                ltc.next = to;
+            }
+        }
+    }
 
-        println!(
-            "Output Stream Config - Sample Rate: {}, Channels: {}",
-            output_sample_rate, config.channels
-        );
+    // The timecode at an absolute frame, counted on from the latest one received
+    fn timecode_at(&self, frame: u64) -> Option<(Timecode, f64)> {
+        let reference = self.timecode.as_ref()?;
+        let rate = self.recorder.config.sample_rate.0 as f64;
+        let seconds = reference.timecode.seconds() + (frame as f64 - reference.frame as f64) / rate;
+        let seconds = seconds.rem_euclid(24.0 * 3600.0);
+        Some((
+            Timecode::from_seconds(seconds, reference.timecode.rate),
+            seconds,
+        ))
+    }
 
-        if (input_sample_rate - output_sample_rate).abs() > f64::EPSILON {
-            // print resampler ratio
-            println!(
-                "Resampling ratio: {}",
-                output_sample_rate / input_sample_rate
-            );
-            let resampler = SincFixedIn::<f32>::new(
-                output_sample_rate / input_sample_rate, // Resampling ratio
-                2.0,
-                SincInterpolationParameters {
-                    sinc_len: 256, // Increased from default
-                    f_cutoff: 0.95,
-                    interpolation: SincInterpolationType::Linear,
-                    oversampling_factor: 256, // Increased from default
-                    window: WindowFunction::BlackmanHarris2,
+    #[cfg(feature = "gui")]
+    fn current_timecode(&self) -> Option<Timecode> {
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let now = self.recorder.buffer.lock().unwrap().total_written / channels;
+        self.timecode_at(now).map(|(timecode, _)| timecode)
+    }
+
+    // Give a take starting at absolute sample `start` its timecode: the BWF time reference, the
+    // metadata and, if asked, the name
+    fn stamp_timecode(&self, take: &mut Take, start: u64) {
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let Some((timecode, seconds)) = self.timecode_at(start / channels) else {
+            return;
+        };
+        take.time_reference = Some((seconds * take.sample_rate as f64).round() as u64);
+        take.metadata
+            .push(("timecode".to_owned(), timecode.to_string()));
+        if self.settings.timecode_in_name {
+            take.rename(&format!("{}_{}", take.name, timecode.file_safe()));
+        }
+    }
+
+    fn poll_hits(&mut self) {
+        let Some(capture) = &mut self.hit_capture else {
+            return;
+        };
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let samples_per_ms = self.recorder.config.sample_rate.0 as f32 / 1000.0 * channels as f32;
+        let pre_roll =
+            (self.settings.hit_pre_roll_ms * samples_per_ms) as u64 / channels * channels;
+        let length = (self.settings.hit_length_ms * samples_per_ms) as u64 / channels * channels;
+
+        let mut one_shots = Vec::new();
+        {
+            let buffer = self.recorder.buffer.lock().unwrap();
+            if capture.buffer_id != buffer.id {
+                // Swapped by a grab or reset: hits still waiting on audio are lost with it
+                capture.buffer_id = buffer.id;
+                capture.next = buffer.total_written;
+                capture.pending.clear();
+            }
+            capture.next = capture.next.max(buffer.window_start());
+
+            while capture.next < buffer.total_written {
+                let level = (0..channels)
+                    .map(|channel| buffer.sample_at(capture.next + channel).abs())
+                    .fold(0.0f32, f32::max);
+                if capture.detector.process(level) {
+                    capture.pending.push(capture.next);
+                }
+                capture.next += channels;
+            }
+
+            capture.pending.retain(|&hit| {
+                if buffer.total_written < hit + length {
+                    return true; // Still coming in
+                }
+                let start = hit.saturating_sub(pre_roll).max(buffer.window_start());
+                let samples = buffer.copy_range(start, hit + length);
+                capture.count += 1;
+                let name = format!("{}_hit{:03}", get_file_safe_timestamp(), capture.count);
+                one_shots.push((name, start, samples));
+                false
+            });
+        }
+
+        for (name, start, samples) in one_shots {
+            println!("Hit detected, saving {}", name);
+            match self.new_take(name, samples, None) {
+                Ok(mut take) => {
+                    self.stamp_timecode(&mut take, start);
+                    self.save_take(take);
+                }
+                Err(e) => self.toasts.error(e),
+            }
+        }
+    }
+
+    fn drop_marker(&mut self) {
+        let session = self.settings.session_mut();
+        session.marker_count += 1;
+        let number = session.marker_count;
+        self.recorder.drop_marker(number);
+    }
+
+    #[cfg(feature = "link")]
+    fn set_link_enabled(&mut self, enabled: bool) {
+        self.link = None; // Stops following
+        if !enabled {
+            return;
+        }
+        match LinkFollower::start() {
+            Ok(link) => self.link = Some(link),
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to join Ableton Link: {}",
+                e
+            ))),
+        }
+    }
+
+    #[cfg(not(feature = "link"))]
+    fn set_link_enabled(&mut self, enabled: bool) {
+        if enabled {
+            self.toasts.error(link_not_built());
+        }
+    }
+
+    // The tempo of the Link session being followed, once one is found
+    #[cfg(feature = "link")]
+    fn link_bpm(&self) -> Option<f32> {
+        self.link
+            .as_ref()
+            .and_then(|link| link.status().timeline)
+            .map(|timeline| timeline.bpm() as f32)
+    }
+
+    #[cfg(not(feature = "link"))]
+    fn link_bpm(&self) -> Option<f32> {
+        None
+    }
+
+    // The script in the settings, if any, replacing the one running
+    #[cfg(feature = "scripting")]
+    fn load_script(&mut self) {
+        self.script = None;
+        let Some(path) = &self.settings.script_path else {
+            return;
+        };
+        match Script::load(Path::new(path)) {
+            Ok(script) => self.script = Some(script),
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to load the script: {}",
+                e
+            ))),
+        }
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn load_script(&mut self) {
+        if self.settings.script_path.is_some() {
+            self.toasts.error(RecorderError::Other(
+                "Failed to load the script: built without the `scripting` feature".to_owned(),
+            ));
+        }
+    }
+
+    // Runs a script hook, then whatever it asked for. Tags go on `take`, when there is one.
+    #[cfg(feature = "scripting")]
+    fn call_script(
+        &mut self,
+        hook: &str,
+        args: impl rhai::FuncArgs,
+        take: Option<&mut Take>,
+    ) -> rhai::Dynamic {
+        let Some(script) = &mut self.script else {
+            return rhai::Dynamic::UNIT;
+        };
+        let result = script.call(hook, args);
+        let actions = script.take_actions();
+        let mut take = take;
+        for action in actions {
+            match action {
+                ScriptAction::Command(line) => match Command::parse(&line) {
+                    Ok(command) => self.dispatch(command),
+                    Err(e) => eprintln!("Script command: {}", e),
                 },
-                4096,               // Chunk size
-                num_input_channels, // Number of channels
-            )
-            .expect("Failed to create resampler");
-            self.resampler = Some(resampler);
+                ScriptAction::Tag(key, value) => match take.as_deref_mut() {
+                    Some(take) => take.metadata.push((key, value)),
+                    None => eprintln!("Script: tag() only works in on_grab"),
+                },
+                ScriptAction::Note(text) => self.toasts.note(text),
+            }
+        }
+        result.unwrap_or_else(|e| {
+            self.toasts
+                .error(RecorderError::Other(format!("Script: {}", e)));
+            rhai::Dynamic::UNIT
+        })
+    }
+
+    // Edits to the script file, and the clip and silence hooks
+    #[cfg(feature = "scripting")]
+    fn poll_script(&mut self) {
+        let Some(script) = &mut self.script else {
+            return;
+        };
+        if let Err(e) = script.reload_if_changed() {
+            self.toasts.error(RecorderError::Other(format!(
+                "Failed to reload the script: {}",
+                e
+            )));
+        }
+
+        let seen = script.clips_seen;
+        let clips: Vec<(i64, i64)> = self
+            .meters
+            .clip_log
+            .iter()
+            .filter(|clip| seen.is_none_or(|seen| clip.time > seen))
+            .map(|clip| (clip.channel as i64 + 1, clip.samples as i64))
+            .collect();
+        script.clips_seen = self.meters.clip_log.back().map(|clip| clip.time).or(seen);
+
+        let peak = f32::from_bits(self.recorder.input_peak.load(Ordering::Relaxed));
+        let quiet = 20.0 * peak.max(1e-6).log10() < self.settings.script_silence_db;
+        let mut silence = None;
+        if !quiet {
+            script.quiet_since = None;
+            script.silence_reported = false;
         } else {
-            self.resampler = None;
-        }
-
-        self.resample_buffers = vec![Vec::new(); num_input_channels]; // Reset resample buffers
-
-        let monitoring_buffers = Arc::clone(&self.monitoring_buffers);
-        let resampler = Arc::new(Mutex::new(self.resampler.take()));
-
-        let resampler_clone = Arc::clone(&resampler);
-        let output_stream = match sample_format {
-            SampleFormat::F32 => {
-                output_device.build_output_stream(
-                    &output_config,
-                    move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                        let mut m_buffers = monitoring_buffers.lock().unwrap();
-                        let mut samples_per_channel: Vec<Vec<f32>> =
-                            vec![Vec::new(); num_input_channels];
-
-                        // Collect samples per channel
-                        for (channel, m_buffer) in m_buffers.iter_mut().enumerate() {
-                            while let Some(sample) = m_buffer.pop_front() {
-                                samples_per_channel[channel].push(sample);
-                            }
-                        }
-
-                        // Determine the minimum number of samples across all channels
-                        let min_samples = samples_per_channel
-                            .iter()
-                            .map(|v| v.len())
-                            .min()
-                            .unwrap_or(0);
-
-                        // Resample if necessary and if we have enough samples
-                        let resampled_samples_per_channel: Vec<Vec<f32>> =
-                            if let Some(resampler) = resampler_clone.lock().unwrap().as_mut() {
-                                let chunk_size = resampler.input_frames_next();
-                                if min_samples >= chunk_size {
-                                    // We have enough samples to resample
-                                    let input: Vec<&[f32]> = samples_per_channel
-                                        .iter()
-                                        .map(|v| &v[..chunk_size])
-                                        .collect();
-                                    match resampler.process(&input, None) {
-                                        Ok(output) => output,
-                                        Err(e) => {
-                                            eprintln!("Resampling failed: {}", e);
-                                            vec![vec![0.0; chunk_size]; num_input_channels]
-                                            // Return silence on error
-                                        }
-                                    }
-                                } else {
-                                    // Not enough samples, return the original samples
-                                    samples_per_channel.clone()
-                                }
-                            } else {
-                                samples_per_channel.clone()
-                            };
-
-                        // Trim the original samples_per_channel to remove processed samples
-                        if min_samples > 0 {
-                            for channel_samples in samples_per_channel.iter_mut() {
-                                channel_samples.drain(..min_samples);
-                            }
-                        }
-
-                        // Determine the number of frames to write
-                        let num_frames = data.len() / num_output_channels;
-
-                        for frame_idx in 0..num_frames {
-                            for channel in 0..num_output_channels {
-                                if num_input_channels == 1 {
-                                    // Mono input, duplicate the sample for both output channels
-                                    let mono_sample =
-                                        if frame_idx < resampled_samples_per_channel[0].len() {
-                                            resampled_samples_per_channel[0][frame_idx]
-                                        } else {
-                                            0.0 // If out of bounds, fill with silence
-                                        };
-                                    data[frame_idx * num_output_channels + channel] = mono_sample;
-                                } else if channel < resampled_samples_per_channel.len() {
-                                    let channel_samples = &resampled_samples_per_channel[channel];
-                                    if frame_idx < channel_samples.len() {
-                                        data[frame_idx * num_output_channels + channel] =
-                                            channel_samples[frame_idx];
-                                    } else {
-                                        data[frame_idx * num_output_channels + channel] = 0.0;
-                                        // Silence
-                                    }
-                                } else {
-                                    data[frame_idx * num_output_channels + channel] = 0.0;
-                                    // Silence
-                                }
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )
-            }
-            _ => panic!("Unsupported sample format for monitoring"),
-        }
-        .unwrap();
-
-        // Stop the previous output stream if it exists
-        if let Some(old_stream) = self.output_stream.take() {
-            drop(old_stream);
-        }
-        self.output_stream = Some(output_stream);
-        self.is_monitoring.store(true, Ordering::SeqCst);
-        println!("Monitoring started");
+            let since = *script.quiet_since.get_or_insert_with(Instant::now);
+            let seconds = since.elapsed().as_secs_f64();
+            if !script.silence_reported && seconds >= self.settings.script_silence_seconds as f64 {
+                script.silence_reported = true;
+                silence = Some(seconds);
+            }
+        }
+
+        for (channel, samples) in clips {
+            let _ = self.call_script("on_clip", (channel, samples), None);
+        }
+        if let Some(seconds) = silence {
+            let _ = self.call_script("on_silence", (seconds,), None);
+        }
     }
 
-    fn stop_monitoring(&mut self) {
-        if let Some(output_stream) = self.output_stream.take() {
-            drop(output_stream); // Stop the output stream
+    // Lets the script's on_grab rename or tag the take, or throw it away (returning false)
+    #[cfg(feature = "scripting")]
+    fn script_on_grab(&mut self, take: &mut Take) -> bool {
+        if self.script.is_none() {
+            return true;
+        }
+        let mut metadata = rhai::Map::new();
+        for (key, value) in &take.metadata {
+            metadata.insert(key.as_str().into(), value.clone().into());
+        }
+        let mut info = rhai::Map::new();
+        info.insert("name".into(), take.name.clone().into());
+        info.insert("preset".into(), take.preset.clone().into());
+        info.insert(
+            "session".into(),
+            self.settings.session().name.clone().into(),
+        );
+        info.insert(
+            "n".into(),
+            (self.settings.session().take_counter as i64).into(),
+        );
+        info.insert("seconds".into(), take.duration_seconds().into());
+        info.insert("channels".into(), (take.channels as i64).into());
+        info.insert("sample_rate".into(), (take.sample_rate as i64).into());
+        let peak = dsp::peak(&take.samples);
+        info.insert(
+            "peak_db".into(),
+            (20.0 * peak.max(1e-6).log10() as f64).into(),
+        );
+        let clipped = take
+            .metadata
+            .iter()
+            .any(|(key, _)| key == "clipped_samples");
+        info.insert("clipped".into(), clipped.into());
+        info.insert("metadata".into(), metadata.into());
+
+        let result = self.call_script("on_grab", (info,), Some(take));
+        if result.as_bool() == Ok(false) {
+            println!("Script discarded {}", take.name);
+            self.toasts.note(format!("Script discarded {}", take.name));
+            return false;
+        }
+        if let Ok(name) = result.into_string() {
+            match take_name_problem(take, &name) {
+                None => take.rename(name.trim()),
+                Some(problem) => self
+                    .toasts
+                    .note(format!("Script's name {} not used: {}", name, problem)),
+            }
         }
-        self.is_monitoring.store(false, Ordering::SeqCst);
+        true
+    }
 
-        // Clear the monitoring buffers
-        let mut m_buffers = self.monitoring_buffers.lock().unwrap();
-        for buffer in m_buffers.iter_mut() {
-            buffer.clear();
+    // Save the last whole `bars` bars of the Link session, ending on the latest bar line, so the
+    // take loops cleanly at the session's tempo
+    #[cfg(feature = "link")]
+    fn grab_bars(&mut self, bars: u32) {
+        if self.recorder.state.is_grabbing() {
+            self.toasts.note("Stop the grab in progress first");
+            return;
+        }
+        let Some(timeline) = self.link.as_ref().and_then(|link| link.status().timeline) else {
+            self.toasts.error(RecorderError::Other(
+                "No Ableton Link session found on the network".to_owned(),
+            ));
+            return;
+        };
+        let channels = self.recorder.config.channels.max(1) as u64;
+        let rate = self.recorder.config.sample_rate.0 as f64;
+        let beats_per_bar = self.settings.link_beats_per_bar.max(1.0) as f64;
+        let seconds_per_beat = 60.0 / timeline.bpm();
+        let (start, samples) = {
+            let buffer = self.recorder.buffer.lock().unwrap();
+            // The newest sample is taken to be now, the input latency aside
+            let beat = timeline.beat_at(Instant::now());
+            let since_bar = beat - (beat / beats_per_bar).floor() * beats_per_bar;
+            let now_frame = buffer.total_written / channels;
+            let bar_frame = now_frame.saturating_sub((since_bar * seconds_per_beat * rate) as u64);
+            let length = (bars as f64 * beats_per_bar * seconds_per_beat * rate).round() as u64;
+            let start = bar_frame.saturating_sub(length) * channels;
+            if bar_frame < length || start < buffer.window_start() {
+                drop(buffer);
+                self.toasts.error(RecorderError::Other(format!(
+                    "The buffer doesn't hold {} bars at {:.1} BPM yet",
+                    bars,
+                    timeline.bpm()
+                )));
+                return;
+            }
+            (start, buffer.copy_range(start, bar_frame * channels))
+        };
+        let name = format!("{}_{}bars", get_file_safe_timestamp(), bars);
+        println!(
+            "Grabbing the last {} bars at {:.1} BPM",
+            bars,
+            timeline.bpm()
+        );
+        match self.new_take(name, samples, None) {
+            Ok(mut take) => {
+                take.metadata
+                    .push(("bpm".to_owned(), format!("{:.1}", timeline.bpm())));
+                take.metadata.push(("bars".to_owned(), bars.to_string()));
+                self.stamp_timecode(&mut take, start);
+                self.save_take(take);
+            }
+            Err(e) => self.toasts.error(e),
         }
+    }
 
-        println!("Monitoring stopped");
+    #[cfg(not(feature = "link"))]
+    fn grab_bars(&mut self, _bars: u32) {
+        self.toasts.error(link_not_built());
     }
-    fn reset_monitoring_buffers(&mut self) {
-        let num_channels = self.config.channels as usize;
-        let initial_monitoring_capacity = self.config.sample_rate.0 as usize * 4; // Example: 4 second buffer
-        self.monitoring_buffers = Arc::new(Mutex::new(vec![
-            VecDeque::with_capacity(
-                initial_monitoring_capacity
-            );
-            num_channels
-        ]));
-        self.resample_buffers = vec![Vec::new(); num_channels];
+
+    #[cfg(feature = "gui")]
+    fn export_marker_range(&mut self, from: usize, to: usize) {
+        let (Some(a), Some(b)) = (self.recorder.marker(from), self.recorder.marker(to)) else {
+            self.toasts.error(RecorderError::Other(format!(
+                "Marker {} or {} is no longer in the buffer",
+                from, to
+            )));
+            return;
+        };
+        let (start, end) = (a.index.min(b.index), a.index.max(b.index));
+        let name = format!("{}_m{}-m{}", get_file_safe_timestamp(), from, to);
+        self.recorder.export_between(name, start, end);
+    }
+
+    #[cfg(feature = "gui")]
+    fn export_around_marker(&mut self, number: usize) {
+        let Some(marker) = self.recorder.marker(number) else {
+            return;
+        };
+        let half = (self.settings.marker_export_seconds / 2.0
+            * self.recorder.config.sample_rate.0 as f32
+            * self.recorder.config.channels as f32) as u64;
+        let index = marker.index;
+        let name = format!("{}_m{}", get_file_safe_timestamp(), number);
+        self.recorder
+            .export_between(name, index.saturating_sub(half), index + half);
     }
-}
 
-impl CircularBuffer {
-    fn new(max_size: usize) -> Self {
-        CircularBuffer {
-            circular_buffer: Vec::with_capacity(max_size),
-            static_buffer: Vec::new(), // Start with an empty static buffer
-            max_size,
-            write_pos: 0,
-            current_size: 0,
-            is_static_mode: false,
+    fn poll_markers(&mut self) {
+        for (name, start, samples) in self.recorder.poll_markers() {
+            println!("Saving marker export {}", name);
+            match self.new_take(name, samples, None) {
+                Ok(mut take) => {
+                    self.stamp_timecode(&mut take, start);
+                    self.save_take(take);
+                }
+                Err(e) => self.toasts.error(e),
+            }
         }
     }
 
-    fn add_samples(&mut self, samples: &[f32]) {
-        if self.is_static_mode {
-            // In static mode, add samples to the static buffer
-            self.static_buffer.extend_from_slice(samples);
-            self.current_size += samples.len();
-        } else {
-            // In circular mode, overwrite old data if necessary
-            for &sample in samples {
-                if self.current_size < self.max_size {
-                    self.circular_buffer.push(sample);
-                    self.current_size += 1;
-                } else {
-                    self.circular_buffer[self.write_pos] = sample;
+    fn write_session_report(&mut self) {
+        if self.history.is_empty() {
+            println!("Nothing saved this session, so no report");
+            return;
+        }
+        let Some(save_path) = self.save_path.clone() else {
+            return;
+        };
+        // The report reads every file back, so they all have to be finished
+        self.wait_for_saves();
+        let history = std::mem::take(&mut self.history);
+        self.write_report(&save_path, self.session_started, &history);
+        self.history = history;
+    }
+
+    fn write_report(&mut self, save_path: &str, started: DateTime<Local>, history: &[HistoryItem]) {
+        match report::write_session_report(Path::new(save_path), started, history) {
+            Ok(path) => self
+                .toasts
+                .note(format!("Session report written to {}", path.display())),
+            Err(e) => self.toasts.error(RecorderError::Save(format!(
+                "Failed to write the session report: {}",
+                e
+            ))),
+        }
+    }
+
+    // Takes still waiting on the user when the app quits are saved rather than dropped, as they
+    // stand since nobody is left to answer the prompts
+    fn save_held_takes(&mut self) {
+        // Quitting with the naming prompt open saves under what has been typed
+        if let Some((take, name)) = self.pending_name.take() {
+            self.save_named(take, &name);
+        }
+        #[cfg(feature = "gui")]
+        {
+            let reviews: Vec<Review> = self
+                .review
+                .take()
+                .into_iter()
+                .chain(std::mem::take(&mut self.reviews_waiting))
+                .collect();
+            for review in reviews {
+                println!("Saving {} without a review on quit", review.take.name);
+                self.save_take(review.kept(self.settings.loop_crossfade_ms));
+            }
+        }
+        if let Some((take, _)) = self.pending_mono_check.take() {
+            println!("Saving {} as is on quit", take.name);
+            self.save_take(take);
+        }
+        if let Some(layering) = self.layering.take() {
+            if let (Some(layer), false) = (layering.layer, layering.saved) {
+                println!("Saving the unsaved layer {} on quit", layer.name);
+                self.save_take(layer);
+            }
+        }
+    }
+
+    fn on_close(&mut self) {
+        self.save_held_takes();
+        if self.settings.session_report_on_exit {
+            self.write_session_report();
+            // and one for each other session that saved something
+            for (name, parked) in std::mem::take(&mut self.parked_sessions) {
+                let save_path = self
+                    .settings
+                    .sessions
+                    .iter()
+                    .find(|session| session.name == name)
+                    .and_then(|session| session.save_path.clone())
+                    .or_else(default_save_path);
+                if let (false, Some(save_path)) = (parked.history.is_empty(), save_path) {
+                    self.write_report(&save_path, parked.started, &parked.history);
                 }
-                self.write_pos = (self.write_pos + 1) % self.max_size;
             }
         }
+        self.wait_for_saves();
+        self.settings.save();
+    }
+
+    // Switch to another session: its save folder, naming, settings and history. Not while a take
+    // is still waiting on something, since it would be saved under the wrong session.
+    fn switch_session(&mut self, index: usize) {
+        if index == self.settings.active_session || index >= self.settings.sessions.len() {
+            return;
+        }
+        if self.recorder.state.is_grabbing()
+            || self.review.is_some()
+            || self.pending_name.is_some()
+            || self.pending_mono_check.is_some()
+        {
+            self.toasts
+                .note("Finish the current take before switching sessions");
+            return;
+        }
+        // Finished saves land in the history of the session they were made in
+        self.wait_for_saves();
+        let previous = self.settings.session().name.clone();
+        let parked = ParkedSession {
+            history: std::mem::take(&mut self.history),
+            markers: std::mem::take(&mut self.recorder.markers),
+            started: self.session_started,
+        };
+        self.parked_sessions.insert(previous, parked);
+
+        let buffer_seconds = self.settings.buffer_seconds;
+        self.settings.load_session(index);
+        let name = self.settings.session().name.clone();
+        let markers = match self.parked_sessions.remove(&name) {
+            Some(parked) => {
+                self.history = parked.history;
+                self.session_started = parked.started;
+                parked.markers
+            }
+            None => {
+                self.session_started = Local::now();
+                Vec::new()
+            }
+        };
+        // History indices point into the old session's list
+        self.ab_pick = [None, None];
+        self.ab_playback = None;
+        self.history_playback = None;
+        self.renaming = None;
+        self.confirm_delete = None;
+        self.recorder.cancel_exports();
+        self.learning_binding = None;
+        self.save_path = self.settings.save_path.clone().or_else(default_save_path);
+        println!("Switched to session {}", name);
+
+        self.settings.save();
+        if self.settings.buffer_seconds != buffer_seconds {
+            self.start_recording(); // Also moves the safety recording to the new folder
+        } else if self.safety.is_some() {
+            self.set_safety_enabled(true);
+        }
+        // After any restart, which drops the markers that pointed into the old history
+        self.recorder.restore_markers(markers);
     }
 
-    fn start_static_mode(&mut self) {
-        self.is_static_mode = true;
+    #[cfg(feature = "gui")]
+    fn start_ab(&mut self) {
+        let [Some(a), Some(b)] = self.ab_pick else {
+            return;
+        };
+        let decode = |index: usize| {
+            let item = &self.history[index];
+            read_wav(&item.path).map_err(|e| format!("Failed to read {}: {}", item.name, e))
+        };
+        let (a, b) = match (decode(a), decode(b)) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => {
+                self.toasts.error(RecorderError::Other(e.to_string()));
+                return;
+            }
+        };
+        match self.recorder.play_ab(a, b) {
+            Ok(ab) => self.ab_playback = Some(ab),
+            Err(e) => self.toasts.error(e.context("Failed to start A/B playback")),
+        }
+    }
 
-        println!("Transitioning to static mode");
+    // Play a history item from `from`, a fraction of its length
+    #[cfg(feature = "gui")]
+    fn audition(&mut self, index: usize, from: f32) {
+        let path = self.history[index].path.clone();
+        if let Some(playback) = self.play_file(&path, from) {
+            self.history_playback = Some((index, playback));
+        }
+    }
 
-        // Copy the contents of the circular buffer to the static buffer
-        if self.current_size < self.max_size {
-            self.static_buffer
-                .extend_from_slice(&self.circular_buffer[..self.current_size]);
-        } else {
-            self.static_buffer
-                .extend_from_slice(&self.circular_buffer[self.write_pos..]);
-            self.static_buffer
-                .extend_from_slice(&self.circular_buffer[..self.write_pos]);
+    #[cfg(feature = "gui")]
+    fn play_file(&mut self, path: &Path, from: f32) -> Option<Playback> {
+        let audio = match read_wav(path) {
+            Ok(audio) => audio,
+            Err(e) => {
+                self.toasts.error(RecorderError::Other(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )));
+                return None;
+            }
+        };
+        let frames = audio.samples.len() / audio.channels.max(1);
+        let playback = self.recorder.play(
+            Arc::new(audio.samples),
+            audio.channels,
+            audio.sample_rate,
+            (frames as f32 * from.clamp(0.0, 1.0)) as usize,
+            frames,
+        );
+        match playback {
+            Ok(playback) => Some(playback),
+            Err(e) => {
+                self.toasts.error(e.context("Failed to start playback"));
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn open_library(&mut self, dir: PathBuf) {
+        match history::scan_folder(&dir) {
+            Ok(items) => {
+                println!("Found {} takes in {}", items.len(), dir.display());
+                self.library = Some(Library {
+                    dir,
+                    items,
+                    playback: None,
+                });
+            }
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to read {}: {}",
+                dir.display(),
+                e
+            ))),
+        }
+    }
+
+    // Rates or tags a take in its sidecar, and wherever it's listed
+    #[cfg(feature = "gui")]
+    fn edit_take(&mut self, path: &Path, edit: TakeEdit) {
+        let library = self
+            .library
+            .iter_mut()
+            .flat_map(|library| library.items.iter_mut());
+        let mut items = self
+            .history
+            .iter_mut()
+            .chain(library)
+            .filter(|item| item.path == path);
+        let Some(first) = items.next() else {
+            return;
+        };
+        if let Err(e) = first.apply(edit) {
+            self.toasts.error(RecorderError::Other(format!(
+                "Failed to update {}: {}",
+                first.name, e
+            )));
+            return;
+        }
+        let (rating, tags) = (first.rating, first.tags.clone());
+        for item in items {
+            item.rating = rating;
+            item.tags = tags.clone();
+        }
+    }
+
+    // Forget a deleted history item, keeping the indices held elsewhere pointing at the same takes
+    #[cfg(feature = "gui")]
+    fn remove_history_item(&mut self, index: usize) {
+        self.history.remove(index);
+        let shift = |pick: Option<usize>| match pick {
+            Some(i) if i == index => None,
+            Some(i) if i > index => Some(i - 1),
+            pick => pick,
+        };
+        if self.ab_pick.contains(&Some(index)) {
+            self.ab_playback = None;
+        }
+        self.ab_pick = self.ab_pick.map(shift);
+        self.history_playback = self
+            .history_playback
+            .take()
+            .filter(|(i, _)| *i != index)
+            .and_then(|(i, playback)| Some((shift(Some(i))?, playback)));
+    }
+
+    fn buffered_frames(&self) -> usize {
+        self.recorder.buffered_frames()
+    }
+
+    fn min_grab_frames(&self) -> usize {
+        (self.settings.min_grab_seconds * self.recorder.config.sample_rate.0 as f32) as usize
+    }
+
+    // Stop the current grab, applying the short grab policy if barely any audio was captured
+    fn request_grab(&mut self) {
+        if self.buffered_frames() >= self.min_grab_frames() {
+            self.grab_recording();
+            return;
+        }
+
+        println!(
+            "Grab is shorter than {} s, applying short grab policy",
+            self.settings.min_grab_seconds
+        );
+        match self.settings.short_grab_policy {
+            ShortGrabPolicy::Ask => self.short_grab = Some(ShortGrab::Prompting),
+            ShortGrabPolicy::WaitAndExtend => self.short_grab = Some(ShortGrab::Extending),
+            ShortGrabPolicy::SaveAnyway => self.grab_recording(),
+        }
+    }
+
+    // Called every frame: finish an extended grab once it is long enough
+    fn poll_short_grab(&mut self) {
+        if self.short_grab == Some(ShortGrab::Extending)
+            && self.buffered_frames() >= self.min_grab_frames()
+        {
+            self.short_grab = None;
+            self.grab_recording();
         }
     }
 
-    // Method to get the current samples for plotting (whether static or circular)
-    fn get_samples_for_plot(&self) -> Vec<f32> {
-        if self.is_static_mode {
-            self.static_buffer.clone()
+    // Start a grab, or stop and save the one in progress
+    fn toggle_grab(&mut self) {
+        if self.short_grab.is_some() {
+            return; // Already waiting on a short grab to be resolved
+        }
+        if self.recorder.state.is_grabbing() {
+            self.request_grab();
         } else {
-            if self.current_size < self.max_size {
-                self.circular_buffer.clone()
+            self.start_grab();
+        }
+    }
+
+    fn start_grab(&mut self) -> bool {
+        self.recorder.start_grab()
+    }
+
+    // Called every frame, see `Recorder::poll_auto_grab`
+    fn poll_auto_grab(&mut self) {
+        self.recorder.poll_auto_grab();
+    }
+
+    fn poll_gamepad(&mut self) {
+        for button in self.gamepad.poll() {
+            if self.learning_gamepad_button {
+                println!("Grab button set to {}", button);
+                self.settings.gamepad_grab_button = Some(button);
+                self.learning_gamepad_button = false;
+                self.settings.save();
+            } else if self.settings.gamepad_grab_button.as_ref() == Some(&button) {
+                println!("Grab toggled from gamepad");
+                self.toggle_grab();
             } else {
-                let mut plot_samples = Vec::with_capacity(self.max_size);
-                plot_samples.extend_from_slice(&self.circular_buffer[self.write_pos..]);
-                plot_samples.extend_from_slice(&self.circular_buffer[..self.write_pos]);
-                plot_samples
+                self.handle_trigger(Trigger::Gamepad(button));
             }
         }
     }
-}
 
-impl App for Recorder {
-    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
-        // Repaint the UI to update the plot
-        ctx.request_repaint_after(std::time::Duration::from_millis(33));
-
-        CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(10.0); // Add some space at the top
-
-            ui.vertical_centered(|ui| {
-                // ui.add_space(10.0); // Add some space at the top
-                let panel_width = ui.available_width();
-
-                // Center the contents inside the horizontal layout
-                ui.vertical_centered(|ui| {
-                    // Device selection dropdown - can't centre this because it isn't an atomic widget 🤷
-                    ui.horizontal(|ui| {
-                        ui.label("Input Device:");
-                        let current_input_device_index = self.current_input_device_index; // Store the current device index for later comparison
-                        egui::ComboBox::from_id_source("Device") // Using an ID instead of a label
-                            .selected_text(
-                                self.input_devices[self.current_input_device_index]
-                                    .name()
-                                    .unwrap_or_default()
-                                    .clone(),
-                            )
-                            .show_ui(ui, |ui| {
-                                for (idx, device) in self.input_devices.iter().enumerate() {
-                                    ui.selectable_value(
-                                        &mut self.current_input_device_index,
-                                        idx,
-                                        device.name().unwrap_or_default(),
-                                    );
-                                }
-                            });
-                        // Check if the selected device has changed
-                        if current_input_device_index != self.current_input_device_index {
-                            // Stop current recording
-                            if let Some(stream) = self.input_stream.take() {
-                                drop(stream);
-                            }
-                            // Update config for new device
-                            let new_device = &self.input_devices[self.current_input_device_index];
-                            self.config = new_device
-                                .default_input_config()
-                                .expect("Failed to get default input config")
-                                .into();
-                            // Start recording with new device
-                            self.start_recording();
-                            self.stop_monitoring();
-
-                            // start monitoring again if it was previously enabled
-                            if self.is_monitoring.load(Ordering::SeqCst) {
-                                self.start_monitoring();
-                            }
-                        }
-                    });
+    fn poll_midi(&mut self) {
+        for message in self.midi.poll() {
+            self.handle_trigger(Trigger::Midi(message));
+        }
+    }
 
-                    // Output Device Selection
-                    ui.horizontal(|ui| {
-                        ui.label("Output Device:");
-                        let output_device =
-                            self.output_devices[self.current_output_device_index].clone();
-                        egui::ComboBox::from_id_source("OutputDevice")
-                            .selected_text(output_device.name().unwrap_or_default())
-                            .show_ui(ui, |ui| {
-                                for device in &self.output_devices {
-                                    // Get the name of the current device
-                                    if let Ok(device_name) = device.name() {
-                                        // Check if the device's name matches the currently selected one
-                                        let is_selected = self.output_devices
-                                            [self.current_output_device_index]
-                                            .name()
-                                            .unwrap_or_default()
-                                            == device_name;
-
-                                        if ui
-                                            .selectable_label(is_selected, device_name.clone())
-                                            .clicked()
-                                        {
-                                            self.current_output_device_index = self
-                                                .output_devices
-                                                .iter()
-                                                .position(|d| {
-                                                    d.name().unwrap_or_default() == device_name
-                                                })
-                                                .unwrap_or(0); // Update the selected device
-                                        }
-                                    }
-                                }
-                            });
-                    });
+    // Run whatever the active preset binds to a trigger, or bind it while learning. Returns
+    // false when nothing is bound.
+    fn handle_trigger(&mut self, trigger: Trigger) -> bool {
+        if let Some(index) = self.learning_binding.take() {
+            let preset = &mut self.settings.presets[self.settings.active_preset];
+            if let Some(binding) = preset.bindings.get_mut(index) {
+                println!("{} bound to '{}'", trigger.label(), binding.command);
+                binding.trigger = trigger;
+                self.settings.save();
+            }
+            return true;
+        }
+        let Some(command) = self.settings.active_preset().binding_for(&trigger) else {
+            return false;
+        };
+        match Command::parse(command) {
+            Ok(command) => {
+                println!("{} -> {:?}", trigger.label(), command);
+                self.dispatch(command);
+            }
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Binding for {}: {}",
+                trigger.label(),
+                e
+            ))),
+        }
+        true
+    }
 
-                    // Add a checkbox to enable/disable monitoring
-                    ui.horizontal(|ui| {
-                        let mut monitoring = self.is_monitoring.load(Ordering::SeqCst);
-                        if ui.checkbox(&mut monitoring, "Enable Monitoring").changed() {
-                            if monitoring {
-                                self.start_monitoring();
-                            } else {
-                                self.stop_monitoring();
-                            }
-                        }
-                    });
+    fn poll_commands(&mut self) {
+        for command in self.commands.poll() {
+            println!("Command received: {:?}", command);
+            self.dispatch(command);
+        }
+    }
 
-                    // Plot the waveform
-                    if let Ok(buffer) = self.sample_buffer.lock() {
-                        let plot_data = buffer.get_samples_for_plot(); // Fetch up to 10,000 samples
-                        let downsample_factor = 10; // Adjust as needed
-                        let points: Vec<[f64; 2]> = plot_data
-                            .iter()
-                            .enumerate()
-                            .filter(|(i, _)| i % downsample_factor == 0)
-                            .map(|(i, &sample)| [i as f64, sample as f64])
-                            .collect();
-
-                        let plot_points = PlotPoints::new(points);
-                        let line = Line::new(plot_points);
-
-                        // Plot the waveform as before
-                        Plot::new("Rolling Waveform Plot")
-                            .view_aspect(4.0)
-                            .auto_bounds(Vec2b::new(true, false))
-                            .show_axes(false)
-                            .show_grid(false)
-                            .show_background(false)
-                            .allow_zoom(false)
-                            .allow_drag(false)
-                            .allow_scroll(false)
-                            .sharp_grid_lines(true)
-                            .coordinates_formatter(
-                                Corner::LeftBottom,
-                                CoordinatesFormatter::new(|_, _| String::new()),
-                            )
-                            .show(ui, |plot_ui: &mut PlotUi| {
-                                plot_ui.line(line);
-                            });
+    // Every control surface (text commands, OSC, bindings) ends up here
+    fn dispatch(&mut self, command: Command) {
+        match command {
+            Command::ToggleGrab => self.toggle_grab(),
+            Command::GrabBars(bars) => self.grab_bars(bars),
+            Command::SwitchAb => {
+                if let Some(ab) = &self.ab_playback {
+                    ab.switch();
+                }
+            }
+            Command::Discard => {
+                if self.recorder.state.is_grabbing() {
+                    self.discard_grab();
+                }
+            }
+            Command::Osc { address, command } => {
+                // Bound addresses win, anything else is read as a plain command
+                if !self.handle_trigger(Trigger::Osc(address)) {
+                    match Command::parse(&command) {
+                        Ok(command) => self.dispatch(command),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            }
+            Command::Marker => self.drop_marker(),
+            Command::Session(name) => {
+                let index = self
+                    .settings
+                    .sessions
+                    .iter()
+                    .position(|session| session.name.to_ascii_lowercase() == name);
+                match index {
+                    Some(index) => self.switch_session(index),
+                    None => eprintln!("No session called '{}'", name),
+                }
+            }
+            #[cfg(feature = "gui")]
+            Command::Show => self.bring_to_front(),
+            #[cfg(not(feature = "gui"))]
+            Command::Show => println!("Already running (pid {})", std::process::id()),
+            Command::SetBufferSeconds(seconds) => {
+                // Same range and units as the buffer size slider
+                self.set_buffer_seconds(seconds.clamp(1.0, 60.0));
+            }
+            Command::Monitor(on) => {
+                let monitoring = self.recorder.is_monitoring();
+                let on = on.unwrap_or(!monitoring);
+                if on != monitoring {
+                    if on {
+                        self.start_monitoring();
+                    } else {
+                        self.stop_monitoring();
                     }
+                }
+            }
+            Command::Arm(on) => {
+                self.arm_enabled = on.unwrap_or(!self.arm_enabled);
+                self.sync_arming();
+            }
+            Command::MeasureLatency => self.start_latency_test(),
+            Command::SetTempo(bpm) => {
+                self.settings.metronome.bpm = bpm;
+                self.settings.save();
+                self.update_metronome();
+            }
+            Command::Metronome(on) => {
+                let on = on.unwrap_or(!self.metronome_running());
+                self.set_metronome_running(on);
+            }
+            Command::Overdub(action) => match action {
+                OverdubAction::Pass => self.start_overdub(true),
+                OverdubAction::SaveLayer => self.save_overdub(false),
+                OverdubAction::SaveBounce => self.save_overdub(true),
+                OverdubAction::Stop => self.layering = None,
+            },
+            Command::AutoSample(on) => {
+                let on = on.unwrap_or(self.auto_sampler.is_none());
+                self.set_auto_sampler_running(on);
+            }
+            Command::Stream(on) => {
+                let on = on.unwrap_or(self.broadcast.is_none());
+                self.settings.broadcast_enabled = on;
+                self.set_broadcast_enabled(on);
+            }
+            Command::Vox(on) => {
+                self.vox_enabled = on.unwrap_or(!self.vox_enabled);
+                self.sync_arming();
+            }
+        }
+    }
 
-                    ui.label(
-                        RichText::new("Choose how much past audio to include in the recording:")
-                            .italics(),
-                    );
+    fn set_control_socket_enabled(&mut self, enabled: bool) {
+        if let Err(e) = self.commands.set_socket_enabled(enabled) {
+            self.toasts.error(RecorderError::Other(format!(
+                "Failed to open the control socket: {}",
+                e
+            )));
+        }
+    }
 
-                    // Slider to control buffer size
-                    let mut buffer_size = *self.buffer_size.lock().unwrap();
+    fn input_level_db(&self) -> f32 {
+        let peak = f32::from_bits(self.recorder.input_peak.load(Ordering::Relaxed));
+        20.0 * peak.max(1e-5).log10()
+    }
 
-                    let desired_width = panel_width * 0.8;
-                    ui.style_mut().spacing.slider_width = desired_width;
+    fn set_remote_enabled(&mut self, enabled: bool) {
+        self.remote = None; // Stops any running server
+        if !enabled {
+            return;
+        }
+        match RemoteServer::start(self.settings.remote_port) {
+            Ok(server) => {
+                #[cfg(feature = "gui")]
+                if let Some(ctx) = self.repaint_ctx.clone() {
+                    server.set_waker(move || ctx.request_repaint());
+                }
+                self.remote = Some(server);
+            }
+            Err(e) => self.toasts.error(RecorderError::Other(format!(
+                "Failed to start remote: {}",
+                e
+            ))),
+        }
+    }
 
-                    // Convert buffer size from samples to seconds for the slider display
-                    let buffer_size_seconds = buffer_size as f32 / self.config.sample_rate.0 as f32;
-                    let max_buffer_seconds = 60.0; // Maximum 60 seconds for the slider
-                    let mut new_buffer_size_seconds = buffer_size_seconds;
+    fn poll_remote(&mut self) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let commands = remote.poll();
+        remote.set_status(RemoteStatus {
+            level_db: self.input_level_db(),
+            grabbing: self.recorder.state.is_grabbing(),
+        });
+        for command in commands {
+            match command {
+                RemoteCommand::ToggleGrab => {
+                    println!("Grab toggled from remote");
+                    self.toggle_grab();
+                }
+            }
+        }
+    }
 
-                    ui.horizontal(|ui| {
-                        ui.label("Buffer Size (s):"); // Text label before the slider
-                        let response = ui.add(egui::Slider::new(
-                            &mut new_buffer_size_seconds,
-                            1.0..=max_buffer_seconds,
-                        ));
+    // Length of the rolling window in seconds
+    fn buffer_seconds(&self) -> f32 {
+        self.recorder.window_seconds()
+    }
 
-                        let new_buffer_size =
-                            (new_buffer_size_seconds * self.config.sample_rate.0 as f32) as usize;
+    // A delayed grab is taken half a window after the cue, so the cue sits in the middle of it
+    fn countdown_capture_time(&self) -> Option<Instant> {
+        self.countdown_cue
+            .map(|cue| cue + std::time::Duration::from_secs_f32(self.buffer_seconds() / 2.0))
+    }
 
-                        if response.drag_stopped() && new_buffer_size != buffer_size {
-                            buffer_size = new_buffer_size;
-                            self.update_buffer_size(buffer_size);
-                            self.start_recording();
-                        }
-                    });
+    fn poll_countdown(&mut self) {
+        let Some(capture) = self.countdown_capture_time() else {
+            return;
+        };
+        if Instant::now() < capture {
+            return;
+        }
+        self.countdown_cue = None;
+        if self.recorder.state.is_grabbing() || self.short_grab.is_some() {
+            println!("Skipping delayed grab, a grab is already in progress");
+            return;
+        }
+        println!("Delayed grab fired");
+        if self.start_grab() {
+            self.grab_recording();
+        }
+    }
 
-                    ui.add_space(20.0); // Add some space between the slider and the button
+    fn apply_meter_settings(&mut self) {
+        self.meters.ballistics = self.settings.meter_ballistics;
+        self.meters.reference_db = self.settings.meter_reference_db;
+        self.meters.hold_seconds = self.settings.meter_hold_seconds;
+        #[cfg(feature = "gui")]
+        {
+            self.monitor_meters.ballistics = self.settings.meter_ballistics;
+            self.monitor_meters.reference_db = self.settings.meter_reference_db;
+            self.monitor_meters.hold_seconds = self.settings.meter_hold_seconds;
+        }
+    }
 
-                    // File path selection button
-                    if ui.button("Select Save Folder").clicked() {
-                        self.open_file_dialog(); // Open the native file dialog
-                    }
+    // Save whatever is in the rolling buffer right now, or the end of it, for scheduled snapshots
+    fn poll_schedules(&mut self) {
+        for index in self.recorder.scheduler.poll() {
+            if self.recorder.state.is_grabbing() || self.short_grab.is_some() {
+                println!(
+                    "Skipping scheduled grab {}, a grab is already in progress",
+                    index + 1
+                );
+                continue;
+            }
+            let Some(schedule) = self.settings.schedules.get(index) else {
+                continue;
+            };
+            let capture_seconds = schedule.capture_seconds;
+            if let Err(e) = schedule.check(self.settings.buffer_seconds) {
+                self.toasts.error(RecorderError::Other(format!(
+                    "Skipping scheduled grab {}: {}",
+                    index + 1,
+                    e
+                )));
+                continue;
+            }
+            println!("Scheduled grab {} fired", index + 1);
+            if !self.start_grab() {
+                continue;
+            }
+            let save_dir = match self.save_dir() {
+                Ok(save_dir) => save_dir,
+                Err(e) => {
+                    self.toasts.error(e);
+                    continue;
+                }
+            };
+            if let Some(mut grabbed) = self.recorder.grab() {
+                if capture_seconds > 0.0 {
+                    let channels = self.recorder.config.channels.max(1) as usize;
+                    let frames =
+                        (capture_seconds * self.recorder.config.sample_rate.0 as f32) as usize;
+                    let keep = grabbed.static_buffer.len().min(frames * channels);
+                    grabbed
+                        .static_buffer
+                        .drain(..grabbed.static_buffer.len() - keep);
+                }
+                self.save_grab(&save_dir, grabbed);
+            }
+        }
+    }
 
-                    if let Some(path) = &self.save_path {
-                        ui.label(format!("Selected Folder: {}", path));
-                    }
+    fn discard_grab(&mut self) {
+        println!("Grab discarded");
+        self.short_grab = None;
+        self.recorder.discard();
+    }
 
-                    ui.add_space(20.0); // Add some space between the path selector and the button
-                                        // Start/Stop Recording button
-                    let record_button_text = if self.is_grabbing.load(Ordering::SeqCst) {
-                        "Stop Grab"
-                    } else {
-                        "Start Grab"
-                    };
-
-                    if ui
-                        .add_sized([100.0, 40.0], egui::Button::new(record_button_text))
-                        .clicked()
-                    {
-                        if self.is_grabbing.load(Ordering::SeqCst) {
-                            println!("Stop button clicked");
-                            self.grab_recording();
-                        } else {
-                            println!("Start grab button clicked");
-                            let mut buffer = self.sample_buffer.lock().unwrap();
-                            buffer.start_static_mode(); // Transition the buffer to static mode
-                            self.is_grabbing.store(true, Ordering::SeqCst);
-                        }
-                    }
-                });
-            });
+    // Restarting the input resizes the buffer to match
+    fn set_buffer_seconds(&mut self, seconds: f32) {
+        self.settings.buffer_seconds = seconds;
+        self.settings.save();
+        self.start_recording();
+    }
+
+    // Passes the wait-for-signal and VOX switches and settings on to the recorder
+    fn sync_arming(&mut self) {
+        let settings = &self.settings;
+        self.recorder.set_arming(Arming {
+            threshold_db: self.arm_enabled.then_some(settings.arm_threshold_db),
+            vox: self.vox_enabled.then_some(VoxSettings {
+                threshold_db: settings.vox_threshold_db,
+                hang_seconds: settings.vox_hang_seconds,
+                pre_roll_seconds: settings.vox_pre_roll_seconds,
+            }),
         });
     }
-}
 
-fn err_fn(err: cpal::StreamError) {
-    eprintln!("An error occurred on the input stream: {}", err);
+    // Save the utterance once a VOX recording has gone quiet
+    fn poll_vox(&mut self) {
+        if self.recorder.vox_finished() {
+            println!("VOX hang time elapsed, saving");
+            self.grab_recording();
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn diagnostics_report(&self) -> String {
+        let preset = self.settings.active_preset();
+        let recorder = &self.recorder;
+        let state = EngineState {
+            input_devices: &recorder.input_devices,
+            input_index: recorder.input_index,
+            output_devices: &recorder.output_devices,
+            output_index: recorder.output_index,
+            config: &recorder.config,
+            settings: vec![
+                (
+                    "Buffer size (samples)",
+                    recorder.buffer.lock().unwrap().max_size.to_string(),
+                ),
+                ("State", format!("{:?}", recorder.state)),
+                ("Monitoring", recorder.is_monitoring().to_string()),
+                ("Wait for signal", self.arm_enabled.to_string()),
+                ("VOX", self.vox_enabled.to_string()),
+                ("Preset", preset.name.clone()),
+                ("Export stages", preset.export_stages.len().to_string()),
+                ("Save folder", self.save_path.clone().unwrap_or_default()),
+                ("Web remote", self.remote.is_some().to_string()),
+                ("Streaming", self.broadcast.is_some().to_string()),
+                ("Metronome", self.metronome_running().to_string()),
+                ("Overdubbing", self.layering.is_some().to_string()),
+                (
+                    "Round-trip latency",
+                    self.settings
+                        .round_trip_latency_ms
+                        .map_or("not measured".to_owned(), |ms| format!("{:.1} ms", ms)),
+                ),
+            ],
+        };
+        diagnostics::report(&recorder.backend, &state, &recorder.stream_stats)
+    }
+
+    fn start_monitoring(&mut self) {
+        let settings = MonitorSettings {
+            routing: self.settings.monitor_routing,
+            custom_routes: self.settings.monitor_custom_routes.clone(),
+            latency_ms: self.settings.monitor_latency_ms,
+            auto_latency: self.settings.monitor_auto_latency,
+            resampler: self.settings.monitor_resampler,
+            limiter_ceiling_db: self
+                .settings
+                .monitor_limiter
+                .then_some(self.settings.monitor_limiter_ceiling_db),
+        };
+        if let Err(e) = self.recorder.start_monitoring(&settings) {
+            self.toasts.error(e);
+            return;
+        }
+        self.settings.monitoring = true;
+        self.settings.save();
+        println!("Monitoring started");
+    }
+
+    // Monitoring is paused for the test, since with the output looped back to the input it
+    // would feed back
+    fn start_latency_test(&mut self) {
+        if self.round_trip.is_some() {
+            return;
+        }
+        if !self.recorder.has_input() {
+            self.toasts.error(RecorderError::Other(
+                "Start the input before measuring the latency".to_owned(),
+            ));
+            return;
+        }
+        let was_monitoring = self.recorder.is_monitoring();
+        if was_monitoring {
+            self.stop_monitoring();
+        }
+        match self.recorder.start_round_trip() {
+            Ok(test) => self.round_trip = Some((test, was_monitoring)),
+            Err(e) => {
+                self.toasts
+                    .error(e.context("Failed to play the latency clicks"));
+                if was_monitoring {
+                    self.start_monitoring();
+                }
+            }
+        }
+    }
+
+    fn poll_latency_test(&mut self) {
+        if !self
+            .round_trip
+            .as_ref()
+            .is_some_and(|(test, _)| test.is_done())
+        {
+            return;
+        }
+        let Some((test, was_monitoring)) = self.round_trip.take() else {
+            return;
+        };
+        let result = test.result(&self.recorder.buffer.lock().unwrap());
+        drop(test);
+        match result {
+            Some((ms, heard)) => {
+                println!("Round-trip latency {:.1} ms ({} clicks heard)", ms, heard);
+                self.toasts
+                    .note(format!("Round-trip latency: {:.1} ms", ms));
+                self.settings.round_trip_latency_ms = Some(ms);
+                self.settings.save();
+            }
+            None => self.toasts.error(RecorderError::Other(
+                "The clicks didn't come back: connect the output to the input, or put a mic by a speaker"
+                    .to_owned(),
+            )),
+        }
+        if was_monitoring {
+            self.start_monitoring();
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn monitor_latency(&self) -> Option<MonitorLatency> {
+        self.recorder.monitor_latency()
+    }
+
+    fn stop_monitoring(&mut self) {
+        self.recorder.stop_monitoring();
+        self.settings.monitoring = false;
+        self.settings.save();
+        println!("Monitoring stopped");
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let app_name = "Rolling Sampler";
-    let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 445.0]), // Set your desired width and height
-        ..Default::default()
+    let args = cli::Args::parse();
+    // Launching again brings the running instance forward (or grabs with --grab) rather than
+    // opening the same input twice
+    let command = if args.grab { "grab" } else { "show" };
+    if control::forward_to_running(command) {
+        println!("Rolling Sampler is already running, sent it '{}'", command);
+        return Ok(());
+    }
+    // Before any device is opened
+    let Some(instance) = InstanceLock::acquire()? else {
+        // The other one may have only just started and not be listening yet
+        for _ in 0..INSTANCE_RETRIES {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            if control::forward_to_running(command) {
+                println!("Rolling Sampler is already running, sent it '{}'", command);
+                return Ok(());
+            }
+        }
+        return Err("Rolling Sampler is already running".into());
     };
-    let app_creator =
-        move |_cc: &CreationContext| -> Result<Box<dyn App>, Box<dyn Error + Send + Sync>> {
-            Ok(Box::new(Recorder::new(5))) // Initialize with a buffer size of 44100 samples
-        };
-    run_native(app_name, native_options, Box::new(app_creator))?;
-    Ok(())
+    let mut settings = Config::load();
+    args.apply(&mut settings);
+    if args.headless {
+        return headless::run(&args, settings, instance);
+    }
+
+    #[cfg(feature = "gui")]
+    return gui::run(&args, settings, instance);
+    #[cfg(not(feature = "gui"))]
+    return headless::run(&args, settings, instance); // Built without a window, so headless is all there is
 }
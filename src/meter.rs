@@ -0,0 +1,273 @@
+use chrono::{DateTime, Local};
+use rolling_sampler_core::meter::MeterTap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+const FLOOR_DB: f32 = -60.0;
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+const RMS_TIME_CONSTANT_SECONDS: f32 = 0.3;
+// A VU needle reaches 99% of a step in 300 ms, about 4.6 time constants
+const VU_TIME_CONSTANT_SECONDS: f32 = 0.065;
+// Scales the rectified average so a sine reads its RMS level, as a VU meter is calibrated
+const VU_SINE_CALIBRATION: f32 = std::f32::consts::PI / (2.0 * std::f32::consts::SQRT_2);
+#[cfg(feature = "gui")]
+const BAR_WIDTH: f32 = 10.0;
+#[cfg(feature = "gui")]
+const GAP: f32 = 3.0;
+#[cfg(feature = "gui")]
+const LED_HEIGHT: f32 = 8.0;
+const MAX_CLIP_EVENTS: usize = 500;
+
+// A run of clipped samples on one channel, from one capture block
+#[derive(Clone, Debug)]
+pub struct ClipEvent {
+    pub time: DateTime<Local>,
+    #[cfg_attr(not(any(feature = "gui", feature = "scripting")), allow(dead_code))]
+    pub channel: usize, // Only read to show or script it
+    pub samples: u64,
+}
+
+// What the main bar of each meter follows
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Ballistics {
+    Peak, // Sample peak with a 20 dB/s fall
+    Rms,  // 300 ms RMS, with the peak shown lighter above it
+    Vu,   // Rectified average with VU needle timing
+}
+
+impl Ballistics {
+    #[cfg(feature = "gui")]
+    pub const ALL: [Ballistics; 3] = [Ballistics::Peak, Ballistics::Rms, Ballistics::Vu];
+
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Ballistics::Peak => "Peak",
+            Ballistics::Rms => "RMS",
+            Ballistics::Vu => "VU",
+        }
+    }
+}
+
+// Ballistics for one channel, in dBFS
+#[derive(Clone, Copy)]
+struct ChannelMeter {
+    peak_db: f32,
+    hold_db: f32,
+    hold_since: Instant,
+    rms: f32,      // Smoothed mean square
+    vu: f32,       // Smoothed rectified average
+    clipped: bool, // Latches until reset
+}
+
+// Peak (with decay and a hold line), RMS or VU per channel, as drawn next to the waveform
+pub struct Meters {
+    channels: Vec<ChannelMeter>,
+    last_update: Instant,
+    pub clip_log: VecDeque<ClipEvent>, // Oldest first
+    pub ballistics: Ballistics,
+    pub reference_db: f32, // dBFS that reads 0 on the console, marked on the bars
+    pub hold_seconds: f32, // How long the peak line stays up, 0 for none
+    #[cfg(feature = "gui")]
+    name: &'static str, // What's being metered, for the hover text
+}
+
+impl Default for Meters {
+    fn default() -> Self {
+        Meters {
+            channels: Vec::new(),
+            last_update: Instant::now(),
+            clip_log: VecDeque::new(),
+            ballistics: Ballistics::Rms,
+            reference_db: -18.0,
+            hold_seconds: 1.5,
+            #[cfg(feature = "gui")]
+            name: "Input",
+        }
+    }
+}
+
+impl Meters {
+    #[cfg(feature = "gui")]
+    pub fn new(name: &'static str) -> Self {
+        Meters {
+            name,
+            ..Meters::default()
+        }
+    }
+
+    pub fn update(&mut self, tap: &MeterTap) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        let levels = tap.take();
+        if self.channels.len() != levels.len() {
+            let silent = ChannelMeter {
+                peak_db: FLOOR_DB,
+                hold_db: FLOOR_DB,
+                hold_since: now,
+                rms: 0.0,
+                vu: 0.0,
+                clipped: false,
+            };
+            self.channels = vec![silent; levels.len()];
+        }
+
+        let smoothing = 1.0 - (-elapsed / RMS_TIME_CONSTANT_SECONDS).exp();
+        let vu_smoothing = 1.0 - (-elapsed / VU_TIME_CONSTANT_SECONDS).exp();
+        for (channel, (meter, level)) in self.channels.iter_mut().zip(levels).enumerate() {
+            if let Some(time) = level.first_clip {
+                meter.clipped = true;
+                if self.clip_log.len() == MAX_CLIP_EVENTS {
+                    self.clip_log.pop_front();
+                }
+                self.clip_log.push_back(ClipEvent {
+                    time,
+                    channel,
+                    samples: level.clipped,
+                });
+            }
+            let block_db = to_db(level.peak);
+            meter.peak_db = (meter.peak_db - PEAK_FALL_DB_PER_SECOND * elapsed).max(block_db);
+            if block_db >= meter.hold_db
+                || now.duration_since(meter.hold_since).as_secs_f32() > self.hold_seconds
+            {
+                meter.hold_db = block_db.max(meter.peak_db);
+                meter.hold_since = now;
+            }
+            if level.count > 0 {
+                let mean_square = (level.sum_squares / level.count as f64) as f32;
+                meter.rms += (mean_square - meter.rms) * smoothing;
+                let average = (level.sum_abs / level.count as f64) as f32 * VU_SINE_CALIBRATION;
+                meter.vu += (average - meter.vu) * vu_smoothing;
+            }
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn reset_clip_leds(&mut self) {
+        for meter in &mut self.channels {
+            meter.clipped = false;
+        }
+    }
+
+    // Clip events within a span of wall clock time, e.g. while a grab was being captured
+    pub fn clips_between(&self, start: DateTime<Local>, end: DateTime<Local>) -> Vec<&ClipEvent> {
+        self.clip_log
+            .iter()
+            .filter(|event| event.time >= start && event.time <= end)
+            .collect()
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn width(&self) -> f32 {
+        self.channels.len() as f32 * (BAR_WIDTH + GAP)
+    }
+
+    // The level the main bar shows, in dBFS
+    #[cfg(feature = "gui")]
+    fn level_db(&self, meter: &ChannelMeter) -> f32 {
+        match self.ballistics {
+            Ballistics::Peak => meter.peak_db,
+            Ballistics::Rms => to_db(meter.rms.sqrt()),
+            Ballistics::Vu => to_db(meter.vu),
+        }
+    }
+
+    // Red near full scale, yellow above the reference level
+    #[cfg(feature = "gui")]
+    fn level_color(&self, db: f32) -> egui::Color32 {
+        if db > -3.0 {
+            egui::Color32::from_rgb(220, 50, 40)
+        } else if db > self.reference_db {
+            egui::Color32::from_rgb(230, 200, 40)
+        } else {
+            egui::Color32::from_rgb(60, 190, 80)
+        }
+    }
+
+    // One vertical bar per channel: the chosen ballistics solid (RMS with the peak lighter on
+    // top), hold as a line and the reference level as a tick, with a latching clip LED above.
+    // Clicking the meters resets the LEDs.
+    #[cfg(feature = "gui")]
+    pub fn ui(&mut self, ui: &mut egui::Ui, height: f32) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(self.width(), height), egui::Sense::click());
+        if response.clicked() {
+            self.reset_clip_leds();
+        }
+        let painter = ui.painter_at(rect);
+        let meter_top = rect.top() + LED_HEIGHT + GAP;
+        let y_for = |db: f32| {
+            let fraction = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+            rect.bottom() - fraction * (rect.bottom() - meter_top)
+        };
+
+        for (i, meter) in self.channels.iter().enumerate() {
+            let left = rect.left() + i as f32 * (BAR_WIDTH + GAP);
+            let led = egui::Rect::from_x_y_ranges(
+                left..=left + BAR_WIDTH,
+                rect.top()..=rect.top() + LED_HEIGHT,
+            );
+            let led_color = if meter.clipped {
+                egui::Color32::RED
+            } else {
+                egui::Color32::from_gray(50)
+            };
+            painter.rect_filled(led, 2.0, led_color);
+
+            let bar =
+                egui::Rect::from_x_y_ranges(left..=left + BAR_WIDTH, meter_top..=rect.bottom());
+            painter.rect_filled(bar, 1.0, egui::Color32::from_gray(30));
+
+            let level_db = self.level_db(meter);
+            let color = self.level_color(meter.peak_db);
+            if self.ballistics == Ballistics::Rms {
+                let peak_rect =
+                    egui::Rect::from_x_y_ranges(bar.x_range(), y_for(meter.peak_db)..=bar.bottom());
+                painter.rect_filled(peak_rect, 1.0, color.gamma_multiply(0.5));
+            }
+            let level_rect =
+                egui::Rect::from_x_y_ranges(bar.x_range(), y_for(level_db)..=bar.bottom());
+            painter.rect_filled(level_rect, 1.0, color);
+
+            painter.hline(
+                bar.x_range(),
+                y_for(self.reference_db),
+                egui::Stroke::new(1.0, egui::Color32::from_gray(160)),
+            );
+            if self.hold_seconds > 0.0 {
+                painter.hline(
+                    bar.x_range(),
+                    y_for(meter.hold_db),
+                    egui::Stroke::new(1.5, self.level_color(meter.hold_db)),
+                );
+            }
+        }
+
+        response.on_hover_text(
+            std::iter::once(self.name.to_owned())
+                .chain(self.channels.iter().enumerate().map(|(i, meter)| {
+                    let level_db = self.level_db(meter);
+                    format!(
+                        "Ch {}: peak {:.1} dBFS, {} {:.1} dBFS ({:+.1} dB re {:.0} dBFS)",
+                        i + 1,
+                        meter.hold_db,
+                        self.ballistics.label(),
+                        level_db,
+                        level_db - self.reference_db,
+                        self.reference_db
+                    )
+                }))
+                .chain(std::iter::once("Click to reset the clip lights".to_owned()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+}
+
+fn to_db(level: f32) -> f32 {
+    (20.0 * level.max(1e-6).log10()).max(FLOOR_DB)
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "midi")]
+use midir::{MidiInput, MidiInputConnection};
+#[cfg(feature = "midi")]
+use rolling_sampler_core::timecode::MtcDecoder;
+use rolling_sampler_core::timecode::Timecode;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+type Waker = Box<dyn Fn() + Send>;
+type LatestTimecode = Arc<Mutex<Option<(Timecode, Instant)>>>; // And when it arrived
+
+#[cfg(feature = "midi")]
+type Connection = MidiInputConnection<()>;
+#[cfg(not(feature = "midi"))]
+type Connection = ();
+
+// Listens to every MIDI input that is connected at startup. Messages are reported by name,
+// e.g. "Ch 1 Note 36" for a pad or "Ch 1 CC 64" for a sustain pedal going down, in the same way
+// the gamepad listener names its buttons. MIDI Timecode is kept apart, as the latest time.
+pub struct MidiListener {
+    messages: Receiver<String>,
+    #[cfg(feature = "gui")]
+    waker: Arc<Mutex<Option<Waker>>>,
+    timecode: LatestTimecode,
+    _connections: Vec<Connection>, // Closing these stops the callbacks
+}
+
+impl MidiListener {
+    pub fn spawn() -> Self {
+        let (sender, messages) = channel();
+        let waker: Arc<Mutex<Option<Waker>>> = Arc::new(Mutex::new(None));
+        let timecode = Arc::new(Mutex::new(None));
+        let connections = connect_all(&sender, &waker, &timecode);
+        MidiListener {
+            messages,
+            #[cfg(feature = "gui")]
+            waker,
+            timecode,
+            _connections: connections,
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    // Messages received since the last call
+    pub fn poll(&self) -> Vec<String> {
+        self.messages.try_iter().collect()
+    }
+
+    pub fn timecode(&self) -> Option<(Timecode, Instant)> {
+        *self.timecode.lock().unwrap()
+    }
+}
+
+#[cfg(feature = "midi")]
+fn connect_all(
+    sender: &Sender<String>,
+    waker: &Arc<Mutex<Option<Waker>>>,
+    timecode: &LatestTimecode,
+) -> Vec<Connection> {
+    let ports = match MidiInput::new("Rolling Sampler") {
+        Ok(input) => input.ports(),
+        Err(e) => {
+            eprintln!("MIDI support unavailable: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut connections = Vec::new();
+    for port in ports {
+        // Each connection takes its own client
+        let Ok(input) = MidiInput::new("Rolling Sampler") else {
+            continue;
+        };
+        let name = input.port_name(&port).unwrap_or_default();
+        let sender = sender.clone();
+        let waker = Arc::clone(waker);
+        let mut held = vec![false; 16 * 128];
+        let timecode = Arc::clone(timecode);
+        let mut mtc = MtcDecoder::default();
+        let callback = move |_: u64, message: &[u8], _: &mut ()| {
+            if let Some(time) = mtc.message(message) {
+                *timecode.lock().unwrap() = Some((time, Instant::now()));
+                return;
+            }
+            let Some(name) = message_name(message, &mut held) else {
+                return;
+            };
+            if sender.send(name).is_ok() {
+                if let Some(wake) = waker.lock().unwrap().as_ref() {
+                    wake();
+                }
+            }
+        };
+        match input.connect(&port, "rolling-sampler-in", callback, ()) {
+            Ok(connection) => {
+                println!("Listening to MIDI input {}", name);
+                connections.push(connection);
+            }
+            Err(e) => eprintln!("Failed to open MIDI input {}: {}", name, e),
+        }
+    }
+    connections
+}
+
+// Built without the `midi` feature, so there is nothing to listen to
+#[cfg(not(feature = "midi"))]
+fn connect_all(
+    _sender: &Sender<String>,
+    _waker: &Arc<Mutex<Option<Waker>>>,
+    _timecode: &LatestTimecode,
+) -> Vec<Connection> {
+    Vec::new()
+}
+
+// Only presses count: note ons, and controllers crossing halfway on the way up (so a knob
+// held past it doesn't fire again). `held` tracks which controllers are up, per channel.
+#[cfg(feature = "midi")]
+fn message_name(message: &[u8], held: &mut [bool]) -> Option<String> {
+    let [status, data1, data2, ..] = *message else {
+        return None;
+    };
+    let channel = (status & 0x0f) as usize;
+    match status & 0xf0 {
+        0x90 if data2 > 0 => Some(format!("Ch {} Note {}", channel + 1, data1)),
+        0xb0 => {
+            let up = data2 >= 64;
+            let was_up = std::mem::replace(&mut held[channel * 128 + data1 as usize % 128], up);
+            (up && !was_up).then(|| format!("Ch {} CC {}", channel + 1, data1))
+        }
+        _ => None,
+    }
+}
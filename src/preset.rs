@@ -0,0 +1,131 @@
+use rolling_sampler_core::export::{ExportStage, ExportVariant};
+use serde::{Deserialize, Serialize};
+
+// A single key/value pair written into every grab made with a preset (e.g. "mic" = "U87")
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MetadataField {
+    pub key: String,
+    pub value: String,
+}
+
+// What a binding listens for. Keys use egui's key names ("Space", "M"), gamepad buttons and MIDI
+// messages are named the way their listeners report them, and OSC bindings match the address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", content = "input")]
+pub enum Trigger {
+    Key(String),
+    Gamepad(String),
+    Midi(String),
+    Osc(String),
+}
+
+impl Trigger {
+    pub fn label(&self) -> String {
+        match self {
+            Trigger::Key(key) => format!("Key {}", key),
+            Trigger::Gamepad(button) => format!("Gamepad {}", button),
+            Trigger::Midi(message) => format!("MIDI {}", message),
+            Trigger::Osc(address) => format!("OSC {}", address),
+        }
+    }
+}
+
+// A trigger and the text command it runs (same syntax as the command interface, e.g. "grab")
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub command: String,
+}
+
+impl Binding {
+    pub fn new(trigger: Trigger, command: &str) -> Self {
+        Binding {
+            trigger,
+            command: command.to_owned(),
+        }
+    }
+
+    pub fn defaults() -> Vec<Binding> {
+        vec![
+            Binding::new(Trigger::Key("Space".to_owned()), "grab"),
+            Binding::new(Trigger::Key("M".to_owned()), "monitor"),
+            Binding::new(Trigger::Key("K".to_owned()), "marker"),
+            Binding::new(Trigger::Key("A".to_owned()), "arm"),
+            Binding::new(Trigger::Key("V".to_owned()), "vox"),
+            Binding::new(Trigger::Key("B".to_owned()), "ab"),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub metadata: Vec<MetadataField>, // Kept as a Vec so the fields stay in the order the user entered them
+    pub export_stages: Vec<ExportStage>,
+    pub export_variants: Vec<ExportVariant>, // Extra renders (reversed, repitched) of every grab
+    pub bindings: Vec<Binding>,              // Controls available while this preset is active
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Preset {
+            name: "Default".to_owned(),
+            metadata: Vec::new(),
+            export_stages: Vec::new(),
+            export_variants: Vec::new(),
+            bindings: Binding::defaults(),
+        }
+    }
+}
+
+impl Preset {
+    // Cleaning chain for sampling from turntables: repair clicks, remove rumble, then normalise
+    #[cfg(feature = "gui")]
+    pub fn vinyl_sampling() -> Self {
+        Preset {
+            name: "Vinyl Sampling".to_owned(),
+            metadata: vec![MetadataField {
+                key: "source".to_owned(),
+                value: "vinyl".to_owned(),
+            }],
+            export_stages: vec![
+                ExportStage::DeClick { strength: 0.5 },
+                ExportStage::HighPass { cutoff_hz: 25.0 },
+                ExportStage::Normalize { peak_db: -1.0 },
+            ],
+            export_variants: Vec::new(),
+            bindings: Binding::defaults(),
+        }
+    }
+
+    // Stripped down controls for playing live: one key grabs, another throws the grab away
+    #[cfg(feature = "gui")]
+    pub fn performance() -> Self {
+        Preset {
+            name: "Performance".to_owned(),
+            bindings: vec![
+                Binding::new(Trigger::Key("Space".to_owned()), "grab"),
+                Binding::new(Trigger::Key("Escape".to_owned()), "discard"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    // The command bound to a trigger, if any
+    pub fn binding_for(&self, trigger: &Trigger) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.trigger == *trigger)
+            .map(|binding| binding.command.as_str())
+    }
+
+    // Fields with an empty key are half-typed entries in the UI, so skip them
+    pub fn metadata_pairs(&self) -> Vec<(String, String)> {
+        self.metadata
+            .iter()
+            .filter(|field| !field.key.trim().is_empty())
+            .map(|field| (field.key.trim().to_owned(), field.value.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::Local;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rolling_sampler_core::CircularBuffer;
+use std::fs;
+use std::panic;
+#[cfg(feature = "gui")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::thread;
+use std::time::Duration;
+
+// A last copy of the rolling buffer when the app dies, so a crash doesn't take the one good
+// take with it. It goes to a temp folder and is offered back on the next launch.
+
+static FLUSHED: AtomicBool = AtomicBool::new(false); // One copy per run, however many panics
+
+pub fn recovery_dir() -> PathBuf {
+    std::env::temp_dir().join("rolling-sampler-recovery")
+}
+
+// Recordings left by an earlier run, oldest first
+pub fn pending() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(recovery_dir()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wav"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// Write out whatever the buffer holds. The audio callback only holds the lock briefly, so wait a
+// little for it, but give up if it's the crashing thread that has it.
+pub fn flush(buffer: &Mutex<CircularBuffer>) -> Option<PathBuf> {
+    if FLUSHED.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    for _ in 0..50 {
+        let buffer = match buffer.try_lock() {
+            Ok(buffer) => buffer,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+        };
+        if buffer.sample_rate == 0 || buffer.current_size == 0 {
+            return None;
+        }
+        let samples = buffer.copy_range(buffer.window_start(), buffer.total_written);
+        let (channels, sample_rate) = (buffer.channels as u16, buffer.sample_rate);
+        drop(buffer);
+        return match write(&samples, channels, sample_rate) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("Failed to write the recovery file: {}", e);
+                None
+            }
+        };
+    }
+    eprintln!("Couldn't get at the buffer to write a recovery file");
+    None
+}
+
+fn write(
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = recovery_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.wav", Local::now().format("%Y-%m-%d_%H-%M-%S")));
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(&path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+    Ok(path)
+}
+
+// Flush from a panic on any thread, after the usual panic message
+pub fn install_panic_hook(buffer: Arc<Mutex<CircularBuffer>>) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if let Some(path) = flush(&buffer) {
+            eprintln!("Saved the rolling buffer to {}", path.display());
+        }
+    }));
+}
+
+// Flush when the terminal, a logout or `kill` asks the app to quit, then set `quit` to the
+// signal and wake the UI, which finishes the files still being written before it exits. A
+// second signal exits straight away, for when that hangs. Not for headless mode, which handles
+// these itself by saving any grab in progress.
+#[cfg(all(unix, feature = "gui"))]
+pub fn flush_on_signals(
+    buffer: Arc<Mutex<CircularBuffer>>,
+    quit: Arc<AtomicI32>,
+    wake: impl Fn() + Send + 'static,
+) -> std::io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+    let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if quit.swap(signal, Ordering::SeqCst) != 0 {
+                eprintln!("Quitting without waiting for the writers");
+                std::process::exit(128 + signal);
+            }
+            if let Some(path) = flush(&buffer) {
+                eprintln!("Saved the rolling buffer to {}", path.display());
+            }
+            wake();
+        }
+    });
+    Ok(())
+}
+
+// Windows ends the process on Ctrl+C without a chance to flush
+#[cfg(all(not(unix), feature = "gui"))]
+pub fn flush_on_signals(
+    _buffer: Arc<Mutex<CircularBuffer>>,
+    _quit: Arc<AtomicI32>,
+    _wake: impl Fn() + Send + 'static,
+) -> std::io::Result<()> {
+    Ok(())
+}
+
+// Move a recovered recording into the save folder, keeping its timestamp name
+#[cfg(feature = "gui")]
+pub fn restore(path: &Path, save_dir: &Path) -> std::io::Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default();
+    let mut target = save_dir.join(name);
+    if target.exists() {
+        target = save_dir.join(format!("recovered_{}", name.to_string_lossy()));
+    }
+    fs::create_dir_all(save_dir)?;
+    // A rename can't cross file systems, and the temp folder often is a different one
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        fs::remove_file(path)?;
+    }
+    Ok(target)
+}
@@ -0,0 +1,244 @@
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+#[cfg(feature = "gui")]
+use std::net::{IpAddr, UdpSocket};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "remote")]
+use tungstenite::{Error as WsError, Message};
+
+// The whole remote UI: a level meter and a big grab button talking to /ws
+const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Rolling Sampler</title>
+<style>
+  body { font-family: sans-serif; background: #1b1b1b; color: #ddd; text-align: center; margin: 0; padding: 20px; }
+  #meter { height: 24px; background: #333; border-radius: 4px; overflow: hidden; margin: 20px 0; }
+  #level { height: 100%; width: 0%; background: #4caf50; }
+  #grab { width: 100%; height: 45vh; font-size: 3em; border: none; border-radius: 12px; background: #444; color: #fff; }
+  #grab.grabbing { background: #c62828; }
+</style>
+</head>
+<body>
+<h2>Rolling Sampler</h2>
+<div id="status">Connecting...</div>
+<div id="meter"><div id="level"></div></div>
+<button id="grab">GRAB</button>
+<script>
+  const status = document.getElementById("status");
+  const level = document.getElementById("level");
+  const grab = document.getElementById("grab");
+  let socket;
+  function connect() {
+    socket = new WebSocket(`ws://${location.host}/ws`);
+    socket.onmessage = (event) => {
+      const state = JSON.parse(event.data);
+      const percent = Math.max(0, Math.min(100, (state.level_db + 60) / 60 * 100));
+      level.style.width = percent + "%";
+      level.style.background = state.level_db > -1 ? "#c62828" : "#4caf50";
+      grab.classList.toggle("grabbing", state.grabbing);
+      grab.textContent = state.grabbing ? "STOP GRAB" : "GRAB";
+      status.textContent = `${state.level_db.toFixed(1)} dBFS`;
+    };
+    socket.onclose = () => { status.textContent = "Disconnected, retrying..."; setTimeout(connect, 1000); };
+  }
+  grab.onclick = () => socket.send("grab");
+  connect();
+</script>
+</body>
+</html>
+"##;
+
+pub enum RemoteCommand {
+    #[cfg_attr(not(feature = "remote"), allow(dead_code))] // Only sent by the server
+    ToggleGrab,
+}
+
+// What connected remotes are shown, refreshed by the recorder every frame
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct RemoteStatus {
+    pub level_db: f32,
+    pub grabbing: bool,
+}
+
+type Waker = Box<dyn Fn() + Send>;
+
+// Serves the remote page over HTTP and pushes status / receives commands over a WebSocket on
+// the same port, so any phone on the LAN can act as a grab button
+pub struct RemoteServer {
+    #[cfg(feature = "gui")]
+    pub port: u16,
+    commands: Receiver<RemoteCommand>,
+    status: Arc<Mutex<RemoteStatus>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl RemoteServer {
+    pub fn start(port: u16) -> std::io::Result<Self> {
+        if !cfg!(feature = "remote") {
+            return Err(std::io::Error::new(
+                ErrorKind::Unsupported,
+                "built without the `remote` feature",
+            ));
+        }
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        // Poll for connections so the thread notices when the server is stopped
+        listener.set_nonblocking(true)?;
+
+        let (sender, commands) = channel();
+        let server = RemoteServer {
+            #[cfg(feature = "gui")]
+            port,
+            commands,
+            status: Arc::new(Mutex::new(RemoteStatus::default())),
+            waker: Arc::new(Mutex::new(None)),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+
+        let status = Arc::clone(&server.status);
+        let waker = Arc::clone(&server.waker);
+        let running = Arc::clone(&server.running);
+        thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let sender = sender.clone();
+                        let status = Arc::clone(&status);
+                        let waker = Arc::clone(&waker);
+                        let running = Arc::clone(&running);
+                        thread::spawn(move || {
+                            if let Err(e) =
+                                handle_connection(stream, sender, status, waker, running)
+                            {
+                                eprintln!("Remote connection error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => eprintln!("Remote accept failed: {}", e),
+                }
+            }
+        });
+
+        println!("Remote listening on port {}", port);
+        Ok(server)
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn set_waker(&self, waker: impl Fn() + Send + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    pub fn set_status(&self, status: RemoteStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    // Commands received since the last call
+    pub fn poll(&self) -> Vec<RemoteCommand> {
+        self.commands.try_iter().collect()
+    }
+
+    // Address to show the user, using the interface that would route to the outside world
+    #[cfg(feature = "gui")]
+    pub fn url(&self) -> String {
+        let ip = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| {
+                socket.connect("8.8.8.8:80")?; // Nothing is sent, this just picks a route
+                socket.local_addr()
+            })
+            .map(|addr| addr.ip())
+            .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+        format!("http://{}:{}", ip, self.port)
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    sender: Sender<RemoteCommand>,
+    status: Arc<Mutex<RemoteStatus>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    running: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_nonblocking(false)?;
+
+    // Peek at the request to decide between serving the page and upgrading to a WebSocket
+    let mut head = [0u8; 2048];
+    let read = stream.peek(&mut head)?;
+    let request = String::from_utf8_lossy(&head[..read]).to_ascii_lowercase();
+    if !request.contains("upgrade: websocket") {
+        stream.read_exact(&mut head[..read])?; // Consume the request before answering
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            PAGE.len(),
+            PAGE
+        )?;
+        return Ok(());
+    }
+    serve_websocket(stream, sender, status, waker, running)
+}
+
+// Pushes the status and takes grab presses until the remote or the server goes away
+#[cfg(feature = "remote")]
+fn serve_websocket(
+    stream: TcpStream,
+    sender: Sender<RemoteCommand>,
+    status: Arc<Mutex<RemoteStatus>>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    running: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut socket = tungstenite::accept(stream)?;
+    // Short read timeout so status updates keep flowing while waiting for button presses
+    socket
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    while running.load(Ordering::SeqCst) {
+        let current = *status.lock().unwrap();
+        socket.send(Message::Text(serde_json::to_string(&current)?))?;
+
+        match socket.read() {
+            Ok(Message::Text(text)) if text.trim() == "grab" => {
+                sender.send(RemoteCommand::ToggleGrab)?;
+                if let Some(wake) = waker.lock().unwrap().as_ref() {
+                    wake();
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(WsError::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(WsError::ConnectionClosed) | Err(WsError::AlreadyClosed) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+// Never reached, `RemoteServer::start` refuses to run without the feature
+#[cfg(not(feature = "remote"))]
+fn serve_websocket(
+    _stream: TcpStream,
+    _sender: Sender<RemoteCommand>,
+    _status: Arc<Mutex<RemoteStatus>>,
+    _waker: Arc<Mutex<Option<Waker>>>,
+    _running: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("built without the `remote` feature".into())
+}
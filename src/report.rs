@@ -0,0 +1,131 @@
+use crate::history::{read_wav, HistoryItem};
+use chrono::{DateTime, Local};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const THUMBNAIL_WIDTH: usize = 400;
+const THUMBNAIL_HEIGHT: usize = 60;
+
+// A self-contained HTML page listing every grab of the session with its duration, levels,
+// metadata and a waveform thumbnail, saved next to the audio as a record of the session
+pub fn write_session_report(
+    dir: &Path,
+    started: DateTime<Local>,
+    items: &[HistoryItem],
+) -> io::Result<PathBuf> {
+    let mut html = String::new();
+    let title = format!(
+        "Rolling Sampler session {}",
+        started.format("%Y-%m-%d %H:%M")
+    );
+    let total: f64 = items.iter().map(|item| item.duration_seconds).sum();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+         td,th{{border-bottom:1px solid #ddd;padding:6px 10px;text-align:left;vertical-align:middle}}\
+         svg{{background:#222}}</style></head><body>\n\
+         <h1>{title}</h1>\n<p>{} to {}, {} grabs, {:.1} s of audio in total</p>\n\
+         <table>\n<tr><th>Take</th><th>Saved</th><th>Duration</th><th>Peak</th><th>RMS</th>\
+         <th>Preset &amp; metadata</th><th>Waveform</th></tr>\n",
+        started.format("%H:%M:%S"),
+        Local::now().format("%H:%M:%S"),
+        items.len(),
+        total,
+        title = escape(&title),
+    );
+
+    for item in items {
+        // Files may have been moved or deleted since, which is worth saying in the report
+        let (peak, rms, thumbnail) = match read_wav(&item.path) {
+            Ok(audio) => {
+                let (peak, rms) = levels(&audio.samples);
+                (
+                    format!("{:.1} dBFS", to_db(peak)),
+                    format!("{:.1} dBFS", to_db(rms)),
+                    thumbnail(&audio.samples, audio.channels),
+                )
+            }
+            Err(e) => (String::new(), String::new(), escape(&format!("({})", e))),
+        };
+        let tags: Vec<String> = std::iter::once(escape(&item.preset))
+            .chain(
+                item.metadata
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", escape(key), escape(value))),
+            )
+            .collect();
+        let file_name = item
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{:.1} s</td><td>{}</td><td>{}</td>\
+             <td>{}</td><td>{}</td></tr>",
+            escape(&file_name),
+            escape(&item.name),
+            item.saved_at.format("%H:%M:%S"),
+            item.duration_seconds,
+            peak,
+            rms,
+            tags.join("<br>"),
+            thumbnail,
+        );
+    }
+    html.push_str("</table>\n</body></html>\n");
+
+    let path = dir.join(format!(
+        "session_report_{}.html",
+        started.format("%Y-%m-%d_%H-%M-%S")
+    ));
+    fs::write(&path, html)?;
+    Ok(path)
+}
+
+fn levels(samples: &[f32]) -> (f32, f32) {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum / samples.len().max(1) as f64).sqrt() as f32;
+    (peak, rms)
+}
+
+fn to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-6).log10()
+}
+
+// Min/max envelope of the first channel as an inline SVG
+fn thumbnail(samples: &[f32], channels: usize) -> String {
+    let frames: Vec<f32> = samples.iter().step_by(channels.max(1)).copied().collect();
+    let per_column = frames.len().div_ceil(THUMBNAIL_WIDTH).max(1);
+    let middle = THUMBNAIL_HEIGHT as f32 / 2.0;
+    let mut path = String::new();
+    for (x, column) in frames.chunks(per_column).enumerate() {
+        let (low, high) = column.iter().fold((0.0f32, 0.0f32), |(low, high), &s| {
+            (low.min(s), high.max(s))
+        });
+        let _ = write!(
+            path,
+            "M{} {:.1}V{:.1}",
+            x,
+            middle - high.clamp(-1.0, 1.0) * middle,
+            middle - low.clamp(-1.0, 1.0) * middle + 0.5
+        );
+    }
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><path d=\"{path}\" \
+         stroke=\"#6c6\" stroke-width=\"1\"/></svg>",
+        w = THUMBNAIL_WIDTH,
+        h = THUMBNAIL_HEIGHT,
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,261 @@
+use chrono::Local;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rolling_sampler_core::tap::InputTap;
+use rolling_sampler_core::RecorderError;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Kept while waiting for signal so the first note of a segment isn't clipped
+const GATE_PRE_ROLL_SECONDS: f32 = 2.0;
+const POLL: Duration = Duration::from_millis(20); // How often the writer empties the tap
+
+// Only record while there is signal, for always-on installs that would otherwise fill the disk
+// with hours of empty room
+pub struct Gate {
+    pub threshold: f32,       // Linear peak level that counts as signal
+    pub silence_seconds: f32, // Pause (close the segment) after this long below threshold
+}
+
+pub struct SafetySettings {
+    pub dir: PathBuf,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub segment_seconds: f32,
+    pub gate: Option<Gate>,
+}
+
+// Continuous recording of everything that comes in, split into fixed length WAV segments, as
+// a safety net alongside the rolling grab buffer. Stopping (dropping) it finalises the
+// current segment.
+pub struct SafetyRecorder {
+    tap: Arc<InputTap>,
+    stop: Arc<AtomicBool>,
+    #[cfg(feature = "gui")]
+    recording: Arc<AtomicBool>,
+    #[cfg(feature = "gui")]
+    segments: Arc<AtomicU32>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl SafetyRecorder {
+    // Takes over the input stream's tap, which has to be from the stream the settings describe.
+    // A segment that can't be written is reported once through `errors` and ends the recording.
+    pub fn start(
+        settings: SafetySettings,
+        tap: Arc<InputTap>,
+        errors: Sender<RecorderError>,
+    ) -> std::io::Result<Self> {
+        fs::create_dir_all(&settings.dir)?;
+        tap.skip();
+        let stop = Arc::new(AtomicBool::new(false));
+        let recording = Arc::new(AtomicBool::new(false));
+        let segments = Arc::new(AtomicU32::new(0));
+        let writer = {
+            let tap = Arc::clone(&tap);
+            let stop = Arc::clone(&stop);
+            let recording = Arc::clone(&recording);
+            let segments = Arc::clone(&segments);
+            thread::spawn(move || {
+                write_segments(settings, &tap, &stop, &recording, &segments, errors)
+            })
+        };
+        tap.set_enabled(true);
+        Ok(SafetyRecorder {
+            tap,
+            stop,
+            #[cfg(feature = "gui")]
+            recording,
+            #[cfg(feature = "gui")]
+            segments,
+            writer: Some(writer),
+        })
+    }
+
+    // False while the gate is waiting for signal
+    #[cfg(feature = "gui")]
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn segments_written(&self) -> u32 {
+        self.segments.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for SafetyRecorder {
+    fn drop(&mut self) {
+        // The writer takes what's left in the tap and finishes the segment
+        self.tap.set_enabled(false);
+        self.stop.store(true, Ordering::Release);
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+type Segment = WavWriter<BufWriter<File>>;
+
+fn write_segments(
+    settings: SafetySettings,
+    tap: &InputTap,
+    stop: &AtomicBool,
+    recording: &AtomicBool,
+    segments: &AtomicU32,
+    errors: Sender<RecorderError>,
+) {
+    let mut segment = None;
+    // Rather than trying again on every block, a segment that can't be opened or written stops
+    // the recording, which can be started again once the disk is sorted out
+    let result = record(&settings, tap, stop, recording, segments, &mut segment)
+        .and_then(|()| close_segment(segment.take()));
+    if let Err(message) = result {
+        tap.set_enabled(false);
+        let _ = close_segment(segment);
+        eprintln!("{}", message);
+        let _ = errors.send(RecorderError::Save(message));
+    }
+    recording.store(false, Ordering::Relaxed);
+}
+
+fn record(
+    settings: &SafetySettings,
+    tap: &InputTap,
+    stop: &AtomicBool,
+    recording: &AtomicBool,
+    segments: &AtomicU32,
+    segment: &mut Option<(Segment, usize)>,
+) -> Result<(), String> {
+    let channels = settings.channels.max(1) as usize;
+    let rate = settings.sample_rate as f32;
+    let segment_frames = (settings.segment_seconds.max(1.0) * rate) as usize;
+    let pre_roll_len = (GATE_PRE_ROLL_SECONDS * rate) as usize * channels;
+
+    let mut pre_roll: VecDeque<f32> = VecDeque::new();
+    let mut quiet_frames = 0;
+
+    // Without a gate recording starts straight away
+    if settings.gate.is_none() {
+        *segment = Some(open_segment(settings, segments)?);
+    }
+
+    let mut block = Vec::new();
+    let mut dropped = 0;
+    loop {
+        // Read before emptying the tap, so nothing pushed before the stop is left behind
+        let stopping = stop.load(Ordering::Acquire);
+        block.clear();
+        if tap.pop(&mut block) == 0 {
+            if stopping {
+                return Ok(());
+            }
+            thread::sleep(POLL);
+            continue;
+        }
+        if tap.dropped() > dropped {
+            dropped = tap.dropped();
+            eprintln!(
+                "Safety recording fell behind, {} samples lost so far",
+                dropped
+            );
+        }
+
+        if let Some(gate) = &settings.gate {
+            let signal = block.iter().any(|s| s.abs() >= gate.threshold);
+            if segment.is_none() {
+                if !signal {
+                    pre_roll.extend(&block);
+                    let excess = pre_roll.len().saturating_sub(pre_roll_len);
+                    pre_roll.drain(..excess);
+                    continue;
+                }
+                let (writer, frames) = segment.insert(open_segment(settings, segments)?);
+                *frames += write(writer, pre_roll.drain(..), channels)?;
+                quiet_frames = 0;
+            }
+            if signal {
+                quiet_frames = 0;
+            } else {
+                quiet_frames += block.len() / channels;
+            }
+        }
+
+        let Some((writer, frames)) = segment else {
+            continue;
+        };
+        recording.store(true, Ordering::Relaxed);
+        *frames += write(writer, block.iter().copied(), channels)?;
+
+        let silent = settings
+            .gate
+            .as_ref()
+            .is_some_and(|gate| quiet_frames as f32 >= gate.silence_seconds * rate);
+        if silent || *frames >= segment_frames {
+            close_segment(segment.take())?;
+            recording.store(false, Ordering::Relaxed);
+            if silent {
+                println!("Safety recording paused, waiting for signal");
+            } else {
+                *segment = Some(open_segment(settings, segments)?);
+            }
+        }
+    }
+}
+
+fn open_segment(
+    settings: &SafetySettings,
+    segments: &AtomicU32,
+) -> Result<(Segment, usize), String> {
+    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut path = settings.dir.join(format!("safety_{}.wav", timestamp));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = settings
+            .dir
+            .join(format!("safety_{}_{}.wav", timestamp, count));
+    }
+    let spec = WavSpec {
+        channels: settings.channels,
+        sample_rate: settings.sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let writer = WavWriter::create(&path, spec)
+        .map_err(|e| format!("Failed to start safety segment {}: {}", path.display(), e))?;
+    segments.fetch_add(1, Ordering::Relaxed);
+    println!("Safety recording to {}", path.display());
+    Ok((writer, 0))
+}
+
+// Returns the number of frames written
+fn write(
+    writer: &mut Segment,
+    samples: impl Iterator<Item = f32>,
+    channels: usize,
+) -> Result<usize, String> {
+    let mut count = 0;
+    for sample in samples {
+        writer
+            .write_sample(sample)
+            .map_err(|e| format!("Failed to write the safety recording: {}", e))?;
+        count += 1;
+    }
+    Ok(count / channels)
+}
+
+fn close_segment(segment: Option<(Segment, usize)>) -> Result<(), String> {
+    match segment {
+        Some((writer, _)) => writer
+            .finalize()
+            .map_err(|e| format!("Failed to finalise safety segment: {}", e)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Local};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope, AST};
+use std::fs;
+use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+// A Rhai script of the user's with hooks the recorder calls as things happen, for the custom
+// behaviour that isn't worth a setting of its own:
+//
+//   fn on_grab(take) { if take.seconds < 1.0 { return false; } "riff_" + take.n }
+//   fn on_clip(channel, samples) { osc_send("10.0.0.5:9000", "/clip", [channel]); }
+//   fn on_silence(seconds) { command("monitor off"); }
+//
+// `on_grab` gets the take (name, preset, session, n, seconds, channels, sample_rate, peak_db,
+// clipped and metadata) before it is reviewed or saved, and may return a new name, or false to
+// throw it away. Hooks act on the app through the functions registered below, which are
+// carried out once the hook returns. `this` is a map kept between calls, for counters and such.
+
+pub const HOOKS: [&str; 3] = ["on_grab", "on_clip", "on_silence"];
+const MAX_OPERATIONS: u64 = 1_000_000; // So a runaway loop can't freeze the app
+
+// What a hook asked for, carried out by the recorder after it returns
+pub enum ScriptAction {
+    Command(String),     // A text command, as typed on stdin
+    Tag(String, String), // Metadata for the take `on_grab` was called with
+    Note(String),        // Shown like any other notification
+}
+
+pub struct Script {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    state: Dynamic, // `this` in every hook
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    modified: Option<SystemTime>, // Of the file when it was loaded, to reload on edits
+    // Newest clip event already passed to on_clip
+    pub clips_seen: Option<DateTime<Local>>,
+    pub quiet_since: Option<Instant>, // Input below the silence threshold since then
+    pub silence_reported: bool,       // on_silence ran for the current silence
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script, String> {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let engine = engine(&actions);
+        let ast = compile(&engine, path)?;
+        let mut script = Script {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+            actions,
+            modified: modified(path),
+            clips_seen: Some(Local::now()), // Clips from before the script don't count
+            quiet_since: None,
+            silence_reported: false,
+        };
+        script.run_top_level()?;
+        println!(
+            "Loaded script {} with {}",
+            path.display(),
+            script.hooks().join(", ")
+        );
+        Ok(script)
+    }
+
+    // Picks up edits to the file. A script that no longer compiles is reported and the old
+    // one kept running.
+    pub fn reload_if_changed(&mut self) -> Result<bool, String> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+        self.ast = compile(&self.engine, &self.path)?;
+        self.run_top_level()?;
+        println!("Reloaded script {}", self.path.display());
+        Ok(true)
+    }
+
+    // The hooks the script defines
+    pub fn hooks(&self) -> Vec<&'static str> {
+        HOOKS
+            .into_iter()
+            .filter(|&hook| self.ast.iter_functions().any(|f| f.name == hook))
+            .collect()
+    }
+
+    // Calls a hook if the script has it, returning what it returned (unit if it hasn't)
+    pub fn call(&mut self, hook: &str, args: impl FuncArgs) -> Result<Dynamic, String> {
+        if !self.hooks().contains(&hook) {
+            return Ok(Dynamic::UNIT);
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, hook, args)
+            .map_err(|e| format!("{} failed: {}", hook, e))
+    }
+
+    pub fn take_actions(&self) -> Vec<ScriptAction> {
+        std::mem::take(&mut *self.actions.lock().unwrap())
+    }
+
+    // Statements outside the hooks run once, on load
+    fn run_top_level(&mut self) -> Result<(), String> {
+        self.engine
+            .run_ast(&self.ast)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<AST, String> {
+    engine
+        .compile_file(path.to_path_buf())
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn engine(actions: &Arc<Mutex<Vec<ScriptAction>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| println!("[script] {}", text));
+    engine.on_debug(|text, _, _| println!("[script] {}", text));
+
+    let queue = Arc::clone(actions);
+    engine.register_fn("command", move |line: &str| {
+        queue
+            .lock()
+            .unwrap()
+            .push(ScriptAction::Command(line.to_owned()));
+    });
+    let queue = Arc::clone(actions);
+    engine.register_fn("tag", move |key: &str, value: Dynamic| {
+        queue
+            .lock()
+            .unwrap()
+            .push(ScriptAction::Tag(key.to_owned(), value.to_string()));
+    });
+    let queue = Arc::clone(actions);
+    engine.register_fn("notify", move |text: &str| {
+        queue
+            .lock()
+            .unwrap()
+            .push(ScriptAction::Note(text.to_owned()));
+    });
+    engine.register_fn("osc_send", |target: &str, address: &str, args: Array| {
+        osc_send(target, address, &args)
+    });
+    engine.register_fn("osc_send", |target: &str, address: &str| {
+        osc_send(target, address, &[])
+    });
+    engine
+}
+
+// Sent straight away, e.g. to light a button on a controller or tell a DAW to do something
+fn osc_send(target: &str, address: &str, args: &[Dynamic]) {
+    let mut tags = String::from(",");
+    let mut data = Vec::new();
+    for arg in args {
+        if let Ok(int) = arg.as_int() {
+            tags.push('i');
+            data.extend_from_slice(&(int as i32).to_be_bytes());
+        } else if let Ok(float) = arg.as_float() {
+            tags.push('f');
+            data.extend_from_slice(&(float as f32).to_be_bytes());
+        } else if let Ok(on) = arg.as_bool() {
+            tags.push(if on { 'T' } else { 'F' });
+        } else {
+            tags.push('s');
+            push_osc_string(&mut data, &arg.to_string());
+        }
+    }
+    let mut packet = Vec::new();
+    push_osc_string(&mut packet, address);
+    push_osc_string(&mut packet, &tags);
+    packet.extend_from_slice(&data);
+    let sent = UdpSocket::bind(("0.0.0.0", 0)).and_then(|socket| socket.send_to(&packet, target));
+    if let Err(e) = sent {
+        eprintln!("Script OSC to {} failed: {}", target, e);
+    }
+}
+
+// Null terminated and padded to a multiple of four bytes
+fn push_osc_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(text.as_bytes());
+    packet.extend(std::iter::repeat_n(0, 4 - text.len() % 4));
+}
@@ -0,0 +1,64 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+// A named set of save folder, take naming and settings, so band practice and a podcast don't
+// end up in the same folder with the same numbering. The active session's values live in the
+// config's own fields while it is active, and are copied back here when switching away.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub name: String,
+    pub save_path: Option<String>, // The Desktop when not chosen yet
+    pub naming_template: String,   // See `take_name`
+    pub take_counter: u32,         // Takes named so far, for {n}
+    pub marker_count: usize,       // Markers dropped so far, so numbers carry on
+    pub active_preset: usize,
+    pub buffer_seconds: f32,
+    pub hot_folder: Option<String>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            name: "Default".to_owned(),
+            save_path: None,
+            naming_template: "{timestamp}".to_owned(),
+            take_counter: 0,
+            marker_count: 0,
+            active_preset: 0,
+            buffer_seconds: 5.0,
+            hot_folder: None,
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub const TEMPLATE_HELP: &str =
+    "{timestamp}, {session}, {preset} and {n} (a take number) are filled in";
+
+impl Session {
+    // The next take's name from the template, counting it. Characters that can't go in a file
+    // name become underscores, and an empty result falls back to the timestamp.
+    pub fn take_name(&mut self, preset: &str) -> String {
+        self.take_counter += 1;
+        let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let name: String = self
+            .naming_template
+            .replace("{timestamp}", &timestamp)
+            .replace("{session}", &self.name)
+            .replace("{preset}", preset)
+            .replace("{n}", &format!("{:03}", self.take_counter))
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c => c,
+            })
+            .collect();
+        let name = name.trim();
+        if name.is_empty() {
+            timestamp
+        } else {
+            name.to_owned()
+        }
+    }
+}
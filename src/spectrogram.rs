@@ -0,0 +1,161 @@
+use rolling_sampler_core::CircularBuffer;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+const FFT_SIZE: usize = 1024;
+const MAX_COLUMNS: usize = 512; // Across the whole history
+const ROWS: usize = 128; // Log spaced from LOWEST_HZ up to Nyquist
+const LOWEST_HZ: f32 = 20.0;
+const FLOOR_DB: f32 = -100.0;
+
+// Short-time FFT of the rolling history, kept in step with the buffer the same way as the
+// waveform cache: columns are computed once as audio arrives and dropped as it rolls out.
+pub struct Spectrogram {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    buffer_id: u64,
+    hop: u64,                      // In frames
+    next: u64,                     // Absolute frame the next column starts at
+    columns: VecDeque<[u8; ROWS]>, // Oldest first, 0 = silence, 255 = full scale
+    rows: Vec<(usize, usize)>,     // Range of FFT bins shown in each row, bottom up
+    texture: Option<egui::TextureHandle>,
+}
+
+impl Default for Spectrogram {
+    fn default() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        Spectrogram {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            buffer_id: u64::MAX,
+            hop: 1,
+            next: 0,
+            columns: VecDeque::new(),
+            rows: Vec::new(),
+            texture: None,
+        }
+    }
+}
+
+impl Spectrogram {
+    pub fn update(&mut self, buffer: &CircularBuffer, channels: usize, sample_rate: u32) {
+        let channels = channels.max(1) as u64;
+        let hop = ((buffer.max_size as u64 / channels) / MAX_COLUMNS as u64).max(64);
+        let first_frame = buffer.window_start().div_ceil(channels);
+        if buffer.id != self.buffer_id || hop != self.hop || self.next < first_frame {
+            // A different buffer or length, or too far behind: start again
+            self.buffer_id = buffer.id;
+            self.hop = hop;
+            self.columns.clear();
+            self.next = first_frame.div_ceil(hop) * hop;
+            self.rows = row_bins(sample_rate);
+        }
+
+        // Forget columns that have rolled out of the window...
+        let oldest = self.next - self.columns.len() as u64 * self.hop;
+        let expired = first_frame.saturating_sub(oldest).div_ceil(self.hop) as usize;
+        self.columns.drain(..expired.min(self.columns.len()));
+
+        // ...and analyse the audio written since the last frame
+        let total_frames = buffer.total_written / channels;
+        let mut spectrum = vec![Complex::default(); FFT_SIZE];
+        while self.next + FFT_SIZE as u64 <= total_frames {
+            for (i, (bin, weight)) in spectrum.iter_mut().zip(&self.window).enumerate() {
+                let frame = (self.next + i as u64) * channels;
+                let mono: f32 = (0..channels)
+                    .map(|c| buffer.sample_at(frame + c))
+                    .sum::<f32>()
+                    / channels as f32;
+                *bin = Complex::new(mono * weight, 0.0);
+            }
+            self.fft.process(&mut spectrum);
+            self.columns.push_back(self.column(&spectrum));
+            self.next += self.hop;
+        }
+    }
+
+    fn column(&self, spectrum: &[Complex<f32>]) -> [u8; ROWS] {
+        // A full scale sine comes out at FFT_SIZE / 4 through the Hann window
+        let scale = 4.0 / FFT_SIZE as f32;
+        let mut column = [0u8; ROWS];
+        for (value, &(low, high)) in column.iter_mut().zip(&self.rows) {
+            let magnitude = spectrum[low..high]
+                .iter()
+                .map(|bin| bin.norm())
+                .fold(0.0f32, f32::max)
+                * scale;
+            let db = 20.0 * magnitude.max(1e-9).log10();
+            *value = ((1.0 - db / FLOOR_DB).clamp(0.0, 1.0) * 255.0) as u8;
+        }
+        column
+    }
+
+    // Draw the history into the given size, oldest on the left like the waveform
+    pub fn ui(&mut self, ui: &mut egui::Ui, size: egui::Vec2) {
+        let width = self.columns.len().max(1);
+        let mut image = egui::ColorImage::new([width, ROWS], egui::Color32::BLACK);
+        for (x, column) in self.columns.iter().enumerate() {
+            for (row, &value) in column.iter().enumerate() {
+                // Low frequencies at the bottom
+                image[(x, ROWS - 1 - row)] = heat_color(value);
+            }
+        }
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, egui::TextureOptions::LINEAR);
+                texture
+            }
+            None => self.texture.insert(ui.ctx().load_texture(
+                "spectrogram",
+                image,
+                egui::TextureOptions::LINEAR,
+            )),
+        };
+        ui.add(egui::Image::new((texture.id(), size)));
+    }
+}
+
+// FFT bins covered by each log spaced row, at least one bin each
+fn row_bins(sample_rate: u32) -> Vec<(usize, usize)> {
+    let nyquist = sample_rate as f32 / 2.0;
+    let bins = FFT_SIZE / 2;
+    let bin_at = |row: f32| {
+        let hz = LOWEST_HZ * (nyquist / LOWEST_HZ).powf(row / ROWS as f32);
+        ((hz / nyquist * bins as f32) as usize).min(bins - 1)
+    };
+    (0..ROWS)
+        .map(|row| {
+            let low = bin_at(row as f32);
+            let high = bin_at(row as f32 + 1.0).max(low + 1);
+            (low, high)
+        })
+        .collect()
+}
+
+// Black through purple and orange to pale yellow
+fn heat_color(value: u8) -> egui::Color32 {
+    const STOPS: [(f32, [f32; 3]); 4] = [
+        (0.0, [0.0, 0.0, 0.0]),
+        (0.35, [90.0, 20.0, 120.0]),
+        (0.7, [230.0, 90.0, 30.0]),
+        (1.0, [255.0, 250.0, 180.0]),
+    ];
+    let t = value as f32 / 255.0;
+    let upper = STOPS
+        .iter()
+        .position(|&(stop, _)| stop >= t)
+        .unwrap_or(3)
+        .max(1);
+    let (t0, c0) = STOPS[upper - 1];
+    let (t1, c1) = STOPS[upper];
+    let f = (t - t0) / (t1 - t0);
+    let mix = |i: usize| (c0[i] + (c1[i] - c0[i]) * f) as u8;
+    egui::Color32::from_rgb(mix(0), mix(1), mix(2))
+}
@@ -0,0 +1,137 @@
+use egui_plot::{Line, Plot, PlotPoint, PlotPoints, Text};
+use rolling_sampler_core::CircularBuffer;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+const FFT_SIZE: usize = 4096;
+const FLOOR_DB: f64 = -120.0;
+const LOWEST_HZ: f64 = 20.0;
+
+// Live magnitude spectrum of the newest input, with exponential averaging and a peak hold
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    last_total: u64,
+    sample_rate: u32,
+    average: Vec<f32>, // Power per bin
+    peak: Vec<f32>,    // Highest average power per bin since the last reset
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        SpectrumAnalyzer {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            window,
+            last_total: 0,
+            sample_rate: 0,
+            average: vec![0.0; FFT_SIZE / 2],
+            peak: vec![0.0; FFT_SIZE / 2],
+        }
+    }
+}
+
+impl SpectrumAnalyzer {
+    // `averaging` is how much of the previous spectrum is kept each update, 0 for none
+    pub fn update(
+        &mut self,
+        buffer: &CircularBuffer,
+        channels: usize,
+        sample_rate: u32,
+        averaging: f32,
+    ) {
+        let channels = channels.max(1) as u64;
+        let needed = FFT_SIZE as u64 * channels;
+        let total = buffer.total_written;
+        if total == self.last_total || total - buffer.window_start() < needed {
+            return; // Nothing new, or not enough audio yet
+        }
+        self.last_total = total;
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.reset_peak();
+        }
+
+        // The newest FFT_SIZE frames, mixed to mono
+        let start = (total - needed) / channels * channels;
+        let mut spectrum: Vec<Complex<f32>> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                let frame = start + i as u64 * channels;
+                let mono: f32 = (0..channels)
+                    .map(|c| buffer.sample_at(frame + c))
+                    .sum::<f32>()
+                    / channels as f32;
+                Complex::new(mono * weight, 0.0)
+            })
+            .collect();
+        self.fft.process(&mut spectrum);
+
+        // Scaled so a full scale sine reads 0 dB through the Hann window
+        let scale = 4.0 / FFT_SIZE as f32;
+        for ((average, peak), bin) in self.average.iter_mut().zip(&mut self.peak).zip(&spectrum) {
+            let power = (bin.norm() * scale).powi(2);
+            *average = *average * averaging + power * (1.0 - averaging);
+            *peak = peak.max(*average);
+        }
+    }
+
+    pub fn reset_peak(&mut self) {
+        self.peak.fill(0.0);
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, width: f32, show_peak: bool) {
+        let hz_per_bin = self.sample_rate as f64 / FFT_SIZE as f64;
+        // x is log10 of the frequency so the octaves are evenly spaced
+        let points = |powers: &[f32]| -> PlotPoints {
+            powers
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(bin, _)| *bin as f64 * hz_per_bin >= LOWEST_HZ)
+                .map(|(bin, &power)| {
+                    let db = (10.0 * (power as f64).max(1e-12).log10()).max(FLOOR_DB);
+                    [(bin as f64 * hz_per_bin).log10(), db]
+                })
+                .collect()
+        };
+
+        Plot::new("Spectrum Analyzer")
+            .width(width)
+            .view_aspect(4.0)
+            .show_axes([false, true])
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .include_x(LOWEST_HZ.log10())
+            .include_x((self.sample_rate.max(1) as f64 / 2.0).log10())
+            .include_y(FLOOR_DB)
+            .include_y(0.0)
+            .label_formatter(|_, point| format!("{:.0} Hz\n{:.1} dB", 10f64.powf(point.x), point.y))
+            .show(ui, |plot_ui| {
+                if show_peak {
+                    plot_ui.line(
+                        Line::new(points(&self.peak))
+                            .color(egui::Color32::from_rgb(230, 120, 40))
+                            .name("Peak hold"),
+                    );
+                }
+                plot_ui.line(Line::new(points(&self.average)).name("Spectrum"));
+                // Frequency ruler in place of an x axis
+                for (hz, label) in [(100.0, "100"), (1_000.0, "1k"), (10_000.0, "10k")] {
+                    plot_ui.text(Text::new(
+                        PlotPoint::new(f64::log10(hz), FLOOR_DB + 8.0),
+                        label,
+                    ));
+                }
+            });
+    }
+}
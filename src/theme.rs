@@ -0,0 +1,66 @@
+#[cfg(feature = "gui")]
+use crate::config::Config;
+#[cfg(feature = "gui")]
+use egui::{Color32, Visuals};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    Dark,
+    Light,
+    Stage, // Black background and bright text, for reading in a dark room
+}
+
+impl Theme {
+    #[cfg(feature = "gui")]
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::Stage];
+
+    #[cfg(feature = "gui")]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::Stage => "Stage",
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn visuals(&self) -> Visuals {
+        match self {
+            Theme::Dark => Visuals::dark(),
+            Theme::Light => Visuals::light(),
+            Theme::Stage => {
+                let mut visuals = Visuals::dark();
+                visuals.panel_fill = Color32::BLACK;
+                visuals.window_fill = Color32::from_gray(12);
+                visuals.extreme_bg_color = Color32::BLACK;
+                visuals.faint_bg_color = Color32::from_gray(20);
+                visuals.override_text_color = Some(Color32::from_gray(240));
+                visuals.widgets.noninteractive.bg_stroke.color = Color32::from_gray(90);
+                visuals
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn rgb([r, g, b]: [u8; 3]) -> Color32 {
+    Color32::from_rgb(r, g, b)
+}
+
+// The chosen theme with any custom colors on top
+#[cfg(feature = "gui")]
+pub fn apply(ctx: &egui::Context, settings: &Config) {
+    let mut visuals = settings.theme.visuals();
+    if let Some(accent) = settings.accent_color.map(rgb) {
+        visuals.selection.bg_fill = accent;
+        visuals.hyperlink_color = accent;
+        visuals.widgets.hovered.bg_stroke.color = accent;
+        visuals.widgets.active.bg_fill = accent;
+    }
+    if let Some(background) = settings.background_color.map(rgb) {
+        visuals.panel_fill = background;
+        visuals.window_fill = background;
+    }
+    ctx.set_visuals(visuals);
+}
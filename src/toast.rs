@@ -0,0 +1,121 @@
+#[cfg(feature = "gui")]
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{self, Sender};
+#[cfg(feature = "gui")]
+use std::time::{Duration, Instant};
+
+use rolling_sampler_core::RecorderError;
+
+// Notes go away by themselves, errors stay until they're dismissed
+#[cfg(feature = "gui")]
+const NOTE_SECONDS: u64 = 4;
+#[cfg(feature = "gui")]
+const MAX_TOASTS: usize = 6;
+
+#[cfg(feature = "gui")]
+struct Toast {
+    text: String,
+    error: bool,
+    shown: Instant,
+}
+
+// Queue of messages drawn in the bottom right corner over the rest of the UI
+pub struct Toasts {
+    sender: Sender<RecorderError>,
+    #[cfg(feature = "gui")]
+    receiver: Receiver<RecorderError>,
+    #[cfg(feature = "gui")]
+    toasts: Vec<Toast>,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        #[cfg(feature = "gui")]
+        let (sender, receiver) = mpsc::channel();
+        // Without a window nothing shows them, and the threads sending them print them first
+        #[cfg(not(feature = "gui"))]
+        let (sender, _) = mpsc::channel();
+        Toasts {
+            sender,
+            #[cfg(feature = "gui")]
+            receiver,
+            #[cfg(feature = "gui")]
+            toasts: Vec::new(),
+        }
+    }
+}
+
+impl Toasts {
+    // For other threads to report errors through
+    pub fn sender(&self) -> Sender<RecorderError> {
+        self.sender.clone()
+    }
+
+    pub fn error(&mut self, error: RecorderError) {
+        eprintln!("{}", error);
+        #[cfg(feature = "gui")]
+        self.push(error.to_string(), true);
+    }
+
+    pub fn note(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        println!("{}", text);
+        #[cfg(feature = "gui")]
+        self.push(text, false);
+    }
+
+    #[cfg(feature = "gui")]
+    fn push(&mut self, text: String, error: bool) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast {
+            text,
+            error,
+            shown: Instant::now(),
+        });
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        while let Ok(error) = self.receiver.try_recv() {
+            self.push(error.to_string(), true); // Already printed where it happened
+        }
+        self.toasts.retain(|toast| {
+            toast.error || toast.shown.elapsed() < Duration::from_secs(NOTE_SECONDS)
+        });
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("Toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(320.0);
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    let fill = if toast.error {
+                        egui::Color32::from_rgb(120, 25, 25)
+                    } else {
+                        ui.visuals().window_fill
+                    };
+                    egui::Frame::popup(ui.style()).fill(fill).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if toast.error {
+                                ui.colored_label(egui::Color32::WHITE, &toast.text);
+                            } else {
+                                ui.label(&toast.text);
+                            }
+                            if ui.small_button("x").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use rolling_sampler_core::pitch::{detect_pitch, Reading, LOWEST_HZ, WINDOW};
+use rolling_sampler_core::CircularBuffer;
+
+// Monophonic pitch of the newest input, found with the YIN difference function
+#[derive(Default)]
+pub struct Tuner {
+    last_total: u64,
+    pitch: Option<f32>, // Smoothed, in Hz
+}
+
+impl Tuner {
+    pub fn update(&mut self, buffer: &CircularBuffer, channels: usize, sample_rate: u32) {
+        let channels = channels.max(1) as u64;
+        let max_lag = (sample_rate as f32 / LOWEST_HZ) as usize;
+        let needed = (WINDOW + max_lag) as u64 * channels;
+        let total = buffer.total_written;
+        if total == self.last_total || total - buffer.window_start() < needed {
+            return; // Nothing new, or not enough audio yet
+        }
+        self.last_total = total;
+
+        // The newest frames, mixed to mono
+        let start = (total - needed) / channels * channels;
+        let frames: Vec<f32> = (0..WINDOW + max_lag)
+            .map(|i| {
+                let frame = start + i as u64 * channels;
+                (0..channels)
+                    .map(|c| buffer.sample_at(frame + c))
+                    .sum::<f32>()
+                    / channels as f32
+            })
+            .collect();
+
+        match detect_pitch(&frames, sample_rate) {
+            Some(hz) => {
+                // Smooth small wobbles, but follow a new note straight away
+                self.pitch = Some(match self.pitch {
+                    Some(previous) if (1200.0 * (hz / previous).log2()).abs() < 50.0 => {
+                        previous + (hz - previous) * 0.3
+                    }
+                    _ => hz,
+                });
+            }
+            None => self.pitch = None,
+        }
+    }
+
+    // Note name, frequency and how far off it is, with a needle from -50 to +50 cents
+    pub fn ui(&self, ui: &mut egui::Ui, width: f32, reference_hz: f32) {
+        let reading = self.pitch.map(|hz| Reading::new(hz, reference_hz));
+        let text = match &reading {
+            Some(reading) => format!(
+                "{}{}  {:+.0} cents  ({:.1} Hz)",
+                reading.name, reading.octave, reading.cents, reading.hz
+            ),
+            None => "No pitch".to_owned(),
+        };
+        ui.label(egui::RichText::new(text).size(20.0).monospace());
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, 18.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 3.0, ui.visuals().extreme_bg_color);
+        let x_for =
+            |cents: f32| rect.center().x + cents.clamp(-50.0, 50.0) / 50.0 * rect.width() / 2.0;
+        for cents in [-50.0, -25.0, 0.0, 25.0, 50.0] {
+            let tick = if cents == 0.0 { 1.0 } else { 0.4 };
+            painter.vline(
+                x_for(cents),
+                rect.y_range(),
+                egui::Stroke::new(1.0, ui.visuals().text_color().gamma_multiply(tick)),
+            );
+        }
+        if let Some(reading) = reading {
+            let color = if reading.cents.abs() <= 5.0 {
+                egui::Color32::from_rgb(60, 190, 80)
+            } else {
+                egui::Color32::from_rgb(230, 200, 40)
+            };
+            painter.vline(
+                x_for(reading.cents),
+                rect.y_range(),
+                egui::Stroke::new(4.0, color),
+            );
+        }
+    }
+}